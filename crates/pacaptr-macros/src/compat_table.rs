@@ -1,35 +1,97 @@
 use std::{collections::BTreeMap, ffi::OsString, fmt::Debug, fs, path::Path, str::FromStr};
 
-use anyhow::Context;
+use anyhow::{anyhow, bail, Context};
 use itertools::{chain, Itertools};
 use once_cell::sync::Lazy;
 use proc_macro2::{Span, TokenStream};
-use regex::Regex;
-use syn::{Error, Result};
+use syn::{Error, Expr, ImplItem, Item, Lit, Result};
 use tabled::{style::Style as TableStyle, Table, Tabled};
 
 const PM_IMPL_DIR: &str = "src/pm/";
 
+/// The name of the registry of the operations supported by a backend, eg.
+/// `pub(crate) const SUPPORTED: &[&str] = &["q", "qi", ..];`.
+const REGISTRY: &str = "SUPPORTED";
+
+/// The capability traits which the operations are implemented in.
+const CAPABILITIES: [&str; 3] = ["PmQuery", "PmRemove", "PmSync"];
+
 // We have to specify the length there (the elision is blocked by https://github.com/rust-lang/rfcs/pull/2545).
 // TODO: Fix this when the issue is resolved.
-const METHODS: [&str; 30] = [
-    "q", "qc", "qe", "qi", "qk", "ql", "qm", "qo", "qp", "qs", "qu", "r", "rn", "rns", "rs", "rss",
-    "s", "sc", "scc", "sccc", "sg", "si", "sii", "sl", "ss", "su", "suy", "sw", "sy", "u",
+const METHODS: [&str; 38] = [
+    "f", "fy", "q", "qc", "qd", "qdt", "qe", "qi", "qk", "ql", "qm", "qo", "qp", "qs", "qt", "qu",
+    "r", "rc", "rcs", "rn", "rns", "rs", "rss", "s", "sc", "scc", "sccc", "sg", "si", "sii", "sl",
+    "ss", "su", "suy", "sw", "sy", "t", "u",
 ];
 
-/// Checks the implementation status of `pacman` commands in a specific file
-/// (eg. `homebrew.rs`).
+/// Reads the registry of the operations supported in a specific file (eg.
+/// `brew.rs`), checking that each operation implemented in the capability
+/// traits there is registered.
+///
+/// The operations registered but not implemented there are accepted, since
+/// they might be delegated, eg. to a macro.
 fn check_methods(file: &Path) -> anyhow::Result<BTreeMap<String, bool>> {
-    let bytes = fs::read(file)?;
-    let contents = String::from_utf8(bytes)?;
+    let contents = fs::read_to_string(file)?;
+    let items = syn::parse_file(&contents)?.items;
+
+    let registry: Vec<String> = items
+        .iter()
+        .find_map(|item| match item {
+            Item::Const(c) if c.ident == REGISTRY => Some(&*c.expr),
+            _ => None,
+        })
+        .with_context(|| format!("`{REGISTRY}` not found"))
+        .and_then(registry_entries)?;
+    if let Some(op) = registry.iter().find(|op| !METHODS.contains(&op.as_str())) {
+        bail!("`{REGISTRY}` contains the unknown operation `{op}`");
+    }
+
+    let implemented = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Impl(i) => i.trait_.as_ref().map(|(_, path, _)| (path, &i.items)),
+            _ => None,
+        })
+        .filter(|(path, _)| {
+            path.segments
+                .last()
+                .is_some_and(|seg| CAPABILITIES.iter().any(|&cap| seg.ident == cap))
+        })
+        .flat_map(|(_, items)| items)
+        .filter_map(|item| match item {
+            ImplItem::Method(m) => Some(m.sig.ident.to_string()),
+            _ => None,
+        });
+    for method in implemented {
+        if !registry.contains(&method) {
+            bail!("`{method}` is implemented but not registered in `{REGISTRY}`");
+        }
+    }
 
-    METHODS
+    Ok(METHODS
         .iter()
-        .map(|&method| {
-            // A function definition (rg. `rs`) is written as follows:
-            // `(async) fn rs(..) {..}`
-            let found = Regex::new(&format!(r#"fn\s+{}\s*\("#, method))?.is_match(&contents);
-            Ok((method.to_owned(), found))
+        .map(|&method| (method.to_owned(), registry.iter().any(|op| op == method)))
+        .collect())
+}
+
+/// Gets the string literals in the registry `expr`, ie. `&["q", "qi", ..]`.
+fn registry_entries(expr: &Expr) -> anyhow::Result<Vec<String>> {
+    let malformed = || anyhow!("`{REGISTRY}` should be a slice of string literals");
+    let Expr::Reference(r) = expr else {
+        return Err(malformed());
+    };
+    let Expr::Array(array) = &*r.expr else {
+        return Err(malformed());
+    };
+    array
+        .elems
+        .iter()
+        .map(|elem| match elem {
+            Expr::Lit(lit) => match &lit.lit {
+                Lit::Str(s) => Ok(s.value()),
+                _ => Err(malformed()),
+            },
+            _ => Err(malformed()),
         })
         .try_collect()
 }
@@ -60,11 +122,15 @@ fn make_table() -> anyhow::Result<String> {
         .map(|entry| entry.context("Error while reading path"))
         .try_collect()?;
 
-    let excluded_names = ["mod.rs", "unknown.rs"];
+    let excluded_names = ["mod.rs", "custom.rs", "testing.rs", "unknown.rs"];
     let impls: BTreeMap<OsString, BTreeMap<String, bool>> = paths
         .iter()
         .filter(|entry| !excluded_names.iter().any(|&ex| ex == entry.file_name()))
-        .map(|entry| check_methods(&entry.path()).map(|impl_| (entry.file_name(), impl_)))
+        .map(|entry| {
+            check_methods(&entry.path())
+                .map(|impl_| (entry.file_name(), impl_))
+                .with_context(|| format!("Failed while checking `{:?}`", entry.file_name()))
+        })
         .try_collect()?;
 
     let make_row = |name, data| {
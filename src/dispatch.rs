@@ -12,50 +12,66 @@
 //!   `.suy()`, according to the combination of flags and options obtained
 //!   above.
 
+mod audit;
+mod builder;
+mod bundle;
+mod cache;
+mod chunk;
 mod cmd;
+mod compat;
 mod config;
+mod daemon;
+mod detect;
+mod diagnose;
+mod guard;
+mod history;
+mod hooks;
+mod hosts;
+mod local;
+mod lock;
+mod logging;
+mod man;
+mod manifest;
+mod news;
+mod normalize;
+mod outdated;
+mod partial;
+mod partial_upgrade;
+mod pick;
+mod pin;
+mod policy;
+mod preflight;
+mod print_format;
+mod reload;
+mod resume;
+mod retry;
+mod services;
+mod settings;
+mod shell_aliases;
+mod size;
+mod snapshot;
+mod space;
+mod stats;
+mod summary;
+mod top;
+mod translate;
+mod tree;
+mod undo;
+mod versioned;
+mod wizard;
 
 pub use self::cmd::Pacaptr;
-pub(crate) use self::config::Config;
-use crate::{
-    exec::is_exe,
-    pm::{
-        Apk, Apt, Brew, Choco, Conda, Dnf, Emerge, Pip, Pm, Port, Scoop, Tlmgr, Unknown, Xbps,
-        Zypper,
-    },
+pub use self::{builder::PmBuilder, config::Config};
+pub(crate) use self::{
+    config::{Backend, ENV_PREFIX},
+    history::is_mutating,
+    logging::EXEC_TARGET,
+};
+use crate::exec::interop::{self, Interop};
+use crate::pm::{
+    Apk, Apt, Brew, Choco, Conda, CustomPm, Dnf, Emerge, Pip, Pm, Port, Scoop, Tlmgr, Unknown,
+    Xbps, Zypper,
 };
-
-/// Detects the name of the package manager to be used in auto dispatch.
-#[must_use]
-fn detect_pm_str<'s>() -> &'s str {
-    let pairs: &[(&str, &str)] = match () {
-        _ if cfg!(target_os = "windows") => &[("scoop", ""), ("choco", "")],
-
-        _ if cfg!(target_os = "macos") => &[
-            ("brew", "/usr/local/bin/brew"),
-            ("port", "/opt/local/bin/port"),
-            ("apt", "/opt/procursus/bin/apt"),
-        ],
-
-        _ if cfg!(target_os = "ios") => &[("apt", "/usr/bin/apt")],
-
-        _ if cfg!(target_os = "linux") => &[
-            ("apk", "/sbin/apk"),
-            ("apt", "/usr/bin/apt"),
-            ("emerge", "/usr/bin/emerge"),
-            ("dnf", "/usr/bin/dnf"),
-            ("xbps-install", "/usr/bin/xbps-install"),
-            ("zypper", "/usr/bin/zypper"),
-        ],
-
-        _ => &[],
-    };
-
-    pairs
-        .iter()
-        .find_map(|(name, path)| is_exe(name, path).then(|| *name))
-        .unwrap_or("unknown")
-}
 
 impl From<Config> for Box<dyn Pm> {
     /// Generates the `Pm` instance according it's name, feeding it with the
@@ -63,10 +79,25 @@ impl From<Config> for Box<dyn Pm> {
     fn from(mut cfg: Config) -> Self {
         // If the `Pm` to be used is not stated in any config,
         // we should fall back to automatic detection and overwrite `cfg`.
-        let pm = cfg.default_pm.get_or_insert_with(|| detect_pm_str().into());
+        let pm = cfg
+            .default_pm
+            .get_or_insert_with(|| detect::pm_str(&cfg.detect_priority).into())
+            .clone();
+        // `wsl:apt` runs `apt` in the default WSL distro from Windows, while the
+        // Windows package managers are run through the interop layer from WSL.
+        let pm = if let Some(pm) = pm.strip_prefix("wsl:") {
+            cfg.interop = Interop::Wsl;
+            cfg.default_pm = Some(pm.into());
+            pm.to_owned()
+        } else {
+            if matches!(pm.as_str(), "choco" | "scoop") && interop::is_wsl() {
+                cfg.interop = Interop::Windows;
+            }
+            pm
+        };
 
         #[allow(clippy::match_single_binding)]
-        match pm as _ {
+        match pm.as_str() {
             // Chocolatey
             "choco" => Choco::new(cfg).boxed(),
 
@@ -86,7 +117,7 @@ impl From<Config> for Box<dyn Pm> {
             "apk" => Apk::new(cfg).boxed(),
 
             // Dnf for RedHat
-            "dnf" => Dnf::new(cfg).boxed(),
+            "dnf" | "dnf5" => Dnf::new(cfg).boxed(),
 
             // Portage for Gentoo
             "emerge" => Emerge::new(cfg).boxed(),
@@ -110,10 +141,10 @@ impl From<Config> for Box<dyn Pm> {
 
             // Test-only mock package manager
             #[cfg(test)]
-            "mockpm" => {
-                use self::cmd::tests::MockPm;
-                MockPm { cfg }.boxed()
-            }
+            crate::pm::testing::MockPm::NAME => self::cmd::tests::mock_pm(cfg).boxed(),
+
+            // Custom package manager X defined in the config
+            x if CustomPm::is_defined(x, &cfg) => CustomPm::new(x.into(), cfg).boxed(),
 
             // Unknown package manager X
             x => Unknown::new(x).boxed(),
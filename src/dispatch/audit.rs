@@ -0,0 +1,105 @@
+//! The security advisories affecting the installed packages, as shown by
+//! `pacaptr audit`.
+//!
+//! The advisories are listed by the native tooling of each package manager
+//! with [`PmBase::advisories`](crate::pm::PmBase::advisories), eg. `debsecan`
+//! for `apt`, `dnf updateinfo` or `zypper list-patches`, then summed up by
+//! package.
+
+use std::collections::BTreeMap;
+
+use itertools::{chain, Itertools};
+
+use crate::{
+    error::Result,
+    exec::pager::{self, Pager},
+    pm::{Advisory, Pm},
+};
+
+/// Renders the advisories `advs` grouped by package, marking the packages
+/// which can be fixed by an upgrade, with a line summing them up.
+#[must_use]
+fn render(advs: &[Advisory]) -> String {
+    if advs.is_empty() {
+        return "No known advisory affects the packages installed".into();
+    }
+    let by_pkg: BTreeMap<&str, Vec<&Advisory>> =
+        advs.iter().fold(BTreeMap::new(), |mut by_pkg, adv| {
+            by_pkg.entry(adv.pkg.as_str()).or_default().push(adv);
+            by_pkg
+        });
+    let width = by_pkg.keys().map(|pkg| pkg.len()).max().unwrap_or(0);
+    let body = by_pkg.iter().map(|(pkg, advs)| {
+        let ids = advs
+            .iter()
+            .map(|adv| match adv.severity.as_str() {
+                "" => adv.id.clone(),
+                severity => format!("{} ({severity})", adv.id),
+            })
+            .join(", ");
+        let fix = if advs.iter().any(|adv| adv.fixed) {
+            "  [upgrade available]"
+        } else {
+            ""
+        };
+        format!("{pkg:<width$}  {ids}{fix}")
+    });
+    let fixable = by_pkg
+        .values()
+        .filter(|advs| advs.iter().any(|adv| adv.fixed))
+        .count();
+    let summary = format!(
+        "{} advisories affect {} installed packages, {fixable} of which can be fixed by upgrading",
+        advs.len(),
+        by_pkg.len(),
+    );
+    chain!(body, [String::new(), summary]).join("\n")
+}
+
+/// Prints the security advisories affecting the packages `kws` installed
+/// with `pm`, or all the packages installed if `kws` is empty.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`](crate::error::Error)
+/// when `pm` has no security tooling, or any error returned by it.
+pub(crate) async fn show(pm: &dyn Pm, kws: &[&str], flags: &[&str]) -> Result<()> {
+    let mut advs = pm.advisories(flags).await?;
+    if !kws.is_empty() {
+        advs.retain(|adv| kws.contains(&adv.pkg.as_str()));
+    }
+    let _pager = Pager::spawn(pm.cfg().no_pager);
+    pager::print(&render(&advs));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendered() {
+        let adv = |id: &str, pkg: &str, severity: &str, fixed| Advisory {
+            id: id.into(),
+            pkg: pkg.into(),
+            severity: severity.into(),
+            fixed,
+        };
+        let table = render(&[
+            adv("CVE-2023-38545", "curl", "high", true),
+            adv("CVE-2023-4039", "gcc-12", "", false),
+            adv("CVE-2023-38546", "curl", "low", false),
+        ]);
+        assert_eq!(
+            table,
+            indoc::indoc! {"
+                curl    CVE-2023-38545 (high), CVE-2023-38546 (low)  [upgrade available]
+                gcc-12  CVE-2023-4039
+
+                3 advisories affect 2 installed packages, 1 of which can be fixed by upgrading"}
+        );
+        assert_eq!(
+            render(&[]),
+            "No known advisory affects the packages installed"
+        );
+    }
+}
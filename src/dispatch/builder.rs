@@ -0,0 +1,262 @@
+//! Building a [`Pm`] when `pacaptr` is used as a library, eg. by a
+//! provisioning tool which would rather not shell out to it.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use tap::prelude::*;
+
+use crate::{
+    dispatch::Config,
+    error::Result,
+    exec::executor::{Executor, Shared},
+    pm::Pm,
+};
+
+/// A builder of the [`Pm`] to be used, which is detected like `pacaptr` does
+/// unless set with [`using`](PmBuilder::using).
+///
+/// # Examples
+/// ```no_run
+/// # async fn run() -> pacaptr::error::Result<()> {
+/// use pacaptr::PmBuilder;
+///
+/// let pm = PmBuilder::new().using("apt").no_confirm(true).build();
+/// pm.refresh().await?;
+/// for run in pm.install(&["curl", "fish"]).await? {
+///     println!("{} exited with {}", run.cmd.args.join(" "), run.output.code);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[must_use]
+#[derive(Clone, Debug, Default)]
+pub struct PmBuilder {
+    cfg: Config,
+}
+
+impl From<Config> for PmBuilder {
+    fn from(cfg: Config) -> Self {
+        PmBuilder { cfg }
+    }
+}
+
+impl PmBuilder {
+    /// Makes a new [`PmBuilder`] with the default [`Config`].
+    pub fn new() -> Self {
+        PmBuilder::default()
+    }
+
+    /// Makes a new [`PmBuilder`] with the config file of `pacaptr`, if any.
+    ///
+    /// # Errors
+    /// Returns an [`Error::ConfigError`](crate::error::Error::ConfigError) if
+    /// the config file cannot be parsed.
+    pub fn load() -> Result<Self> {
+        Config::try_load().map(PmBuilder::from)
+    }
+
+    /// Sets the package manager to be used, eg. `apt`.
+    pub fn using(self, pm: impl Into<String>) -> Self {
+        PmBuilder {
+            cfg: Config {
+                default_pm: Some(pm.into()),
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Performs a dry run, only printing the commands which would change the
+    /// system, or simulating them where the package manager can.
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        PmBuilder {
+            cfg: Config {
+                dry_run,
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Answers yes to every question.
+    pub fn no_confirm(self, no_confirm: bool) -> Self {
+        PmBuilder {
+            cfg: Config {
+                no_confirm,
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Leaves the packages already installed as they are instead of
+    /// reinstalling them.
+    pub fn needed(self, needed: bool) -> Self {
+        PmBuilder {
+            cfg: Config { needed, ..self.cfg },
+        }
+    }
+
+    /// Sets the extra flags always passed to the package manager, eg.
+    /// `["--verbose"]`.
+    pub fn flags(self, flags: &[impl AsRef<str>]) -> Self {
+        PmBuilder {
+            cfg: Config {
+                flags: flags.iter().map(|s| s.as_ref().into()).collect(),
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Removes the cache after installing packages.
+    pub fn no_cache(self, no_cache: bool) -> Self {
+        PmBuilder {
+            cfg: Config {
+                no_cache,
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Sets the command used to run commands as `root`, eg. `doas`, instead of
+    /// the first of `sudo`, `doas`, `run0` and `pkexec` found.
+    pub fn sudo(self, sudo: impl Into<String>) -> Self {
+        PmBuilder {
+            cfg: Config {
+                sudo: Some(sudo.into()),
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Runs commands as `root` even if we are `root` already or no command to
+    /// do so is found (if `true`), or never (if `false`), instead of detecting
+    /// it.
+    pub fn force_sudo(self, force_sudo: bool) -> Self {
+        PmBuilder {
+            cfg: Config {
+                force_sudo: Some(force_sudo),
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Terminates the commands of the package manager after `timeout`, in
+    /// whole seconds.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        PmBuilder {
+            cfg: Config {
+                timeout: Some(timeout.as_secs()),
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Operates on the system under the directory `root` instead of `/`, eg.
+    /// a chroot being built, where the package manager supports it.
+    pub fn root(self, root: impl Into<PathBuf>) -> Self {
+        PmBuilder {
+            cfg: Config {
+                root: Some(root.into()),
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Saves the packages downloaded by [`PmSync::sw`](crate::pm::PmSync::sw)
+    /// to the directory `dir` instead of the cache of the package manager.
+    pub fn download_dir(self, dir: impl Into<PathBuf>) -> Self {
+        PmBuilder {
+            cfg: Config {
+                download_dir: Some(dir.into()),
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Runs the commands of the package manager with `executor` instead of
+    /// spawning them, eg. to capture their output or mock them.
+    ///
+    /// The package manager is still detected on this machine unless set with
+    /// [`using`](PmBuilder::using), and `dnf` is never taken for `dnf5`, so
+    /// both should be set when the commands run elsewhere.
+    pub fn executor(self, executor: impl Executor + 'static) -> Self {
+        PmBuilder {
+            cfg: Config {
+                executor: Some(Shared(Arc::new(executor))),
+                ..self.cfg
+            },
+        }
+    }
+
+    /// Builds the [`Pm`].
+    #[must_use]
+    pub fn build(self) -> Box<dyn Pm> {
+        self.cfg.conv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        error::Error,
+        exec::executor::{CmdLine, CmdOutput},
+    };
+
+    /// Records the commands run, failing the ones installing `missing`.
+    #[derive(Debug, Default)]
+    struct Recorder(Arc<Mutex<Vec<Vec<String>>>>);
+
+    #[async_trait]
+    impl Executor for Recorder {
+        async fn exec(&self, cmd: &CmdLine) -> Result<CmdOutput> {
+            self.0.lock().unwrap().push(cmd.args.clone());
+            let code = i32::from(cmd.args.iter().any(|arg| arg == "missing"));
+            Ok(CmdOutput {
+                code,
+                output: b"E: Unable to locate package".to_vec(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn executed() {
+        let cmds = Arc::default();
+        let pm = PmBuilder::new()
+            .using("apt")
+            .no_confirm(true)
+            .executor(Recorder(Arc::clone(&cmds)))
+            .build();
+        assert_eq!(pm.name(), "apt");
+
+        let runs = pm.install(&["curl"]).await.unwrap();
+        let cmd = cmds.lock().unwrap().pop().unwrap();
+        assert!(cmd.ends_with(&["curl".to_owned()]));
+        assert!(cmd.contains(&"install".to_owned()));
+        let run = runs.last().unwrap();
+        assert_eq!(run.cmd.args, cmd);
+        assert_eq!(run.output.code, 0);
+        assert_eq!(run.output.output, b"E: Unable to locate package");
+
+        let err = pm.install(&["missing"]).await.unwrap_err();
+        assert!(matches!(err, Error::CmdStatusCodeError { code: 1, .. }));
+    }
+
+    #[test]
+    fn settings() {
+        let pm = PmBuilder::new()
+            .using("apk")
+            .no_cache(true)
+            .sudo("doas")
+            .timeout(Duration::from_secs(90))
+            .root("/mnt")
+            .build();
+        let cfg = pm.cfg();
+        assert!(cfg.no_cache);
+        assert_eq!(cfg.sudo.as_deref(), Some("doas"));
+        assert_eq!(cfg.timeout, Some(90));
+        assert_eq!(cfg.root, Some(PathBuf::from("/mnt")));
+    }
+}
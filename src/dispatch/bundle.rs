@@ -0,0 +1,282 @@
+//! Package lists in the style of a `Brewfile`, for provisioning new machines.
+//!
+//! `pacaptr export <file>` writes the explicitly installed packages of the
+//! current package manager to the list, and `pacaptr import <file>` installs
+//! them all, while `pacaptr diff <file>` shows (and with `--apply`, makes) the
+//! changes needed for the packages installed to match the list. A single list
+//! can hold the packages of several package managers:
+//!
+//! ```toml
+//! [apt]
+//! packages = ["curl", "git"]
+//!
+//! [brew]
+//! packages = ["ripgrep"]
+//! ```
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use colored::Colorize;
+use itertools::{chain, Itertools};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dispatch::{chunk, guard::Guard, history},
+    error::{Error, Result},
+    pm::Pm,
+    print::{self, PROMPT_INFO},
+};
+
+/// A package list, keyed by the names of the package managers.
+type Bundle = BTreeMap<String, Section>;
+
+/// The packages of a package manager in a [`Bundle`].
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Section {
+    #[serde(default)]
+    packages: Vec<String>,
+}
+
+/// Checks if the package list at `path` should be written in JSON rather
+/// than TOML.
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+/// Parses a package list at `path` from `s`.
+fn parse(path: &Path, s: &str) -> Result<Bundle> {
+    let res = if is_json(path) {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(s).map_err(|e| e.to_string())
+    };
+    res.map_err(|e| Error::OtherError(format!("Failed to parse package list: {e}")))
+}
+
+/// Renders a package list to be written to `path`.
+fn render(path: &Path, bundle: &Bundle) -> Result<String> {
+    let res = if is_json(path) {
+        serde_json::to_string_pretty(bundle).map_err(|e| e.to_string())
+    } else {
+        toml::to_string(bundle).map_err(|e| e.to_string())
+    };
+    res.map_err(|e| Error::OtherError(format!("Failed to serialize package list: {e}")))
+}
+
+/// Writes the explicitly installed packages of `pm` to the package list at
+/// `path`, keeping the packages of the other package managers if it already
+/// exists.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] when `pm` cannot list
+/// the explicitly installed packages, or an [`Error::OtherError`] when the
+/// existing package list cannot be parsed.
+pub(crate) async fn export(pm: &dyn Pm, path: &Path) -> Result<()> {
+    let packages = pm.explicit().await?.into_iter().sorted().dedup().collect();
+    let mut bundle = match fs::read_to_string(path) {
+        Ok(s) => parse(path, &s)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Bundle::new(),
+        Err(e) => return Err(e.into()),
+    };
+    bundle.insert(pm.name().into(), Section { packages });
+    fs::write(path, render(path, &bundle)?)?;
+    print::print_msg(
+        &format!("Package list written to `{}`", path.display()),
+        PROMPT_INFO,
+    );
+    Ok(())
+}
+
+/// Reads the packages of `pm` in the package list at `path`.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when the package list cannot be parsed or
+/// has no packages for `pm`.
+fn read(pm: &dyn Pm, path: &Path) -> Result<Vec<String>> {
+    let mut bundle = parse(path, &fs::read_to_string(path)?)?;
+    let Section { packages } = bundle.remove(pm.name()).ok_or_else(|| {
+        Error::OtherError(format!(
+            "No packages listed for `{}` in `{}`",
+            pm.name(),
+            path.display()
+        ))
+    })?;
+    Ok(packages)
+}
+
+/// Runs the operation `options`, ie. `S` or `R`, on the packages `kws` in
+/// chunks under the [`Guard`], recording it in the history.
+async fn run(pm: &dyn Pm, options: &str, kws: &[&str], flags: &[&str]) -> Result<()> {
+    let guard = Guard::enter(pm, options, kws).await?;
+    let res = chunk::run(guard.pm(), options, kws, flags).await;
+    if !pm.cfg().dry_run {
+        history::record(pm.name(), options, kws, &res);
+    }
+    guard.leave(&res).await?;
+    res
+}
+
+/// Installs the packages of `pm` in the package list at `path`. With
+/// [`Config::needed`](crate::dispatch::Config::needed), the packages which are
+/// already installed are skipped.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when the package list cannot be parsed or
+/// has no packages for `pm`, an [`Error::PolicyViolationError`] when
+/// installing them is denied, or any error returned by
+/// [`PmSync::s`](crate::pm::PmSync::s). Large package lists are installed in
+/// chunks, see [`chunk::run`].
+pub(crate) async fn import(pm: &dyn Pm, flags: &[&str], path: &Path) -> Result<()> {
+    let mut packages = read(pm, path)?;
+    if pm.cfg().needed {
+        // Either list might be unavailable, depending on the package manager.
+        let installed = pm.installed().await.unwrap_or_default();
+        let explicit = pm.explicit().await.unwrap_or_default();
+        packages.retain(|pkg| {
+            !explicit.contains(pkg) && !installed.iter().any(|(name, _)| name == pkg)
+        });
+    }
+    if packages.is_empty() {
+        print::print_msg("Nothing to install", PROMPT_INFO);
+        return Ok(());
+    }
+    let kws = packages.iter().map(String::as_str).collect_vec();
+    run(pm, "S", &kws, flags).await
+}
+
+/// The changes needed for the packages installed to match a package list.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Diff {
+    /// The packages listed but not installed.
+    install: Vec<String>,
+    /// The packages explicitly installed but not listed.
+    remove: Vec<String>,
+}
+
+impl Diff {
+    /// Compares the packages `listed` with the ones `explicit`ly installed,
+    /// a listed package being also present if it's `installed` as a
+    /// dependency.
+    #[must_use]
+    fn new(listed: &[String], explicit: &[String], installed: &[(String, String)]) -> Self {
+        let install = listed
+            .iter()
+            .filter(|pkg| {
+                !explicit.contains(pkg) && !installed.iter().any(|(name, _)| name == *pkg)
+            })
+            .cloned()
+            .sorted()
+            .dedup()
+            .collect();
+        let remove = explicit
+            .iter()
+            .filter(|pkg| !listed.contains(pkg))
+            .cloned()
+            .sorted()
+            .dedup()
+            .collect();
+        Diff { install, remove }
+    }
+
+    /// Renders the changes, one package per line.
+    #[must_use]
+    fn render(&self, path: &Path) -> String {
+        if self.install.is_empty() && self.remove.is_empty() {
+            return format!("The packages installed match `{}`", path.display());
+        }
+        let header = format!("The packages installed differ from `{}`:", path.display());
+        let install = self
+            .install
+            .iter()
+            .map(|pkg| format!("  + {}", pkg.green()));
+        let remove = self.remove.iter().map(|pkg| format!("  - {}", pkg.red()));
+        chain!([header], install, remove).join("\n")
+    }
+}
+
+/// Shows the packages of `pm` in the package list at `path` which are not
+/// installed, and the ones explicitly installed which are not listed. With
+/// `apply`, the former are then installed and the latter removed.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] when `pm` cannot list
+/// the explicitly installed packages, an [`Error::OtherError`] when the
+/// package list cannot be parsed or has no packages for `pm`, an
+/// [`Error::PolicyViolationError`] when the changes are denied, or any error
+/// returned by [`PmSync::s`](crate::pm::PmSync::s) or
+/// [`PmRemove::r`](crate::pm::PmRemove::r).
+pub(crate) async fn diff(pm: &dyn Pm, flags: &[&str], path: &Path, apply: bool) -> Result<()> {
+    let listed = read(pm, path)?;
+    let explicit = pm.explicit().await?;
+    let installed = pm.installed().await.unwrap_or_default();
+    let diff = Diff::new(&listed, &explicit, &installed);
+    print::print_msg(&diff.render(path), PROMPT_INFO);
+    if !apply {
+        return Ok(());
+    }
+    if !diff.install.is_empty() {
+        let kws = diff.install.iter().map(String::as_str).collect_vec();
+        run(pm, "S", &kws, flags).await?;
+    }
+    if !diff.remove.is_empty() {
+        let kws = diff.remove.iter().map(String::as_str).collect_vec();
+        run(pm, "R", &kws, flags).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_roundtrip() {
+        let bundle = Bundle::from([
+            (
+                "apt".into(),
+                Section {
+                    packages: vec!["curl".into(), "git".into()],
+                },
+            ),
+            (
+                "brew".into(),
+                Section {
+                    packages: vec!["ripgrep".into()],
+                },
+            ),
+        ]);
+        for file in ["pkgs.toml", "pkgs.JSON"] {
+            let path = Path::new(file);
+            let s = render(path, &bundle).unwrap();
+            assert_eq!(s.starts_with('{'), is_json(path));
+            assert_eq!(parse(path, &s).unwrap(), bundle);
+        }
+        let s = render(Path::new("pkgs.toml"), &bundle).unwrap();
+        assert!(s.starts_with("[apt]\npackages = [\"curl\", \"git\"]\n"));
+        assert!(parse(Path::new("pkgs.toml"), "[apt]\npackages = 42").is_err());
+    }
+
+    #[test]
+    fn diffed() {
+        let pkgs = |pkgs: &[&str]| pkgs.iter().map(|&pkg| pkg.to_owned()).collect_vec();
+        let diff = Diff::new(
+            &pkgs(&["git", "curl", "libssl3"]),
+            &pkgs(&["git", "vim", "htop"]),
+            &[("libssl3".into(), "3.0.13".into())],
+        );
+        assert_eq!(diff.install, ["curl"]);
+        assert_eq!(diff.remove, ["htop", "vim"]);
+
+        let path = Path::new("pkgs.toml");
+        let rendered = diff.render(path);
+        let lines = rendered.lines().collect_vec();
+        assert_eq!(lines[0], "The packages installed differ from `pkgs.toml`:");
+        assert!(lines[1].starts_with("  + ") && lines[1].contains("curl"));
+        assert!(lines[3].starts_with("  - ") && lines[3].contains("vim"));
+        assert_eq!(
+            Diff::default().render(path),
+            "The packages installed match `pkgs.toml`"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! The package cache of each package manager, as shown by `pacaptr cache
+//! info`, and the space freed by cleaning it with `-Sc` or `-Scc`.
+//!
+//! The cache directories are listed with
+//! [`PmBase::cache_dirs`](crate::pm::PmBase::cache_dirs), then sized on disk.
+
+use itertools::Itertools;
+
+use crate::{
+    dispatch::size,
+    error::Result,
+    pm::{dir_size, Pm},
+    print::{self, PROMPT_INFO},
+};
+
+/// Sizes each of the cache directories of `pm`.
+async fn sizes(pm: &dyn Pm) -> Result<Vec<(String, u64)>> {
+    Ok(pm
+        .cache_dirs()
+        .await?
+        .into_iter()
+        .map(|dir| {
+            let size = dir_size(&dir);
+            (dir.display().to_string(), size)
+        })
+        .collect())
+}
+
+/// Describes the space freed by cleaning the cache directories `rows`, either
+/// `all` of it or only the outdated packages.
+#[must_use]
+fn freed_msg(rows: &[(String, u64)], all: bool) -> String {
+    let total = size::human_size(rows.iter().map(|(_, size)| size).sum());
+    let dirs = rows.iter().map(|(dir, _)| format!("`{dir}`")).join(", ");
+    if all {
+        format!("{total} would be freed in {dirs}")
+    } else {
+        format!("Up to {total} would be freed in {dirs}")
+    }
+}
+
+/// Prints the cache directories of `pm` along with their sizes.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`](crate::error::Error)
+/// when the cache of `pm` is unknown, or any error returned by it.
+pub(crate) async fn info(pm: &dyn Pm) -> Result<()> {
+    let rows = sizes(pm).await?;
+    print::print_msg(
+        &format!("The package cache of `{}`:", pm.name()),
+        PROMPT_INFO,
+    );
+    println!("{}", size::render(&rows));
+    Ok(())
+}
+
+/// Prints the space to be freed before running `-{options}` if it cleans the
+/// cache, ie. `-Sc` or `-Scc`. Nothing is printed if the cache of `pm` is
+/// unknown.
+pub(crate) async fn preview(pm: &dyn Pm, options: &str) {
+    let all = match options {
+        "Sc" => false,
+        "Scc" | "Sccc" => true,
+        _ => return,
+    };
+    if let Ok(rows) = sizes(pm).await {
+        print::print_msg(&freed_msg(&rows, all), PROMPT_INFO);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freed() {
+        let rows = [
+            ("/var/cache/apt/archives".to_owned(), 3 << 20),
+            ("/var/cache/apk".to_owned(), 1 << 20),
+        ];
+        assert_eq!(
+            freed_msg(&rows, true),
+            "4.0 MiB would be freed in `/var/cache/apt/archives`, `/var/cache/apk`"
+        );
+        assert!(freed_msg(&rows[..1], false).starts_with("Up to 3.0 MiB would be freed"));
+    }
+}
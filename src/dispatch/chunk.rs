@@ -0,0 +1,124 @@
+//! Splitting large transactions, eg. importing hundreds of packages, into
+//! chunks, so as to stay under the command line length limit of the platform
+//! and the [`max_kws`](crate::dispatch::Config::max_kws) of the package
+//! manager.
+
+use crate::{
+    dispatch::partial,
+    error::Result,
+    pm::Pm,
+    print::{self, PROMPT_INFO},
+};
+
+/// The maximum length of the keywords passed to a single command, leaving
+/// some room for the command and the flags. The command line of a `.cmd`
+/// shim on Windows is limited to 8191 characters.
+const MAX_LEN: usize = if cfg!(windows) { 7_000 } else { 100_000 };
+
+/// Splits `kws` into chunks of at most `max_kws` keywords each, whose total
+/// length (separators included) doesn't exceed `max_len`. A single keyword
+/// longer than `max_len` makes up a chunk on its own.
+#[must_use]
+pub(crate) fn split<'k>(kws: &[&'k str], max_len: usize, max_kws: usize) -> Vec<Vec<&'k str>> {
+    let mut chunks: Vec<Vec<&str>> = vec![];
+    let mut len = 0;
+    for &kw in kws {
+        match chunks.last_mut() {
+            Some(chunk) if chunk.len() < max_kws && len + 1 + kw.len() <= max_len => {
+                chunk.push(kw);
+                len += 1 + kw.len();
+            }
+            _ => {
+                chunks.push(vec![kw]);
+                len = kw.len();
+            }
+        }
+    }
+    chunks
+}
+
+/// Splits `kws` into chunks to be passed to `pm`.
+fn split_for<'k>(pm: &dyn Pm, kws: &[&'k str]) -> Vec<Vec<&'k str>> {
+    split(kws, MAX_LEN, pm.cfg().max_kws.unwrap_or(usize::MAX).max(1))
+}
+
+/// Checks if `kws` would be split into several chunks when passed to `pm`.
+#[must_use]
+pub(crate) fn needs_split(pm: &dyn Pm, kws: &[&str]) -> bool {
+    split_for(pm, kws).len() > 1
+}
+
+/// Runs the operation `options`, one of the
+/// [splittable](partial::is_splittable) ones, on the packages `kws`, in as
+/// many chunks as needed. Each chunk is a transaction of its own, so this
+/// stops at the first one failing, after reporting the result for each chunk.
+///
+/// # Errors
+/// Returns the error of the chunk which failed, if any.
+pub(crate) async fn run(pm: &dyn Pm, options: &str, kws: &[&str], flags: &[&str]) -> Result<()> {
+    let chunks = split_for(pm, kws);
+    if chunks.len() < 2 {
+        return partial::run_one(pm, options, kws, flags).await;
+    }
+    print::print_msg(
+        &format!(
+            "Splitting {} packages into {} chunks",
+            kws.len(),
+            chunks.len()
+        ),
+        PROMPT_INFO,
+    );
+    let total = chunks.len();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let res = partial::run_one(pm, options, chunk, flags).await;
+        let (first, last) = (chunk[0], chunk[chunk.len() - 1]);
+        let status = match &res {
+            Ok(()) => "ok".to_owned(),
+            Err(e) => format!("failed: {e}"),
+        };
+        print::print_msg(
+            &format!(
+                "Chunk {}/{total} ({} packages, `{first}`..`{last}`): {status}",
+                i + 1,
+                chunk.len()
+            ),
+            PROMPT_INFO,
+        );
+        if res.is_err() {
+            let skipped = chunks[i + 1..].iter().map(Vec::len).sum::<usize>();
+            if skipped > 0 {
+                print::print_msg(
+                    &format!("Skipped the remaining {skipped} packages"),
+                    PROMPT_INFO,
+                );
+            }
+            return res;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks() {
+        let kws = ["curl", "git", "ripgrep", "fd", "bat"];
+        assert_eq!(split(&kws, 1000, usize::MAX), [kws.to_vec()]);
+        assert_eq!(
+            split(&kws, 1000, 2),
+            [vec!["curl", "git"], vec!["ripgrep", "fd"], vec!["bat"]]
+        );
+        assert_eq!(
+            split(&kws, 12, usize::MAX),
+            [vec!["curl", "git"], vec!["ripgrep", "fd"], vec!["bat"]]
+        );
+        assert_eq!(
+            split(&["a-very-long-name", "x"], 4, 10),
+            [vec!["a-very-long-name"], vec!["x"]]
+        );
+        assert!(split(&[], 10, 10).is_empty());
+        assert_eq!(split(&kws, 1000, 2).concat(), kws);
+    }
+}
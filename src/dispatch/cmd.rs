@@ -1,18 +1,45 @@
 //! Definitions for command line argument mapping and dispatching.
 
-use clap::{self, Parser};
-use itertools::Itertools;
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use clap::{self, Parser, Subcommand};
+use itertools::{chain, Itertools};
 use tap::prelude::*;
 use tokio::task;
 use tt_call::tt_call;
 
 use crate::{
-    dispatch::Config,
+    dispatch::{
+        audit, bundle, cache, chunk, compat, daemon, detect, diagnose, history, hooks, hosts,
+        local, lock::Lock, logging, man, manifest, news, normalize, outdated, partial,
+        partial_upgrade, pick, pin, preflight, print_format, resume, retry, services, settings,
+        shell_aliases, size, snapshot, space, stats, summary, top, translate, tree, undo,
+        versioned, wizard, Config, ENV_PREFIX,
+    },
     error::{Error, Result},
+    exec::{container::Container, pager::Pager, record::Recording, signal, status_code},
     methods,
-    pm::Pm,
+    pm::{Dnf, Pm},
+    print::{self, ColorChoice},
 };
 
+/// The flags of the same operation which exclude each other, eg. `-Qp` and
+/// `-Qu`, with the reason why.
+static CONFLICTING_FLAGS: &[(char, char, char, &str)] = &[
+    ('Q', 'd', 'e', "dependencies are not explicitly installed"),
+    ('Q', 'p', 'u', "a package file cannot be out of date"),
+    ('S', 'c', 'u', "cleaning the cache doesn't upgrade anything"),
+    ('S', 's', 'u', "searching doesn't upgrade anything"),
+    ('S', 's', 'w', "searching doesn't download anything"),
+];
+
 /// The command line options to be collected.
 #[derive(Debug, Parser)]
 #[clap(
@@ -21,6 +48,8 @@ use crate::{
     about = clap::crate_description!(),
     subcommand_required = true,
     arg_required_else_help = true,
+    // The `help` subcommand clashes with the global `KEYWORDS`.
+    disable_help_subcommand = true,
 )]
 #[allow(clippy::struct_excessive_bools)]
 pub struct Pacaptr {
@@ -59,6 +88,131 @@ pub struct Pacaptr {
     #[clap(global = true, long = "no-cache", visible_alias = "nocache")]
     no_cache: bool,
 
+    /// When an installation fails, analyze the error output to find out the
+    /// root cause and suggest some fixes.
+    #[clap(global = true, long = "root-cause")]
+    root_cause: bool,
+
+    /// Run commands as `root` with this, eg. `doas` or `pkexec`, instead of
+    /// the first of `sudo`, `doas`, `run0` and `pkexec` found.
+    #[clap(global = true, long = "sudo-cmd", value_name = "cmd")]
+    sudo_cmd: Option<String>,
+
+    /// With `-S`, `-Sw`, `-U` or `-R`, handle the given packages one by one,
+    /// carrying on when one of them fails, eg. because it's not found, and
+    /// report the result for each package at the end.
+    #[clap(global = true, long = "skip-missing")]
+    skip_missing: bool,
+
+    /// Run `-S`, `-Sw` and `-Sy` again up to this many times, waiting longer
+    /// each time, when they fail because of the network.
+    #[clap(global = true, long = "retries", value_name = "n")]
+    retries: Option<u32>,
+
+    /// Terminate the commands run by the package manager after this many
+    /// seconds, eg. when a mirror hangs.
+    #[clap(global = true, long = "timeout", value_name = "secs")]
+    timeout: Option<u64>,
+
+    /// Operate on the system under this directory instead of `/`, eg. a
+    /// chroot or an image being built.
+    #[clap(
+        global = true,
+        long = "root",
+        visible_alias = "sysroot",
+        value_name = "dir"
+    )]
+    root: Option<PathBuf>,
+
+    /// Run the operation over SSH on the given host instead, eg.
+    /// `admin@web1`. Can be given several times.
+    #[clap(
+        global = true,
+        long = "host",
+        value_name = "user@server",
+        multiple_occurrences = true,
+        number_of_values = 1,
+        parse(try_from_str = parse_host)
+    )]
+    hosts: Vec<String>,
+
+    /// Run the operation in the given running container instead, with
+    /// `docker exec` or `podman exec`. Its package manager is detected from
+    /// its `os-release`, unless set with `--using`.
+    #[clap(
+        global = true,
+        long = "container",
+        value_name = "name",
+        conflicts_with = "hosts"
+    )]
+    container: Option<String>,
+
+    /// Don't take the lock preventing another `pacaptr` instance from
+    /// changing the installed packages at the same time.
+    #[clap(global = true, long = "no-lock")]
+    no_lock: bool,
+
+    /// With `brew`, install, remove and upgrade casks rather than formulae.
+    #[clap(global = true, long = "cask")]
+    cask: bool,
+
+    /// With `zypper`, apply the patches, eg. the security fixes, rather than
+    /// upgrading all the packages with `-Su`, and list them with `-Qu`.
+    #[clap(global = true, long = "patch")]
+    patch: bool,
+
+    /// Run large operations even on battery power or a metered connection,
+    /// if configured to be refused.
+    #[clap(global = true, long = "force")]
+    force: bool,
+
+    /// Skip the setup wizard offered on the first run without a config file.
+    #[clap(global = true, long = "no-wizard")]
+    no_wizard: bool,
+
+    /// Don't pipe the long outputs of `-Ss`, `-Qi`, `-Ql` and `pacaptr
+    /// compat` through `$PAGER`.
+    #[clap(global = true, long = "no-pager")]
+    no_pager: bool,
+
+    /// Match the keywords of the searches filtered by `pacaptr` itself, eg.
+    /// `-Qs` with `brew`, literally and case-sensitively, rather than as
+    /// case-insensitive regular expressions.
+    #[clap(global = true, long = "exact")]
+    exact: bool,
+
+    /// Accept keywords beginning with `-`, which are otherwise taken as
+    /// misplaced extra flags.
+    #[clap(global = true, long = "allow-dash-keywords")]
+    allow_dash_keywords: bool,
+
+    /// Print what `pacaptr` does under the hood, eg. the package manager
+    /// detected and the commands built. Use `-vv` for even more details.
+    #[clap(global = true, short = 'v', long = "verbose", parse(from_occurrences))]
+    verbose: u8,
+
+    /// When to use colors in the output: `auto` uses them only if `stdout` is
+    /// a terminal and `NO_COLOR` is not set.
+    #[clap(
+        global = true,
+        long = "color",
+        value_name = "when",
+        value_enum,
+        default_value = "auto"
+    )]
+    color: ColorChoice,
+
+    /// Make the output friendly to screen readers and braille displays:
+    /// no colors or progress control characters, and each line beginning
+    /// with its role, eg. `INFO:`, `RUN:` or `ASK:`.
+    #[clap(global = true, long = "plain-accessible")]
+    plain_accessible: bool,
+
+    /// Reformat the output of `-Ss`, `-Si` and `-Qi` into the layout of
+    /// `pacman`, whatever the package manager.
+    #[clap(global = true, long = "normalize")]
+    normalize: bool,
+
     /// Package name or (sometimes) regex.
     #[clap(global = true, name = "KEYWORDS")]
     keywords: Vec<String>,
@@ -72,6 +226,14 @@ pub struct Pacaptr {
 #[derive(Debug, Parser)]
 #[clap(about = clap::crate_description!())]
 enum Operations {
+    /// Query the files database.
+    #[clap(short_flag = 'F', long_flag = "files")]
+    Files {
+        /// Download fresh package databases from the server.
+        #[clap(short, long = "refresh")]
+        y: bool,
+    },
+
     /// Query the package database.
     #[clap(short_flag = 'Q', long_flag = "query")]
     Query {
@@ -79,10 +241,19 @@ enum Operations {
         #[clap(short, long = "changelog")]
         c: bool,
 
+        /// Restrict or filter output to packages installed as dependencies.
+        #[clap(short, long = "deps")]
+        d: bool,
+
         /// Restrict or filter output to explicitly installed packages.
         #[clap(short, long = "explicit")]
         e: bool,
 
+        /// Display the history of the operations which changed the installed
+        /// packages, the same as `pacaptr log`.
+        #[clap(long)]
+        history: bool,
+
         /// Display information on a given package.
         #[clap(short, long = "info", parse(from_occurrences))]
         i: u32,
@@ -115,15 +286,45 @@ enum Operations {
         #[clap(short, long = "search")]
         s: bool,
 
+        /// Restrict or filter output to print only packages neither required
+        /// nor optionally required by any currently installed package.
+        #[clap(short, long = "unrequired")]
+        t: bool,
+
         /// Restrict or filter output to packages that are out-of-date on the
         /// local system.
         #[clap(short, long = "upgrades")]
         u: bool,
+
+        /// Print only the number of the packages that are out-of-date, eg.
+        /// for a status bar.
+        #[clap(long, requires = "u")]
+        count: bool,
+
+        /// Print the number saved by a recent `--count` instead if there is
+        /// one, without checking the repositories again.
+        #[clap(long, requires = "count")]
+        cached: bool,
+
+        /// Restrict output to packages installed since the given date
+        /// (`YYYY-MM-DD` or `YYYY-MM-DD HH:MM`) or duration ago (eg. `7d`).
+        #[clap(long, value_name = "date", parse(try_from_str = parse_date))]
+        since: Option<SystemTime>,
+
+        /// Restrict output to packages installed before the given date
+        /// (`YYYY-MM-DD` or `YYYY-MM-DD HH:MM`) or duration ago (eg. `7d`).
+        #[clap(long, value_name = "date", parse(try_from_str = parse_date))]
+        before: Option<SystemTime>,
     },
 
     /// Remove package(s) from the system.
     #[clap(short_flag = 'R', long_flag = "remove")]
     Remove {
+        /// Remove all target packages, as well as all packages that depend on
+        /// one or more target packages.
+        #[clap(short, long = "cascade")]
+        c: bool,
+
         /// Ignore file backup designations.
         #[clap(short, long = "nosave")]
         n: bool,
@@ -136,6 +337,12 @@ enum Operations {
         /// required.
         #[clap(short, long = "recursive", parse(from_occurrences))]
         s: u32,
+
+        /// Print the targets with the given format instead of performing the
+        /// actual operation, where `%n`, `%v` and `%r` are replaced by their
+        /// name, version and repository.
+        #[clap(long = "print-format", value_name = "string")]
+        print_format: Option<String>,
     },
 
     /// Synchronize packages.
@@ -150,10 +357,35 @@ enum Operations {
         #[clap(short, long = "groups")]
         g: bool,
 
+        /// With `-u`, do not upgrade the given packages. Can be a
+        /// comma-separated list, and can be used more than once.
+        #[clap(
+            long,
+            value_name = "pkg",
+            multiple_occurrences = true,
+            number_of_values = 1,
+            use_value_delimiter = true
+        )]
+        ignore: Vec<String>,
+
         /// Display information on a given sync database package.
         #[clap(short, long = "info", parse(from_occurrences))]
         i: u32,
 
+        /// Display packages which require the given packages, the same as
+        /// `-ii`.
+        #[clap(
+            long = "reverse-deps",
+            visible_alias = "rdepends",
+            conflicts_with = "i"
+        )]
+        reverse_deps: bool,
+
+        /// Search for the given keywords, then pick the packages to be
+        /// installed among the results.
+        #[clap(long)]
+        interactive: bool,
+
         /// List all packages in the specified repositories.
         #[clap(short, long = "list")]
         l: bool,
@@ -181,8 +413,29 @@ enum Operations {
         /// server.
         #[clap(short, long = "refresh")]
         y: bool,
+
+        /// With `-w`, copy the downloaded package files next to the given
+        /// manifest file, which records their checksums for `-U --manifest`.
+        #[clap(long, value_name = "file")]
+        manifest: Option<PathBuf>,
+
+        /// With `-w`, save the packages to the given directory instead of the
+        /// cache of the package manager.
+        #[clap(long = "download-dir", value_name = "dir", conflicts_with = "manifest")]
+        download_dir: Option<PathBuf>,
+
+        /// Print the targets with the given format instead of performing the
+        /// actual operation, where `%n`, `%v`, `%r` and `%l` are replaced by
+        /// their name, version, repository and URL.
+        #[clap(long = "print-format", value_name = "string")]
+        print_format: Option<String>,
     },
 
+    /// Check if the given dependencies are satisfied, exiting with code 127 if
+    /// any of them is not.
+    #[clap(short_flag = 'T', long_flag = "deptest")]
+    Test {},
+
     /// Upgrade or add package(s) to the system and install the required
     /// dependencies from sync repositories.
     #[clap(short_flag = 'U', long_flag = "update")]
@@ -190,20 +443,548 @@ enum Operations {
         /// Only print the targets instead of performing the actual operation.
         #[clap(short, long = "print")]
         p: bool,
+
+        /// Verify and install the package files listed in the given manifest
+        /// file written by `-Sw --manifest`.
+        #[clap(long, value_name = "file")]
+        manifest: Option<PathBuf>,
+    },
+
+    /// Generate man pages from the command line definition.
+    #[clap(name = "man")]
+    Man {
+        /// Write `pacaptr.1` and a page for each operation to this directory
+        /// instead of printing the main page to `stdout`.
+        #[clap(long = "out-dir", value_name = "dir")]
+        out_dir: Option<PathBuf>,
+    },
+
+    /// Show a live view of an ongoing operation run by another instance.
+    #[clap(name = "top")]
+    Top {},
+
+    /// Check for updates with the given package managers (or the one in use)
+    /// every so often, sending a desktop notification when some are
+    /// available.
+    #[clap(name = "watch")]
+    Watch {
+        /// How long to wait between the checks, in minutes, hours, days or
+        /// weeks (eg. `30m`, `6h`, `1d`).
+        #[clap(
+            long,
+            value_name = "duration",
+            default_value = "6h",
+            parse(try_from_str = parse_duration)
+        )]
+        interval: Duration,
+
+        /// Write the number of updates available to this file after each
+        /// check.
+        #[clap(long = "status-file", value_name = "file")]
+        status_file: Option<PathBuf>,
+    },
+
+    /// Show the history of the operations which changed the installed
+    /// packages, optionally only those involving the given packages.
+    #[clap(name = "log")]
+    Log {},
+
+    /// Continue the last system upgrade which has failed partway, upgrading
+    /// only the packages left.
+    #[clap(name = "resume")]
+    Resume {},
+
+    /// Write the explicitly installed packages to a package list, which is
+    /// TOML unless its name ends with `.json`.
+    #[clap(name = "export")]
+    Export {
+        /// The package list to be written, updating only the packages of the
+        /// current package manager if it already exists.
+        #[clap(value_name = "FILE")]
+        file: PathBuf,
+    },
+
+    /// Install all the packages in a package list written by `pacaptr
+    /// export`.
+    #[clap(name = "import")]
+    Import {
+        /// The package list to be read.
+        #[clap(value_name = "FILE")]
+        file: PathBuf,
+    },
+
+    /// Show the packages in a package list written by `pacaptr export` which
+    /// are not installed, and the explicitly installed packages which are not
+    /// listed.
+    #[clap(name = "diff")]
+    Diff {
+        /// The package list to be compared with.
+        #[clap(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Install the packages missing and remove the ones not listed, so
+        /// that the packages installed match the package list.
+        #[clap(long)]
+        apply: bool,
+    },
+
+    /// Convert the package manager aliases in the shell startup files, eg.
+    /// `alias agi='sudo apt install'`, into aliases in the config file.
+    #[clap(name = "alias-import")]
+    AliasImport {},
+
+    /// Show the package managers detected and why the one used has been
+    /// chosen.
+    #[clap(name = "doctor", visible_alias = "which")]
+    Doctor {},
+
+    /// List all the package managers supported, whether each is found and
+    /// its version, marking the one used.
+    #[clap(name = "list-pms")]
+    ListPms {},
+
+    /// Show the disk usage of the given packages, or of all the packages
+    /// installed, largest first.
+    #[clap(name = "size")]
+    Size {
+        /// Only show the given number of the largest packages.
+        #[clap(long, value_name = "N")]
+        top: Option<usize>,
+    },
+
+    /// Inspect the package cache.
+    #[clap(name = "cache")]
+    Cache {
+        #[clap(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Show the known security advisories affecting the given packages, or
+    /// all the packages installed.
+    #[clap(name = "audit")]
+    Audit {},
+
+    /// Show the operations supported by each package manager.
+    #[clap(name = "compat")]
+    Compat {
+        /// Print a JSON object mapping each package manager to the operations
+        /// it supports.
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Show the dependency tree of the given packages.
+    #[clap(name = "tree")]
+    Tree {
+        /// Only show the dependencies down to the given depth.
+        #[clap(long, value_name = "n")]
+        depth: Option<usize>,
+    },
+
+    /// Start, stop or restart the services of the given packages, or list
+    /// the services, with `brew services`, `systemctl` or `sc.exe`.
+    #[clap(name = "services")]
+    Services {
+        /// What to do with the services.
+        #[clap(value_enum)]
+        action: services::Action,
+    },
+
+    /// Pin the given packages to their installed versions, keeping them from
+    /// being upgraded, or list the packages pinned.
+    #[clap(name = "pin")]
+    Pin {},
+
+    /// Release the given packages pinned with `pacaptr pin`.
+    #[clap(name = "unpin")]
+    Unpin {},
+
+    /// Print the `pacaptr` equivalent of a package manager command, eg.
+    /// `pacaptr translate "apt-get install -y nginx"`.
+    #[clap(name = "translate")]
+    Translate {
+        /// The command to be translated, quoted as a single argument if it
+        /// has flags.
+        #[clap(value_name = "COMMAND")]
+        cmd: String,
+    },
+
+    /// Reverse the last successful operation which changed the installed
+    /// packages, after previewing it.
+    #[clap(name = "undo")]
+    Undo {},
+
+    /// Show statistics of the operations recorded when `metrics` is enabled
+    /// in the config file.
+    #[clap(name = "stats")]
+    Stats {
+        /// Show the number of runs, failures and durations of each
+        /// operation, slowest first.
+        #[clap(long, required = true)]
+        perf: bool,
+    },
+
+    /// Read and write the settings in the config file, or show where the
+    /// config files are looked for.
+    #[clap(name = "config")]
+    Config {
+        /// Show the config files looked for, in ascending order of precedence,
+        /// and which of them are loaded.
+        #[clap(long = "where")]
+        r#where: bool,
+
+        #[clap(subcommand)]
+        action: Option<ConfigAction>,
+    },
+}
+
+/// The actions of `pacaptr cache`.
+#[derive(Debug, Subcommand)]
+enum CacheAction {
+    /// Show where the package cache is and how much space it takes up.
+    #[clap(name = "info")]
+    Info {},
+}
+
+/// The actions of `pacaptr config`.
+#[derive(Debug, Subcommand)]
+enum ConfigAction {
+    /// Print the value of a setting merged from all the config files, eg.
+    /// `default_pm` or `brew.no_cache`.
+    #[clap(name = "get")]
+    Get {
+        #[clap(value_name = "KEY")]
+        key: String,
+    },
+
+    /// Change a setting in the user's config file, eg. `no_confirm true`,
+    /// keeping the rest of the file untouched.
+    #[clap(name = "set")]
+    Set {
+        #[clap(value_name = "KEY")]
+        key: String,
+        #[clap(value_name = "VALUE")]
+        value: String,
     },
+
+    /// Open the user's config file with `$VISUAL` or `$EDITOR`, checking it
+    /// afterwards.
+    #[clap(name = "edit")]
+    Edit {},
+
+    /// Write a new config file with all the settings commented out.
+    #[clap(name = "init")]
+    Init {},
+}
+
+/// Parses a duration given in minutes, hours, days or weeks, eg. `30m`, `12h`,
+/// `7d` or `2w`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    s.char_indices()
+        .last()
+        .and_then(|(i, unit)| {
+            let unit = match unit {
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 24 * 60 * 60,
+                'w' => 7 * 24 * 60 * 60,
+                _ => return None,
+            };
+            s[..i].parse::<u64>().ok()?.checked_mul(unit)
+        })
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("invalid duration `{s}`, expected eg. `30m`, `6h` or `7d`"))
+}
+
+/// Parses a host given to `--host`, which cannot start with `-` as `ssh` would
+/// take it as an option, eg. `-oProxyCommand=...`.
+fn parse_host(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    if s.is_empty() || s.starts_with('-') {
+        return Err(format!("invalid host `{s}`, expected eg. `admin@web1`"));
+    }
+    Ok(s.into())
+}
+
+/// Parses a point in time given as a local date (`YYYY-MM-DD`), a local date
+/// and time (`YYYY-MM-DD HH:MM`), or a duration ago (eg. `12h`, `7d`, `2w`).
+fn parse_date(s: &str) -> Result<SystemTime, String> {
+    let s = s.trim();
+    if let Ok(ago) = parse_duration(s) {
+        return SystemTime::now()
+            .checked_sub(ago)
+            .ok_or_else(|| format!("duration `{s}` is too long"));
+    }
+
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN)))
+        .ok()
+        .and_then(|dt| dt.and_local_timezone(Local).earliest())
+        .map(SystemTime::from)
+        .ok_or_else(|| {
+            format!("invalid date `{s}`, expected `YYYY-MM-DD`, `YYYY-MM-DD HH:MM` or eg. `7d`")
+        })
+}
+
+/// Expands the first argument after the program name in `args` if it's one of
+/// the `aliases`, eg. `pacaptr up` to `pacaptr -Suy` with `up = "-Suy"`.
+///
+/// Aliases can expand to other aliases, eg. `orphans = "-Qdt"` and
+/// `clean = "orphans -q"`, but like in a shell, an alias is never expanded
+/// twice, so that `up = "up -y"` or cycles don't recurse forever.
+fn expand_aliases(mut args: Vec<OsString>, aliases: &BTreeMap<String, String>) -> Vec<OsString> {
+    let mut seen: Vec<&str> = vec![];
+    while let Some((alias, expanded)) = args
+        .get(1)
+        .and_then(|arg| arg.to_str())
+        .and_then(|arg| aliases.get_key_value(arg))
+    {
+        if seen.contains(&alias.as_str()) {
+            break;
+        }
+        seen.push(alias);
+        let expanded = expanded
+            .split_whitespace()
+            .map(OsString::from)
+            .collect_vec();
+        args.splice(1..2, expanded);
+    }
+    args
 }
 
 impl Pacaptr {
+    /// Parses the command line arguments like [`Parser::parse`], expanding the
+    /// `aliases` in the config file first. An invalid config
+    /// file is reported later on in [`dispatch`](Pacaptr::dispatch).
+    #[must_use]
+    pub fn parse_with_aliases() -> Self {
+        let args = env::args_os().collect_vec();
+        let args = match Config::try_load() {
+            Ok(cfg) => expand_aliases(args, &cfg.aliases),
+            Err(_) => args,
+        };
+        Self::parse_from(args)
+    }
+
+    /// Checks that no keyword looks like a flag, eg. `-`, as extra flags are
+    /// to be given after `--` instead, unless
+    /// [`allow_dash_keywords`](field@Pacaptr::allow_dash_keywords) is set.
+    ///
+    /// # Errors
+    /// Returns an [`Error::ArgParseError`] when a keyword begins with `-`.
+    fn check_keywords(&self) -> Result<()> {
+        if self.allow_dash_keywords {
+            return Ok(());
+        }
+        match self.keywords.iter().find(|kw| kw.starts_with('-')) {
+            Some(kw) => Err(Error::ArgParseError {
+                msg: format!(
+                    "Keyword `{kw}` looks like a flag, extra flags should be given after `--`, \
+                     eg. `pacaptr -S curl -- {kw}`, use `--allow-dash-keywords` if it's \
+                     really a keyword"
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the flags given along with the operation `options`, eg.
+    /// `Qpu`, make sense together.
+    ///
+    /// # Errors
+    /// Returns an [`Error::ArgParseError`] naming the conflict, if any.
+    fn check_conflicts(&self, options: &str) -> Result<()> {
+        let conflict = |msg: String| Err(Error::ArgParseError { msg });
+        let mut chars = options.chars();
+        let (op, letters) = (chars.next().unwrap_or_default(), chars.as_str());
+        for &(this_op, a, b, reason) in CONFLICTING_FLAGS {
+            if this_op == op && letters.contains(a) && letters.contains(b) {
+                return conflict(format!(
+                    "`-{op}{a}` and `-{op}{b}` cannot be used together, as {reason}"
+                ));
+            }
+        }
+        if self.no_cache && !history::is_install(options) {
+            return conflict(format!(
+                "`--no-cache` cannot be used with `-{options}`, as it doesn't install anything"
+            ));
+        }
+        if self.needed && !history::is_install(options) {
+            return conflict(format!(
+                "`--needed` cannot be used with `-{options}`, as it doesn't install anything"
+            ));
+        }
+        Ok(())
+    }
+
     /// Generates current [`Config`] by merging current command line arguments
     /// and options obtained with [`clap`] with the dotfile [`Config`], which
-    /// has a lower precedence.
-    fn merge_cfg(&self, dotfile: Config) -> Config {
-        Config {
+    /// has a lower precedence. The `PACAPTR_*` environment variables, looked
+    /// up by `var`, sit in between.
+    ///
+    /// # Errors
+    /// Returns an [`Error::ConfigError`] when an environment variable cannot
+    /// be parsed.
+    fn merge_cfg(&self, dotfile: Config, var: impl Fn(&str) -> Option<String>) -> Result<Config> {
+        // The section of the package manager to be used overrides the global
+        // config, and the command line overrides both.
+        let pm = self
+            .using
+            .clone()
+            .or_else(|| var(&format!("{ENV_PREFIX}USING")))
+            .or_else(|| dotfile.default_pm.clone())
+            .unwrap_or_else(|| detect::pm_str(&dotfile.detect_priority).into());
+        // `wsl:apt` is still configured in the section of `apt`.
+        let section = pm.strip_prefix("wsl:").unwrap_or(&pm);
+        let dotfile = dotfile.with_backend(section).with_env(var)?;
+        Ok(Config {
             dry_run: self.dry_run || dotfile.dry_run,
-            needed: self.needed || dotfile.dry_run,
+            needed: self.needed || dotfile.needed,
             no_confirm: self.no_confirm || dotfile.no_confirm,
             no_cache: self.no_cache || dotfile.no_cache,
-            default_pm: self.using.clone().or(dotfile.default_pm),
+            default_pm: Some(pm),
+            sudo: self.sudo_cmd.clone().or(dotfile.sudo),
+            timeout: self.timeout.or(dotfile.timeout),
+            retries: self.retries.unwrap_or(dotfile.retries),
+            root: self.root.clone().or(dotfile.root),
+            cask: self.cask,
+            patch: self.patch,
+            no_pager: self.no_pager,
+            exact: self.exact,
+            no_lock: self.no_lock,
+            download_dir: match &self.ops {
+                Operations::Sync {
+                    download_dir: Some(dir),
+                    ..
+                } => Some(dir.clone()),
+                _ => dotfile.download_dir,
+            },
+            ..dotfile
+        })
+    }
+
+    /// Handles the options which are not simply mapped to a [`Pm`] method,
+    /// eg. `--manifest`. Returns `None` if there is no such option.
+    #[allow(clippy::too_many_lines)]
+    async fn dispatch_options(
+        &self,
+        pm: &dyn Pm,
+        options: &str,
+        kws: &[&str],
+        flags: &[&str],
+    ) -> Option<Result<()>> {
+        match &self.ops {
+            _ if self.root_cause && !history::is_install(options) => {
+                Some(Err(Error::ArgParseError {
+                    msg: format!("`--root-cause` cannot be used with `-{options}`"),
+                }))
+            }
+            Operations::Sync {
+                manifest: Some(path),
+                ..
+            } if options == "Sw" => Some(manifest::download(pm, kws, flags, path).await),
+            Operations::Update {
+                manifest: Some(path),
+                ..
+            } if options == "U" => Some(manifest::install(pm, flags, path).await),
+            Operations::Sync {
+                manifest: Some(_), ..
+            }
+            | Operations::Update {
+                manifest: Some(_), ..
+            } => Some(Err(Error::ArgParseError {
+                msg: format!("`--manifest` cannot be used with `-{options}`"),
+            })),
+            Operations::Sync {
+                interactive: true, ..
+            } => Some(match options {
+                "S" | "Ss" => pick::install(pm, kws, flags).await,
+                _ => Err(Error::ArgParseError {
+                    msg: format!("`--interactive` cannot be used with `-{options}`"),
+                }),
+            }),
+            Operations::Sync {
+                print_format: Some(fmt),
+                ..
+            }
+            | Operations::Remove {
+                print_format: Some(fmt),
+                ..
+            } => Some(print_format::run(pm, options, kws, flags, fmt).await),
+            _ if options == "U" && pm.cfg().is_local() && local::needs_download(pm, kws) => {
+                Some(local::install(pm, kws, flags).await)
+            }
+            _ if self.normalize => Some(normalize::run(pm, options, kws, flags).await),
+            _ if matches!(options, "S" | "Sw") && versioned::is_versioned(kws) => {
+                Some(versioned::run(pm, options, kws, flags, self.skip_missing).await)
+            }
+            // The packages are run one by one with `--skip-missing`, which
+            // never needs to be split.
+            _ if self.skip_missing => Some(if partial::is_splittable(options) {
+                partial::run(pm, options, kws, flags).await
+            } else {
+                Err(Error::ArgParseError {
+                    msg: format!("`--skip-missing` cannot be used with `-{options}`"),
+                })
+            }),
+            _ if partial::is_splittable(options) && chunk::needs_split(pm, kws) => {
+                Some(chunk::run(pm, options, kws, flags).await)
+            }
+            Operations::Sync { ignore, .. } if !ignore.is_empty() && !options.contains('u') => {
+                Some(Err(Error::ArgParseError {
+                    msg: format!("`--ignore` cannot be used with `-{options}`"),
+                }))
+            }
+            Operations::Sync {
+                download_dir: Some(_),
+                w: false,
+                ..
+            } => Some(Err(Error::ArgParseError {
+                msg: "`--download-dir` can only be used with `-w`".into(),
+            })),
+            _ if options == "Sw"
+                && pm.cfg().download_dir.is_some()
+                && !pm.supports_download_dir() =>
+            {
+                Some(Err(Error::OperationUnimplementedError {
+                    op: "sw --download-dir".into(),
+                    pm: pm.name().into(),
+                }))
+            }
+            Operations::Sync { u: true, .. }
+                if !pm.cfg().ignore.is_empty() && !pm.supports_ignore() =>
+            {
+                Some(Err(Error::OperationUnimplementedError {
+                    op: format!("{} --ignore", options.to_lowercase()),
+                    pm: pm.name().into(),
+                }))
+            }
+            Operations::Query {
+                count: true,
+                cached,
+                ..
+            } => Some(match options {
+                "Qu" => outdated::count(pm, kws, *cached).await,
+                _ => Err(Error::ArgParseError {
+                    msg: format!("`--count` cannot be used with `-{options}`"),
+                }),
+            }),
+            Operations::Query { history: true, .. } => Some(match options {
+                "Q" => history::show(kws),
+                _ => Err(Error::ArgParseError {
+                    msg: format!("`--history` cannot be used with `-{options}`"),
+                }),
+            }),
+            Operations::Query { since, before, .. } if since.is_some() || before.is_some() => {
+                Some(match options {
+                    "Q" => pm.q_by_date(kws, flags, *since, *before).await,
+                    _ => Err(Error::ArgParseError {
+                        msg: format!("`--since` and `--before` cannot be used with `-{options}`"),
+                    }),
+                })
+            }
+            _ => None,
         }
     }
 
@@ -212,7 +993,11 @@ impl Pacaptr {
     ///
     /// # Errors
     /// See [`Error`](crate::error::Error) for a list of possible errors.
-    #[allow(trivial_numeric_casts)]
+    #[allow(
+        trivial_numeric_casts,
+        clippy::too_many_lines,
+        clippy::single_match_else
+    )]
     async fn dispatch_from(&self, mut cfg: Config) -> Result<()> {
         /// Collect options as a `String`, eg. `-S -y -u => "Suy"`.
         ///
@@ -225,6 +1010,7 @@ impl Pacaptr {
             $( $op:ident {
                 $( mappings: [$( $key:ident -> $val:ident ), *], )?
                 $( flags: [$( $flag:ident ), *], )?
+                $( aliases: [$( $alias:ident -> $expanded:ident ), *], )?
             }, )*
         ) => {{
             let mut options = String::new();
@@ -232,6 +1018,8 @@ impl Pacaptr {
                 $( Operations::$op {
                     $( $( $key, )* )?
                     $( $( $flag, )* )?
+                    $( $( $alias, )* )?
+                    ..
                 } => {
                     options.push_str(&stringify!($op)[0..1]);
                     $( $(if $key {
@@ -240,34 +1028,65 @@ impl Pacaptr {
                     $( $(for _ in 0..($flag as u32) {
                         options.push_str(stringify!($flag));
                     })* )?
+                    $( $(if $alias {
+                        options.push_str(stringify!($expanded));
+                    })* )?
                 } )*
+                // Operations not derived from `pacman` are handled in `dispatch`.
+                _ => unreachable!("unexpected operation `{:?}`", self.ops),
             }
             options.chars().sorted_unstable().pipe(String::from_iter)
         }};}
 
-        let options = collect_options! {
+        let mut options = collect_options! {
+            Files {
+                flags: [y],
+            },
             Query {
-                flags: [c, e, i, k, l, m, o, p, s, u],
+                flags: [c, d, e, i, k, l, m, o, p, s, t, u],
             },
             Remove {
                 mappings: [p -> dry_run],
-                flags: [n, s],
+                flags: [c, n, s],
             },
             Sync {
                 mappings: [p -> dry_run],
                 flags: [c, g, i, l, s, u, w, y],
+                aliases: [reverse_deps -> ii],
             },
+            Test {},
             Update {
                 mappings: [p -> dry_run],
             },
         };
 
+        self.check_conflicts(&options)?;
+        if let Operations::Sync { ignore, .. } = &self.ops {
+            cfg.ignore.extend(ignore.iter().cloned());
+        }
+        cfg.confine_no_confirm(&options);
+        if let Operations::Sync {
+            print_format: Some(_),
+            ..
+        }
+        | Operations::Remove {
+            print_format: Some(_),
+            ..
+        } = &self.ops
+        {
+            // Resolving the targets only runs queries, which shouldn't be
+            // skipped as in a dry run.
+            cfg.dry_run = false;
+        }
+        Dnf::resolve_remote(&mut cfg).await;
         let pm = cfg.conv::<Box<dyn Pm>>();
 
         let kws = self.keywords.iter().map(|s| s as _).collect_vec();
-        let flags = self.extra_flags.iter().map(|s| s as _).collect_vec();
+        let flags = chain!(&pm.cfg().flags, &self.extra_flags)
+            .map(|s| s as _)
+            .collect_vec();
 
-        /// Call the method indicated by `options` on `pm`. That is:
+        /// Call the method indicated by `options` on the given `pm`. That is:
         ///
         /// ```rust
         /// match &options.to_lowercase() as _ {
@@ -276,91 +1095,368 @@ impl Pacaptr {
         /// }
         /// ```
         macro_rules! dispatch_match {(
+            pm = [{ $pm:expr }]
             methods = [{ $(
-                $( #[$meta:meta] )*
-                async fn $method:ident;
+                $( #[$tmeta:meta] )*
+                trait $cap:ident { $(
+                    $( #[$meta:meta] )*
+                    async fn $method:ident;
+                )* }
             )* }]
         ) => {
             match &options.to_lowercase() as _ {
-                $(stringify!($method) => pm.$method(&kws, &flags).await,)*
+                $( $(stringify!($method) => $pm.$method(&kws, &flags).await,)* )*
                 _ => Err(Error::ArgParseError {
                     msg: format!("Invalid flag combination `-{}`", &options),
                 }),
             }
         };}
 
-        // Send `methods!()` to `dispatch_match`. That is,
-        // `dispatch_match!( methods = [{ q qc qe .. }] )`.
-        tt_call! {
-            macro = [{ methods }]
-            ~~> dispatch_match
+        // The state of this machine, eg. the lock, the history, the free space
+        // or the snapshots, says nothing about a remote host or a container,
+        // where the package files given to `-U` are not found either.
+        let is_local = pm.cfg().is_local();
+        if is_local {
+            options = partial_upgrade::guard(&*pm, &options, &kws);
+        }
+        tracing::trace!(options, ?kws, ?flags, "Collected the options");
+        pm.cfg().policy.check(pm.name(), &options, &kws)?;
+        if is_local {
+            local::check(&options, &kws)?;
+        } else {
+            local::check_remote(&*pm, &options, &kws)?;
+        }
+        let _lock = if is_local {
+            Lock::acquire(&options, pm.cfg().dry_run, pm.cfg().no_lock)?
+        } else {
+            None
+        };
+        preflight::check(pm.cfg(), &options, self.force).await?;
+        if is_local {
+            space::guard(&*pm, &options, &kws, &flags).await?;
+            news::show(&*pm, &options).await;
+            cache::preview(&*pm, &options).await;
+            hooks::run(&*pm, hooks::Stage::Pre, &options, &kws).await?;
+            snapshot::take(&*pm, &options, &kws).await?;
+        }
+        let planned = if is_local {
+            resume::snapshot(&*pm, &options, &kws).await
+        } else {
+            None
+        };
+        let started = Instant::now();
+        let res = match self.dispatch_options(&*pm, &options, &kws, &flags).await {
+            Some(res) => res,
+            // Send `methods!()` to `dispatch_match`. That is,
+            // `dispatch_match!( pm = [{ pm }] methods = [{ trait PmQuery { q qc qe .. } .. }] )`.
+            None => 'run: {
+                let _pager = Pager::start(&options, pm.cfg());
+                let mut confirmed = None;
+                if let Some(previewer) = summary::previewer(&*pm, &options) {
+                    let recording = Recording::start();
+                    tt_call! {
+                        macro = [{ methods }]
+                        ~~> dispatch_match! { pm = [{ previewer }] }
+                    }?;
+                    let cmds = recording.finish();
+                    match summary::confirm(pm.name(), &cmds, pm.cfg().summary_prompt) {
+                        Some(false) => break 'run Ok(()),
+                        // The whole plan is confirmed, so the commands are
+                        // not confirmed one by one, but the package manager
+                        // still shows what it adds to them, eg. the removals.
+                        Some(true) => {
+                            confirmed = Some(
+                                Config {
+                                    confirmed: true,
+                                    ..pm.cfg().clone()
+                                }
+                                .conv::<Box<dyn Pm>>(),
+                            );
+                        }
+                        None => (),
+                    }
+                }
+                let runner = confirmed.as_deref().unwrap_or(&*pm);
+                let mut attempt = 0;
+                loop {
+                    attempt += 1;
+                    let res = tt_call! {
+                        macro = [{ methods }]
+                        ~~> dispatch_match! { pm = [{ runner }] }
+                    };
+                    let Err(e) = &res else { break res };
+                    if !retry::should_retry(&*pm, &options, e, attempt).await {
+                        break res;
+                    }
+                }
+            }
+        };
+        tracing::debug!(
+            elapsed = ?started.elapsed(),
+            ok = res.is_ok(),
+            "Finished `-{options}`"
+        );
+        if let Some(planned) = planned {
+            resume::track(&*pm, planned, &res).await;
+        }
+        if let (true, Err(e)) = (self.root_cause, &res) {
+            diagnose::explain(pm.name(), &kws, e);
+        }
+        if is_local && res.is_ok() {
+            hooks::run(&*pm, hooks::Stage::Post, &options, &kws).await?;
+        }
+        if is_local && !pm.cfg().dry_run {
+            if pm.cfg().metrics {
+                stats::record(pm.name(), &options, started.elapsed(), res.is_ok());
+            }
+            partial_upgrade::record(&*pm, &options, &res);
+            if history::is_mutating(&options) {
+                history::record(pm.name(), &options, &kws, &res);
+            }
+        }
+        if pm.cfg().raw_exit_codes {
+            res
+        } else {
+            res.map_err(|e| status_code::map_err(pm.name(), e))
         }
     }
 
     /// Runs [`dispatch_from`](Pacaptr::dispatch_from) with automatically
-    /// detected [`Config`].
+    /// detected [`Config`], handling Ctrl-C in the meantime.
     ///
     /// # Errors
     /// See [`Error`](crate::error::Error) for a list of possible errors.
-    #[allow(trivial_numeric_casts)]
+    /// [`Error::CmdInterruptedError`] is returned once the package manager has
+    /// exited if `pacaptr` has been interrupted.
     pub async fn dispatch(&self) -> Result<()> {
-        let dotfile = task::block_in_place(Config::try_load);
-        let cfg = self.merge_cfg(dotfile?);
-        self.dispatch_from(cfg).await
-    }
-}
-
-#[cfg(test)]
-pub(super) mod tests {
-    use async_trait::async_trait;
-    use once_cell::sync::Lazy;
-    use tokio::test;
-    use tt_call::tt_call;
-
-    use super::*;
-
-    pub(crate) struct MockPm {
-        pub cfg: Config,
+        print::set_color(self.color);
+        print::set_accessible(self.plain_accessible);
+        let signals = signal::forward();
+        let res = self.dispatch_ops().await;
+        signals.abort();
+        if signal::interrupted() {
+            return Err(Error::CmdInterruptedError);
+        }
+        res
     }
 
-    macro_rules! make_mock_op_body {
-        ($self:ident, $kws:ident, $flags:ident, $method:ident) => {{
-            let kws: Vec<_> = itertools::chain!($kws, $flags).collect();
-            panic!("should run: {} {:?}", stringify!($method), &kws)
-        }};
+    /// Checks that the package manager of `cfg` supports the global flags
+    /// given which are specific to some of them, eg. `--root`.
+    ///
+    /// # Errors
+    /// Returns an [`Error::OperationUnimplementedError`] naming the first flag
+    /// which is not supported.
+    fn check_supported(&self, cfg: &Config) -> Result<()> {
+        if cfg.root.is_none() && !self.cask && !self.patch {
+            return Ok(());
+        }
+        let pm = cfg.clone().conv::<Box<dyn Pm>>();
+        let op = if cfg.root.is_some() && !pm.supports_root() {
+            "--root"
+        } else if self.cask && pm.name() != "brew" {
+            "--cask"
+        } else if self.patch && pm.name() != "zypper" {
+            "--patch"
+        } else {
+            return Ok(());
+        };
+        Err(Error::OperationUnimplementedError {
+            op: op.into(),
+            pm: pm.name().into(),
+        })
     }
 
-    macro_rules! impl_pm_mock {(
-        methods = [{ $(
-            $( #[$meta:meta] )*
-            async fn $method:ident;
-        )* }]
-    ) => {
-        #[async_trait]
-        impl Pm for MockPm {
-            /// Gets the name of the package manager.
-            fn name(&self) -> &str {
-                "mockpm"
+    /// Implements [`dispatch`](Pacaptr::dispatch).
+    #[allow(trivial_numeric_casts, clippy::too_many_lines)]
+    async fn dispatch_ops(&self) -> Result<()> {
+        self.check_keywords()?;
+        match &self.ops {
+            Operations::Man { out_dir } => return man::generate(out_dir.as_deref()),
+            Operations::Top {} => return top::watch().await,
+            Operations::Translate { cmd } => {
+                // The words of an unquoted command end up in the keywords.
+                let cmd = chain!([cmd], &self.keywords).join(" ");
+                return translate::translate(&cmd);
             }
-
-            fn cfg(&self) -> &Config {
-                &self.cfg
+            Operations::Stats { .. } => return stats::perf(),
+            Operations::Config { r#where, action } => {
+                return match (r#where, action) {
+                    (true, None) => {
+                        Config::print_paths();
+                        Ok(())
+                    }
+                    (false, Some(ConfigAction::Get { key })) => settings::get(key),
+                    (false, Some(ConfigAction::Set { key, value })) => settings::set(key, value),
+                    (false, Some(ConfigAction::Edit {})) => task::block_in_place(settings::edit),
+                    (false, Some(ConfigAction::Init {})) => settings::init(),
+                    _ => Err(Error::ArgParseError {
+                        msg: "`pacaptr config` expects either `--where` or one of `get`, `set`, \
+                              `edit` and `init`"
+                            .into(),
+                    }),
+                };
+            }
+            Operations::Log {} => {
+                let kws = self.keywords.iter().map(|s| s as _).collect_vec();
+                return history::show(&kws);
             }
+            _ => (),
+        }
 
-            // * Automatically generated methods below... *
-            $( async fn $method(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-                    make_mock_op_body!(self, kws, flags, $method)
-            } )*
+        if !self.no_wizard && !self.no_confirm {
+            task::block_in_place(wizard::offer)?;
+        }
+        let mut dotfile = task::block_in_place(Config::try_load)?;
+        let is_using = env::var(format!("{ENV_PREFIX}USING")).is_ok();
+        if let Some(name) = &self.container {
+            let container = Container::find(name).await?;
+            // The package manager of this machine says nothing about that of
+            // the container.
+            if self.using.is_none() && !is_using {
+                dotfile.default_pm = Some(detect::container_pm(&container).await?.into());
+            }
+            dotfile.container = Some(container);
+        }
+        // Where the package manager to be used is set, for `pacaptr doctor`.
+        let chosen_by = if self.using.is_some() {
+            Some("`--using`")
+        } else if is_using {
+            Some("`PACAPTR_USING`")
+        } else if self.container.is_some() {
+            Some("the `os-release` of the container")
+        } else {
+            dotfile
+                .default_pm
+                .as_ref()
+                .map(|_| "`default_pm` in the config file")
+        };
+        let cfg = self.merge_cfg(dotfile, |key| env::var(key).ok())?;
+        logging::init(self.verbose, cfg.log_file.as_deref())?;
+        tracing::debug!(
+            pm = cfg.default_pm.as_deref(),
+            detected = ?detect::pms(&cfg.detect_priority),
+            "Selected the package manager"
+        );
+        tracing::debug!(?cfg, "Merged the config");
+        self.check_supported(&cfg)?;
+        let is_pacman_op = matches!(
+            self.ops,
+            Operations::Files { .. }
+                | Operations::Query { .. }
+                | Operations::Remove { .. }
+                | Operations::Sync { .. }
+                | Operations::Test {}
+                | Operations::Update { .. }
+        );
+        if (!self.hosts.is_empty() || self.container.is_some()) && !is_pacman_op {
+            return Err(Error::ArgParseError {
+                msg: "`--host` and `--container` can only be used with the operations of \
+                      `pacman`, eg. `-Syu`"
+                    .into(),
+            });
+        }
+        let flags = chain!(&cfg.flags, &self.extra_flags).cloned().collect_vec();
+        let flags = flags.iter().map(|s| s as _).collect_vec();
+        match &self.ops {
+            Operations::Resume {} => {
+                return resume::resume(&*cfg.conv::<Box<dyn Pm>>(), &flags).await
+            }
+            Operations::Undo {} => return undo::undo(&cfg, &flags).await,
+            Operations::Doctor {} => {
+                detect::doctor(&cfg, chosen_by);
+                return Ok(());
+            }
+            Operations::ListPms {} => {
+                detect::list(&cfg).await;
+                return Ok(());
+            }
+            Operations::Compat { json } => return compat::show(&cfg, *json),
+            Operations::AliasImport {} => return shell_aliases::import(&cfg),
+            Operations::Cache {
+                action: CacheAction::Info {},
+            } => return cache::info(&*cfg.conv::<Box<dyn Pm>>()).await,
+            Operations::Audit {} => {
+                let kws = self.keywords.iter().map(|s| s as _).collect_vec();
+                return audit::show(&*cfg.conv::<Box<dyn Pm>>(), &kws, &flags).await;
+            }
+            Operations::Size { top } => {
+                let kws = self.keywords.iter().map(|s| s as _).collect_vec();
+                return size::show(&*cfg.conv::<Box<dyn Pm>>(), &kws, &flags, *top).await;
+            }
+            Operations::Tree { depth } => {
+                let kws = self.keywords.iter().map(|s| s as _).collect_vec();
+                return tree::show(&*cfg.conv::<Box<dyn Pm>>(), &kws, &flags, *depth).await;
+            }
+            Operations::Watch {
+                interval,
+                status_file,
+            } => {
+                let names = self.keywords.iter().map(|s| s as _).collect_vec();
+                let reload = || self.merge_cfg(Config::try_load()?, |key| env::var(key).ok());
+                return daemon::watch(&cfg, reload, &names, *interval, status_file.as_deref())
+                    .await;
+            }
+            Operations::Services { action } => {
+                let kws = self.keywords.iter().map(|s| s as _).collect_vec();
+                return services::run(&cfg, *action, &kws).await;
+            }
+            Operations::Pin {} => {
+                let kws = self.keywords.iter().map(|s| s as _).collect_vec();
+                return pin::pin(&*cfg.conv::<Box<dyn Pm>>(), &kws, &flags).await;
+            }
+            Operations::Unpin {} => {
+                let kws = self.keywords.iter().map(|s| s as _).collect_vec();
+                return pin::unpin(&*cfg.conv::<Box<dyn Pm>>(), &kws, &flags).await;
+            }
+            Operations::Export { file } => {
+                return bundle::export(&*cfg.conv::<Box<dyn Pm>>(), file).await
+            }
+            Operations::Import { file } => {
+                return bundle::import(&*cfg.conv::<Box<dyn Pm>>(), &flags, file).await
+            }
+            Operations::Diff { file, apply } => {
+                return bundle::diff(&*cfg.conv::<Box<dyn Pm>>(), &flags, file, *apply).await
+            }
+            _ => (),
+        }
+        if self.hosts.is_empty() {
+            return self.dispatch_from(cfg).await;
+        }
+        let mut results = vec![];
+        for host in &self.hosts {
+            print::print_msg(&format!("On `{host}`"), print::PROMPT_INFO);
+            let cfg = Config {
+                host: Some(host.clone()),
+                ..cfg.clone()
+            };
+            results.push((host.as_str(), self.dispatch_from(cfg).await));
+            if signal::interrupted() {
+                break;
+            }
         }
-    };}
+        hosts::report(&results)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use once_cell::sync::Lazy;
+    use tokio::test;
 
-    tt_call! {
-        macro = [{ methods }]
-        ~~> impl_pm_mock
+    use super::*;
+    use crate::pm::testing::MockPm;
+
+    /// Makes a [`MockPm`] which panics with the method called, so that it
+    /// can be checked with `#[should_panic]` when dispatching.
+    pub(crate) fn mock_pm(cfg: Config) -> MockPm {
+        MockPm::new(cfg).respond_with(|call| {
+            let kws = chain!(&call.kws, &call.flags).collect_vec();
+            panic!("should run: {} {:?}", call.method, &kws)
+        })
     }
 
     static MOCK_CFG: Lazy<Config> = Lazy::new(|| Config {
-        default_pm: Some("mockpm".into()),
+        default_pm: Some(MockPm::NAME.into()),
         ..Config::default()
     });
 
@@ -408,6 +1504,542 @@ pub(super) mod tests {
         opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
     }
 
+    #[test]
+    #[should_panic(expected = r#"should run: fy ["bin/curl"]"#)]
+    #[allow(clippy::semicolon_if_nothing_returned)]
+    async fn simple_fy() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-Fy", "bin/curl"]));
+        let subcmd = &opt.ops;
+
+        assert!(matches!(subcmd, &Operations::Files { y } if y));
+        assert_eq!(opt.keywords, &["bin/curl"]);
+
+        opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = r#"should run: t ["curl>=7.80"]"#)]
+    #[allow(clippy::semicolon_if_nothing_returned)]
+    async fn simple_t() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "--deptest", "curl>=7.80"]));
+        let subcmd = &opt.ops;
+
+        assert!(matches!(subcmd, &Operations::Test {}));
+        assert_eq!(opt.keywords, &["curl>=7.80"]);
+
+        opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
+    }
+
+    #[test]
+    async fn qu_count() {
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr", "-Qu", "--count", "--cached"
+        ]));
+        assert!(matches!(
+            opt.ops,
+            Operations::Query {
+                u: true,
+                count: true,
+                cached: true,
+                ..
+            }
+        ));
+        let err = opt.dispatch_from(MOCK_CFG.clone()).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Operation `qu --count` is not supported by `{}`",
+                MockPm::NAME
+            )
+        );
+        assert!(Pacaptr::try_parse_from(["pacaptr", "-Q", "--count"]).is_err());
+    }
+
+    #[test]
+    async fn q_by_date() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-Q", "--since", "2w"]));
+        assert!(matches!(
+            opt.ops,
+            Operations::Query {
+                since: Some(_),
+                before: None,
+                ..
+            }
+        ));
+        let err = opt.dispatch_from(MOCK_CFG.clone()).await.unwrap_err();
+        assert!(matches!(err, Error::OperationUnimplementedError { .. }));
+
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "-Qi",
+            "--before",
+            "2022-03-01"
+        ]));
+        let err = opt.dispatch_from(MOCK_CFG.clone()).await.unwrap_err();
+        assert!(matches!(err, Error::ArgParseError { .. }));
+    }
+
+    #[test]
+    async fn services() {
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr", "services", "restart", "nginx"
+        ]));
+        assert!(matches!(
+            opt.ops,
+            Operations::Services {
+                action: services::Action::Restart
+            }
+        ));
+        assert_eq!(opt.keywords, &["nginx"]);
+    }
+
+    #[test]
+    async fn pin() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "pin", "linux-image-amd64"]));
+        assert!(matches!(opt.ops, Operations::Pin {}));
+        assert_eq!(opt.keywords, &["linux-image-amd64"]);
+
+        let pm = mock_pm(MOCK_CFG.clone());
+        let err = pin::unpin(&pm, &[], &[]).await.unwrap_err();
+        assert!(matches!(err, Error::ArgParseError { .. }));
+        let err = pin::pin(&pm, &["curl"], &[]).await.unwrap_err();
+        assert!(matches!(err, Error::OperationUnimplementedError { .. }));
+    }
+
+    #[test]
+    async fn dates() {
+        let week_ago = SystemTime::now() - Duration::from_hours(7 * 24);
+        let parsed = parse_date("1w").unwrap();
+        assert!(parsed >= week_ago && parsed < week_ago + Duration::from_mins(1));
+        assert!(parse_date("2022-03-01").unwrap() < parse_date("2022-03-01 12:30").unwrap());
+        assert!(parse_date("yesterday").is_err());
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_mins(30));
+        assert_eq!(parse_duration("6h").unwrap(), Duration::from_hours(6));
+        assert!(parse_duration("6").is_err());
+        assert!(parse_date("2022-13-01").is_err());
+    }
+
+    #[test]
+    async fn hosts() {
+        assert_eq!(parse_host("admin@web1").unwrap(), "admin@web1");
+        assert!(parse_host("-oProxyCommand=touch /tmp/pwned").is_err());
+        assert!(parse_host("").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"should run: rcs ["curl"]"#)]
+    #[allow(clippy::semicolon_if_nothing_returned)]
+    async fn simple_rsc() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-Rsc", "curl"]));
+        let subcmd = &opt.ops;
+
+        assert!(matches!(subcmd, &Operations::Remove { c, s, .. } if c && s == 1));
+        assert_eq!(opt.keywords, &["curl"]);
+
+        opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "should run: qdt []")]
+    #[allow(clippy::semicolon_if_nothing_returned)]
+    async fn simple_qtd() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-Qtd"]));
+        let subcmd = &opt.ops;
+
+        assert!(matches!(subcmd, &Operations::Query { d, t, .. } if d && t));
+        assert!(opt.keywords.is_empty());
+
+        opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = r#"should run: sii ["curl"]"#)]
+    #[allow(clippy::semicolon_if_nothing_returned)]
+    async fn simple_reverse_deps() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-S", "--rdepends", "curl"]));
+        let subcmd = &opt.ops;
+
+        assert!(
+            matches!(subcmd, &Operations::Sync { reverse_deps, i, .. } if reverse_deps && i == 0)
+        );
+        assert_eq!(opt.keywords, &["curl"]);
+
+        opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "should run: su")]
+    #[allow(clippy::semicolon_if_nothing_returned)]
+    async fn simple_ignore() {
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "-Su",
+            "--ignore",
+            "curl,wget",
+            "--ignore=git",
+        ]));
+        let subcmd = &opt.ops;
+
+        assert!(
+            matches!(subcmd, Operations::Sync { u, ignore, .. } if *u && ignore == &["curl", "wget", "git"])
+        );
+        assert!(opt.keywords.is_empty());
+
+        let err = Pacaptr::parse_from(["pacaptr", "-S", "--ignore", "curl", "wget"])
+            .dispatch_from(MOCK_CFG.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ArgParseError { .. }));
+
+        opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = r#"should run: s ["curl"]"#)]
+    #[allow(clippy::semicolon_if_nothing_returned)]
+    async fn simple_root_cause() {
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "-S",
+            "--root-cause",
+            "curl"
+        ]));
+
+        assert!(opt.root_cause);
+        assert_eq!(opt.keywords, &["curl"]);
+
+        let err = Pacaptr::parse_from(["pacaptr", "-Ss", "--root-cause", "curl"])
+            .dispatch_from(MOCK_CFG.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ArgParseError { .. }));
+
+        opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
+    }
+
+    #[test]
+    async fn simple_export() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "export", "pkgs.toml"]));
+        assert!(matches!(&opt.ops, Operations::Export { file } if file.as_os_str() == "pkgs.toml"));
+        assert!(opt.keywords.is_empty());
+
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "import",
+            "--needed",
+            "pkgs.json"
+        ]));
+        assert!(matches!(&opt.ops, Operations::Import { file } if file.as_os_str() == "pkgs.json"));
+        assert!(opt.needed && opt.keywords.is_empty());
+    }
+
+    #[test]
+    async fn dash_keywords() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-S", "curl", "-"]));
+        assert!(matches!(
+            opt.check_keywords(),
+            Err(Error::ArgParseError { msg }) if msg.contains("`-`")
+        ));
+
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "-Ss",
+            "--allow-dash-keywords",
+            "-"
+        ]));
+        assert!(opt.check_keywords().is_ok());
+
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-S", "curl", "--", "-y"]));
+        assert!(opt.check_keywords().is_ok());
+    }
+
+    #[test]
+    async fn simple_config() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "config", "--where"]));
+        assert!(matches!(
+            opt.ops,
+            Operations::Config {
+                r#where: true,
+                action: None
+            }
+        ));
+
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "config",
+            "get",
+            "brew.no_cache"
+        ]));
+        assert!(matches!(
+            &opt.ops,
+            Operations::Config { action: Some(ConfigAction::Get { key }), .. } if key == "brew.no_cache"
+        ));
+        assert!(opt.keywords.is_empty());
+
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "config",
+            "set",
+            "no_confirm",
+            "true"
+        ]));
+        assert!(matches!(
+            &opt.ops,
+            Operations::Config { action: Some(ConfigAction::Set { key, value }), .. }
+                if key == "no_confirm" && value == "true"
+        ));
+    }
+
+    #[test]
+    async fn aliases() {
+        let aliases = BTreeMap::from([("up".to_owned(), "-Suy --yes".to_owned())]);
+        let args = ["pacaptr", "up", "--dry-run"].map(OsString::from).to_vec();
+        let opt = dbg!(Pacaptr::parse_from(expand_aliases(args, &aliases)));
+        assert!(matches!(
+            opt.ops,
+            Operations::Sync {
+                u: true,
+                y: true,
+                ..
+            }
+        ));
+        assert!(opt.no_confirm && opt.dry_run);
+
+        let aliases = BTreeMap::from([
+            ("orphans".to_owned(), "-Qdt".to_owned()),
+            ("clean".to_owned(), "orphans -q".to_owned()),
+            ("loop".to_owned(), "again".to_owned()),
+            ("again".to_owned(), "loop -S".to_owned()),
+        ]);
+        let args = ["pacaptr", "clean"].map(OsString::from).to_vec();
+        assert_eq!(expand_aliases(args, &aliases), ["pacaptr", "-Qdt", "-q"]);
+        let args = ["pacaptr", "loop", "curl"].map(OsString::from).to_vec();
+        assert_eq!(
+            expand_aliases(args, &aliases),
+            ["pacaptr", "loop", "-S", "curl"]
+        );
+
+        let aliases = BTreeMap::from([("up".to_owned(), "-Suy --yes".to_owned())]);
+        let args = ["pacaptr", "-S", "up"].map(OsString::from).to_vec();
+        let opt = dbg!(Pacaptr::parse_from(expand_aliases(args, &aliases)));
+        assert_eq!(opt.keywords, &["up"]);
+    }
+
+    #[test]
+    async fn merge_needed_cfg() {
+        // `needed` is read from the config file, not taken from `dry_run`.
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-S", "curl"]));
+        let dotfile = Config {
+            needed: true,
+            ..Config::default()
+        };
+        assert!(opt.merge_cfg(dotfile, |_| None).unwrap().needed);
+        let dotfile = Config {
+            dry_run: true,
+            ..Config::default()
+        };
+        assert!(!opt.merge_cfg(dotfile, |_| None).unwrap().needed);
+    }
+
+    #[test]
+    async fn merge_backend_cfg() {
+        let dotfile: Config = toml::from_str(indoc::indoc! {r#"
+            default_pm = "mockpm"
+            no_cache = true
+
+            [mockpm]
+            no_cache = false
+            needed = true
+            flags = ["--verbose"]
+        "#})
+        .unwrap();
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-S", "curl"]));
+        let cfg = opt.merge_cfg(dotfile.clone(), |_| None).unwrap();
+        assert!(cfg.needed && !cfg.no_cache);
+        assert_eq!(cfg.flags, ["--verbose"]);
+
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-S", "--no-cache", "curl"]));
+        assert!(opt.merge_cfg(dotfile.clone(), |_| None).unwrap().no_cache);
+
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr", "--pm", "brew", "-S", "curl"
+        ]));
+        let cfg = opt.merge_cfg(dotfile.clone(), |_| None).unwrap();
+        assert!(!cfg.needed && cfg.no_cache && cfg.flags.is_empty());
+
+        // The environment sits between the config file and the command line.
+        let var = |key: &str| match key {
+            "PACAPTR_USING" => Some("brew".into()),
+            "PACAPTR_NOCACHE" => Some("0".into()),
+            _ => None,
+        };
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-S", "curl"]));
+        let cfg = opt.merge_cfg(dotfile.clone(), var).unwrap();
+        assert_eq!(cfg.default_pm.as_deref(), Some("brew"));
+        assert!(!cfg.no_cache);
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "--pm",
+            "mockpm",
+            "-S",
+            "--no-cache"
+        ]));
+        let cfg = opt.merge_cfg(dotfile.clone(), var).unwrap();
+        assert_eq!(cfg.default_pm.as_deref(), Some("mockpm"));
+        assert!(cfg.no_cache && cfg.needed);
+
+        let var = |key: &str| (key == "PACAPTR_SUDO").then(|| "doas".into());
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-S", "curl"]));
+        assert_eq!(
+            opt.merge_cfg(dotfile.clone(), var).unwrap().sudo.as_deref(),
+            Some("doas")
+        );
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "--sudo-cmd",
+            "pkexec",
+            "-S",
+            "curl"
+        ]));
+        assert_eq!(
+            opt.merge_cfg(dotfile.clone(), var).unwrap().sudo.as_deref(),
+            Some("pkexec")
+        );
+
+        let dotfile = Config {
+            download_dir: Some("/srv/packages".into()),
+            ..dotfile
+        };
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "-Sw",
+            "--download-dir",
+            "/tmp/packages",
+            "curl"
+        ]));
+        let cfg = opt.merge_cfg(dotfile.clone(), |_| None).unwrap();
+        assert_eq!(cfg.download_dir, Some("/tmp/packages".into()));
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-Sw", "curl"]));
+        let cfg = opt.merge_cfg(dotfile.clone(), |_| None).unwrap();
+        assert_eq!(cfg.download_dir, Some("/srv/packages".into()));
+        assert_eq!(cfg.root, None);
+
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "--sysroot",
+            "/mnt",
+            "-S",
+            "curl"
+        ]));
+        let cfg = opt.merge_cfg(dotfile, |_| None).unwrap();
+        assert_eq!(cfg.root, Some("/mnt".into()));
+    }
+
+    #[test]
+    async fn pm_specific_flags() {
+        let opt = dbg!(Pacaptr::parse_from(["pacaptr", "-Su", "--patch"]));
+        let cfg = |pm: &str| Config {
+            default_pm: Some(pm.into()),
+            ..Config::default()
+        };
+        assert!(opt.check_supported(&cfg("zypper")).is_ok());
+        let err = opt.check_supported(&cfg("apt")).unwrap_err();
+        assert!(matches!(err, Error::OperationUnimplementedError { op, .. } if op == "--patch"));
+    }
+
+    #[test]
+    async fn conflicting_flags() {
+        async fn err_msg(args: &[&str]) -> String {
+            let opt = dbg!(Pacaptr::parse_from(args));
+            match opt.dispatch_from(MOCK_CFG.clone()).await {
+                Err(Error::ArgParseError { msg }) => msg,
+                res => panic!("unexpected result: {res:?}"),
+            }
+        }
+        assert!(err_msg(&["pacaptr", "-Qpu"])
+            .await
+            .contains("`-Qp` and `-Qu`"));
+        assert!(err_msg(&["pacaptr", "-Qde"])
+            .await
+            .contains("`-Qd` and `-Qe`"));
+        assert!(err_msg(&["pacaptr", "-Ssu", "curl"])
+            .await
+            .contains("`-Ss` and `-Su`"));
+        assert!(err_msg(&["pacaptr", "-Sw", "--no-cache", "curl"])
+            .await
+            .contains("`--no-cache`"));
+        assert!(err_msg(&["pacaptr", "-R", "--needed", "curl"])
+            .await
+            .contains("`--needed`"));
+    }
+
+    #[test]
+    async fn policy() {
+        let cfg = Config {
+            policy: toml::from_str(indoc::indoc! {r#"
+                [[deny]]
+                op = "R"
+                kws = ["linux*"]
+            "#})
+            .unwrap(),
+            ..MOCK_CFG.clone()
+        };
+        let err = Pacaptr::parse_from(["pacaptr", "-Rns", "curl", "linux-lts"])
+            .dispatch_from(cfg)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PolicyViolationError { op, .. } if op == "-Rns linux-lts"
+        ));
+    }
+
+    #[test]
+    async fn simple_interactive() {
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "-Ss",
+            "--interactive",
+            "curl"
+        ]));
+        let subcmd = &opt.ops;
+
+        assert!(matches!(subcmd, &Operations::Sync { interactive, s, .. } if interactive && s));
+        assert_eq!(opt.keywords, &["curl"]);
+
+        // The mock package manager can't search.
+        let err = opt.dispatch_from(MOCK_CFG.clone()).await.unwrap_err();
+        assert!(matches!(err, Error::OperationUnimplementedError { .. }));
+
+        let err = Pacaptr::parse_from(["pacaptr", "-Si", "--interactive", "curl"])
+            .dispatch_from(MOCK_CFG.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ArgParseError { .. }));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"should run: s ["curl"]"#)]
+    #[allow(clippy::semicolon_if_nothing_returned)]
+    async fn skip_missing() {
+        let opt = dbg!(Pacaptr::parse_from([
+            "pacaptr",
+            "-S",
+            "--skip-missing",
+            "curl",
+            "wget"
+        ]));
+        assert!(opt.skip_missing);
+
+        let err = Pacaptr::parse_from(["pacaptr", "-Ss", "--skip-missing", "curl"])
+            .dispatch_from(MOCK_CFG.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ArgParseError { .. }));
+
+        opt.dispatch_from(MOCK_CFG.clone()).await.unwrap();
+    }
+
     #[test]
     #[should_panic(expected = r#"should run: s ["docker"]"#)]
     #[allow(clippy::semicolon_if_nothing_returned)]
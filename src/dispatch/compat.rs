@@ -0,0 +1,105 @@
+//! The compatibility table of the package managers, ie. the operations
+//! supported by each of them, as shown by `pacaptr compat`.
+//!
+//! The table is built from the registries of the built-in package managers,
+//! see [`COMPAT`], and from the methods of the custom ones defined in the
+//! config.
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+
+use crate::{
+    dispatch::Config,
+    error::{Error, Result},
+    exec::pager::{self, Pager},
+    pm::{CustomPm, COMPAT, OPS},
+};
+
+/// Lists the operations supported by each built-in package manager, then by
+/// each custom one defined in `cfg`, in the order of [`OPS`].
+#[must_use]
+fn rows(cfg: &Config) -> Vec<(&str, Vec<&'static str>)> {
+    let builtins = COMPAT.iter().map(|&(name, supported)| {
+        let ops = OPS.iter().copied().filter(|op| supported.contains(op));
+        (name, ops.collect_vec())
+    });
+    let customs = cfg
+        .backends
+        .iter()
+        .filter(|(name, _)| CustomPm::is_defined(name, cfg))
+        .map(|(name, backend)| {
+            let ops = OPS
+                .iter()
+                .copied()
+                .filter(|&op| backend.methods.contains_key(op));
+            (name.as_str(), ops.collect_vec())
+        });
+    builtins.chain(customs).collect()
+}
+
+/// Renders the compatibility table of `rows`, marking the operations
+/// supported with a `*`.
+#[must_use]
+fn render(rows: &[(&str, Vec<&str>)]) -> String {
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let line = |name: &str, cells: &mut dyn Iterator<Item = String>| {
+        let line = format!("{name:<width$}  {}", cells.join(" "));
+        line.trim_end().to_owned()
+    };
+    let header = line("", &mut OPS.iter().map(|op| (*op).to_owned()));
+    let body = rows.iter().map(|(name, ops)| {
+        let mut cells = OPS.iter().map(|&op| {
+            let mark = if ops.contains(&op) { "*" } else { "" };
+            format!("{mark:<0$}", op.len())
+        });
+        line(name, &mut cells)
+    });
+    [header].into_iter().chain(body).join("\n")
+}
+
+/// Prints the compatibility table of the package managers known with `cfg`,
+/// or a JSON object mapping each of them to the operations it supports if
+/// `json`.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when the JSON object cannot be written.
+pub(crate) fn show(cfg: &Config, json: bool) -> Result<()> {
+    let rows = rows(cfg);
+    if !json {
+        let _pager = Pager::spawn(cfg.no_pager);
+        pager::print(&render(&rows));
+        return Ok(());
+    }
+    let compat = rows.into_iter().collect::<BTreeMap<_, _>>();
+    let out = serde_json::to_string_pretty(&compat)
+        .map_err(|e| Error::OtherError(format!("Failed to write the JSON object: {e}")))?;
+    println!("{out}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compat() {
+        let cfg: Config = toml::from_str(indoc::indoc! {r#"
+            [spack.methods]
+            s = "spack install"
+            ss = "spack list"
+        "#})
+        .unwrap();
+        let rows = rows(&cfg);
+        let (name, ops) = rows.last().unwrap();
+        assert_eq!((*name, ops.as_slice()), ("spack", ["s", "ss"].as_slice()));
+        let (_, apt) = rows.iter().find(|(name, _)| *name == "apt").unwrap();
+        assert!(apt.contains(&"suy") && !apt.contains(&"qm"));
+
+        let table = render(&[("spack", vec!["q", "qc"]), ("apt", vec!["qc"])]);
+        let mut lines = table.lines();
+        assert!(lines.next().unwrap().starts_with("       f fy q qc qd"));
+        assert_eq!(lines.next().unwrap(), "spack       * *");
+        assert_eq!(lines.next().unwrap(), "apt           *");
+    }
+}
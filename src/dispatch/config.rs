@@ -1,41 +1,529 @@
 //! APIs for reading [`pacaptr`](crate) configurations from the filesystem.
 
-use std::{env, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
 
-use serde::{Deserialize, Serialize};
+use itertools::chain;
+use serde::{
+    de::{self, value::StrDeserializer, DeserializeOwned, IntoDeserializer},
+    Deserialize, Serialize,
+};
+use toml::{value::Table, Value};
 
-use crate::error::{Error, Result};
+use super::{
+    policy::Policy,
+    preflight::{Action, Preflight},
+    snapshot::Provider as SnapshotProvider,
+    summary::SummaryPrompt,
+};
+use crate::{
+    error::{Error, Result},
+    exec::{container::Container, executor::Shared, interop::Interop},
+    print,
+};
 
 /// The environment variable name for custom config file path.
 const CONFIG_ENV_VAR: &str = "PACAPTR_CONFIG";
 
-/// Configurations that may vary when running the package manager.
+/// The system-wide config file path on Unix.
+const SYSTEM_PATH: &str = "/etc/pacaptr.toml";
+
+/// The prefix of the environment variables overriding the config file, eg.
+/// `PACAPTR_NOCONFIRM`.
+pub(crate) const ENV_PREFIX: &str = "PACAPTR_";
+
+/// Configurations that may vary when running the package manager, read from
+/// the config files and overridden with a [`PmBuilder`](crate::PmBuilder).
 #[must_use]
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[non_exhaustive]
 #[allow(clippy::struct_excessive_bools)]
-pub(crate) struct Config {
+pub struct Config {
     /// Perform a dry run.
     #[serde(default)]
-    pub dry_run: bool,
+    pub(crate) dry_run: bool,
 
     /// Prevent reinstalling previously installed packages.
     #[serde(default)]
-    pub needed: bool,
+    pub(crate) needed: bool,
 
     /// Answer yes to every question.
     #[serde(default)]
-    pub no_confirm: bool,
+    pub(crate) no_confirm: bool,
+
+    /// The operations for which `no_confirm` is honored, as the lowercase
+    /// method names, eg. `["s", "su", "suy"]`. `no_confirm` is ignored for the
+    /// other ones. All the operations are allowed if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) no_confirm_allowed_ops: Option<Vec<String>>,
 
     /// Remove cache after installation.
     #[serde(default)]
-    pub no_cache: bool,
+    pub(crate) no_cache: bool,
 
     /// The default package manager to be invoked.
     #[serde(default)]
-    pub default_pm: Option<String>,
+    pub(crate) default_pm: Option<String>,
+
+    /// The package managers to be preferred, in this order, when
+    /// `default_pm` is not set and more than one is detected, eg.
+    /// `["brew", "apt"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) detect_priority: Vec<String>,
+
+    /// When to show a summary of the commands to be run by an operation
+    /// changing the installed packages, confirmed with a single prompt
+    /// instead of one for each command: `never`, `multi` (only when more
+    /// than one command is to be run) or `always`.
+    #[serde(default)]
+    pub(crate) summary_prompt: SummaryPrompt,
+
+    /// Install, remove and upgrade casks rather than formulae with `brew`
+    /// when a cask of the same name exists.
+    #[serde(default)]
+    pub(crate) brew_prefer_cask: bool,
+
+    /// Packages to be left out when upgrading the system, like `IgnorePkg` in
+    /// `pacman.conf`.
+    #[serde(default)]
+    pub(crate) ignore: Vec<String>,
+
+    /// Record the duration of each operation locally for `pacaptr stats`.
+    #[serde(default)]
+    pub(crate) metrics: bool,
+
+    /// The free space in MiB to be kept on the filesystems written to by
+    /// upgrades and installations, which are refused if it would be used up.
+    /// `0` turns the check off.
+    #[serde(default)]
+    pub(crate) min_free_space: u64,
+
+    /// Only warn instead of refusing an operation when the free space would
+    /// go below `min_free_space`.
+    #[serde(default)]
+    pub(crate) warn_low_space: bool,
+
+    /// What to do when running large operations on battery power.
+    #[serde(default)]
+    pub(crate) on_battery: Preflight,
+
+    /// What to do when running large operations on a metered connection.
+    #[serde(default)]
+    pub(crate) on_metered: Preflight,
+
+    /// The command used to run commands as `root`, eg. `doas`, `run0` or
+    /// `pkexec`. The first of those found, starting from `sudo`, is used if
+    /// not set.
+    #[serde(default)]
+    pub(crate) sudo: Option<String>,
+
+    /// Whether to run commands as `root` even if we are `root` already or no
+    /// command to do so is found (if `true`), or never (if `false`). Detected
+    /// automatically if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) force_sudo: Option<bool>,
+
+    /// The number of seconds after which a command run by the package manager
+    /// is terminated. Commands may run for as long as they need if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) timeout: Option<u64>,
+
+    /// The number of times `-S`, `-Sw` and `-Sy` are run again when they fail
+    /// because of the network.
+    #[serde(default)]
+    pub(crate) retries: u32,
+
+    /// The directory to which `-Sw` saves the packages, instead of the cache
+    /// of the package manager.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) download_dir: Option<PathBuf>,
+
+    /// The file to which a record of each command executed is appended, as a
+    /// line of JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) log_file: Option<PathBuf>,
+
+    /// Whether to exit with the codes of the package manager as is, instead of
+    /// mapping them to the ones of `pacman`, eg. `66` when a target is not
+    /// found.
+    #[serde(default)]
+    pub(crate) raw_exit_codes: bool,
+
+    /// Print the unread news of the distribution before upgrading the system,
+    /// where there is a source of news.
+    #[serde(default)]
+    pub(crate) show_news: bool,
+
+    /// Take a snapshot of the system before the operations which change the
+    /// installed packages, ie. `-S`, `-R` and `-Su`.
+    #[serde(default)]
+    pub(crate) snapshot_before: bool,
+
+    /// The tool used to take the snapshots, detected automatically among
+    /// `snapper`, `timeshift` and `tmutil` if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) snapshot_provider: Option<SnapshotProvider>,
+
+    /// The `zfs` dataset or `btrfs` subvolume to take the snapshots of, eg.
+    /// `rpool/ROOT` or `/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) snapshot_target: Option<String>,
+
+    /// The maximum number of packages passed to a single command, larger
+    /// transactions being split into chunks. Only the length of the command
+    /// line is limited if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) max_kws: Option<usize>,
+
+    /// Shortcuts for frequently used commands, eg. `up = "-Suy"` turns
+    /// `pacaptr up` into `pacaptr -Suy`.
+    #[serde(default)]
+    pub(crate) aliases: BTreeMap<String, String>,
+
+    /// The commands run before and after the operations, eg.
+    /// `post_suy = "flatpak update -y"`, with the operation, the package
+    /// manager and the keywords in `PACAPTR_OP`, `PACAPTR_PM` and
+    /// `PACAPTR_PACKAGES`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) hooks: BTreeMap<String, String>,
+
+    /// The operations denied by the administrator, only read from the
+    /// system-wide config file.
+    #[serde(default, skip_serializing_if = "Policy::is_empty")]
+    pub(crate) policy: Policy,
+
+    /// The directory of the system to operate on instead of `/`, eg. a chroot
+    /// being built, as given with `--root`.
+    #[serde(skip)]
+    pub(crate) root: Option<PathBuf>,
+
+    /// Apply the patches rather than upgrading all the packages with
+    /// `zypper`, as given with `--patch`.
+    #[serde(skip)]
+    pub(crate) patch: bool,
+
+    /// Don't page the long outputs of the queries, as given with
+    /// `--no-pager`.
+    #[serde(skip)]
+    pub(crate) no_pager: bool,
+
+    /// Match the keywords of the searches filtered by `pacaptr` itself, eg.
+    /// `-Qs` with `brew`, literally and case-sensitively, as given with
+    /// `--exact`.
+    #[serde(skip)]
+    pub(crate) exact: bool,
+
+    /// The commands to be run have been confirmed as a whole with a summary,
+    /// so that they are not confirmed one by one by `pacaptr`, though the
+    /// package manager still asks before making changes of its own, eg.
+    /// removing the packages in conflict.
+    #[serde(skip)]
+    pub(crate) confirmed: bool,
+
+    /// Install, remove and upgrade casks rather than formulae with `brew`, as
+    /// given with `--cask`.
+    #[serde(skip)]
+    pub(crate) cask: bool,
+
+    /// Don't take the lock preventing another `pacaptr` instance from changing
+    /// the installed packages at the same time, as given with `--no-lock`.
+    #[serde(skip)]
+    pub(crate) no_lock: bool,
+
+    /// The extra flags always passed to the package manager, as set in its
+    /// section.
+    #[serde(skip)]
+    pub(crate) flags: Vec<String>,
+
+    /// Where the package manager runs, eg. on Windows from WSL, as set by
+    /// `--using`.
+    #[serde(skip)]
+    pub(crate) interop: Interop,
+
+    /// The remote host to run the package manager on over SSH, as given with
+    /// `--host`.
+    #[serde(skip)]
+    pub(crate) host: Option<String>,
+
+    /// The running container to run the package manager in, as given with
+    /// `--container`.
+    #[serde(skip)]
+    pub(crate) container: Option<Container>,
+
+    /// The custom executor running the commands in place of `pacaptr`, as set
+    /// with [`PmBuilder::executor`](crate::PmBuilder::executor).
+    #[serde(skip)]
+    pub(crate) executor: Option<Shared>,
+
+    /// The overrides for each package manager, eg. `[brew]`.
+    ///
+    /// Any other key, eg. a typo in a global setting, is rejected, see
+    /// [`deserialize_backends`].
+    #[serde(flatten, deserialize_with = "deserialize_backends")]
+    pub(crate) backends: BTreeMap<String, Backend>,
+}
+
+/// The built-in package managers which can have a section in the config file,
+/// besides those in [`COMPAT`](crate::pm::COMPAT), ie. their other names.
+static BUILTIN_ALIASES: &[&str] = &["dnf5", "pip3", "xbps-install"];
+
+/// Deserializes the sections of the package managers, which are all the keys
+/// of the config file left unknown. Each one should be a table named after a
+/// built-in package manager (possibly run through WSL, eg. `wsl:apt`), or
+/// defining the `methods` of a custom one, so that a typo is reported instead
+/// of being taken for a package manager.
+fn deserialize_backends<'de, D>(
+    deserializer: D,
+) -> std::result::Result<BTreeMap<String, Backend>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error as _;
+
+    let sections = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    sections
+        .into_iter()
+        .map(|(name, section)| {
+            if !section.is_table() {
+                return Err(D::Error::custom(format!("unknown key `{name}`")));
+            }
+            let backend = section
+                .try_into::<Backend>()
+                .map_err(|e| D::Error::custom(format!("in `[{name}]`: {e}")))?;
+            let pm = name.strip_prefix("wsl:").unwrap_or(&name);
+            let is_builtin = crate::pm::COMPAT.iter().any(|&(builtin, _)| builtin == pm)
+                || BUILTIN_ALIASES.contains(&pm)
+                || (cfg!(test) && pm == crate::pm::testing::MockPm::NAME);
+            if !is_builtin && backend.methods.is_empty() {
+                return Err(D::Error::custom(format!(
+                    "unknown section `[{name}]`, which is neither a built-in package manager nor \
+                     a custom one with `methods`"
+                )));
+            }
+            Ok((name, backend))
+        })
+        .collect()
+}
+
+/// The configurations of a single package manager, overriding the global ones
+/// when it's being used.
+#[must_use]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Backend {
+    /// Perform a dry run.
+    pub(crate) dry_run: Option<bool>,
+
+    /// Prevent reinstalling previously installed packages.
+    pub(crate) needed: Option<bool>,
+
+    /// Answer yes to every question.
+    pub(crate) no_confirm: Option<bool>,
+
+    /// Remove cache after installation.
+    pub(crate) no_cache: Option<bool>,
+
+    /// The command used to run commands as `root`.
+    pub(crate) sudo: Option<String>,
+
+    /// The maximum number of packages passed to a single command.
+    pub(crate) max_kws: Option<usize>,
+
+    /// The extra flags always passed to the package manager, eg.
+    /// `["--verbose"]`.
+    #[serde(default)]
+    pub(crate) flags: Vec<String>,
+
+    /// The commands run for each operation by a custom package manager, eg.
+    /// `s = "spack install"`, the keywords being appended to them. Only used
+    /// if the package manager is not a built-in one.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) methods: BTreeMap<String, String>,
+
+    /// The flags disabling the prompt of a custom package manager when
+    /// answering yes to every question, eg. `["-y"]`. Without them, `pacaptr`
+    /// prompts by itself before changing the installed packages.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) no_confirm_flags: Vec<String>,
+
+    /// The flags making a custom package manager perform a dry run. Without
+    /// them, the commands are only printed in a dry run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) dry_run_flags: Vec<String>,
+
+    /// The flags making a custom package manager reinstall the packages
+    /// already installed with `-S`, unless `needed` is set, eg.
+    /// `["--force"]`. Without them, those packages are left as is.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) reinstall_flags: Vec<String>,
+
+    /// Run the commands changing the installed packages as `root` with a
+    /// custom package manager.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) needs_root: bool,
 }
 
 impl Config {
+    /// Checks if the package manager runs on this machine, ie. neither on a
+    /// remote host nor in a container.
+    #[must_use]
+    pub(crate) fn is_local(&self) -> bool {
+        self.host.is_none() && self.container.is_none()
+    }
+
+    /// Applies the overrides in the section of the package manager `pm`, if
+    /// any.
+    pub(crate) fn with_backend(mut self, pm: &str) -> Self {
+        let Some(backend) = self.backends.get(pm).cloned() else {
+            return self;
+        };
+        self.dry_run = backend.dry_run.unwrap_or(self.dry_run);
+        self.needed = backend.needed.unwrap_or(self.needed);
+        self.no_confirm = backend.no_confirm.unwrap_or(self.no_confirm);
+        self.no_cache = backend.no_cache.unwrap_or(self.no_cache);
+        self.sudo = backend.sudo.or(self.sudo);
+        self.max_kws = backend.max_kws.or(self.max_kws);
+        self.flags = backend.flags;
+        self
+    }
+
+    /// Turns `no_confirm` off with a warning if the operation `options`, eg.
+    /// `Rs`, is not in `no_confirm_allowed_ops`.
+    pub(crate) fn confine_no_confirm(&mut self, options: &str) {
+        let Some(allowed) = &self.no_confirm_allowed_ops else {
+            return;
+        };
+        let method = options.to_lowercase();
+        if !self.no_confirm || allowed.contains(&method) {
+            return;
+        }
+        self.no_confirm = false;
+        print::print_msg(
+            &format!("`--no-confirm` is ignored for `-{options}`, as it's not in `no_confirm_allowed_ops`"),
+            print::PROMPT_WARN,
+        );
+    }
+
+    /// Applies the overrides in the `PACAPTR_*` environment variables, as
+    /// looked up by `var`:
+    /// - `PACAPTR_USING` for `default_pm`, `PACAPTR_SUDO`,
+    ///   `PACAPTR_LOG_FILE`, `PACAPTR_DOWNLOAD_DIR`, `PACAPTR_SNAPSHOT_TARGET`
+    ///   and `PACAPTR_ROOT` for `--root`.
+    /// - `PACAPTR_DRYRUN`, `PACAPTR_NEEDED`, `PACAPTR_NOCONFIRM`,
+    ///   `PACAPTR_NOCACHE`, `PACAPTR_METRICS`, `PACAPTR_WARN_LOW_SPACE`,
+    ///   `PACAPTR_RAW_EXIT_CODES`, `PACAPTR_SHOW_NEWS`,
+    ///   `PACAPTR_SNAPSHOT_BEFORE`, `PACAPTR_BREW_PREFER_CASK` and
+    ///   `PACAPTR_FORCE_SUDO`, set to eg. `1` or `false`.
+    /// - `PACAPTR_IGNORE`, `PACAPTR_DETECT_PRIORITY` and
+    ///   `PACAPTR_NO_CONFIRM_ALLOWED_OPS`, as comma-separated lists.
+    /// - `PACAPTR_MIN_FREE_SPACE`, in MiB, `PACAPTR_TIMEOUT`, in seconds,
+    ///   `PACAPTR_RETRIES` and `PACAPTR_MAX_KWS`.
+    /// - `PACAPTR_SUMMARY_PROMPT` and `PACAPTR_SNAPSHOT_PROVIDER`, set to one
+    ///   of their values in the config file, eg. `multi` or `snapper`.
+    /// - `PACAPTR_ON_BATTERY` and `PACAPTR_ON_METERED`, set to an action for
+    ///   all the classes of operations, eg. `warn`.
+    ///
+    /// # Errors
+    /// Returns an [`Error::ConfigError`] when a value cannot be parsed.
+    pub(crate) fn with_env(mut self, var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        // The values of the enums, eg. `multi`, are spelled as in the config file.
+        fn parse_enum<T: DeserializeOwned>(val: &str) -> Option<T> {
+            let de: StrDeserializer<'_, de::value::Error> = val.trim().into_deserializer();
+            T::deserialize(de).ok()
+        }
+
+        let var = |key: &str| var(&format!("{ENV_PREFIX}{key}"));
+        let invalid = |key: &str, val: &str| Error::ConfigError {
+            msg: format!("Invalid value `{val}` for `{ENV_PREFIX}{key}`"),
+        };
+        let parse_flag = |key: &str, val: &str| match val.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "" | "0" | "false" | "no" | "off" => Ok(false),
+            _ => Err(invalid(key, val)),
+        };
+        let flag = |key: &str, curr: bool| -> Result<bool> {
+            var(key).map_or(Ok(curr), |val| parse_flag(key, &val))
+        };
+        let list = |key: &str| -> Option<Vec<String>> {
+            var(key).map(|val| {
+                val.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+        };
+        let preflight = |key: &str, curr: Preflight| -> Result<Preflight> {
+            var(key).map_or(Ok(curr), |val| {
+                let action = match val.trim().to_ascii_lowercase().as_str() {
+                    "off" => Action::Off,
+                    "warn" => Action::Warn,
+                    "refuse" => Action::Refuse,
+                    _ => return Err(invalid(key, &val)),
+                };
+                Ok(Preflight {
+                    upgrade: action,
+                    install: action,
+                    download: action,
+                })
+            })
+        };
+
+        self.default_pm = var("USING").or(self.default_pm);
+        self.sudo = var("SUDO").or(self.sudo);
+        self.log_file = var("LOG_FILE").map(PathBuf::from).or(self.log_file);
+        self.download_dir = var("DOWNLOAD_DIR").map(PathBuf::from).or(self.download_dir);
+        self.snapshot_target = var("SNAPSHOT_TARGET").or(self.snapshot_target);
+        self.root = var("ROOT").map(PathBuf::from).or(self.root);
+        self.dry_run = flag("DRYRUN", self.dry_run)?;
+        self.needed = flag("NEEDED", self.needed)?;
+        self.no_confirm = flag("NOCONFIRM", self.no_confirm)?;
+        self.no_cache = flag("NOCACHE", self.no_cache)?;
+        self.metrics = flag("METRICS", self.metrics)?;
+        self.warn_low_space = flag("WARN_LOW_SPACE", self.warn_low_space)?;
+        self.raw_exit_codes = flag("RAW_EXIT_CODES", self.raw_exit_codes)?;
+        self.show_news = flag("SHOW_NEWS", self.show_news)?;
+        self.snapshot_before = flag("SNAPSHOT_BEFORE", self.snapshot_before)?;
+        self.brew_prefer_cask = flag("BREW_PREFER_CASK", self.brew_prefer_cask)?;
+        if let Some(val) = var("FORCE_SUDO") {
+            self.force_sudo = Some(parse_flag("FORCE_SUDO", &val)?);
+        }
+        self.ignore = list("IGNORE").unwrap_or(self.ignore);
+        self.detect_priority = list("DETECT_PRIORITY").unwrap_or(self.detect_priority);
+        self.no_confirm_allowed_ops = list("NO_CONFIRM_ALLOWED_OPS")
+            .map(|ops| ops.iter().map(|op| op.to_ascii_lowercase()).collect())
+            .or(self.no_confirm_allowed_ops);
+        if let Some(val) = var("SUMMARY_PROMPT") {
+            self.summary_prompt =
+                parse_enum(&val).ok_or_else(|| invalid("SUMMARY_PROMPT", &val))?;
+        }
+        if let Some(val) = var("SNAPSHOT_PROVIDER") {
+            self.snapshot_provider =
+                Some(parse_enum(&val).ok_or_else(|| invalid("SNAPSHOT_PROVIDER", &val))?);
+        }
+        if let Some(val) = var("MAX_KWS") {
+            self.max_kws = Some(val.trim().parse().map_err(|_| invalid("MAX_KWS", &val))?);
+        }
+        if let Some(val) = var("RETRIES") {
+            self.retries = val.trim().parse().map_err(|_| invalid("RETRIES", &val))?;
+        }
+        if let Some(val) = var("TIMEOUT") {
+            self.timeout = Some(val.trim().parse().map_err(|_| invalid("TIMEOUT", &val))?);
+        }
+        if let Some(val) = var("MIN_FREE_SPACE") {
+            self.min_free_space = val
+                .trim()
+                .parse()
+                .map_err(|_| invalid("MIN_FREE_SPACE", &val))?;
+        }
+        self.on_battery = preflight("ON_BATTERY", self.on_battery)?;
+        self.on_metered = preflight("ON_METERED", self.on_metered)?;
+        Ok(self)
+    }
+
     /// The default config file path is `$HOME/.config/pacaptr/pacaptr.toml`.
     ///
     /// # Errors
@@ -48,7 +536,19 @@ impl Config {
         Ok(home
             .join(".config")
             .join(crate_name)
-            .join(&format!("{}.toml", crate_name)))
+            .join(format!("{}.toml", crate_name)))
+    }
+
+    /// Gets the config file path under `$XDG_CONFIG_HOME`, ie.
+    /// `$XDG_CONFIG_HOME/pacaptr/pacaptr.toml`, if the variable is set.
+    fn xdg_path() -> Option<PathBuf> {
+        let crate_name = clap::crate_name!();
+        let dir = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty())?;
+        Some(
+            PathBuf::from(dir)
+                .join(crate_name)
+                .join(format!("{crate_name}.toml")),
+        )
     }
 
     /// Gets the custom config file path specified by the `PACAPTR_CONFIG`
@@ -65,23 +565,299 @@ impl Config {
             .map(PathBuf::from)
     }
 
-    /// Loads up the config file from the user-specified path.
+    /// Gets the path of the user's config file, which is the custom one if
+    /// specified, or the first one of `$XDG_CONFIG_HOME/pacaptr/pacaptr.toml`
+    /// and the default one otherwise.
+    ///
+    /// # Errors
+    /// Returns an [`Error::ConfigError`] when `$HOME` is not found.
+    pub(crate) fn path() -> Result<PathBuf> {
+        Self::custom_path()
+            .ok()
+            .or_else(Self::xdg_path)
+            .map_or_else(Self::default_path, Ok)
+    }
+
+    /// Gets the paths of the config files to be looked for, in ascending order
+    /// of precedence: the system-wide one (on Unix), the default one and the
+    /// one under `$XDG_CONFIG_HOME`. A custom path replaces all of them.
+    #[must_use]
+    pub(crate) fn paths() -> Vec<PathBuf> {
+        if let Ok(path) = Self::custom_path() {
+            return vec![path];
+        }
+        let mut paths = vec![];
+        if cfg!(unix) {
+            paths.push(PathBuf::from(SYSTEM_PATH));
+        }
+        for path in chain!(Self::default_path().ok(), Self::xdg_path()) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+
+    /// Prints the paths of the config files looked for, marking those which
+    /// exist and are thus loaded.
+    pub(crate) fn print_paths() {
+        for path in Self::paths() {
+            let prompt = if path.exists() { "Loaded" } else { "Missing" };
+            print::print_msg(&path.display().to_string(), prompt);
+        }
+    }
+
+    /// Loads up the config files which exist among [`Config::paths`], merging
+    /// them in order. The [`Policy`] is taken from the system-wide config file
+    /// only, even with a custom path, and ignored in the other ones.
     ///
     /// I decided not to trash user's `$HOME` without their permission, so:
     /// - If the user hasn't yet specified any path to look at, we will look for
-    ///   the config file in the default path.
-    /// - If the config file is not present anyway, a default one will be loaded
-    ///   with [`Default::default`], and no files will be written.
+    ///   the config files in the default paths.
+    /// - If no config file is present anyway, a default one will be loaded with
+    ///   [`Default::default`], and no files will be written.
     ///
     /// # Errors
     /// Returns an [`Error::ConfigError`] when the config file loading fails.
     pub(crate) fn try_load() -> Result<Self> {
-        let path = Self::custom_path().or_else(|_e| Self::default_path())?;
-        if !path.exists() {
-            return Ok(Self::default());
+        let mut merged = Value::Table(Table::new());
+        for path in Self::paths().into_iter().filter(|path| path.exists()) {
+            let mut layer: Value = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| toml::from_str(&s).map_err(|e| e.to_string()))
+                .map_err(|e| Error::ConfigError {
+                    msg: format!("Failed to read config at `{}`: {e}", path.display()),
+                })?;
+            let policy = layer
+                .as_table_mut()
+                .and_then(|layer| layer.remove("policy"));
+            if policy.is_some() && path != Path::new(SYSTEM_PATH) {
+                print::print_msg(
+                    &format!(
+                        "`[policy]` is ignored in `{}`, as it can only be set in `{SYSTEM_PATH}`",
+                        path.display()
+                    ),
+                    print::PROMPT_WARN,
+                );
+            }
+            merge(&mut merged, layer);
         }
-        confy::load_path(&path).map_err(|_e| Error::ConfigError {
-            msg: format!("Failed to read config at `{:?}`", &path),
-        })
+        let mut cfg: Self = merged.try_into().map_err(|e| Error::ConfigError {
+            msg: format!("Invalid config: {e}"),
+        })?;
+        if cfg!(unix) {
+            cfg.policy = Policy::load(Path::new(SYSTEM_PATH))?;
+        }
+        Ok(cfg)
+    }
+}
+
+/// Writes `contents` to the config file at `path`, creating its directory if
+/// needed.
+///
+/// # Errors
+/// Returns an [`Error::IoError`] when the config file cannot be written.
+pub(crate) fn write(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Merges the config file `layer` into `base`, overriding its values. Tables
+/// are merged recursively, so that eg. `[brew] no_cache = true` in `layer`
+/// leaves the other settings of `[brew]` in `base` untouched.
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Table(base), Value::Table(layer)) => {
+            for (key, val) in layer {
+                match base.get_mut(&key) {
+                    Some(curr) => merge(curr, val),
+                    None => {
+                        base.insert(key, val);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_overrides() {
+        let cfg: Config = toml::from_str(indoc::indoc! {r#"
+            no_confirm = true
+            no_cache = true
+
+            [aliases]
+            up = "-Suy"
+
+            [apt]
+            sudo = "doas"
+            flags = ["--quiet"]
+
+            [brew]
+            no_cache = false
+        "#})
+        .unwrap();
+        assert_eq!(cfg.aliases["up"], "-Suy");
+        assert_eq!(cfg.backends.len(), 2);
+
+        let apt = cfg.clone().with_backend("apt");
+        assert!(apt.no_confirm && apt.no_cache);
+        assert_eq!(apt.sudo.as_deref(), Some("doas"));
+        assert_eq!(apt.flags, ["--quiet"]);
+
+        let brew = cfg.clone().with_backend("brew");
+        assert!(brew.no_confirm && !brew.no_cache);
+        assert_eq!(brew.sudo, None);
+
+        let dnf = cfg.with_backend("dnf");
+        assert!(dnf.no_confirm && dnf.no_cache);
+        assert!(dnf.flags.is_empty());
+    }
+
+    #[test]
+    fn unknown_sections() {
+        let err = |toml| toml::from_str::<Config>(toml).unwrap_err().to_string();
+        assert!(err("no_confrim = true").contains("unknown key `no_confrim`"));
+        assert!(err("[ap]\nsudo = \"doas\"").contains("unknown section `[ap]`"));
+        assert!(err("[apt]\nsduo = \"doas\"").contains("in `[apt]`: unknown field `sduo`"));
+        let cfg: Config = toml::from_str(indoc::indoc! {r#"
+            [dnf5]
+            no_cache = true
+
+            [spack.methods]
+            s = "spack install"
+        "#})
+        .unwrap();
+        assert_eq!(cfg.backends.keys().collect::<Vec<_>>(), ["dnf5", "spack"]);
+    }
+
+    #[test]
+    fn layers() {
+        let mut merged: Value = toml::from_str(indoc::indoc! {r#"
+            default_pm = "apt"
+            ignore = ["linux"]
+
+            [apt]
+            sudo = "doas"
+            flags = ["--quiet"]
+        "#})
+        .unwrap();
+        let layer = toml::from_str(indoc::indoc! {r#"
+            no_confirm = true
+            ignore = ["curl"]
+
+            [apt]
+            flags = []
+        "#})
+        .unwrap();
+        merge(&mut merged, layer);
+        let cfg: Config = merged.try_into().unwrap();
+        assert_eq!(cfg.default_pm.as_deref(), Some("apt"));
+        assert!(cfg.no_confirm);
+        assert_eq!(cfg.ignore, ["curl"]);
+        assert_eq!(cfg.backends["apt"].sudo.as_deref(), Some("doas"));
+        assert!(cfg.backends["apt"].flags.is_empty());
+    }
+
+    #[test]
+    fn confined_no_confirm() {
+        let cfg: Config = toml::from_str(indoc::indoc! {r#"
+            no_confirm = true
+            no_confirm_allowed_ops = ["s", "su", "suy"]
+        "#})
+        .unwrap();
+        let confined = |options: &str| {
+            let mut cfg = cfg.clone();
+            cfg.confine_no_confirm(options);
+            cfg.no_confirm
+        };
+        assert!(confined("Suy"));
+        assert!(!confined("Rns"));
+        assert!(!confined("Scc"));
+
+        let mut cfg = Config {
+            no_confirm: true,
+            ..Config::default()
+        };
+        cfg.confine_no_confirm("R");
+        assert!(cfg.no_confirm);
+    }
+
+    #[test]
+    fn env_overrides() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |key: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| (*v).to_owned())
+            }
+        };
+        let cfg = Config {
+            no_confirm: true,
+            ignore: vec!["linux".into()],
+            ..Config::default()
+        }
+        .with_env(env(&[
+            ("PACAPTR_USING", "apk"),
+            ("PACAPTR_NOCONFIRM", "0"),
+            ("PACAPTR_DRYRUN", "True"),
+            ("PACAPTR_IGNORE", "curl, git,"),
+            ("PACAPTR_MIN_FREE_SPACE", "512"),
+            ("PACAPTR_TIMEOUT", "60"),
+            ("PACAPTR_RETRIES", "3"),
+            ("PACAPTR_LOG_FILE", "/tmp/pacaptr.log"),
+            ("PACAPTR_RAW_EXIT_CODES", "yes"),
+            ("PACAPTR_SHOW_NEWS", "1"),
+            ("PACAPTR_ON_BATTERY", "refuse"),
+            ("PACAPTR_SUMMARY_PROMPT", "multi"),
+            ("PACAPTR_SNAPSHOT_PROVIDER", "zfs"),
+            ("PACAPTR_SNAPSHOT_TARGET", "rpool/ROOT"),
+            ("PACAPTR_FORCE_SUDO", "off"),
+            ("PACAPTR_MAX_KWS", "100"),
+            ("PACAPTR_DETECT_PRIORITY", "brew,apt"),
+            ("PACAPTR_NO_CONFIRM_ALLOWED_OPS", "S, Suy"),
+            ("PACAPTR_ROOT", "/mnt"),
+        ]))
+        .unwrap();
+        assert_eq!(cfg.default_pm.as_deref(), Some("apk"));
+        assert!(!cfg.no_confirm && cfg.dry_run && !cfg.needed);
+        assert_eq!(cfg.ignore, ["curl", "git"]);
+        assert_eq!(cfg.min_free_space, 512);
+        assert_eq!(cfg.timeout, Some(60));
+        assert_eq!(cfg.retries, 3);
+        assert_eq!(cfg.log_file, Some(PathBuf::from("/tmp/pacaptr.log")));
+        assert!(cfg.raw_exit_codes);
+        assert!(cfg.show_news);
+        assert_eq!(cfg.on_battery.install, Action::Refuse);
+        assert_eq!(cfg.on_metered.install, Action::Off);
+        assert_eq!(cfg.summary_prompt, SummaryPrompt::Multi);
+        assert_eq!(cfg.snapshot_provider, Some(SnapshotProvider::Zfs));
+        assert_eq!(cfg.snapshot_target.as_deref(), Some("rpool/ROOT"));
+        assert_eq!(cfg.force_sudo, Some(false));
+        assert_eq!(cfg.max_kws, Some(100));
+        assert_eq!(cfg.detect_priority, ["brew", "apt"]);
+        assert_eq!(cfg.no_confirm_allowed_ops.unwrap(), ["s", "suy"]);
+        assert_eq!(cfg.root, Some(PathBuf::from("/mnt")));
+
+        assert!(Config::default()
+            .with_env(env(&[("PACAPTR_NEEDED", "maybe")]))
+            .is_err());
+        assert!(Config::default()
+            .with_env(env(&[("PACAPTR_ON_METERED", "sometimes")]))
+            .is_err());
+        assert!(Config::default()
+            .with_env(env(&[("PACAPTR_SUMMARY_PROMPT", "often")]))
+            .is_err());
+        assert!(Config::default()
+            .with_env(env(&[("PACAPTR_TIMEOUT", "1m")]))
+            .is_err());
     }
 }
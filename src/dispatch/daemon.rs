@@ -0,0 +1,195 @@
+//! Checking for updates in the background with `pacaptr watch`, eg. from a
+//! `systemd` user service or a login item, instead of `cron` jobs.
+//!
+//! At each interval, the databases of the package managers watched are
+//! refreshed, unless that needs `root`, and the packages which can be
+//! upgraded are counted. A desktop
+//! notification is sent whenever the number of updates available changes,
+//! and the total is written to the status file if one is given, eg. for a
+//! status bar.
+//!
+//! The config files are watched in between, and `default_pm` is reloaded
+//! without a restart, see [`reload`](super::reload).
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use is_root::is_root;
+use itertools::Itertools;
+use tap::prelude::*;
+use tokio::time;
+
+use crate::{
+    dispatch::{
+        outdated,
+        reload::{self, Reloader},
+        Config,
+    },
+    error::{Error, Result},
+    exec::{is_exe, Cmd, Mode},
+    pm::Pm,
+    print::{self, PROMPT_ERROR, PROMPT_INFO},
+};
+
+/// How often the config files are checked for changes in between two checks
+/// for updates.
+const RELOAD_POLL: Duration = Duration::from_secs(10);
+
+/// Builds the package managers named `names`, or the one used with `cfg` if
+/// `names` is empty.
+#[must_use]
+fn pms(cfg: &Config, names: &[&str]) -> Vec<Box<dyn Pm>> {
+    if names.is_empty() {
+        return vec![cfg.clone().conv()];
+    }
+    names
+        .iter()
+        .map(|&name| {
+            Config {
+                default_pm: Some(name.into()),
+                ..cfg.clone()
+            }
+            .conv()
+        })
+        .collect()
+}
+
+/// The package managers whose databases are refreshed without `root`, eg.
+/// as `brew` keeps them in its own prefix.
+const UNPRIVILEGED_REFRESH: &[&str] = &["brew", "dnf", "scoop"];
+
+/// Refreshes the databases of each of `pms` if it can, then counts the
+/// packages which can be upgraded with it. The package managers failing to
+/// do so are reported and left out.
+async fn check(pms: &[Box<dyn Pm>]) -> Vec<(&str, usize)> {
+    let mut counts = vec![];
+    for pm in pms {
+        let count = async {
+            // Refreshing the databases needs `root` with most package
+            // managers, eg. `apt update`, which would only ask for a password
+            // in the background, so it's left to the system, eg. to
+            // `apt-daily.timer`.
+            if is_root() || UNPRIVILEGED_REFRESH.contains(&pm.name()) {
+                pm.sy(&[], &[]).await?;
+            }
+            outdated::save_count(&**pm, &[]).await
+        };
+        match count.await {
+            Ok(count) => counts.push((pm.name(), count)),
+            Err(e) => print::print_err(e, PROMPT_ERROR),
+        }
+    }
+    counts
+}
+
+/// Sums up `counts`, eg. `3 updates available: apt (2), brew (1)`.
+#[must_use]
+fn summary(counts: &[(&str, usize)]) -> String {
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    let updates = if total == 1 { "update" } else { "updates" };
+    let details = counts
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(name, count)| format!("{name} ({count})"))
+        .join(", ");
+    if details.is_empty() {
+        format!("{total} {updates} available")
+    } else {
+        format!("{total} {updates} available: {details}")
+    }
+}
+
+/// Builds the command sending a desktop notification of `msg`, if the
+/// platform has one.
+#[must_use]
+fn notify_cmd(msg: &str) -> Option<Cmd> {
+    if cfg!(target_os = "macos") {
+        let script = format!("display notification {msg:?} with title \"pacaptr\"");
+        Some(Cmd::new(&["osascript", "-e", &script]))
+    } else if is_exe("notify-send", "") {
+        Some(Cmd::new(&[
+            "notify-send",
+            "--app-name=pacaptr",
+            "pacaptr",
+            msg,
+        ]))
+    } else {
+        None
+    }
+}
+
+/// Checks for updates with the package managers named `names` (or the one
+/// used with `cfg`) every `interval`, until interrupted. The total number of
+/// updates is written to `status_file` if given.
+///
+/// In between, the config is reloaded with `reload` whenever the config files
+/// change, and a notification is sent if it has changed.
+///
+/// # Errors
+/// Returns an [`Error::ArgParseError`] when `interval` is zero, or an
+/// [`Error::IoError`] when the status file cannot be written.
+pub(crate) async fn watch(
+    cfg: &Config,
+    reload: impl Fn() -> Result<Config>,
+    names: &[&str],
+    interval: Duration,
+    status_file: Option<&Path>,
+) -> Result<()> {
+    if interval.is_zero() {
+        return Err(Error::ArgParseError {
+            msg: "`--interval` should be longer than zero".into(),
+        });
+    }
+    let mut cfg = cfg.clone();
+    let mut watched = pms(&cfg, names);
+    let mut reloader = Reloader::new();
+    let mut last = None;
+    loop {
+        let counts = check(&watched).await;
+        let total: usize = counts.iter().map(|(_, count)| count).sum();
+        if let Some(path) = status_file {
+            fs::write(path, format!("{total}\n"))?;
+        }
+        let msg = summary(&counts);
+        print::print_msg(&msg, PROMPT_INFO);
+        if total > 0 && last != Some(total) {
+            if let Some(cmd) = notify_cmd(&msg) {
+                // A missing notification server shouldn't stop the checks.
+                cmd.exec(Mode::Mute).await.ok();
+            }
+        }
+        last = Some(total);
+
+        let next = Instant::now() + interval;
+        while let Some(left) = next.checked_duration_since(Instant::now()) {
+            time::sleep(left.min(RELOAD_POLL)).await;
+            if !reloader.changed() {
+                continue;
+            }
+            if let Some(msg) = reload::reload_into(&mut cfg, &reload) {
+                if let Some(cmd) = notify_cmd(&msg) {
+                    cmd.exec(Mode::Mute).await.ok();
+                }
+                watched = pms(&cfg, names);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summed_up() {
+        assert_eq!(
+            summary(&[("apt", 2), ("dnf", 0), ("brew", 1)]),
+            "3 updates available: apt (2), brew (1)"
+        );
+        assert_eq!(summary(&[("apt", 1)]), "1 update available: apt (1)");
+        assert_eq!(summary(&[("apt", 0)]), "0 updates available");
+    }
+}
@@ -0,0 +1,443 @@
+//! Detecting the package managers available on this machine, and choosing the
+//! one to be used when `default_pm` is not set.
+//!
+//! Each candidate found is scored: it gets a bonus when `os-release` says the
+//! system is a distro it belongs to, eg. `apt` on Debian, and a malus when
+//! the system is immutable and it cannot change the base system, eg. `dnf` on
+//! Fedora Silverblue. The package managers listed in `detect_priority` come
+//! first, in that order. `pacaptr doctor` shows how the choice has been made.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use colored::Colorize;
+use itertools::Itertools;
+use tap::prelude::*;
+
+use crate::{
+    dispatch::Config,
+    error::{Error, Result},
+    exec::{container::Container, is_exe, Cmd, Mode},
+    pm::{CustomPm, Pm},
+    print::{self, PROMPT_INFO},
+};
+
+/// A package manager which can be detected.
+struct Candidate {
+    /// The name of the package manager, as in `default_pm`.
+    name: &'static str,
+    /// The executable of the package manager, which is usually its name.
+    exe: &'static str,
+    /// The usual path of the executable, which might not be in `$PATH`.
+    path: &'static str,
+    /// The `ID`s in `os-release` of the distros it belongs to.
+    distros: &'static [&'static str],
+}
+
+/// A file marking an immutable system, where some package managers cannot
+/// change the base system.
+struct Marker {
+    path: &'static str,
+    /// The package managers which should not be used on such a system.
+    pms: &'static [&'static str],
+    /// Why they should not be used.
+    note: &'static str,
+}
+
+/// The score of a candidate found.
+const FOUND: i32 = 10;
+/// The bonus of a candidate belonging to the distro.
+const DISTRO: i32 = 20;
+/// The malus of a candidate on an immutable system.
+const IMMUTABLE: i32 = -20;
+/// The bonus of the first package manager in `detect_priority`, the next
+/// ones getting less.
+const PRIORITY: i32 = 100;
+
+macro_rules! candidate {
+    ($name:literal => $exe:literal, $path:literal $(, $distro:literal)* $(,)?) => {
+        Candidate { name: $name, exe: $exe, path: $path, distros: &[$($distro),*] }
+    };
+    ($name:literal, $path:literal $(, $distro:literal)* $(,)?) => {
+        candidate!($name => $name, $path $(, $distro)*)
+    };
+}
+
+/// The candidates on Linux, also used for the containers.
+static LINUX: &[Candidate] = &[
+    candidate!("apk", "/sbin/apk", "alpine", "postmarketos"),
+    candidate!("apt", "/usr/bin/apt", "debian", "ubuntu"),
+    candidate!("emerge", "/usr/bin/emerge", "gentoo"),
+    candidate!("dnf", "/usr/bin/dnf", "fedora", "rhel", "centos"),
+    candidate!("dnf5", "/usr/bin/dnf5", "fedora"),
+    candidate!("xbps" => "xbps-install", "/usr/bin/xbps-install", "void"),
+    candidate!("zypper", "/usr/bin/zypper", "suse", "opensuse"),
+];
+
+/// The candidates on this platform, in the order of preference when they
+/// score the same.
+fn candidates() -> &'static [Candidate] {
+    match () {
+        _ if cfg!(target_os = "windows") => &[candidate!("scoop", ""), candidate!("choco", "")],
+
+        _ if cfg!(target_os = "macos") => &[
+            candidate!("brew", "/usr/local/bin/brew"),
+            candidate!("port", "/opt/local/bin/port"),
+            candidate!("apt", "/opt/procursus/bin/apt"),
+        ],
+
+        _ if cfg!(target_os = "ios") => &[candidate!("apt", "/usr/bin/apt")],
+
+        _ if cfg!(target_os = "linux") => LINUX,
+
+        _ => &[],
+    }
+}
+
+/// The package managers supported, with the command printing their version.
+static BACKENDS: &[(&str, &[&str])] = &[
+    ("apk", &["apk", "--version"]),
+    ("apt", &["apt", "--version"]),
+    ("brew", &["brew", "--version"]),
+    ("choco", &["choco", "--version"]),
+    ("conda", &["conda", "--version"]),
+    ("dnf", &["dnf", "--version"]),
+    ("emerge", &["emerge", "--version"]),
+    ("pip", &["pip", "--version"]),
+    #[cfg(target_os = "macos")]
+    ("port", &["port", "version"]),
+    ("scoop", &["scoop", "--version"]),
+    ("tlmgr", &["tlmgr", "--version"]),
+    ("xbps", &["xbps-install", "--version"]),
+    ("zypper", &["zypper", "--version"]),
+];
+
+/// The markers of immutable systems.
+static MARKERS: &[Marker] = &[
+    Marker {
+        path: "/run/ostree-booted",
+        pms: &["dnf", "dnf5"],
+        note: "the system is immutable (ostree), use `rpm-ostree` or a toolbox instead",
+    },
+    Marker {
+        path: "/usr/sbin/transactional-update",
+        pms: &["zypper"],
+        note: "the system is immutable, use `transactional-update` instead",
+    },
+];
+
+/// How a package manager has been detected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Detection {
+    pub name: &'static str,
+    pub found: bool,
+    pub score: i32,
+    /// What the score is made of.
+    pub reasons: Vec<String>,
+}
+
+/// Parses the contents of an `os-release` file into its keys and unquoted
+/// values.
+fn parse_os_release(contents: &str) -> BTreeMap<String, String> {
+    contents
+        .lines()
+        .filter_map(|line| line.trim().split_once('='))
+        .filter(|(key, _)| !key.starts_with('#'))
+        .map(|(key, val)| (key.into(), val.trim_matches(['"', '\''].as_ref()).into()))
+        .collect()
+}
+
+/// Reads the `os-release` of this machine, if any.
+fn os_release() -> BTreeMap<String, String> {
+    ["/etc/os-release", "/usr/lib/os-release"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map(|contents| parse_os_release(&contents))
+        .unwrap_or_default()
+}
+
+/// Gets the `ID` and then the `ID_LIKE`s in `os_release`, eg. `rocky` and
+/// then `rhel`, `centos` and `fedora`.
+fn distro_ids(os_release: &BTreeMap<String, String>) -> Vec<&str> {
+    os_release
+        .get("ID")
+        .into_iter()
+        .chain(os_release.get("ID_LIKE"))
+        .flat_map(|ids| ids.split_whitespace())
+        .collect()
+}
+
+/// Scores the `candidates`, given whether each `is_found`, the `os_release`
+/// of the system, the immutable-system `markers` present and the `priority`
+/// list. The candidates found come first, best first.
+fn rank(
+    candidates: &'static [Candidate],
+    is_found: impl Fn(&Candidate) -> bool,
+    os_release: &BTreeMap<String, String>,
+    markers: &[&Marker],
+    priority: &[String],
+) -> Vec<Detection> {
+    let ids = distro_ids(os_release);
+    candidates
+        .iter()
+        .map(|candidate| {
+            let name = candidate.name;
+            if !is_found(candidate) {
+                return Detection {
+                    name,
+                    found: false,
+                    score: 0,
+                    reasons: vec![format!("`{}` not found", candidate.exe)],
+                };
+            }
+            let mut score = FOUND;
+            let mut reasons = vec![format!("`{}` found (+{FOUND})", candidate.exe)];
+            if let Some(id) = ids.iter().find(|id| candidate.distros.contains(id)) {
+                score += DISTRO;
+                reasons.push(format!("the system is `{id}` in os-release (+{DISTRO})"));
+            }
+            for marker in markers.iter().filter(|marker| marker.pms.contains(&name)) {
+                score += IMMUTABLE;
+                reasons.push(format!("{} ({IMMUTABLE})", marker.note));
+            }
+            if let Some(i) = priority.iter().position(|pm| pm == name) {
+                let bonus = PRIORITY - i32::try_from(i).unwrap_or(PRIORITY);
+                score += bonus;
+                reasons.push(format!("#{} in `detect_priority` (+{bonus})", i + 1));
+            }
+            Detection {
+                name,
+                found: true,
+                score,
+                reasons,
+            }
+        })
+        .sorted_by_key(|detection| (!detection.found, -detection.score))
+        .collect()
+}
+
+/// Detects all the package managers which can be used on this machine, found
+/// or not, the best first, with the `priority` list in `detect_priority`.
+#[must_use]
+pub(crate) fn detect(priority: &[String]) -> Vec<Detection> {
+    let markers = MARKERS
+        .iter()
+        .filter(|marker| Path::new(marker.path).exists())
+        .collect_vec();
+    rank(
+        candidates(),
+        |candidate| is_exe(candidate.exe, candidate.path),
+        &os_release(),
+        &markers,
+        priority,
+    )
+}
+
+/// Detects the names of all the package managers available on this machine,
+/// in order of preference.
+#[must_use]
+pub(crate) fn pms(priority: &[String]) -> Vec<&'static str> {
+    detect(priority)
+        .into_iter()
+        .filter(|detection| detection.found)
+        .map(|detection| detection.name)
+        .collect()
+}
+
+/// Detects the name of the package manager to be used in auto dispatch.
+#[must_use]
+pub(crate) fn pm_str(priority: &[String]) -> &'static str {
+    pms(priority).first().copied().unwrap_or("unknown")
+}
+
+/// Detects the package manager of the distro described by the `os-release`
+/// file `contents`, eg. that of a container.
+#[must_use]
+fn distro_pm(contents: &str) -> Option<&'static str> {
+    let os_release = parse_os_release(contents);
+    distro_ids(&os_release).into_iter().find_map(|id| {
+        LINUX
+            .iter()
+            .find(|candidate| candidate.distros.contains(&id))
+            .map(|candidate| candidate.name)
+    })
+}
+
+/// Detects the package manager of the running `container` from its
+/// `os-release`.
+///
+/// # Errors
+/// Returns an [`Error`] if the `os-release` of `container` cannot be read, or
+/// says nothing about its package manager.
+pub(crate) async fn container_pm(container: &Container) -> Result<&'static str> {
+    let contents = container.os_release().await?;
+    distro_pm(&contents).ok_or_else(|| {
+        Error::OtherError(format!(
+            "Failed to detect the package manager of the container `{}`, set it with `--using`",
+            container.name
+        ))
+    })
+}
+
+/// Shows the package managers detected and why `cfg.default_pm` has been
+/// chosen, set by `chosen_by`, eg. `--using`, or detected if `None`.
+pub(crate) fn doctor(cfg: &Config, chosen_by: Option<&str>) {
+    let pm = cfg.default_pm.as_deref().unwrap_or("unknown");
+    for detection in detect(&cfg.detect_priority) {
+        let name = if detection.name == pm {
+            detection.name.bold().to_string()
+        } else {
+            detection.name.to_owned()
+        };
+        let score = if detection.found {
+            detection.score.to_string()
+        } else {
+            "-".into()
+        };
+        println!("{name:<14}{score:>4}  {}", detection.reasons.join(", "));
+    }
+    let why = chosen_by.map_or_else(
+        || "it has the highest score".to_owned(),
+        |source| format!("it is set by {source}"),
+    );
+    print::print_msg(&format!("Using `{pm}`, as {why}"), PROMPT_INFO);
+}
+
+/// Gets the version of a package manager from the output `out` of its
+/// version command, ie. its first line.
+fn version_of(out: &[u8]) -> Option<String> {
+    let out = String::from_utf8_lossy(out);
+    let line = out.lines().map(str::trim).find(|ln| !ln.is_empty())?;
+    Some(line.to_owned())
+}
+
+/// Lists all the package managers supported and the custom ones defined in
+/// `cfg`, with whether each is found and its version, marking the one used.
+pub(crate) async fn list(cfg: &Config) {
+    let used = cfg.clone().conv::<Box<dyn Pm>>().name().to_owned();
+    let default = |name: &str| if name == used { "  (default)" } else { "" };
+    for (name, cmd) in BACKENDS {
+        let version = if is_exe(cmd[0], "") {
+            Cmd::new(cmd)
+                .exec(Mode::Mute)
+                .await
+                .ok()
+                .and_then(|out| version_of(&out))
+                .unwrap_or_else(|| "found".into())
+        } else {
+            "not found".into()
+        };
+        println!("{name:<10}{version}{}", default(name));
+    }
+    for name in cfg.backends.keys() {
+        if CustomPm::is_defined(name, cfg) {
+            println!("{name:<10}custom{}", default(name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CANDIDATES: &[Candidate] = &[
+        Candidate {
+            name: "apt",
+            exe: "apt",
+            path: "",
+            distros: &["debian"],
+        },
+        Candidate {
+            name: "dnf",
+            exe: "dnf",
+            path: "",
+            distros: &["fedora"],
+        },
+        Candidate {
+            name: "brew",
+            exe: "brew",
+            path: "",
+            distros: &[],
+        },
+    ];
+
+    fn names(detections: &[Detection]) -> Vec<&str> {
+        detections.iter().map(|detection| detection.name).collect()
+    }
+
+    #[test]
+    fn backends() {
+        for (name, _) in BACKENDS {
+            let cfg = Config {
+                default_pm: Some((*name).into()),
+                ..Config::default()
+            };
+            assert_eq!(cfg.conv::<Box<dyn Pm>>().name(), *name);
+        }
+        assert_eq!(
+            version_of(b"\napt 2.6.1 (amd64)\n").as_deref(),
+            Some("apt 2.6.1 (amd64)")
+        );
+        assert_eq!(version_of(b""), None);
+    }
+
+    #[test]
+    fn parse() {
+        let os_release = parse_os_release(indoc::indoc! {r#"
+            # A comment
+            NAME="Fedora Linux"
+            ID=fedora
+            VARIANT_ID='silverblue'
+        "#});
+        assert_eq!(os_release["ID"], "fedora");
+        assert_eq!(os_release["NAME"], "Fedora Linux");
+        assert_eq!(os_release["VARIANT_ID"], "silverblue");
+    }
+
+    #[test]
+    fn distros() {
+        assert_eq!(distro_pm("ID=alpine"), Some("apk"));
+        assert_eq!(
+            distro_pm("ID=linuxmint\nID_LIKE=\"ubuntu debian\""),
+            Some("apt")
+        );
+        assert_eq!(
+            distro_pm("ID=\"rocky\"\nID_LIKE=\"rhel centos fedora\""),
+            Some("dnf")
+        );
+        assert_eq!(distro_pm("ID=void"), Some("xbps"));
+        assert_eq!(distro_pm("ID=nixos"), None);
+    }
+
+    #[test]
+    fn scores() {
+        let all = |_: &Candidate| true;
+        let fedora = parse_os_release("ID=fedora");
+        let ranked = rank(CANDIDATES, all, &fedora, &[], &[]);
+        assert_eq!(names(&ranked), ["dnf", "apt", "brew"]);
+        assert_eq!(ranked[0].score, FOUND + DISTRO);
+
+        // Ubuntu is like Debian.
+        let ubuntu = parse_os_release("ID=ubuntu\nID_LIKE=debian");
+        let ranked = rank(CANDIDATES, |c| c.name != "dnf", &ubuntu, &[], &[]);
+        assert_eq!(names(&ranked), ["apt", "brew", "dnf"]);
+        assert!(!ranked[2].found);
+
+        let silverblue = [&MARKERS[0]];
+        let ranked = rank(CANDIDATES, all, &fedora, &silverblue, &[]);
+        assert_eq!(names(&ranked), ["apt", "dnf", "brew"]);
+        assert_eq!(ranked[1].score, FOUND + DISTRO + IMMUTABLE);
+
+        let priority = ["brew".to_owned()];
+        let ranked = rank(CANDIDATES, all, &fedora, &[], &priority);
+        assert_eq!(names(&ranked), ["brew", "dnf", "apt"]);
+
+        // The names in `detect_priority` are those of `default_pm`, not the
+        // executables.
+        let void = parse_os_release("ID=void");
+        let priority = ["xbps".to_owned()];
+        let ranked = rank(LINUX, all, &void, &[], &priority);
+        assert_eq!(ranked[0].name, "xbps");
+        assert_eq!(ranked[0].score, FOUND + DISTRO + PRIORITY);
+        assert_eq!(ranked[0].reasons[0], "`xbps-install` found (+10)");
+    }
+}
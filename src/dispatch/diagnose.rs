@@ -0,0 +1,219 @@
+//! Post-mortem analysis of failed installations, which matches the error
+//! output of the backend against known patterns to find out the root cause.
+
+use itertools::Itertools;
+
+use crate::{
+    error::Error,
+    print::{self, PROMPT_INFO},
+};
+
+/// A category of root causes of a failed installation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Cause {
+    DiskFull,
+    Network,
+    Signature,
+    Held,
+    UnmetDeps,
+}
+
+impl Cause {
+    /// All the causes, the most fundamental first.
+    const ALL: [Self; 5] = [
+        Self::DiskFull,
+        Self::Network,
+        Self::Signature,
+        Self::Held,
+        Self::UnmetDeps,
+    ];
+
+    /// A short description of the cause.
+    fn describe(self) -> &'static str {
+        match self {
+            Self::DiskFull => "the disk is full",
+            Self::Network => "a network failure",
+            Self::Signature => "a missing or invalid signature",
+            Self::Held => "held, pinned or locked packages",
+            Self::UnmetDeps => "unmet dependencies",
+        }
+    }
+
+    /// The lowercase patterns showing up in the error output when the
+    /// installation fails because of this cause.
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Self::DiskFull => &[
+                "no space left on device",
+                "not enough free space",
+                "not enough space on the disk",
+                "insufficient disk space",
+            ],
+            Self::Network => &[
+                "could not resolve host",
+                "could not resolve '",
+                "temporary failure in name resolution",
+                "failed to fetch",
+                "failed to download",
+                "connection timed out",
+                "connection refused",
+                "network is unreachable",
+                "could not connect",
+                "curl error",
+            ],
+            Self::Signature => &[
+                "no_pubkey",
+                "gpg error",
+                "badsig",
+                "bad signature",
+                "invalid signature",
+                "untrusted signature",
+                "signature verification failed",
+                "public key for",
+                "is not signed",
+            ],
+            Self::Held => &[
+                "held broken packages",
+                "held packages were changed",
+                "is pinned",
+                "is locked",
+                "masked by",
+                "excluded by",
+            ],
+            Self::UnmetDeps => &[
+                "unmet dependencies",
+                "nothing provides",
+                "conflicting requests",
+                "unsatisfiable constraints",
+                "unable to satisfy",
+                "unsatisfiableerror",
+                "resolvepackagenotfound",
+                "could not resolve dependencies",
+            ],
+        }
+    }
+
+    /// The commands which might fix the cause on `pm`, where `{pkgs}` stands
+    /// for the packages being installed.
+    fn fixes(self, pm: &str) -> &'static [&'static str] {
+        match (self, pm) {
+            (Self::DiskFull, _) => &["df -h", "pacaptr -Scc"],
+            (Self::Signature, "apt") => &["sudo apt-get update", "apt-key list"],
+            (Self::Signature, "apk") => &["sudo apk add --upgrade alpine-keys"],
+            (Self::Signature, "brew") => &["brew update-reset"],
+            (Self::Signature, "dnf") => &["sudo dnf clean all", "pacaptr -Sy"],
+            (Self::Signature, "zypper") => &["sudo zypper --gpg-auto-import-keys refresh"],
+            (Self::Network | Self::Signature, _) => &["pacaptr -Sy"],
+            (Self::Held, "apt") => &["apt-mark showhold", "sudo apt-mark unhold {pkgs}"],
+            (Self::Held, "brew") => &["brew list --pinned", "brew unpin {pkgs}"],
+            (Self::Held, "dnf") => &["dnf versionlock list"],
+            (Self::Held, "emerge") => &["emerge --pretend --verbose {pkgs}"],
+            (Self::Held, "zypper") => &["zypper locks", "sudo zypper removelock {pkgs}"],
+            (Self::Held, _) => &[],
+            (Self::UnmetDeps, "apt") => &["sudo apt-get --fix-broken install", "pacaptr -Suy"],
+            (Self::UnmetDeps, "dnf") => &["sudo dnf install --allowerasing {pkgs}"],
+            (Self::UnmetDeps, "zypper") => &["sudo zypper verify", "pacaptr -Suy"],
+            (Self::UnmetDeps, _) => &["pacaptr -Suy"],
+        }
+    }
+}
+
+/// Finds the causes of the failure in the error `output`, along with the
+/// first line showing each of them.
+fn analyze(output: &str) -> Vec<(Cause, &str)> {
+    let lines = output
+        .lines()
+        .map(|line| (line.trim(), line.to_lowercase()))
+        .collect_vec();
+    Cause::ALL
+        .into_iter()
+        .filter_map(|cause| {
+            lines
+                .iter()
+                .find(|(_, lower)| cause.patterns().iter().any(|pat| lower.contains(pat)))
+                .map(|&(line, _)| (cause, line))
+        })
+        .collect()
+}
+
+/// Checks if the most fundamental cause of the failure in the error `output`
+/// is a network failure.
+#[must_use]
+pub(crate) fn is_network_failure(output: &str) -> bool {
+    analyze(output)
+        .first()
+        .is_some_and(|(cause, _)| *cause == Cause::Network)
+}
+
+/// Prints the root causes of `err` returned by an installation of `kws` on
+/// `pm`, along with the commands which might fix them.
+pub(crate) fn explain(pm: &str, kws: &[&str], err: &Error) {
+    let output = match err {
+        Error::CmdStatusCodeError { output, .. } => String::from_utf8_lossy(output),
+        _ => return,
+    };
+    let causes = analyze(&output);
+    if causes.is_empty() {
+        print::print_msg("No known root cause found in the output", PROMPT_INFO);
+        return;
+    }
+    let pkgs = kws.join(" ");
+    for (cause, line) in causes {
+        print::print_msg(
+            &format!("Root cause: {} (`{line}`)", cause.describe()),
+            PROMPT_INFO,
+        );
+        for fix in cause.fixes(pm) {
+            print::print_msg(
+                &format!("Try `{}`", fix.replace("{pkgs}", &pkgs)),
+                PROMPT_INFO,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn causes() {
+        let output = indoc::indoc! {"
+            The following packages have unmet dependencies:
+             curl : Depends: libcurl4 (= 7.81.0-1ubuntu1.4) but 7.81.0-1ubuntu1.3 is to be installed
+            E: Unable to correct problems, you have held broken packages.
+        "};
+        assert_eq!(
+            analyze(output),
+            [
+                (
+                    Cause::Held,
+                    "E: Unable to correct problems, you have held broken packages."
+                ),
+                (
+                    Cause::UnmetDeps,
+                    "The following packages have unmet dependencies:"
+                ),
+            ]
+        );
+        assert_eq!(
+            analyze("E: Write error - write (28: No space left on device)"),
+            [(
+                Cause::DiskFull,
+                "E: Write error - write (28: No space left on device)"
+            )]
+        );
+        assert_eq!(
+            analyze("curl: (6) Could not resolve host: formulae.brew.sh"),
+            [(
+                Cause::Network,
+                "curl: (6) Could not resolve host: formulae.brew.sh"
+            )]
+        );
+        assert!(analyze("E: Unable to locate package curll").is_empty());
+        assert_eq!(
+            Cause::Held.fixes("apt"),
+            ["apt-mark showhold", "sudo apt-mark unhold {pkgs}"]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! The guards around the operations of `pacaptr` itself which end up changing
+//! the installed packages, eg. `pacaptr undo` or `pacaptr import`, so that
+//! they are run like the operations of `pacman`, eg. `-S`.
+
+use itertools::Itertools;
+use tap::prelude::*;
+
+use crate::{
+    dispatch::{
+        hooks::{self, Stage},
+        lock::Lock,
+        snapshot,
+    },
+    error::Result,
+    pm::Pm,
+};
+
+/// An operation being run under the guards, until [`Guard::leave`] is called.
+#[must_use]
+pub(crate) struct Guard {
+    pm: Box<dyn Pm>,
+    options: String,
+    kws: Vec<String>,
+    _lock: Option<Lock>,
+}
+
+impl Guard {
+    /// Enters the operation `options`, eg. `S`, on `kws` with `pm`: checks it
+    /// against the [`Policy`](crate::dispatch::policy::Policy), confines `no_confirm`
+    /// to `no_confirm_allowed_ops`, takes the lock, runs the `pre` hook and
+    /// takes a snapshot, as with `-{options}`.
+    ///
+    /// # Errors
+    /// Returns an
+    /// [`Error::PolicyViolationError`](crate::error::Error::PolicyViolationError)
+    /// when the operation is denied, an
+    /// [`Error::LockError`](crate::error::Error::LockError) when another
+    /// instance holds the lock, or any error returned by the hook or the
+    /// snapshot.
+    pub(crate) async fn enter(pm: &dyn Pm, options: &str, kws: &[&str]) -> Result<Self> {
+        pm.cfg().policy.check(pm.name(), options, kws)?;
+        let mut cfg = pm.cfg().clone();
+        cfg.confine_no_confirm(options);
+        let lock = Lock::acquire(options, cfg.dry_run, cfg.no_lock)?;
+        let pm = cfg.conv::<Box<dyn Pm>>();
+        hooks::run(&*pm, Stage::Pre, options, kws).await?;
+        snapshot::take(&*pm, options, kws).await?;
+        Ok(Guard {
+            pm,
+            options: options.into(),
+            kws: kws.iter().map(|&kw| kw.into()).collect(),
+            _lock: lock,
+        })
+    }
+
+    /// The package manager to run the operation with, whose `no_confirm` is
+    /// confined.
+    #[must_use]
+    pub(crate) fn pm(&self) -> &dyn Pm {
+        &*self.pm
+    }
+
+    /// Leaves the operation which has returned `res`, running the `post` hook
+    /// if it has succeeded, then releasing the lock.
+    ///
+    /// # Errors
+    /// Returns any error returned by the hook.
+    pub(crate) async fn leave(self, res: &Result<()>) -> Result<()> {
+        if res.is_ok() {
+            let kws = self.kws.iter().map(String::as_str).collect_vec();
+            hooks::run(&*self.pm, Stage::Post, &self.options, &kws).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! The history of the operations which change the installed packages, in the
+//! spirit of `/var/log/pacman.log`.
+
+use std::{fmt::Write as _, time::SystemTime};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    error::{Error, Result},
+    state::{State, Transaction},
+};
+
+/// The operations installing packages, with their flags sorted, eg. `Sy` for
+/// `-Sy curl` and `Suy` for `-Syu`.
+const INSTALLS: &[&str] = &["S", "Su", "Suy", "Sy", "U"];
+
+/// Checks if the operation `op` (eg. `Suy`, or `suy` as a method name)
+/// installs packages.
+#[must_use]
+pub(crate) fn is_install(op: &str) -> bool {
+    INSTALLS
+        .iter()
+        .any(|install| install.eq_ignore_ascii_case(op))
+}
+
+/// Checks if the operation `op` (eg. `Suy`, or `suy` as a method name)
+/// changes the installed packages.
+#[must_use]
+pub(crate) fn is_mutating(op: &str) -> bool {
+    is_install(op) || op.starts_with(['R', 'r'])
+}
+
+/// Records a run of `op` on `pm` with `kws` in the history, ignoring any
+/// failure.
+pub(crate) fn record(pm: &str, op: &str, kws: &[&str], res: &Result<()>) {
+    let tx = Transaction {
+        time: SystemTime::now(),
+        pm: pm.into(),
+        op: op.into(),
+        packages: kws.iter().map(|&kw| kw.into()).collect(),
+        code: res.as_ref().map_or_else(Error::exit_code, |()| 0),
+    };
+    State::open()
+        .and_then(|state| state.add_transaction(&tx))
+        .ok();
+}
+
+/// Renders a [`Transaction`] as a line, eg.
+/// `[2022-04-01 12:34] [apt] -S curl wget`.
+pub(crate) fn render(tx: &Transaction) -> String {
+    let time = DateTime::<Local>::from(tx.time).format("%Y-%m-%d %H:%M");
+    let mut line = format!("[{time}] [{}] -{}", tx.pm, tx.op);
+    for pkg in &tx.packages {
+        write!(line, " {pkg}").ok();
+    }
+    if tx.code != 0 {
+        write!(line, " (failed with code {})", tx.code).ok();
+    }
+    line
+}
+
+/// Checks if `tx` involves any of the packages in `kws`, or if `kws` is empty.
+fn involves(tx: &Transaction, kws: &[&str]) -> bool {
+    kws.is_empty() || tx.packages.iter().any(|pkg| kws.contains(&pkg.as_str()))
+}
+
+/// Prints the history, oldest first. If `kws` is not empty, only the
+/// operations involving the packages in it are printed.
+///
+/// # Errors
+/// Returns an [`Error::StateError`] when the history cannot be read.
+pub(crate) fn show(kws: &[&str]) -> Result<()> {
+    State::open()?
+        .history()?
+        .iter()
+        .filter(|tx| involves(tx, kws))
+        .for_each(|tx| println!("{}", render(tx)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutating() {
+        assert!(["S", "Sy", "Suy", "Rns", "U", "suy"]
+            .into_iter()
+            .all(is_mutating));
+        assert!(!["Q", "Ss", "Sw", "Scc"].into_iter().any(is_mutating));
+        assert!(["S", "Sy", "Suy", "u"].into_iter().all(is_install));
+        assert!(!["Rns", "Ss"].into_iter().any(is_install));
+    }
+
+    #[test]
+    fn transactions() {
+        let tx = Transaction {
+            time: SystemTime::now(),
+            pm: "apt".into(),
+            op: "S".into(),
+            packages: vec!["curl".into(), "wget".into()],
+            code: 100,
+        };
+        assert!(render(&tx).ends_with("] [apt] -S curl wget (failed with code 100)"));
+        assert!(involves(&tx, &[]));
+        assert!(involves(&tx, &["wget", "git"]));
+        assert!(!involves(&tx, &["git"]));
+    }
+}
@@ -0,0 +1,146 @@
+//! The user commands run before and after the operations, as set in the
+//! `[hooks]` section of the config file, eg. `pre_s = "timeshift --create"`
+//! or `post_suy = "flatpak update -y"`.
+//!
+//! The hooks are run with a shell, ie. `sh -c` (or `cmd /C` on Windows), and
+//! with the following environment variables:
+//! - `PACAPTR_OP`, the operation, eg. `Suy`.
+//! - `PACAPTR_PM`, the package manager, eg. `apt`.
+//! - `PACAPTR_PACKAGES`, the space-separated keywords, eg. `curl wget`.
+
+use itertools::Itertools;
+
+use crate::{
+    error::{Error, Result},
+    exec::{Cmd, Mode},
+    pm::Pm,
+    print::{self, PROMPT_WARN},
+};
+
+/// When a hook is run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Stage {
+    /// Before the operation, which is canceled if the hook fails.
+    Pre,
+    /// After the operation has succeeded.
+    Post,
+}
+
+impl Stage {
+    /// Checks if the hook `key` in `[hooks]`, eg. `post_su`, is run at this
+    /// stage of the operation `options`, eg. `Suy`. The letters of the hook
+    /// are all those of the operation, in any order, apart from the `y`
+    /// refreshing the databases, so that eg. `pre_s` is also run for
+    /// `-Sy curl`.
+    #[must_use]
+    fn matches(self, key: &str, options: &str) -> bool {
+        let stage = match self {
+            Self::Pre => "pre_",
+            Self::Post => "post_",
+        };
+        let Some(letters) = key.strip_prefix(stage) else {
+            return false;
+        };
+        let mut rest = options.to_lowercase().chars().collect_vec();
+        for c in letters.chars() {
+            let Some(i) = rest.iter().position(|&r| r == c) else {
+                return false;
+            };
+            rest.remove(i);
+        }
+        rest.iter().all(|&c| c == 'y')
+    }
+}
+
+/// Builds the [`Cmd`] running the hook `hook` with a shell.
+fn shell(hook: &str) -> Cmd {
+    if cfg!(windows) {
+        Cmd::new(&["cmd", "/C", hook])
+    } else {
+        Cmd::new(&["sh", "-c", hook])
+    }
+}
+
+/// Runs the hooks of `pm` matching the operation `options` with `kws` at
+/// `stage`, if any, see [`Stage::matches`]. In a dry run, the hooks are only
+/// printed.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when a [`Stage::Pre`] hook fails. A
+/// failing [`Stage::Post`] hook is only warned about, as the operation has
+/// already been run.
+pub(crate) async fn run(pm: &dyn Pm, stage: Stage, options: &str, kws: &[&str]) -> Result<()> {
+    let cfg = pm.cfg();
+    let mode = if cfg.dry_run {
+        Mode::PrintCmd
+    } else {
+        Mode::CheckErr
+    };
+    let hooks = cfg
+        .hooks
+        .iter()
+        .filter(|(key, _)| stage.matches(key, options));
+    for (key, hook) in hooks {
+        let cmd = shell(hook).envs(&[
+            ("PACAPTR_OP", options),
+            ("PACAPTR_PM", pm.name()),
+            ("PACAPTR_PACKAGES", &kws.iter().join(" ")),
+        ]);
+        match (cmd.exec(mode).await, stage) {
+            (Ok(_), _) => (),
+            (Err(e), Stage::Pre) => {
+                return Err(Error::OtherError(format!(
+                    "Canceled `-{options}` as the hook `{key}` failed: {e}"
+                )))
+            }
+            (Err(e), Stage::Post) => {
+                print::print_msg(&format!("The hook `{key}` failed: {e}"), PROMPT_WARN);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{dispatch::Config, pm::testing::MockPm};
+
+    #[test]
+    fn keys() {
+        assert!(Stage::Pre.matches("pre_s", "S"));
+        assert!(Stage::Pre.matches("pre_s", "Sy"));
+        assert!(Stage::Post.matches("post_suy", "Suy"));
+        assert!(Stage::Post.matches("post_syu", "Suy"));
+        assert!(Stage::Post.matches("post_su", "Suy"));
+        assert!(!Stage::Post.matches("pre_su", "Suy"));
+        assert!(!Stage::Pre.matches("pre_s", "Ss"));
+        assert!(!Stage::Pre.matches("pre_s", "Su"));
+        assert!(!Stage::Pre.matches("pre_suy", "Su"));
+        assert!(!Stage::Pre.matches("pre_r", "Rs"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[cfg(unix)]
+    async fn envs() {
+        let pm = MockPm::new(Config {
+            hooks: BTreeMap::from([
+                (
+                    "pre_s".into(),
+                    r#"test "$PACAPTR_OP $PACAPTR_PM $PACAPTR_PACKAGES" = "S mockpm curl wget""#
+                        .into(),
+                ),
+                ("pre_r".into(), "exit 1".into()),
+                ("post_r".into(), "exit 1".into()),
+            ]),
+            ..Config::default()
+        });
+        run(&pm, Stage::Pre, "S", &["curl", "wget"]).await.unwrap();
+        assert!(run(&pm, Stage::Pre, "S", &["curl"]).await.is_err());
+        assert!(run(&pm, Stage::Pre, "R", &["curl"]).await.is_err());
+        run(&pm, Stage::Post, "R", &["curl"]).await.unwrap();
+        run(&pm, Stage::Pre, "Rs", &["curl"]).await.unwrap();
+    }
+}
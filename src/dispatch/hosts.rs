@@ -0,0 +1,74 @@
+//! Running an operation on remote hosts over SSH with `--host`, eg. to keep a
+//! few servers up to date with `pacaptr -Syu --host web1 --host web2`.
+//!
+//! The hosts are gone through one by one, each command being prefixed with
+//! the host it runs on, and the results are summed up at the end.
+
+use crate::{
+    error::{Error, Result},
+    print::{self, PROMPT_INFO},
+};
+
+/// Prints the result on each host, eg. `web1  ok`, returning an error if any
+/// of them failed.
+///
+/// # Errors
+/// Returns an [`Error::HostsError`] naming the hosts which failed, with the
+/// highest exit code among them.
+pub(crate) fn report(results: &[(&str, Result<()>)]) -> Result<()> {
+    let width = results
+        .iter()
+        .map(|(host, _)| host.len())
+        .chain([4])
+        .max()
+        .unwrap_or_default();
+    println!("{:width$}  Result", "Host");
+    for (host, res) in results {
+        match res {
+            Ok(()) => println!("{host:width$}  ok"),
+            Err(e) => println!("{host:width$}  failed: {e}"),
+        }
+    }
+    let failed = results
+        .iter()
+        .filter_map(|(host, res)| res.as_ref().err().map(|e| (*host, e.exit_code())))
+        .collect::<Vec<_>>();
+    print::print_msg(
+        &format!(
+            "{} of {} host(s) succeeded",
+            results.len() - failed.len(),
+            results.len()
+        ),
+        PROMPT_INFO,
+    );
+    match failed.iter().map(|(_, code)| *code).max() {
+        None => Ok(()),
+        Some(code) => Err(Error::HostsError {
+            failed: failed.iter().map(|(host, _)| (*host).to_owned()).collect(),
+            total: results.len(),
+            code,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reported() {
+        let failure = |code| Error::CmdStatusCodeError {
+            code,
+            output: vec![],
+        };
+        assert!(report(&[("web1", Ok(())), ("web2", Ok(()))]).is_ok());
+        let err = report(&[
+            ("web1", Err(failure(1))),
+            ("web2", Ok(())),
+            ("db1", Err(failure(100))),
+        ])
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Failed on 2 of 3 host(s): web1, db1");
+        assert_eq!(err.exit_code(), 100);
+    }
+}
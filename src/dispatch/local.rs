@@ -0,0 +1,119 @@
+//! Checking the package files given to `-U`, eg. `pacaptr -U ./curl.deb`,
+//! before any of them is installed, and downloading the ones given as URLs
+//! when the package manager cannot fetch them by itself.
+
+use std::{path::Path, time::Duration};
+
+use itertools::Itertools;
+
+use crate::{
+    dispatch::partial,
+    error::{Error, Result},
+    net::Downloads,
+    pm::{is_url, Pm},
+};
+
+/// Checks that each of the package files `kws` given to `-U` is either a URL
+/// or an existing file. Does nothing for the other operations `options`.
+///
+/// # Errors
+/// Returns an [`Error::ArgParseError`] naming the first package file not
+/// found.
+pub(crate) fn check(options: &str, kws: &[&str]) -> Result<()> {
+    if options != "U" {
+        return Ok(());
+    }
+    match kws
+        .iter()
+        .find(|&&kw| !is_url(kw) && !Path::new(kw).is_file())
+    {
+        Some(kw) => Err(Error::ArgParseError {
+            msg: format!("Package file `{kw}` is not found"),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Checks that the package files `kws` given to `-U` can be used on a remote
+/// host or in a container, where `pm` runs, ie. that they are URLs which `pm`
+/// can fetch by itself. The local files are not copied there. Does nothing
+/// for the other operations `options`.
+///
+/// # Errors
+/// Returns an [`Error::ArgParseError`] naming the first package file which
+/// cannot be used.
+pub(crate) fn check_remote(pm: &dyn Pm, options: &str, kws: &[&str]) -> Result<()> {
+    if options != "U" {
+        return Ok(());
+    }
+    match kws.iter().find(|&&kw| !is_url(kw) || !pm.supports_url()) {
+        Some(kw) => Err(Error::ArgParseError {
+            msg: format!(
+                "Package file `{kw}` cannot be installed with `--host` or `--container`, \
+                 give a URL which `{}` can fetch instead",
+                pm.name()
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Checks if some of the package files `kws` given to `-U` are URLs which
+/// `pm` cannot fetch by itself.
+#[must_use]
+pub(crate) fn needs_download(pm: &dyn Pm, kws: &[&str]) -> bool {
+    !pm.supports_url() && kws.iter().any(|kw| is_url(kw))
+}
+
+/// Downloads the package files `kws` given as URLs into a temporary directory,
+/// then installs them along with the other ones with `pm`. The downloads are
+/// removed afterwards.
+///
+/// # Errors
+/// Returns any error of the downloads, or of [`u`](crate::pm::PmSync::u).
+pub(crate) async fn install(pm: &dyn Pm, kws: &[&str], flags: &[&str]) -> Result<()> {
+    let cfg = pm.cfg();
+    let downloads = Downloads::new(cfg.dry_run, cfg.timeout.map(Duration::from_secs))?;
+    let mut paths = vec![];
+    for &kw in kws {
+        paths.push(if is_url(kw) {
+            downloads.fetch(kw).await?.to_string_lossy().into_owned()
+        } else {
+            kw.to_owned()
+        });
+    }
+    let paths = paths.iter().map(String::as_str).collect_vec();
+    partial::run_one(pm, "U", &paths, flags).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatch::Config,
+        pm::{Apt, Zypper},
+    };
+
+    #[test]
+    fn checked() {
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let url = "https://example.com/curl.rpm";
+        assert!(check("U", &[manifest, url]).is_ok());
+        assert!(check("S", &["curl"]).is_ok());
+        let err = check("U", &[manifest, "curl.deb"]).unwrap_err();
+        assert!(matches!(err, Error::ArgParseError { .. }));
+
+        assert!(needs_download(
+            &Apt::new(Config::default()),
+            &[manifest, url]
+        ));
+        assert!(!needs_download(&Apt::new(Config::default()), &[manifest]));
+        assert!(!needs_download(&Zypper::new(Config::default()), &[url]));
+
+        let remote = |pm: &dyn Pm, kw| check_remote(pm, "U", &[kw]).is_ok();
+        assert!(remote(&Zypper::new(Config::default()), url));
+        assert!(!remote(&Zypper::new(Config::default()), manifest));
+        assert!(!remote(&Apt::new(Config::default()), url));
+        assert!(check_remote(&Apt::new(Config::default()), "S", &["curl"]).is_ok());
+    }
+}
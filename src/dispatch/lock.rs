@@ -0,0 +1,146 @@
+//! An advisory lock taken before the operations which change the installed
+//! packages, so that two `pacaptr` instances, eg. running `-Syu` in two
+//! terminals or as two users, don't race each other in the package manager.
+//!
+//! The lock is an OS file lock (`flock` on Unix) on a file shared by all the
+//! users, which also holds the pid of its owner. The OS releases it when its
+//! owner is gone, even if it was killed, so that a stale lock cannot be left
+//! behind.
+
+use std::{
+    env,
+    fs::{File, OpenOptions, TryLockError},
+    io::{ErrorKind, Read, Seek, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{
+    dispatch::history,
+    error::{Error, Result},
+};
+
+/// The lock held by this instance, released when dropped.
+#[derive(Debug)]
+pub(crate) struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// The path of the lock file shared by all the users, eg.
+    /// `/run/lock/pacaptr.lock`.
+    #[must_use]
+    fn path() -> PathBuf {
+        let dir = if cfg!(windows) {
+            env::var_os("ProgramData").map_or_else(env::temp_dir, PathBuf::from)
+        } else {
+            Some(Path::new("/run/lock"))
+                .filter(|dir| dir.is_dir())
+                .map_or_else(env::temp_dir, Path::to_owned)
+        };
+        dir.join("pacaptr.lock")
+    }
+
+    /// Takes the lock before running `-{options}` if it changes the installed
+    /// packages, unless `no_lock` is set or it's a `dry_run`.
+    ///
+    /// # Errors
+    /// Returns an [`Error::LockError`] when another instance holds the lock.
+    pub(crate) fn acquire(options: &str, dry_run: bool, no_lock: bool) -> Result<Option<Self>> {
+        if no_lock || dry_run || !history::is_mutating(options) {
+            return Ok(None);
+        }
+        Self::acquire_at(&Self::path()).map(Some)
+    }
+
+    /// Takes the lock at `path`.
+    fn acquire_at(path: &Path) -> Result<Self> {
+        let mut file = open(path)?;
+        match file.try_lock() {
+            Ok(()) => (),
+            Err(TryLockError::WouldBlock) => {
+                let mut pid = String::new();
+                file.read_to_string(&mut pid).ok();
+                return Err(Error::LockError {
+                    pid: pid.trim().parse().unwrap_or_default(),
+                    path: path.into(),
+                });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        // The pid is only informative, and cannot be written when the file
+        // belongs to another user who has not shared it.
+        if file.set_len(0).is_ok() {
+            file.rewind().ok();
+            write!(file, "{}", process::id()).ok();
+        }
+        Ok(Self { _file: file })
+    }
+}
+
+/// Opens the lock file at `path`, creating it writable by all the users if
+/// it's missing.
+///
+/// The file is never opened with `O_CREAT` once it exists, which is denied in
+/// a shared directory like `/tmp` when the file belongs to another user, and
+/// falls back to being read only, which is enough to lock it.
+fn open(path: &Path) -> Result<File> {
+    loop {
+        match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(file) => return Ok(file),
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => return Ok(File::open(path)?),
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            Err(_) => (),
+        }
+        match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+        {
+            Ok(file) => {
+                #[cfg(unix)]
+                {
+                    use std::{fs, os::unix::fs::PermissionsExt};
+                    fs::set_permissions(path, fs::Permissions::from_mode(0o666))?;
+                }
+                return Ok(file);
+            }
+            // Another instance has just created it.
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn lock() {
+        let path = env::temp_dir().join(format!("pacaptr-{}.lock", process::id()));
+        fs::remove_file(&path).ok();
+
+        let lock = Lock::acquire_at(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            process::id().to_string()
+        );
+        let err = Lock::acquire_at(&path).unwrap_err();
+        assert!(matches!(err, Error::LockError { pid, .. } if pid == process::id()));
+        drop(lock);
+
+        // The file is left behind, but not the lock, whatever its pid says.
+        fs::write(&path, "4194304").unwrap();
+        let lock = Lock::acquire_at(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            process::id().to_string()
+        );
+        drop(lock);
+        fs::remove_file(&path).ok();
+    }
+}
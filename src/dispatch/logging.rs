@@ -0,0 +1,82 @@
+//! `-v`/`-vv` and `log_file`, reporting what `pacaptr` does under the hood
+//! with [`tracing`].
+//!
+//! The diagnostics, eg. the package manager detected and the commands built,
+//! are printed to `stderr` according to the verbosity, while a JSON record of
+//! each command executed, and of each config reloaded, is appended to the log
+//! file, if any.
+
+use std::{fs::OpenOptions, path::Path, sync::Mutex};
+
+use tracing::{level_filters::LevelFilter, Level};
+use tracing_subscriber::{filter, fmt, prelude::*};
+
+use crate::error::{Error, Result};
+
+/// The target of the records of the commands executed, kept in the log file.
+pub(crate) const EXEC_TARGET: &str = "pacaptr::exec";
+
+/// The target of the records of the config reloaded by the long-running
+/// modes, kept in the log file.
+pub(crate) const CONFIG_TARGET: &str = "pacaptr::config";
+
+/// Gets the maximum level of the diagnostics printed to `stderr` given the
+/// number of `-v`s.
+#[must_use]
+fn level(verbose: u8) -> LevelFilter {
+    match verbose {
+        0 => LevelFilter::OFF,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// Starts reporting the diagnostics according to `verbose` and appending the
+/// records of the commands executed to `log_file`.
+///
+/// # Errors
+/// Returns an [`Error::ConfigError`] when `log_file` cannot be opened.
+pub(crate) fn init(verbose: u8, log_file: Option<&Path>) -> Result<()> {
+    let file = log_file
+        .map(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| Error::ConfigError {
+                    msg: format!("Failed to open the log file `{}`: {e}", path.display()),
+                })
+        })
+        .transpose()?;
+    let stderr = fmt::layer()
+        .with_writer(std::io::stderr)
+        .without_time()
+        .with_filter(level(verbose));
+    let records = file.map(|file| {
+        fmt::layer()
+            .json()
+            .with_writer(Mutex::new(file))
+            .with_filter(filter::filter_fn(|meta| {
+                [EXEC_TARGET, CONFIG_TARGET].contains(&meta.target())
+                    && *meta.level() <= Level::INFO
+            }))
+    });
+    // `init` is only expected to be called once, and whoever comes first wins.
+    _ = tracing_subscriber::registry()
+        .with(stderr)
+        .with(records)
+        .try_init();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels() {
+        assert_eq!(level(0), LevelFilter::OFF);
+        assert_eq!(level(1), LevelFilter::DEBUG);
+        assert_eq!(level(3), LevelFilter::TRACE);
+    }
+}
@@ -0,0 +1,62 @@
+//! Generation of man pages from the command line definition.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use clap::{Command, CommandFactory};
+use clap_mangen::Man;
+
+use crate::{dispatch::Pacaptr, error::Result};
+
+/// Renders the man page of `cmd` in `roff` format to `out`.
+fn render(cmd: Command, out: &mut impl Write) -> Result<()> {
+    Man::new(cmd).render(out)?;
+    Ok(())
+}
+
+/// Generates the man pages of [`pacaptr`](crate).
+///
+/// If `out_dir` is `None`, the main page is printed to `stdout`.
+/// Otherwise, `pacaptr.1` and `pacaptr-<operation>.1` for each operation
+/// are written to `out_dir`.
+///
+/// # Errors
+/// Returns an [`Error::IoError`](crate::error::Error::IoError) when a page
+/// cannot be written.
+pub(crate) fn generate(out_dir: Option<&Path>) -> Result<()> {
+    let cmd = Pacaptr::command();
+    let Some(out_dir) = out_dir else {
+        return render(cmd, &mut io::stdout().lock());
+    };
+
+    let name = cmd.get_name().to_owned();
+    let write_page = |cmd: Command, file_name: &str| -> Result<()> {
+        let mut out = BufWriter::new(File::create(out_dir.join(file_name))?);
+        render(cmd, &mut out)?;
+        out.flush()?;
+        Ok(())
+    };
+
+    for sub in cmd.get_subcommands() {
+        let sub_name = format!("{name}-{}", sub.get_name());
+        write_page(sub.clone().name(&sub_name), &format!("{sub_name}.1"))?;
+    }
+    write_page(cmd, &format!("{name}.1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_main_page() {
+        let mut out = Vec::new();
+        render(Pacaptr::command(), &mut out).unwrap();
+        let page = String::from_utf8(out).unwrap();
+        assert!(page.contains(".TH pacaptr 1"));
+        assert!(page.contains("\\-\\-using"));
+    }
+}
@@ -0,0 +1,174 @@
+//! Download manifests for reproducible and air-gapped installations.
+//!
+//! `pacaptr -Sw --manifest <file>` copies the downloaded package files next to
+//! the manifest, which records their names, versions, URLs and SHA-256
+//! checksums. After transferring the whole directory, `pacaptr -U --manifest
+//! <file>` verifies the package files and installs them.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{Error, Result},
+    pm::Pm,
+    print::{self, PROMPT_INFO},
+};
+
+/// A download manifest.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    #[serde(default, rename = "package")]
+    packages: Vec<Entry>,
+}
+
+/// A package file recorded in a [`Manifest`].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    name: String,
+    version: String,
+    url: String,
+    /// The file name, relative to the directory of the manifest.
+    file: String,
+    sha256: String,
+}
+
+/// Computes the SHA-256 checksum of the file at `path` as a hex string.
+fn sha256(path: &Path) -> Result<String> {
+    let digest = Sha256::digest(fs::read(path)?);
+    Ok(digest.iter().fold(String::new(), |mut hex, b| {
+        write!(hex, "{b:02x}").ok();
+        hex
+    }))
+}
+
+/// Gets the directory containing the manifest at `path`.
+fn dir_of(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if dir != Path::new("") => dir.into(),
+        _ => ".".into(),
+    }
+}
+
+/// Downloads the packages in `kws` with [`PmSync::sw`](crate::pm::PmSync::sw),
+/// then copies the package files next to the manifest at `path` and writes the
+/// manifest.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] when `pm` cannot list the
+/// downloaded package files, or an [`Error::IoError`] when they cannot be
+/// copied.
+pub(crate) async fn download(pm: &dyn Pm, kws: &[&str], flags: &[&str], path: &Path) -> Result<()> {
+    let files = pm.sw_files(kws, flags).await?;
+    pm.sw(kws, flags).await?;
+    if pm.cfg().dry_run {
+        return Ok(());
+    }
+
+    let dir = dir_of(path);
+    fs::create_dir_all(&dir)?;
+    let packages = files
+        .into_iter()
+        .map(|pkg| {
+            let file = pkg
+                .path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .ok_or_else(|| {
+                    Error::OtherError(format!("Invalid package file `{}`", pkg.path.display()))
+                })?
+                .to_owned();
+            let dest = dir.join(&file);
+            fs::copy(&pkg.path, &dest)?;
+            Ok(Entry {
+                sha256: sha256(&dest)?,
+                name: pkg.name,
+                version: pkg.version,
+                url: pkg.url,
+                file,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let manifest = toml::to_string(&Manifest { packages })
+        .map_err(|e| Error::OtherError(format!("Failed to serialize manifest: {e}")))?;
+    fs::write(path, manifest)?;
+    print::print_msg(
+        &format!("Manifest written to `{}`", path.display()),
+        PROMPT_INFO,
+    );
+    Ok(())
+}
+
+/// Verifies the package files listed in the manifest at `path`, then installs
+/// them with [`PmSync::u`](crate::pm::PmSync::u).
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when the manifest cannot be parsed or a
+/// checksum does not match.
+pub(crate) async fn install(pm: &dyn Pm, flags: &[&str], path: &Path) -> Result<()> {
+    let manifest: Manifest = toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| Error::OtherError(format!("Failed to parse manifest: {e}")))?;
+    let dir = fs::canonicalize(dir_of(path))?;
+
+    let files = manifest
+        .packages
+        .iter()
+        .map(|entry| {
+            let file = dir.join(&entry.file);
+            if sha256(&file)? != entry.sha256.to_ascii_lowercase() {
+                return Err(Error::OtherError(format!(
+                    "Checksum mismatch for `{}`",
+                    file.display()
+                )));
+            }
+            file.into_os_string().into_string().map_err(|file| {
+                Error::OtherError(format!("Invalid package file `{}`", file.to_string_lossy()))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let files = files.iter().map(String::as_str).collect_vec();
+    print::print_msg(
+        &format!("Verified {} package file(s)", files.len()),
+        PROMPT_INFO,
+    );
+    pm.u(&files, flags).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_roundtrip() {
+        let manifest = Manifest {
+            packages: vec![Entry {
+                name: "curl".into(),
+                version: "7.81.0-1".into(),
+                url: "http://deb.debian.org/debian/pool/main/c/curl/curl_7.81.0-1_amd64.deb".into(),
+                file: "curl_7.81.0-1_amd64.deb".into(),
+                sha256: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".into(),
+            }],
+        };
+        let s = toml::to_string(&manifest).unwrap();
+        assert!(s.starts_with("[[package]]\n"));
+        assert_eq!(toml::from_str::<Manifest>(&s).unwrap(), manifest);
+    }
+
+    #[test]
+    fn checksum() {
+        let path = std::env::temp_dir().join("pacaptr-manifest-checksum");
+        fs::write(&path, b"").unwrap();
+        assert_eq!(
+            sha256(&path).unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+//! Printing the unread news of the distribution before upgrading the system
+//! with `show_news` set, eg. the manual interventions announced on the Arch
+//! Linux news feed.
+
+use crate::{
+    exec::{Cmd, Mode},
+    pm::Pm,
+    print::{self, PROMPT_INFO, PROMPT_WARN},
+};
+
+/// The kind of news printed for `pm`, eg. `news`, along with the command
+/// printing them, if there is a source of news for it.
+#[must_use]
+fn news_cmd(pm: &str) -> Option<(&'static str, &'static [&'static str])> {
+    match pm {
+        // The Arch Linux news, for a custom package manager named `pacman`.
+        "pacman" => Some(("news", &["informant", "list", "--unread"])),
+        // The advisories for the available updates.
+        "dnf" => Some(("advisories", &["dnf", "updateinfo", "--summary"])),
+        "zypper" => Some(("advisories", &["zypper", "list-patches"])),
+        _ => None,
+    }
+}
+
+/// Prints the unread news before running `-{options}` if it upgrades the
+/// system and `show_news` is set. A failure to get the news is only warned
+/// about.
+pub(crate) async fn show(pm: &dyn Pm, options: &str) {
+    let cfg = pm.cfg();
+    if !cfg.show_news || !matches!(options, "Su" | "Suy") {
+        return;
+    }
+    let Some((kind, cmd)) = news_cmd(pm.name()) else {
+        return;
+    };
+    if which::which(cmd[0]).is_err() {
+        tracing::debug!(cmd = cmd[0], "Skipped the news as the command is not found");
+        return;
+    }
+    let mode = if cfg.dry_run {
+        Mode::PrintCmd
+    } else {
+        Mode::CheckErr
+    };
+    print::print_msg(&format!("The {kind} for `{}`:", pm.name()), PROMPT_INFO);
+    if let Err(e) = Cmd::new(cmd).exec(mode).await {
+        print::print_msg(&format!("Failed to get the {kind}: {e}"), PROMPT_WARN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources() {
+        assert_eq!(
+            news_cmd("pacman").map(|(kind, cmd)| (kind, cmd[0])),
+            Some(("news", "informant"))
+        );
+        assert_eq!(
+            news_cmd("zypper").map(|(kind, cmd)| (kind, cmd[0])),
+            Some(("advisories", "zypper"))
+        );
+        assert_eq!(news_cmd("apt"), None);
+    }
+}
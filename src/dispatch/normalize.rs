@@ -0,0 +1,145 @@
+//! `--normalize`, reformatting the output of `-Ss`, `-Si` and `-Qi` into the
+//! layout of `pacman`, so that it reads the same whatever the package manager.
+
+use std::fmt::Write;
+
+use crate::{
+    error::{Error, Result},
+    pm::{PkgInfo, Pm, SearchHit},
+};
+
+/// Renders a package found by `-Ss` like `pacman` does, ie.
+/// `<repo>/<name> <version>` followed by its description indented, leaving
+/// out the repository when it's unknown.
+#[must_use]
+fn render_hit(hit: &SearchHit) -> String {
+    let mut res = if hit.repo.is_empty() {
+        hit.name.clone()
+    } else {
+        format!("{}/{}", hit.repo, hit.name)
+    };
+    if !hit.version.is_empty() {
+        _ = write!(res, " {}", hit.version);
+    }
+    if !hit.desc.is_empty() {
+        _ = write!(res, "\n    {}", hit.desc);
+    }
+    res
+}
+
+/// Renders the details of a package like `pacman -Si` does, ie. one
+/// `<key> : <value>` field per line with the keys aligned.
+#[must_use]
+fn render_info(info: &PkgInfo) -> String {
+    let or_none = |value: &str| {
+        if value.is_empty() {
+            "None".to_owned()
+        } else {
+            value.to_owned()
+        }
+    };
+    let mut fields = vec![];
+    if !info.repo.is_empty() {
+        fields.push(("Repository", info.repo.clone()));
+    }
+    fields.extend([
+        ("Name", info.name.clone()),
+        ("Version", info.version.clone()),
+        ("Description", or_none(&info.desc)),
+        ("URL", or_none(&info.url)),
+        ("Licenses", or_none(&info.licenses)),
+        ("Depends On", or_none(&info.depends.join("  "))),
+    ]);
+    if !info.installed_size.is_empty() {
+        fields.push(("Installed Size", info.installed_size.clone()));
+    }
+    fields
+        .into_iter()
+        .map(|(key, value)| format!("{key:<15} : {value}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Runs `-{options}` with its output reformatted, which is either `-Ss`, `-Si`
+/// or `-Qi`.
+///
+/// # Errors
+/// Returns an [`Error::ArgParseError`] when `options` is not supported, and an
+/// [`Error::OperationUnimplementedError`] when `pm` cannot return the packages.
+pub(crate) async fn run(pm: &dyn Pm, options: &str, kws: &[&str], flags: &[&str]) -> Result<()> {
+    match options {
+        "Ss" => {
+            let hits = pm.search(kws, flags).await.map_err(|e| match e {
+                Error::OperationUnimplementedError { pm, .. } => {
+                    Error::OperationUnimplementedError {
+                        op: "ss --normalize".into(),
+                        pm,
+                    }
+                }
+                e => e,
+            })?;
+            for hit in hits {
+                println!("{}", render_hit(&hit));
+            }
+        }
+        "Si" | "Qi" => {
+            let infos = pm.info(kws, flags, options == "Qi").await?;
+            for info in infos {
+                println!("{}\n", render_info(&info));
+            }
+        }
+        _ => {
+            return Err(Error::ArgParseError {
+                msg: format!("`--normalize` cannot be used with `-{options}`"),
+            })
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendered() {
+        let hit = SearchHit {
+            name: "curl".into(),
+            version: "7.88.1-10".into(),
+            desc: "command line tool for transferring data".into(),
+            ..SearchHit::default()
+        };
+        assert_eq!(
+            render_hit(&hit),
+            "curl 7.88.1-10\n    command line tool for transferring data"
+        );
+        let hit = SearchHit {
+            repo: "homebrew/core".into(),
+            ..hit
+        };
+        assert_eq!(
+            render_hit(&hit),
+            "homebrew/core/curl 7.88.1-10\n    command line tool for transferring data"
+        );
+        let info = PkgInfo {
+            name: "curl".into(),
+            version: "7.88.1-10".into(),
+            depends: vec!["libc6".into(), "zlib1g".into()],
+            installed_size: "500 KiB".into(),
+            ..PkgInfo::default()
+        };
+        assert_eq!(
+            render_info(&info),
+            [
+                "Name            : curl",
+                "Version         : 7.88.1-10",
+                "Description     : None",
+                "URL             : None",
+                "Licenses        : None",
+                "Depends On      : libc6  zlib1g",
+                "Installed Size  : 500 KiB",
+            ]
+            .join("\n")
+        );
+    }
+}
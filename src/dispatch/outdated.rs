@@ -0,0 +1,75 @@
+//! The number of the packages which can be upgraded, as printed by `pacaptr
+//! -Qu --count`, eg. for the status lines of `waybar`, `polybar` or `tmux`.
+//!
+//! Each count is saved in the [`State`] store, so that `--cached` can print
+//! the last one without checking the repositories again.
+
+use std::time::Duration;
+
+use crate::{
+    error::{Error, Result},
+    pm::Pm,
+    state::State,
+};
+
+/// How long a saved count is reused with `--cached`.
+const CACHE_TTL: Duration = Duration::from_hours(1);
+
+/// The key of the count saved for the packages `kws` of `pm`.
+#[must_use]
+fn cache_key(pm: &dyn Pm, kws: &[&str]) -> String {
+    format!("outdated/{}/{}", pm.name(), kws.join(" "))
+}
+
+/// Counts the packages `kws` which can be upgraded with `pm`, or all of them
+/// if `kws` is empty, saving the count for `--cached`.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] when `pm` cannot list the
+/// packages to be upgraded, or any error returned by it.
+pub(crate) async fn save_count(pm: &dyn Pm, kws: &[&str]) -> Result<usize> {
+    let count = pm
+        .upgradable()
+        .await
+        .map_err(|e| match e {
+            Error::OperationUnimplementedError { pm, .. } => Error::OperationUnimplementedError {
+                op: "qu --count".into(),
+                pm,
+            },
+            e => e,
+        })?
+        .iter()
+        .filter(|name| kws.is_empty() || kws.contains(&name.as_str()))
+        .count();
+    // Nothing is actually checked in a dry run.
+    if let Some(state) = State::open().ok().filter(|_| !pm.cfg().dry_run) {
+        state
+            .cache(&cache_key(pm, kws), count.to_string().as_bytes())
+            .ok();
+    }
+    Ok(count)
+}
+
+/// Prints the number of the packages `kws` which can be upgraded with `pm`,
+/// or of all the packages installed if `kws` is empty. If `cached`, the
+/// count saved by a previous run within [`CACHE_TTL`] is printed instead if
+/// there is one.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] when `pm` cannot list the
+/// packages to be upgraded, or any error returned by it.
+pub(crate) async fn count(pm: &dyn Pm, kws: &[&str], cached: bool) -> Result<()> {
+    let saved = State::open()
+        .ok()
+        .filter(|_| cached)
+        .and_then(|state| state.cached(&cache_key(pm, kws), CACHE_TTL).ok().flatten())
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    if let Some(count) = saved {
+        println!("{count}");
+        return Ok(());
+    }
+
+    let count = save_count(pm, kws).await?;
+    println!("{count}");
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! `--skip-missing`, running an operation over the given packages one by one,
+//! so that a package which cannot be handled, eg. because it's not found,
+//! doesn't abort the others, whatever the package manager.
+
+use itertools::Itertools;
+
+use crate::{
+    error::{Error, Result},
+    pm::Pm,
+    print,
+};
+
+/// Checks if the operation `options`, eg. `Rs`, can be safely run package by
+/// package.
+#[must_use]
+pub(crate) fn is_splittable(options: &str) -> bool {
+    matches!(options, "S" | "Sw" | "U") || options.starts_with('R')
+}
+
+/// Runs the operation `options` on each of the packages `kws` separately,
+/// then prints the result for each one.
+///
+/// # Errors
+/// Returns an [`Error::BatchError`] listing the packages for which the
+/// operation failed, if any.
+pub(crate) async fn run(pm: &dyn Pm, options: &str, kws: &[&str], flags: &[&str]) -> Result<()> {
+    if kws.len() < 2 {
+        return run_one(pm, options, kws, flags).await;
+    }
+    let mut results = vec![];
+    for &kw in kws {
+        results.push((kw, run_one(pm, options, &[kw], flags).await));
+    }
+    report(&results)
+}
+
+/// Runs the operation `options`, one of the [splittable](is_splittable)
+/// ones, on the packages `kws` at once.
+///
+/// # Errors
+/// Returns an [`Error::ArgParseError`] if `options` is not splittable, or any
+/// error returned by the [`Pm`] method.
+pub(crate) async fn run_one(
+    pm: &dyn Pm,
+    options: &str,
+    kws: &[&str],
+    flags: &[&str],
+) -> Result<()> {
+    match options {
+        "S" => pm.s(kws, flags).await,
+        "Sw" => pm.sw(kws, flags).await,
+        "U" => pm.u(kws, flags).await,
+        "R" => pm.r(kws, flags).await,
+        "Rn" => pm.rn(kws, flags).await,
+        "Rns" => pm.rns(kws, flags).await,
+        "Rs" => pm.rs(kws, flags).await,
+        "Rss" => pm.rss(kws, flags).await,
+        _ => Err(Error::ArgParseError {
+            msg: format!("`--skip-missing` cannot be used with `-{options}`"),
+        }),
+    }
+}
+
+/// Prints a table of the result for each package, eg. `curl  ok`, returning
+/// an error if any of them failed.
+pub(crate) fn report(results: &[(&str, Result<()>)]) -> Result<()> {
+    let width = results
+        .iter()
+        .map(|(kw, _)| kw.len())
+        .chain([7])
+        .max()
+        .unwrap_or_default();
+    println!("{:width$}  Result", "Package");
+    for (kw, res) in results {
+        match res {
+            Ok(()) => println!("{kw:width$}  ok"),
+            Err(e) => println!("{kw:width$}  failed: {e}"),
+        }
+    }
+    let failed = results
+        .iter()
+        .filter(|(_, res)| res.is_err())
+        .map(|(kw, _)| (*kw).to_owned())
+        .collect_vec();
+    print::print_msg(
+        &format!(
+            "{} of {} package(s) succeeded",
+            results.len() - failed.len(),
+            results.len()
+        ),
+        print::PROMPT_INFO,
+    );
+    if failed.is_empty() {
+        return Ok(());
+    }
+    Err(Error::BatchError {
+        failed,
+        total: results.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reported() {
+        let missing = || Error::OperationUnimplementedError {
+            op: "s".into(),
+            pm: "mockpm".into(),
+        };
+        assert!(report(&[("curl", Ok(())), ("wget", Ok(()))]).is_ok());
+
+        let err =
+            report(&[("curl", Ok(())), ("nope", Err(missing())), ("wget", Ok(()))]).unwrap_err();
+        assert_eq!(err.to_string(), "Failed for 1 of 3 package(s): nope");
+        assert_eq!(err.exit_code(), 2);
+
+        let err = report(&[("nope", Err(missing())), ("nah", Err(missing()))]).unwrap_err();
+        assert_eq!(err.exit_code(), 1);
+
+        assert!(is_splittable("Rns") && is_splittable("S"));
+        assert!(!is_splittable("Ss") && !is_splittable("Suy"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Guarding against partial upgrades with the package managers which don't
+//! support them, eg. `pacman`: installing packages after refreshing the
+//! package databases with `-Sy` but without upgrading the system with `-u`
+//! might pull in versions which are incompatible with the installed ones.
+
+use std::time::{Duration, SystemTime};
+
+use itertools::Itertools;
+use tap::prelude::*;
+use tokio::task;
+
+use crate::{
+    error::Result,
+    exec,
+    pm::Pm,
+    print::{self, PROMPT_WARN},
+    state::State,
+};
+
+/// The package managers which don't support partial upgrades, including the
+/// custom ones named after them.
+static GUARDED: &[&str] = &["pacman", "xbps"];
+
+/// How long after a refresh without an upgrade installing packages is
+/// considered a partial upgrade.
+const WINDOW: Duration = Duration::from_hours(24);
+
+/// Checks if running `-{options}` with `kws` is a partial upgrade, given when
+/// the package databases have been refreshed without upgrading the system, if
+/// they have.
+#[must_use]
+fn is_partial(options: &str, kws: &[&str], refreshed: Option<SystemTime>) -> bool {
+    match options {
+        "Sy" => !kws.is_empty(),
+        "S" => {
+            !kws.is_empty()
+                && refreshed.is_some_and(|time| {
+                    SystemTime::now()
+                        .duration_since(time)
+                        .is_ok_and(|elapsed| elapsed < WINDOW)
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Warns if running `-{options}` with `kws` is a partial upgrade, offering to
+/// upgrade the system as well by adding `-u`. Returns the options to be run in
+/// the end.
+#[must_use]
+pub(crate) fn guard(pm: &dyn Pm, options: &str, kws: &[&str]) -> String {
+    if !GUARDED.contains(&pm.name()) {
+        return options.into();
+    }
+    let refreshed = State::open()
+        .and_then(|state| state.refreshed(pm.name()))
+        .ok()
+        .flatten();
+    if !is_partial(options, kws, refreshed) {
+        return options.into();
+    }
+    print::print_msg(
+        &format!(
+            "Installing packages with `-{options}` after refreshing the package databases without \
+             upgrading the system is a partial upgrade, which `{}` doesn't support and might \
+             break the system",
+            pm.name()
+        ),
+        PROMPT_WARN,
+    );
+    let cfg = pm.cfg();
+    let upgrade = !cfg.dry_run
+        && (cfg.no_confirm
+            || task::block_in_place(|| {
+                exec::prompt(
+                    "Upgrade the system as well with `-u`",
+                    "[YES/no]",
+                    &["", "y", "yes", "n", "no"],
+                    false,
+                )
+            })
+            .pipe(|answer| matches!(answer, "" | "y" | "yes")));
+    if upgrade {
+        options.chars().chain(['u']).sorted_unstable().collect()
+    } else {
+        options.into()
+    }
+}
+
+/// Keeps track of the refreshes of the package databases without upgrades
+/// after `-{options}` has returned `res`.
+pub(crate) fn record(pm: &dyn Pm, options: &str, res: &Result<()>) {
+    if !GUARDED.contains(&pm.name()) || res.is_err() {
+        return;
+    }
+    // Failing to record the refresh should not fail the operation itself.
+    let Ok(state) = State::open() else {
+        return;
+    };
+    match options {
+        "Sy" => state.set_refreshed(pm.name(), SystemTime::now()).ok(),
+        "Su" | "Suy" => state.forget_refreshed(pm.name()).ok(),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial() {
+        let now = Some(SystemTime::now());
+        let long_ago = Some(SystemTime::now() - 2 * WINDOW);
+        assert!(is_partial("Sy", &["curl"], None));
+        assert!(!is_partial("Sy", &[], None));
+        assert!(is_partial("S", &["curl"], now));
+        assert!(!is_partial("S", &["curl"], long_ago));
+        assert!(!is_partial("S", &["curl"], None));
+        assert!(!is_partial("Suy", &["curl"], now));
+        assert!(!is_partial("Sw", &["curl"], now));
+    }
+}
@@ -0,0 +1,183 @@
+//! Interactive selection of the packages to be installed among search
+//! results, in the style of `yay <keyword>`.
+
+use std::io::{self, Write};
+
+use itertools::Itertools;
+use tokio::task;
+
+use crate::{
+    error::{Error, Result},
+    pm::{Pm, SearchHit},
+    print::{self, PROMPT_INFO},
+};
+
+/// Parses a selection of the numbered items of a list of `len` items, eg.
+/// `1 2 3`, `1-3` or `^4` (all but the 4th), returning the selected indices
+/// in ascending order. Numbers out of range are ignored.
+///
+/// Returns `None` if `input` is not a selection, in which case it should be
+/// treated as a query to filter the list instead.
+fn parse_selection(input: &str, len: usize) -> Option<Vec<usize>> {
+    let mut selected = vec![];
+    let mut excluded = vec![];
+    for token in input.split(|c: char| c == ',' || c.is_whitespace()) {
+        if token.is_empty() {
+            continue;
+        }
+        let (dest, range) = match token.strip_prefix('^') {
+            Some(range) => (&mut excluded, range),
+            None => (&mut selected, token),
+        };
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let (start, end): (usize, usize) = (start.parse().ok()?, end.parse().ok()?);
+        dest.extend((start.max(1)..=end.min(len)).map(|i| i - 1));
+    }
+    // Only giving exclusions means selecting all the other items.
+    if selected.is_empty() && !excluded.is_empty() {
+        selected.extend(0..len);
+    }
+    Some(
+        selected
+            .into_iter()
+            .filter(|i| !excluded.contains(i))
+            .sorted_unstable()
+            .dedup()
+            .collect(),
+    )
+}
+
+/// Checks if all the characters of `query` appear in `text` in order,
+/// ignoring case and whitespace, eg. `"rpgrp"` matches `"ripgrep"`.
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .all(|q| text.any(|t| t == q))
+}
+
+/// Filters the `hits` with `query`, listing the ones whose names match it
+/// before the ones whose descriptions do.
+fn filter<'h>(hits: &[&'h SearchHit], query: &str) -> Vec<&'h SearchHit> {
+    let (by_name, by_desc): (Vec<_>, Vec<_>) = hits
+        .iter()
+        .filter(|hit| fuzzy_match(query, &hit.name) || fuzzy_match(query, &hit.desc))
+        .partition(|hit| fuzzy_match(query, &hit.name));
+    by_name.into_iter().chain(by_desc).copied().collect()
+}
+
+/// Prints the numbered list of `hits`.
+fn print_hits(hits: &[&SearchHit]) {
+    for (i, hit) in hits.iter().enumerate() {
+        match hit.version.as_str() {
+            "" => println!("{:>3} {}", i + 1, hit.name),
+            version => println!("{:>3} {} {version}", i + 1, hit.name),
+        }
+        if !hit.desc.is_empty() {
+            println!("    {}", hit.desc);
+        }
+    }
+}
+
+/// Lets the user pick some of the `hits`, narrowing them down with fuzzy
+/// queries until a selection is given. An empty answer picks nothing.
+fn pick(hits: &[SearchHit]) -> Result<Vec<&SearchHit>> {
+    let mut shown = hits.iter().collect_vec();
+    loop {
+        print_hits(&shown);
+        print::print_question("Install", "[eg. 1 2 3, 1-3, ^4, or a query]");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(vec![]);
+        }
+        if let Some(selected) = parse_selection(answer, shown.len()) {
+            return Ok(selected.into_iter().map(|i| shown[i]).collect());
+        }
+        match filter(&shown, answer) {
+            filtered if filtered.is_empty() => {
+                print::print_msg(&format!("No package matches `{answer}`"), PROMPT_INFO);
+            }
+            filtered => shown = filtered,
+        }
+    }
+}
+
+/// Searches the sync databases for `kws`, and installs the packages picked
+/// by the user among the results.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when nothing is found, or any error
+/// returned by [`PmBase::search`] and [`PmSync::s`].
+///
+/// [`PmBase::search`]: crate::pm::PmBase::search
+/// [`PmSync::s`]: crate::pm::PmSync::s
+pub(crate) async fn install(pm: &dyn Pm, kws: &[&str], flags: &[&str]) -> Result<()> {
+    let hits = pm
+        .search(kws, flags)
+        .await?
+        .into_iter()
+        .unique_by(|hit| hit.name.clone())
+        .collect_vec();
+    if hits.is_empty() {
+        // The search itself is not run in a dry run.
+        if pm.cfg().dry_run {
+            return Ok(());
+        }
+        return Err(Error::OtherError(format!(
+            "No package found for `{}`",
+            kws.join(" ")
+        )));
+    }
+    let picked = task::block_in_place(|| pick(&hits))?;
+    if picked.is_empty() {
+        print::print_msg("Nothing to install", PROMPT_INFO);
+        return Ok(());
+    }
+    let names = picked.iter().map(|hit| hit.name.as_str()).collect_vec();
+    // The policy has only been checked against the search terms so far.
+    pm.cfg().policy.check(pm.name(), "S", &names)?;
+    pm.s(&names, flags).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selections() {
+        assert_eq!(parse_selection("1 3,2", 5), Some(vec![0, 1, 2]));
+        assert_eq!(parse_selection("2-4 4", 5), Some(vec![1, 2, 3]));
+        assert_eq!(parse_selection("^2 ^4-9", 5), Some(vec![0, 2]));
+        assert_eq!(parse_selection("1-5 ^3", 5), Some(vec![0, 1, 3, 4]));
+        assert_eq!(parse_selection("0 6", 5), Some(vec![]));
+        assert_eq!(parse_selection("curl", 5), None);
+        assert_eq!(parse_selection("1 curl", 5), None);
+    }
+
+    #[test]
+    fn fuzzy_filter() {
+        let hit = |name: &str, desc: &str| SearchHit {
+            name: name.into(),
+            desc: desc.into(),
+            ..SearchHit::default()
+        };
+        let hits = [
+            hit("grep", "Search for patterns in files"),
+            hit("ripgrep", "Recursively search directories"),
+            hit("fd", "A simple alternative to find"),
+        ];
+        let hits = hits.iter().collect_vec();
+        assert!(fuzzy_match("RpGrp", "ripgrep"));
+        assert!(!fuzzy_match("pgr", "grep"));
+        let names = |hits: Vec<&SearchHit>| hits.iter().map(|hit| hit.name.clone()).collect_vec();
+        assert_eq!(names(filter(&hits, "rg")), ["ripgrep"]);
+        // Matching names come first.
+        assert_eq!(names(filter(&hits, "d")), ["fd", "ripgrep"]);
+        assert_eq!(names(filter(&hits, "search")), ["grep", "ripgrep"]);
+    }
+}
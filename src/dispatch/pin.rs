@@ -0,0 +1,59 @@
+//! Pinning packages to their installed versions with `pacaptr pin`, eg. to
+//! keep a kernel from being upgraded, and releasing them with `pacaptr
+//! unpin`.
+//!
+//! The packages are pinned with the native mechanism of the package manager,
+//! eg. `apt-mark hold`, `brew pin` or `zypper addlock`. Otherwise, like with
+//! `IgnorePkg` in `pacman.conf`, they can be listed under `ignore` in the
+//! config file to be left out of the system upgrades.
+
+use crate::{
+    dispatch::guard::Guard,
+    error::{Error, Result},
+    pm::Pm,
+    print::{self, PROMPT_INFO},
+};
+
+/// Suggests listing the packages under `ignore` in the config file instead
+/// if `err` says that the package manager cannot pin them.
+fn advise(err: Error) -> Error {
+    if matches!(err, Error::OperationUnimplementedError { .. }) {
+        print::print_msg(
+            "List the packages under `ignore` in the config file to leave them out of \
+             `pacaptr -Su` instead",
+            PROMPT_INFO,
+        );
+    }
+    err
+}
+
+/// Pins the packages `kws` with `pm`, or lists the packages pinned if `kws`
+/// is empty.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] when `pm` cannot pin
+/// packages, or any error returned by it.
+pub(crate) async fn pin(pm: &dyn Pm, kws: &[&str], flags: &[&str]) -> Result<()> {
+    let guard = Guard::enter(pm, "pin", kws).await?;
+    let res = guard.pm().pin(kws, flags).await.map_err(advise);
+    guard.leave(&res).await?;
+    res
+}
+
+/// Releases the packages `kws` pinned with `pm`.
+///
+/// # Errors
+/// Returns an [`Error::ArgParseError`] when no package is given, an
+/// [`Error::OperationUnimplementedError`] when `pm` cannot pin packages, or
+/// any error returned by it.
+pub(crate) async fn unpin(pm: &dyn Pm, kws: &[&str], flags: &[&str]) -> Result<()> {
+    if kws.is_empty() {
+        return Err(Error::ArgParseError {
+            msg: "`pacaptr unpin` expects some packages".into(),
+        });
+    }
+    let guard = Guard::enter(pm, "unpin", kws).await?;
+    let res = guard.pm().unpin(kws, flags).await.map_err(advise);
+    guard.leave(&res).await?;
+    res
+}
@@ -0,0 +1,199 @@
+//! The operations denied by the administrator of a shared system, where
+//! `pacaptr` is the sanctioned interface to the package manager, eg. `-Scc`
+//! or removing the kernel.
+//!
+//! The policy is only read from the system-wide config file, as the users
+//! could simply lift it in their own config files otherwise:
+//!
+//! ```toml
+//! [[policy.deny]]
+//! op = "Scc"
+//!
+//! [[policy.deny]]
+//! op = "R"
+//! kws = ["linux*"]
+//! pm = "apt"
+//! reason = "ask the admins to remove a kernel"
+//! ```
+
+use std::{fs, path::Path};
+
+use itertools::Itertools;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::error::{Error, Result};
+
+/// The operations denied by the administrator.
+#[must_use]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy {
+    /// The rules denying the operations, checked in order.
+    #[serde(default)]
+    pub deny: Vec<Rule>,
+}
+
+/// A rule of a [`Policy`], denying an operation.
+#[must_use]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    /// The operation denied, eg. `R` for all of `-R`, `-Rs` and `-Rns`, or
+    /// `Scc` for `-Scc` only: an operation is matched if it has all the flags
+    /// given, as many times.
+    pub op: String,
+
+    /// The package manager for which the operation is denied, all of them if
+    /// not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pm: Option<String>,
+
+    /// Only deny the operation when any of the keywords matches one of these
+    /// patterns, where `*` matches any characters and `?` a single one, eg.
+    /// `linux*`. The operation is denied whatever the keywords if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kws: Vec<String>,
+
+    /// Why the operation is denied, shown to the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Turns a `pattern` with the wildcards `*` and `?` into a [`Regex`] matching
+/// whole keywords.
+fn wildcard(pattern: &str) -> Regex {
+    let re = regex::escape(pattern)
+        .replace(r"\*", ".*")
+        .replace(r"\?", ".");
+    Regex::new(&format!("^{re}$")).expect("an escaped pattern should be a valid regex")
+}
+
+impl Rule {
+    /// Checks if the rule matches the operation `options`, eg. `Rns`, of `pm`.
+    fn matches_op(&self, pm: &str, options: &str) -> bool {
+        if self.pm.as_deref().is_some_and(|rule_pm| rule_pm != pm) {
+            return false;
+        }
+        let mut rule = self.op.trim_start_matches('-').chars();
+        let mut chars = options.chars();
+        if rule.next() != chars.next() {
+            return false;
+        }
+        let counts = chars.counts();
+        rule.counts()
+            .into_iter()
+            .all(|(flag, n)| counts.get(&flag).is_some_and(|&m| m >= n))
+    }
+
+    /// Gets the keywords among `kws` the rule denies, all of them if the rule
+    /// has no keyword patterns.
+    fn denied<'k>(&self, kws: &[&'k str]) -> Vec<&'k str> {
+        if self.kws.is_empty() {
+            return kws.to_vec();
+        }
+        let patterns = self.kws.iter().map(|pat| wildcard(pat)).collect_vec();
+        kws.iter()
+            .filter(|kw| patterns.iter().any(|re| re.is_match(kw)))
+            .copied()
+            .collect()
+    }
+}
+
+impl Policy {
+    /// Checks if the policy is empty, ie. nothing is denied.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.deny.is_empty()
+    }
+
+    /// Reads the policy in the `[policy]` section of the config file at
+    /// `path`, if it exists.
+    ///
+    /// # Errors
+    /// Returns an [`Error::ConfigError`] when the config file cannot be read
+    /// or the policy is invalid.
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let invalid = |e: String| Error::ConfigError {
+            msg: format!("Failed to read the policy at `{}`: {e}", path.display()),
+        };
+        let contents = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let layer: Value = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        layer
+            .get("policy")
+            .cloned()
+            .map_or(Ok(Self::default()), Value::try_into)
+            .map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Checks that running `-{options}` with `kws` on `pm` is not denied.
+    ///
+    /// # Errors
+    /// Returns an [`Error::PolicyViolationError`] naming the first rule which
+    /// denies the operation.
+    pub(crate) fn check(&self, pm: &str, options: &str, kws: &[&str]) -> Result<()> {
+        for rule in self.deny.iter().filter(|rule| rule.matches_op(pm, options)) {
+            let denied = rule.denied(kws);
+            if !rule.kws.is_empty() && denied.is_empty() {
+                continue;
+            }
+            let mut op = format!("-{options}");
+            if !rule.kws.is_empty() {
+                op = format!("{op} {}", denied.join(" "));
+            }
+            return Err(Error::PolicyViolationError {
+                op,
+                reason: rule
+                    .reason
+                    .clone()
+                    .unwrap_or_else(|| format!("`{}` is denied for `{pm}`", rule.op)),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let policy: Policy = toml::from_str(indoc::indoc! {r#"
+            [[deny]]
+            op = "Scc"
+
+            [[deny]]
+            op = "-R"
+            kws = ["linux*", "glibc"]
+            pm = "apt"
+            reason = "ask the admins"
+        "#})
+        .unwrap();
+        assert!(policy.check("dnf", "Scc", &[]).is_err());
+        assert!(policy.check("dnf", "Sc", &[]).is_ok());
+        assert!(policy.check("dnf", "Sccy", &[]).is_err());
+
+        let err = policy
+            .check("apt", "Rns", &["curl", "linux-image-amd64"])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PolicyViolationError { op, reason }
+                if op == "-Rns linux-image-amd64" && reason == "ask the admins"
+        ));
+        assert!(policy.check("apt", "R", &["curl", "glibc-doc"]).is_ok());
+        assert!(policy.check("dnf", "R", &["linux-firmware"]).is_ok());
+        assert!(policy.check("apt", "S", &["linux-headers"]).is_ok());
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard("linux*").is_match("linux-lts"));
+        assert!(wildcard("lib?").is_match("libc"));
+        assert!(!wildcard("lib?").is_match("libcc"));
+        assert!(!wildcard("c++").is_match("cc"));
+    }
+}
@@ -0,0 +1,221 @@
+//! Pre-flight checks warning about (or refusing) large operations on battery
+//! power or a metered connection, where these can be detected.
+//!
+//! Currently, the power supply is detected on Linux (with `sysfs`) and macOS
+//! (with `pmset`), and metered connections on Linux (with `nmcli`).
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dispatch::Config,
+    error::{Error, Result},
+    exec::{Cmd, Mode},
+    print::{self, PROMPT_WARN},
+};
+
+/// What to do when a pre-flight check fails.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Carry on silently.
+    #[default]
+    Off,
+    /// Print a warning and carry on.
+    Warn,
+    /// Refuse to carry on unless `--force` is given.
+    Refuse,
+}
+
+/// The [`Action`] to take for each class of operations when a pre-flight
+/// check fails.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preflight {
+    /// For system upgrades, ie. `-Su` and `-Suy`.
+    #[serde(default)]
+    pub upgrade: Action,
+    /// For installations, ie. `-S` and `-U`.
+    #[serde(default)]
+    pub install: Action,
+    /// For downloads, ie. `-Sw`.
+    #[serde(default)]
+    pub download: Action,
+}
+
+impl Preflight {
+    /// Gets the [`Action`] for the operation `op` (eg. `Suy`), if it's a large
+    /// one.
+    fn action(self, op: &str) -> Action {
+        match op {
+            "Su" | "Suy" => self.upgrade,
+            "S" | "U" => self.install,
+            "Sw" => self.download,
+            _ => Action::Off,
+        }
+    }
+}
+
+/// Checks if the machine runs on battery according to the power supplies in
+/// `root`, ie. `/sys/class/power_supply` on Linux. Batteries of peripherals,
+/// eg. mice, are not taken into account.
+fn sysfs_on_battery(root: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(root) else {
+        return false;
+    };
+    let mut has_battery = false;
+    for dir in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+        let read = |file: &str| {
+            fs::read_to_string(dir.join(file))
+                .map(|s| s.trim().to_owned())
+                .unwrap_or_default()
+        };
+        match read("type").as_str() {
+            "Mains" | "USB" if read("online") == "1" => return false,
+            "Battery" if read("scope") != "Device" => has_battery = true,
+            _ => (),
+        }
+    }
+    has_battery
+}
+
+/// Checks if the output of `pmset -g batt` shows that the machine runs on
+/// battery.
+fn pmset_on_battery(out: &str) -> bool {
+    // The first line looks like `Now drawing from 'Battery Power'`.
+    out.lines()
+        .next()
+        .is_some_and(|ln| ln.contains("'Battery Power'"))
+}
+
+/// Checks if the output of `nmcli --terse --fields GENERAL.METERED device
+/// show` shows that any connection is metered.
+fn nmcli_metered(out: &str) -> bool {
+    // Each device is listed as a line like `GENERAL.METERED:yes (guessed)`.
+    out.lines().any(|ln| {
+        ln.strip_prefix("GENERAL.METERED:")
+            .is_some_and(|metered| metered.starts_with("yes"))
+    })
+}
+
+/// Runs `cmd` silently, returning its output if it succeeds.
+async fn output_of(cmd: &[&str]) -> Option<String> {
+    let out = Cmd::new(cmd).exec(Mode::Mute).await.ok()?;
+    String::from_utf8(out).ok()
+}
+
+/// Checks if the machine runs on battery, where detectable.
+async fn on_battery() -> bool {
+    if cfg!(target_os = "linux") {
+        sysfs_on_battery(Path::new("/sys/class/power_supply"))
+    } else if cfg!(target_os = "macos") {
+        output_of(&["pmset", "-g", "batt"])
+            .await
+            .is_some_and(|out| pmset_on_battery(&out))
+    } else {
+        false
+    }
+}
+
+/// Checks if the machine is on a metered connection, where detectable.
+async fn on_metered() -> bool {
+    cfg!(target_os = "linux")
+        && output_of(&[
+            "nmcli",
+            "--terse",
+            "--fields",
+            "GENERAL.METERED",
+            "device",
+            "show",
+        ])
+        .await
+        .is_some_and(|out| nmcli_metered(&out))
+}
+
+/// Takes the `action` for the operation `op` running under the condition
+/// `cond` (eg. `on battery power`). With `force`, refusals are turned into
+/// warnings.
+fn act(action: Action, op: &str, cond: &str, force: bool) -> Result<()> {
+    match action {
+        Action::Off => Ok(()),
+        Action::Refuse if !force => Err(Error::OtherError(format!(
+            "Refusing to run `-{op}` {cond}, use `--force` to run it anyway"
+        ))),
+        Action::Warn | Action::Refuse => {
+            print::print_err(format!("Running `-{op}` {cond}"), PROMPT_WARN);
+            Ok(())
+        }
+    }
+}
+
+/// Runs the pre-flight checks configured in `cfg` for the operation `op`,
+/// printing a warning or refusing to carry on when any of them fails. With
+/// `force`, failures are only warned about.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when a check fails and the configured
+/// [`Action`] is [`Action::Refuse`].
+pub(crate) async fn check(cfg: &Config, op: &str, force: bool) -> Result<()> {
+    if cfg.dry_run {
+        return Ok(());
+    }
+    // The detections are only run when needed, as some of them spawn commands.
+    let action = cfg.on_battery.action(op);
+    if action != Action::Off && on_battery().await {
+        act(action, op, "on battery power", force)?;
+    }
+    let action = cfg.on_metered.action(op);
+    if action != Action::Off && on_metered().await {
+        act(action, op, "on a metered connection", force)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions() {
+        let preflight: Preflight = toml::from_str(r#"upgrade = "refuse""#).unwrap();
+        assert_eq!(preflight.action("Suy"), Action::Refuse);
+        assert_eq!(preflight.action("S"), Action::Off);
+        assert_eq!(preflight.action("Ss"), Action::Off);
+        assert!(toml::from_str::<Preflight>(r#"install = "maybe""#).is_err());
+    }
+
+    #[test]
+    fn detections() {
+        assert!(pmset_on_battery(indoc::indoc! {"
+            Now drawing from 'Battery Power'
+             -InternalBattery-0 (id=1234567)	85%; discharging; 5:12 remaining present: true
+        "}));
+        assert!(!pmset_on_battery("Now drawing from 'AC Power'"));
+        assert!(nmcli_metered(indoc::indoc! {"
+            GENERAL.METERED:unknown
+            GENERAL.METERED:yes (guessed)
+        "}));
+        assert!(!nmcli_metered(
+            "GENERAL.METERED:no\nGENERAL.METERED:unknown"
+        ));
+    }
+
+    #[test]
+    fn power_supplies() {
+        let root = std::env::temp_dir().join("pacaptr-preflight-power-supplies");
+        let write = |supply: &str, file: &str, contents: &str| {
+            fs::create_dir_all(root.join(supply)).unwrap();
+            fs::write(root.join(supply).join(file), contents).unwrap();
+        };
+        write("AC", "type", "Mains\n");
+        write("AC", "online", "0\n");
+        write("hidpp_battery_0", "type", "Battery\n");
+        write("hidpp_battery_0", "scope", "Device\n");
+        assert!(!sysfs_on_battery(&root));
+        write("BAT0", "type", "Battery\n");
+        assert!(sysfs_on_battery(&root));
+        write("AC", "online", "1\n");
+        assert!(!sysfs_on_battery(&root));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+//! `--print-format` for `-Sp` and `-Rp`, printing each target with a custom
+//! format like `pacman` does, eg. `%n %v` for its name and version.
+
+use crate::{
+    error::{Error, Result},
+    pm::Pm,
+};
+
+/// A package to be installed or removed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Target {
+    pub name: String,
+    pub version: String,
+    /// The repository the package comes from, `local` for the installed ones.
+    /// Unknown if empty.
+    pub repo: String,
+    /// The URL the package is downloaded from. Unknown if empty.
+    pub url: String,
+}
+
+/// Replaces the fields of `target` in `fmt`, ie. `%n` (name), `%v` (version),
+/// `%r` (repository) and `%l` (URL). Any other character is kept as is.
+#[must_use]
+pub(crate) fn render(fmt: &str, target: &Target) -> String {
+    let mut res = String::with_capacity(fmt.len());
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => res.push_str(&target.name),
+            Some('v') => res.push_str(&target.version),
+            Some('r') => res.push_str(&target.repo),
+            Some('l') => res.push_str(&target.url),
+            Some(other) => res.extend(['%', other]),
+            None => res.push('%'),
+        }
+    }
+    res
+}
+
+/// Resolves the targets of `-{options}`, which is either `-S` or `-R`.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] when `pm` cannot resolve
+/// them, and an [`Error::OtherError`] when a package to be removed is not
+/// installed.
+async fn resolve(pm: &dyn Pm, options: &str, kws: &[&str], flags: &[&str]) -> Result<Vec<Target>> {
+    let unimplemented = |e| match e {
+        Error::OperationUnimplementedError { pm, .. } => Error::OperationUnimplementedError {
+            op: format!("{} --print-format", options.to_lowercase()),
+            pm,
+        },
+        e => e,
+    };
+    if options == "S" {
+        let files = pm.sw_files(kws, flags).await.map_err(unimplemented)?;
+        return Ok(files
+            .into_iter()
+            .map(|file| Target {
+                name: file.name,
+                version: file.version,
+                repo: file.repo,
+                url: file.url,
+            })
+            .collect());
+    }
+    let installed = pm.installed().await.map_err(unimplemented)?;
+    kws.iter()
+        .map(|&kw| {
+            let (name, version) = installed
+                .iter()
+                .find(|(name, _)| name == kw)
+                .ok_or_else(|| Error::OtherError(format!("Target not found: {kw}")))?;
+            Ok(Target {
+                name: name.clone(),
+                version: version.clone(),
+                repo: "local".into(),
+                ..Target::default()
+            })
+        })
+        .collect()
+}
+
+/// Prints the targets of `-{options}` with the format `fmt`, one per line.
+///
+/// # Errors
+/// Returns an [`Error::ArgParseError`] when `options` is neither `S` nor `R`,
+/// and any error returned when resolving the targets.
+pub(crate) async fn run(
+    pm: &dyn Pm,
+    options: &str,
+    kws: &[&str],
+    flags: &[&str],
+    fmt: &str,
+) -> Result<()> {
+    if !matches!(options, "S" | "R") {
+        return Err(Error::ArgParseError {
+            msg: format!("`--print-format` cannot be used with `-{options}`"),
+        });
+    }
+    for target in resolve(pm, options, kws, flags).await? {
+        println!("{}", render(fmt, &target));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendered() {
+        let target = Target {
+            name: "curl".into(),
+            version: "7.88.1-10".into(),
+            repo: "local".into(),
+            ..Target::default()
+        };
+        assert_eq!(render("%n %v", &target), "curl 7.88.1-10");
+        assert_eq!(render("%r/%n:%l", &target), "local/curl:");
+        assert_eq!(render("100% %x%", &target), "100% %x%");
+    }
+}
@@ -0,0 +1,109 @@
+//! Hot-reloading the config in the long-running modes, so that the settings
+//! which are safe to change on the fly, ie. `default_pm`, are applied without
+//! a restart.
+//!
+//! Each reload is reported, and recorded in the log file if any.
+
+use std::{fs, time::SystemTime};
+
+use crate::{
+    dispatch::{logging::CONFIG_TARGET, Config},
+    error::Result,
+    print::{self, PROMPT_INFO, PROMPT_WARN},
+};
+
+/// Watches the config files for changes, as told by their modification times.
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct Reloader {
+    /// The modification times of the config files at the last check.
+    stamps: Vec<Option<SystemTime>>,
+}
+
+impl Reloader {
+    /// Starts watching the config files as they are now.
+    pub(crate) fn new() -> Self {
+        Reloader { stamps: stamps() }
+    }
+
+    /// Checks if the config files have changed since the last check.
+    #[must_use]
+    pub(crate) fn changed(&mut self) -> bool {
+        let stamps = stamps();
+        if stamps == self.stamps {
+            return false;
+        }
+        self.stamps = stamps;
+        true
+    }
+}
+
+/// Gets the modification times of the config files, which tell when they
+/// have changed.
+#[must_use]
+fn stamps() -> Vec<Option<SystemTime>> {
+    Config::paths()
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+/// Applies the settings of `new` which are safe to change without a restart,
+/// ie. `default_pm`, to `cfg`. Returns the names of those which have changed.
+#[must_use]
+fn apply(cfg: &mut Config, new: Config) -> Vec<&'static str> {
+    let mut changed = vec![];
+    if cfg.default_pm != new.default_pm {
+        cfg.default_pm = new.default_pm;
+        changed.push("default_pm");
+    }
+    changed
+}
+
+/// Reloads the config with `reload` and applies the safe changes to `cfg`,
+/// see [`apply`]. Returns the message reporting the changes applied, if any,
+/// which has been printed already. A config which cannot be loaded is only
+/// warned about, and the current one is kept.
+pub(crate) fn reload_into(cfg: &mut Config, reload: impl Fn() -> Result<Config>) -> Option<String> {
+    let changed = match reload() {
+        Ok(new) => apply(cfg, new),
+        Err(e) => {
+            tracing::info!(target: CONFIG_TARGET, error = %e, "Failed to reload the config");
+            print::print_msg(
+                &format!("Kept the current config, as the new one cannot be loaded: {e}"),
+                PROMPT_WARN,
+            );
+            return None;
+        }
+    };
+    tracing::info!(target: CONFIG_TARGET, ?changed, "Reloaded the config");
+    if changed.is_empty() {
+        return None;
+    }
+    let msg = format!("Reloaded the config, with new {}", changed.join(" and "));
+    print::print_msg(&msg, PROMPT_INFO);
+    Some(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applied() {
+        let mut cfg = Config {
+            default_pm: Some("apt".into()),
+            sudo: Some("doas".into()),
+            ..Config::default()
+        };
+        let new = Config {
+            default_pm: Some("brew".into()),
+            ..Config::default()
+        };
+        assert_eq!(apply(&mut cfg, new.clone()), ["default_pm"]);
+        assert_eq!(cfg.default_pm.as_deref(), Some("brew"));
+        // The other settings need a restart.
+        assert_eq!(cfg.sudo.as_deref(), Some("doas"));
+        assert!(apply(&mut cfg, new).is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! Resumable system upgrades. When `-Su` fails partway, eg. because of a
+//! network drop, the packages which are not upgraded yet are recorded, so that
+//! `pacaptr resume` can continue with only those.
+
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use itertools::Itertools;
+
+use crate::{
+    dispatch::{guard::Guard, history},
+    error::{Error, Result},
+    pm::Pm,
+    print::{self, PROMPT_INFO},
+    state::{State, Upgrade},
+};
+
+/// Lists the packages about to be upgraded by `op` on `pm`, if it's a system
+/// upgrade which can be resumed. Returns `None` otherwise.
+pub(crate) async fn snapshot(pm: &dyn Pm, op: &str, kws: &[&str]) -> Option<Vec<String>> {
+    if !matches!(op, "Su" | "Suy") || !kws.is_empty() || pm.cfg().dry_run {
+        return None;
+    }
+    pm.upgradable().await.ok()
+}
+
+/// Splits the `planned` packages into the ones which have been upgraded and
+/// the ones which remain to be, according to the packages which are still
+/// `upgradable`, if known. The `ignored` packages are never left to be
+/// upgraded.
+fn split(
+    planned: &[String],
+    upgradable: Option<&[String]>,
+    ignored: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let remaining = upgradable
+        .unwrap_or(planned)
+        .iter()
+        .filter(|pkg| !ignored.contains(pkg))
+        .cloned()
+        .collect_vec();
+    let done = planned
+        .iter()
+        .filter(|pkg| !remaining.contains(pkg) && !ignored.contains(pkg))
+        .cloned()
+        .collect();
+    (done, remaining)
+}
+
+/// Records the outcome `res` of an upgrade of the `planned` packages on `pm`,
+/// ignoring any failure. The upgrade is remembered for
+/// [`resume`] if it has failed with some packages left, and forgotten
+/// otherwise.
+pub(crate) async fn track(pm: &dyn Pm, planned: Vec<String>, res: &Result<()>) {
+    let Ok(state) = State::open() else {
+        return;
+    };
+    if res.is_ok() {
+        state.forget_upgrade(pm.name()).ok();
+        return;
+    }
+    let upgradable = pm.upgradable().await.ok();
+    let (done, remaining) = split(&planned, upgradable.as_deref(), &pm.cfg().ignore);
+    if remaining.is_empty() {
+        state.forget_upgrade(pm.name()).ok();
+        return;
+    }
+    print::print_msg(
+        &format!(
+            "{} packages upgraded, {} left: run `pacaptr resume` to continue",
+            done.len(),
+            remaining.len(),
+        ),
+        PROMPT_INFO,
+    );
+    let upgrade = Upgrade {
+        time: SystemTime::now(),
+        pm: pm.name().into(),
+        planned,
+        remaining,
+    };
+    state.set_upgrade(&upgrade).ok();
+}
+
+/// Continues the last system upgrade on `pm` which has failed partway,
+/// upgrading only the packages left.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when there is no such upgrade, an
+/// [`Error::PolicyViolationError`] when it's denied, or any error returned by
+/// [`PmBase::su_only`](crate::pm::PmBase::su_only).
+pub(crate) async fn resume(pm: &dyn Pm, flags: &[&str]) -> Result<()> {
+    let upgrade = State::open()?.upgrade(pm.name())?.ok_or_else(|| {
+        Error::OtherError(format!("No failed upgrade to resume for `{}`", pm.name()))
+    })?;
+    let time = DateTime::<Local>::from(upgrade.time).format("%Y-%m-%d %H:%M");
+    print::print_msg(
+        &format!(
+            "Resuming the upgrade which failed at {time}, {} packages left",
+            upgrade.remaining.len()
+        ),
+        PROMPT_INFO,
+    );
+    let remaining = upgrade.remaining.iter().map(String::as_str).collect_vec();
+    let guard = Guard::enter(pm, "Su", &remaining).await?;
+    let res = guard.pm().su_only(&remaining, flags).await;
+    if !pm.cfg().dry_run {
+        history::record(pm.name(), "Su", &remaining, &res);
+        track(pm, upgrade.planned, &res).await;
+    }
+    guard.leave(&res).await?;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits() {
+        let pkgs = |pkgs: &[&str]| pkgs.iter().map(|&pkg| pkg.to_owned()).collect_vec();
+        let planned = pkgs(&["curl", "git", "vim"]);
+        let (done, remaining) = split(&planned, Some(&pkgs(&["git", "vim"])), &[]);
+        assert_eq!((done, remaining), (pkgs(&["curl"]), pkgs(&["git", "vim"])));
+
+        // Without knowing what's still upgradable, nothing is considered done.
+        let (done, remaining) = split(&planned, None, &pkgs(&["vim"]));
+        assert_eq!((done, remaining), (vec![], pkgs(&["curl", "git"])));
+    }
+}
@@ -0,0 +1,119 @@
+//! `--retries`, running download-heavy operations again with an exponential
+//! backoff when they fail because of the network, eg. a flaky mirror.
+
+use std::time::Duration;
+
+use tokio::time;
+
+use crate::{dispatch::diagnose, error::Error, pm::Pm, print};
+
+/// The prompt printed before running an operation again.
+static PROMPT_RETRY: &str = "Retrying";
+
+/// The delay before the first retry, doubled for each of the next ones.
+const BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// Checks if the operation `options`, eg. `Sy`, may be retried.
+#[must_use]
+fn is_retryable(options: &str) -> bool {
+    matches!(options, "S" | "Sw" | "Sy")
+}
+
+/// The exit codes only returned by `pm` on network failures.
+fn network_codes(pm: &str) -> &'static [i32] {
+    match pm {
+        // `ZYPPER_EXIT_INF_REPOS_SKIPPED`: some repositories couldn't be
+        // refreshed.
+        "zypper" => &[106],
+        _ => &[],
+    }
+}
+
+/// The lowercase patterns showing up in the error output of `pm` on network
+/// failures, in addition to the ones of `--root-cause`.
+fn network_patterns(pm: &str) -> &'static [&'static str] {
+    match pm {
+        "apt" => &["temporary failure resolving", "unable to connect to"],
+        "brew" => &["curl: (", "download failed"],
+        "dnf" => &["cannot download", "failed to synchronize cache"],
+        "pip" | "conda" => &["read timed out", "connection broken", "connectionerror"],
+        "choco" | "scoop" => &["the remote name could not be resolved", "unable to connect"],
+        _ => &[],
+    }
+}
+
+/// Checks if `err` returned by `pm` is caused by a network failure.
+#[must_use]
+fn is_network_failure(pm: &str, err: &Error) -> bool {
+    match err {
+        Error::CmdTimeoutError { .. } => true,
+        Error::CmdStatusCodeError { code, output } => {
+            let output = String::from_utf8_lossy(output);
+            let lower = output.to_lowercase();
+            network_codes(pm).contains(code)
+                || network_patterns(pm).iter().any(|pat| lower.contains(pat))
+                || diagnose::is_network_failure(&output)
+        }
+        _ => false,
+    }
+}
+
+/// Gets the delay before the retry number `retry`, starting from `1`.
+#[must_use]
+fn delay(retry: u32) -> Duration {
+    BASE_DELAY * 2_u32.saturating_pow(retry.saturating_sub(1))
+}
+
+/// Checks if the operation `options` should be run again after failing with
+/// `err` on the attempt number `attempt`, starting from `1`, as allowed by
+/// [`Config::retries`](crate::dispatch::Config::retries). If so, waits for the
+/// backoff delay first.
+pub(crate) async fn should_retry(pm: &dyn Pm, options: &str, err: &Error, attempt: u32) -> bool {
+    let retries = pm.cfg().retries;
+    if attempt > retries || !is_retryable(options) || !is_network_failure(pm.name(), err) {
+        return false;
+    }
+    let delay = delay(attempt);
+    print::print_msg(
+        &format!(
+            "({attempt}/{retries}) in {}s after a network failure: {err}",
+            delay.as_secs()
+        ),
+        PROMPT_RETRY,
+    );
+    time::sleep(delay).await;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(code: i32, output: &str) -> Error {
+        Error::CmdStatusCodeError {
+            code,
+            output: output.into(),
+        }
+    }
+
+    #[test]
+    fn network_failures() {
+        let err = failure(100, "E: Failed to fetch http://deb.debian.org/  Temporary failure resolving 'deb.debian.org'");
+        assert!(is_network_failure("apt", &err));
+        assert!(is_network_failure("zypper", &failure(106, "")));
+        assert!(!is_network_failure("apt", &failure(106, "")));
+        assert!(is_network_failure(
+            "dnf",
+            &failure(1, "Error: Cannot download repomd.xml")
+        ));
+        assert!(!is_network_failure(
+            "apt",
+            &failure(100, "E: Unable to locate package nope")
+        ));
+        assert!(!is_network_failure("apt", &Error::CmdInterruptedError));
+
+        assert!(is_retryable("Sy") && !is_retryable("R"));
+        assert_eq!(delay(1), Duration::from_secs(2));
+        assert_eq!(delay(3), Duration::from_secs(8));
+    }
+}
@@ -0,0 +1,158 @@
+//! Managing the services of the packages installed, eg. starting the daemon
+//! of `nginx` right after installing it, with `pacaptr services`.
+//!
+//! The services are managed with `brew services` when using `brew`, and with
+//! the service manager of the platform otherwise, ie. `systemctl` on Linux
+//! and `sc.exe` on Windows.
+
+use std::time::Duration;
+
+use crate::{
+    dispatch::Config,
+    error::{Error, Result},
+    exec::{is_exe, Cmd, Mode},
+};
+
+/// What to do with the services.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Action {
+    Start,
+    Stop,
+    Restart,
+    List,
+}
+
+impl Action {
+    /// The name of the action, as given on the command line.
+    #[must_use]
+    fn name(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+            Self::List => "list",
+        }
+    }
+}
+
+/// A service manager.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Manager {
+    /// `brew services`, for the formulae installed with `brew`.
+    Brew,
+    /// `systemctl`, for the systemd services.
+    Systemctl,
+    /// `sc.exe`, for the Windows services.
+    Sc,
+}
+
+impl Manager {
+    /// Detects the service manager for the package manager `pm`.
+    #[must_use]
+    fn detect(pm: &str) -> Option<Self> {
+        if pm == "brew" {
+            Some(Self::Brew)
+        } else if cfg!(target_os = "windows") {
+            Some(Self::Sc)
+        } else if cfg!(target_os = "linux") && is_exe("systemctl", "") {
+            Some(Self::Systemctl)
+        } else {
+            None
+        }
+    }
+
+    /// Builds the commands doing `action` with the services `kws`.
+    fn cmds(self, action: Action, kws: &[&str]) -> Vec<Cmd> {
+        let name = action.name();
+        match (self, action) {
+            (Self::Brew, Action::List) => vec![Cmd::new(&["brew", "services", "list"])],
+            (Self::Brew, _) => vec![Cmd::new(&["brew", "services", name]).kws(kws)],
+            (Self::Systemctl, Action::List) => {
+                vec![Cmd::new(&["systemctl", "list-units", "--type=service"])]
+            }
+            (Self::Systemctl, _) => vec![Cmd::with_sudo(&["systemctl", name]).kws(kws)],
+            (Self::Sc, Action::List) => vec![Cmd::new(&["sc.exe", "query", "state=", "all"])],
+            // `sc.exe` takes a single service, and has no `restart`.
+            (Self::Sc, Action::Restart) => kws
+                .iter()
+                .flat_map(|&kw| {
+                    [
+                        Cmd::new(&["sc.exe", "stop", kw]),
+                        Cmd::new(&["sc.exe", "start", kw]),
+                    ]
+                })
+                .collect(),
+            (Self::Sc, _) => kws
+                .iter()
+                .map(|&kw| Cmd::new(&["sc.exe", name, kw]))
+                .collect(),
+        }
+    }
+}
+
+/// Does `action` with the services `kws` of the packages installed with the
+/// package manager of `cfg`, unless denied by the
+/// [`Policy`](crate::dispatch::policy::Policy) as `services`. In a dry run, the commands are only printed.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] when no service manager
+/// is found, an [`Error::ArgParseError`] when no service is given to start,
+/// stop or restart, an [`Error::PolicyViolationError`] when the operation is
+/// denied, or any error returned by the service manager.
+pub(crate) async fn run(cfg: &Config, action: Action, kws: &[&str]) -> Result<()> {
+    let pm = cfg.default_pm.as_deref().unwrap_or("unknown");
+    let manager = Manager::detect(pm).ok_or_else(|| Error::OperationUnimplementedError {
+        op: "services".into(),
+        pm: pm.into(),
+    })?;
+    if action != Action::List && kws.is_empty() {
+        return Err(Error::ArgParseError {
+            msg: format!("`pacaptr services {}` expects some services", action.name()),
+        });
+    }
+    cfg.policy.check(pm, "services", kws)?;
+    let mode = if cfg.dry_run {
+        Mode::PrintCmd
+    } else {
+        Mode::CheckErr
+    };
+    for cmd in manager.cmds(action, kws) {
+        cmd.sudo_with(cfg.sudo.clone())
+            .force_sudo(cfg.force_sudo)
+            .timeout(cfg.timeout.map(Duration::from_secs))
+            .exec(mode)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn cmds() {
+        let cmds = |manager: Manager, action| {
+            manager
+                .cmds(action, &["nginx"])
+                .into_iter()
+                .map(|cmd| cmd.force_sudo(Some(false)).to_string())
+                .collect_vec()
+        };
+        assert_eq!(
+            cmds(Manager::Brew, Action::Start),
+            ["brew services start nginx"]
+        );
+        assert_eq!(
+            cmds(Manager::Systemctl, Action::Restart),
+            ["systemctl restart nginx"]
+        );
+        assert_eq!(
+            cmds(Manager::Sc, Action::Restart),
+            ["sc.exe stop nginx", "sc.exe start nginx"]
+        );
+        assert_eq!(cmds(Manager::Sc, Action::List), ["sc.exe query state= all"]);
+    }
+}
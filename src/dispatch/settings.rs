@@ -0,0 +1,442 @@
+//! `pacaptr config`, for reading and writing the settings in the config file
+//! without editing TOML by hand.
+
+use std::{fs, io, process::Command};
+
+use toml::{value::Table, Value};
+
+use crate::{
+    dispatch::{config, Config},
+    error::{Error, Result},
+    print::{self, PROMPT_INFO},
+};
+
+/// The config file written by `pacaptr config init`, with all the settings
+/// commented out.
+static TEMPLATE: &str = r#"# The package manager to be used, detected automatically if not set.
+# default_pm = "apt"
+
+# Where the package manager runs, ie. on a remote host, in a container or on
+# another root, is only chosen on the command line, with `--host`,
+# `--container` or `--root`.
+
+# The package managers to be preferred, in this order, when more than one is
+# detected.
+# detect_priority = ["brew", "apt"]
+
+# dry_run = false
+# needed = false
+# no_confirm = false
+# no_cache = false
+
+# Honor `no_confirm` only for these operations, eg. to never remove packages
+# without asking.
+# no_confirm_allowed_ops = ["s", "su", "suy"]
+
+# Show the commands to be run by an operation changing the installed packages
+# and confirm them all at once: "never", "multi" (only for more than one
+# command, eg. `-Suy`) or "always".
+# summary_prompt = "never"
+
+# Install, remove and upgrade casks rather than formulae with `brew` when casks
+# of the same names exist.
+# brew_prefer_cask = false
+
+# Packages to be left out when upgrading the system.
+# ignore = []
+
+# Record the duration of each operation locally for `pacaptr stats --perf`.
+# metrics = false
+
+# Refuse to upgrade or install if less than this many MiB would be left free,
+# or only warn about it.
+# min_free_space = 0
+# warn_low_space = false
+
+# Run commands as `root` with this, eg. "doas", "run0" or "pkexec", instead of
+# the first of them found, starting from `sudo -S`.
+# sudo = "doas"
+
+# Always (true) or never (false) run commands as `root` with the above, which is
+# otherwise skipped when already `root` or when no such command is found.
+# force_sudo = true
+
+# Warn about ("warn") or refuse without `--force` ("refuse") upgrades,
+# installations or downloads on battery power or a metered connection.
+# [on_battery]
+# upgrade = "off"
+# install = "off"
+# download = "off"
+# [on_metered]
+# upgrade = "off"
+# install = "off"
+# download = "off"
+
+# Terminate the commands of the package manager after this many seconds.
+# timeout = 600
+
+# Run `-S`, `-Sw` and `-Sy` again up to this many times on network failures.
+# retries = 0
+
+# Save the packages downloaded by `-Sw` to this directory instead of the cache
+# of the package manager.
+# download_dir = "/srv/packages"
+
+# Append a JSON record of each command executed to this file.
+# log_file = "/var/log/pacaptr.log"
+
+# Exit with the codes of the package manager as is, instead of the ones of
+# `pacman`, eg. `66` when a target is not found.
+# raw_exit_codes = false
+
+# Print the unread news of the distribution before upgrading the system, eg.
+# with `informant` for a custom package manager named `pacman`.
+# show_news = false
+
+# Take a snapshot of the system before `-S`, `-R` and `-Su`, with the first of
+# "snapper", "timeshift" and "tmutil" found, or with "zfs" or "btrfs" for the
+# dataset or subvolume in `snapshot_target`.
+# snapshot_before = false
+# snapshot_provider = "snapper"
+# snapshot_target = "/"
+
+# The maximum number of packages passed to a single command, larger
+# transactions being split into chunks.
+# max_kws = 100
+
+# Shortcuts for frequently used commands, eg. `pacaptr up` for `pacaptr -Suy`.
+# [aliases]
+# up = "-Suy"
+
+# Commands run before (`pre_*`) or after (`post_*`) an operation, with the
+# operation, the package manager and the keywords in `PACAPTR_OP`, `PACAPTR_PM`
+# and `PACAPTR_PACKAGES`.
+# [hooks]
+# pre_s = "timeshift --create"
+# post_suy = "flatpak update -y"
+
+# The operations denied to everyone, eg. `-Scc`, or `-R` of the kernels with
+# `apt`. Only read from `/etc/pacaptr.toml`, and ignored in the other files.
+# [[policy.deny]]
+# op = "Scc"
+# [[policy.deny]]
+# op = "R"
+# kws = ["linux*"]
+# pm = "apt"
+# reason = "ask the admins to remove a kernel"
+
+# Overrides for a single package manager, along with the extra flags always
+# passed to it.
+# [brew]
+# dry_run = false
+# needed = false
+# no_confirm = false
+# no_cache = true
+# sudo = "doas"
+# max_kws = 100
+# flags = ["--verbose"]
+
+# A custom package manager, with the commands run for the operations of
+# `pacman` in lowercase, eg. `ss` for `-Ss`, the keywords being appended.
+# [spack]
+# needs_root = false
+# no_confirm_flags = ["-y"]
+# dry_run_flags = []
+# reinstall_flags = []
+# [spack.methods]
+# s = "spack install"
+# r = "spack uninstall"
+"#;
+
+/// Splits a `key` like `brew.no_cache` into the table and the name of the
+/// setting, the table being empty for top-level settings.
+fn split_key(key: &str) -> Result<(&str, &str)> {
+    let (table, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let valid = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    if !valid(name) || !(table.is_empty() || valid(table)) {
+        return Err(Error::ConfigError {
+            msg: format!("Invalid key `{key}`, expected eg. `no_confirm` or `brew.no_cache`"),
+        });
+    }
+    Ok((table, name))
+}
+
+/// Renders `value` as a TOML value, taking it as a string unless it's
+/// already a valid one, eg. `true` or `["curl"]`.
+fn render_value(value: &str) -> String {
+    let value = value.trim();
+    let is_toml = toml::from_str::<Table>(&format!("v = {value}"))
+        .is_ok_and(|table| table.len() == 1 && !value.contains('\n'));
+    if is_toml {
+        value.into()
+    } else {
+        Value::String(value.into()).to_string()
+    }
+}
+
+/// Sets `key` to the rendered `value` in the config file `src`, keeping the
+/// other lines (comments included) untouched.
+fn set_in(src: &str, key: &str, value: &str) -> Result<String> {
+    let (table, name) = split_key(key)?;
+    let entry = format!("{name} = {value}");
+    let mut lines: Vec<String> = src.lines().map(String::from).collect();
+
+    // Find the lines of `table`, ie. from its header to the next one.
+    let is_header = |ln: &str| ln.trim_start().starts_with('[');
+    let header = format!("[{table}]");
+    let start = if table.is_empty() {
+        Some(0)
+    } else {
+        lines
+            .iter()
+            .position(|ln| ln.trim() == header)
+            .map(|i| i + 1)
+    };
+    let Some(start) = start else {
+        if lines.last().is_some_and(|ln| !ln.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.extend([header, entry]);
+        return Ok(lines.join("\n") + "\n");
+    };
+    let end = lines[start..]
+        .iter()
+        .position(|ln| is_header(ln))
+        .map_or(lines.len(), |i| start + i);
+
+    let is_entry = |ln: &str| {
+        ln.trim_start()
+            .strip_prefix(name)
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    };
+    if let Some(i) = lines[start..end].iter().position(|ln| is_entry(ln)) {
+        lines[start + i] = entry;
+    } else {
+        // Insert after the last non-blank line of the table.
+        let pos = lines[start..end]
+            .iter()
+            .rposition(|ln| !ln.trim().is_empty())
+            .map_or(start, |i| start + i + 1);
+        lines.insert(pos, entry);
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Checks that the config file `src` is valid.
+fn check(src: &str) -> Result<()> {
+    toml::from_str::<Config>(src)
+        .map(|_| ())
+        .map_err(|e| Error::ConfigError {
+            msg: format!("Invalid config: {e}"),
+        })
+}
+
+/// Prints the value of `key` merged from all the config files.
+///
+/// # Errors
+/// Returns an [`Error::ConfigError`] when the config files cannot be loaded,
+/// or when `key` is not set.
+pub(crate) fn get(key: &str) -> Result<()> {
+    let cfg = Value::try_from(Config::try_load()?).map_err(|e| Error::ConfigError {
+        msg: format!("Failed to serialize config: {e}"),
+    })?;
+    let val = key
+        .split('.')
+        .try_fold(&cfg, |val, k| val.get(k))
+        .ok_or_else(|| Error::ConfigError {
+            msg: format!("`{key}` is not set"),
+        })?;
+    match val {
+        Value::String(s) => println!("{s}"),
+        Value::Table(_) => print!("{}", toml::to_string(val).unwrap_or_default()),
+        val => println!("{val}"),
+    }
+    Ok(())
+}
+
+/// Sets `key` to `value` in the user's config file, which is created if
+/// needed.
+///
+/// # Errors
+/// Returns an [`Error::ConfigError`] when `key` or `value` is invalid, or an
+/// [`Error::IoError`] when the config file cannot be read or written.
+pub(crate) fn set(key: &str, value: &str) -> Result<()> {
+    set_all(&[(key, value)])
+}
+
+/// Sets each key to its value in the user's config file, like [`set`], but
+/// writing the file only once.
+///
+/// # Errors
+/// See [`set`].
+pub(crate) fn set_all(entries: &[(&str, &str)]) -> Result<()> {
+    let path = Config::path()?;
+    let mut src = match fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    for (key, value) in entries {
+        src = set_in(&src, key, &render_value(value))?;
+    }
+    check(&src)?;
+    config::write(&path, &src)
+}
+
+/// Opens the user's config file with `$VISUAL` or `$EDITOR`, writing the
+/// [`TEMPLATE`] first if it doesn't exist yet.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when the editor fails, or an
+/// [`Error::ConfigError`] when the config file is invalid afterwards.
+pub(crate) fn edit() -> Result<()> {
+    let path = Config::path()?;
+    if !path.exists() {
+        config::write(&path, TEMPLATE)?;
+    }
+    let default = if cfg!(windows) { "notepad" } else { "vi" };
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().filter(|s| !s.trim().is_empty()))
+        .unwrap_or_else(|| default.into());
+    let mut editor = editor.split_whitespace();
+    let prog = editor.next().unwrap_or(default);
+    let status = Command::new(prog).args(editor).arg(&path).status()?;
+    if !status.success() {
+        return Err(Error::OtherError(format!("`{prog}` exited with {status}")));
+    }
+    check(&fs::read_to_string(&path)?)
+}
+
+/// Writes the [`TEMPLATE`] to the user's config file.
+///
+/// # Errors
+/// Returns an [`Error::ConfigError`] when the config file already exists, or
+/// an [`Error::IoError`] when it cannot be written.
+pub(crate) fn init() -> Result<()> {
+    let path = Config::path()?;
+    if path.exists() {
+        return Err(Error::ConfigError {
+            msg: format!("`{}` already exists", path.display()),
+        });
+    }
+    config::write(&path, TEMPLATE)?;
+    print::print_msg(
+        &format!("Config file written to `{}`", path.display()),
+        PROMPT_INFO,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::dispatch::{
+        policy::{Policy, Rule},
+        snapshot::Provider as SnapshotProvider,
+        Backend,
+    };
+
+    #[test]
+    fn template() {
+        check(TEMPLATE).unwrap();
+        let cfg: Config = toml::from_str(TEMPLATE).unwrap();
+        assert_eq!(cfg.default_pm, None);
+    }
+
+    #[test]
+    fn template_keys() {
+        let backend = Backend {
+            dry_run: Some(false),
+            needed: Some(false),
+            no_confirm: Some(false),
+            no_cache: Some(false),
+            sudo: Some("doas".into()),
+            max_kws: Some(100),
+            flags: vec!["--verbose".into()],
+            methods: BTreeMap::from([("s".into(), "spack install".into())]),
+            no_confirm_flags: vec!["-y".into()],
+            dry_run_flags: vec!["-n".into()],
+            reinstall_flags: vec!["-f".into()],
+            needs_root: true,
+        };
+        let cfg = Config {
+            no_confirm_allowed_ops: Some(vec!["s".into()]),
+            detect_priority: vec!["apt".into()],
+            force_sudo: Some(true),
+            timeout: Some(600),
+            download_dir: Some("/srv/packages".into()),
+            log_file: Some("/var/log/pacaptr.log".into()),
+            snapshot_provider: Some(SnapshotProvider::Snapper),
+            snapshot_target: Some("/".into()),
+            max_kws: Some(100),
+            hooks: BTreeMap::from([("post_suy".into(), "true".into())]),
+            policy: Policy {
+                deny: vec![Rule {
+                    op: "Scc".into(),
+                    ..Rule::default()
+                }],
+            },
+            ..Config::default()
+        };
+        // Every setting is documented in the template, be it commented out.
+        let documented = |key: &str| {
+            [
+                format!("# {key} = "),
+                format!("# [{key}]"),
+                format!(".{key}]"),
+                format!("# [[{key}."),
+            ]
+            .iter()
+            .any(|s| TEMPLATE.contains(s))
+        };
+        for val in [
+            Value::try_from(cfg).unwrap(),
+            Value::try_from(backend).unwrap(),
+        ] {
+            for key in val.as_table().unwrap().keys() {
+                assert!(documented(key), "`{key}` is missing from the template");
+            }
+        }
+    }
+
+    #[test]
+    fn set_keys() {
+        let src = indoc::indoc! {r#"
+            # Keep this comment.
+            default_pm = "apt"
+
+            [brew]
+            no_cache = true
+        "#};
+        let src = set_in(src, "no_confirm", &render_value("true")).unwrap();
+        let src = set_in(&src, "default_pm", &render_value("brew")).unwrap();
+        let src = set_in(&src, "brew.no_cache", &render_value("false")).unwrap();
+        let src = set_in(&src, "apt.flags", &render_value(r#"["--quiet"]"#)).unwrap();
+        assert_eq!(
+            src,
+            indoc::indoc! {r#"
+                # Keep this comment.
+                default_pm = "brew"
+                no_confirm = true
+
+                [brew]
+                no_cache = false
+
+                [apt]
+                flags = ["--quiet"]
+            "#}
+        );
+        check(&src).unwrap();
+
+        assert!(split_key("brew.").is_err());
+        assert!(split_key("a b").is_err());
+        assert!(check(&set_in("", "no_confirm", &render_value("maybe")).unwrap()).is_err());
+    }
+}
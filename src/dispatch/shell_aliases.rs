@@ -0,0 +1,339 @@
+//! `pacaptr alias-import`, converting the package manager aliases in the
+//! user's shell startup files, eg. `alias agi='sudo apt install'`, into
+//! [`aliases`](crate::dispatch::Config::aliases) of `pacaptr`.
+
+use std::fs;
+
+use itertools::Itertools;
+use tap::prelude::*;
+use tokio::task;
+
+use crate::{
+    dispatch::{settings, Config},
+    error::Result,
+    exec,
+    print::{self, PROMPT_INFO},
+};
+
+/// Subcommands of a package manager, eg. `install`, with the `pacaptr`
+/// operations they correspond to, eg. `-S`.
+type Ops = &'static [(&'static str, &'static str)];
+
+/// The flags of a package manager taking a value as the next word, eg.
+/// `-t bookworm-backports`.
+type ValueFlags = &'static [&'static str];
+
+/// The subcommands and the flags taking a value of the supported package
+/// managers, the first name of each being the one used by `pacaptr`.
+static SUBCOMMANDS: &[(&[&str], Ops, ValueFlags)] = &[
+    (
+        &["apt", "apt-get"],
+        &[
+            ("install", "-S"),
+            ("remove", "-R"),
+            ("purge", "-Rn"),
+            ("autoremove", "-Rs"),
+            ("update", "-Sy"),
+            ("upgrade", "-Su"),
+            ("full-upgrade", "-Su"),
+            ("dist-upgrade", "-Su"),
+            ("search", "-Ss"),
+            ("show", "-Si"),
+            ("clean", "-Scc"),
+        ],
+        &[
+            "-t",
+            "--target-release",
+            "-o",
+            "--option",
+            "-c",
+            "--config-file",
+        ],
+    ),
+    (
+        &["dnf", "yum"],
+        &[
+            ("install", "-S"),
+            ("remove", "-R"),
+            ("autoremove", "-Rs"),
+            ("makecache", "-Sy"),
+            ("upgrade", "-Su"),
+            ("update", "-Su"),
+            ("search", "-Ss"),
+            ("info", "-Si"),
+            ("clean", "-Scc"),
+        ],
+        &[
+            "-c",
+            "--config",
+            "--repo",
+            "--enablerepo",
+            "--disablerepo",
+            "--releasever",
+            "-x",
+            "--exclude",
+        ],
+    ),
+    (
+        &["zypper"],
+        &[
+            ("install", "-S"),
+            ("in", "-S"),
+            ("remove", "-R"),
+            ("rm", "-R"),
+            ("refresh", "-Sy"),
+            ("ref", "-Sy"),
+            ("update", "-Su"),
+            ("up", "-Su"),
+            ("dist-upgrade", "-Su"),
+            ("dup", "-Su"),
+            ("search", "-Ss"),
+            ("se", "-Ss"),
+            ("info", "-Si"),
+            ("if", "-Si"),
+        ],
+        &["-r", "--repo", "--from", "-c", "--config"],
+    ),
+    (
+        &["brew"],
+        &[
+            ("install", "-S"),
+            ("uninstall", "-R"),
+            ("remove", "-R"),
+            ("update", "-Sy"),
+            ("upgrade", "-Su"),
+            ("search", "-Ss"),
+            ("info", "-Si"),
+            ("list", "-Q"),
+            ("cleanup", "-Sc"),
+        ],
+        &[],
+    ),
+    (
+        &["choco"],
+        &[
+            ("install", "-S"),
+            ("uninstall", "-R"),
+            ("upgrade", "-Su"),
+            ("search", "-Ss"),
+            ("find", "-Ss"),
+            ("info", "-Si"),
+            ("list", "-Q"),
+            ("outdated", "-Qu"),
+        ],
+        &["-s", "--source", "--version"],
+    ),
+];
+
+/// The shell startup files looked for aliases in `$HOME`.
+static RC_FILES: &[&str] = &[
+    ".bashrc",
+    ".bash_aliases",
+    ".zshrc",
+    ".config/fish/config.fish",
+];
+
+/// Parses a shell alias definition like `alias agi='sudo apt install'` (in
+/// `bash` or `zsh`) or `alias agi 'sudo apt install'` (in `fish`) into the
+/// name and the command of the alias.
+fn parse_alias(line: &str) -> Option<(&str, &str)> {
+    let def = line.trim().strip_prefix("alias ")?.trim_start();
+    let (name, cmd) = def
+        .split_once('=')
+        .or_else(|| def.split_once(char::is_whitespace))?;
+    let cmd = cmd.trim();
+    let unquoted = ['\'', '"']
+        .into_iter()
+        .find_map(|q| cmd.strip_prefix(q)?.strip_suffix(q))
+        .unwrap_or(cmd);
+    Some((name.trim(), unquoted))
+}
+
+/// Converts a package manager command like `sudo apt install -y` into the
+/// name of the package manager used by `pacaptr`, eg. `apt`, and the
+/// arguments of `pacaptr`, eg. `-S --yes`. Returns `None` if the command
+/// cannot be converted, eg. when it uses shell syntax.
+///
+/// The flags unknown to `pacaptr`, along with their values, are passed
+/// through to the package manager after `--` if `pass_through`, and make the
+/// command unconvertible otherwise.
+pub(crate) fn convert(cmd: &str, pass_through: bool) -> Option<(&'static str, String)> {
+    if cmd.contains(|c| ";|&$`<>(){}\\".contains(c)) {
+        return None;
+    }
+    let mut words = cmd
+        .split_whitespace()
+        .skip_while(|&w| matches!(w, "sudo" | "doas"));
+    let pm = words.next()?;
+    let (pms, ops, value_flags) = SUBCOMMANDS.iter().find(|(pms, ..)| pms.contains(&pm))?;
+    // The flags might come before the subcommand, eg. `apt-get -y install`.
+    let mut subcmd = None;
+    let mut extra_flags = vec![];
+    let mut rest = vec![];
+    while let Some(w) = words.next() {
+        match w {
+            "-y" | "--yes" | "--assumeyes" | "--assume-yes" | "--no-confirm" | "--confirm" => {
+                rest.push("--yes");
+            }
+            // `choco upgrade all` upgrades every package, like `-Su` alone.
+            "all" if pm == "choco" && subcmd == Some("upgrade") => (),
+            w if w.starts_with('-') && pass_through => {
+                extra_flags.push(w);
+                if value_flags.contains(&w) {
+                    extra_flags.push(words.next()?);
+                }
+            }
+            w if w.starts_with('-') => return None,
+            w if subcmd.is_none() => subcmd = Some(w),
+            w => rest.push(w),
+        }
+    }
+    let (_, op) = ops.iter().find(|(name, _)| Some(*name) == subcmd)?;
+    if !extra_flags.is_empty() {
+        rest.push("--");
+        rest.extend(extra_flags);
+    }
+    Some((pms[0], std::iter::once(*op).chain(rest).join(" ")))
+}
+
+/// Finds the aliases convertible to `pacaptr` in the shell startup `files`,
+/// skipping those in `existing`. The first definition of a name wins.
+fn find(files: &[String], existing: &[&str]) -> Vec<(String, String)> {
+    let mut found: Vec<(String, String)> = vec![];
+    for (name, cmd) in files
+        .iter()
+        .flat_map(|src| src.lines())
+        .filter_map(parse_alias)
+    {
+        let valid_name = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name || existing.contains(&name) || found.iter().any(|(n, _)| n == name) {
+            continue;
+        }
+        if let Some((_, args)) = convert(cmd, false) {
+            found.push((name.into(), args));
+        }
+    }
+    found
+}
+
+/// Scans the user's shell startup files for package manager aliases, and
+/// offers to add them to the `[aliases]` section of the config file. Aliases
+/// which are already defined there are left untouched.
+///
+/// # Errors
+/// Returns an [`Error::ConfigError`](crate::error::Error::ConfigError) when
+/// the config file cannot be loaded or written.
+pub(crate) fn import(cfg: &Config) -> Result<()> {
+    let Some(home) = dirs_next::home_dir() else {
+        return Ok(());
+    };
+    let files = RC_FILES
+        .iter()
+        .filter_map(|file| fs::read_to_string(home.join(file)).ok())
+        .collect_vec();
+    let existing = cfg.aliases.keys().map(String::as_str).collect_vec();
+    let found = find(&files, &existing);
+    if found.is_empty() {
+        print::print_msg(
+            "No new package manager alias found in the shell startup files",
+            PROMPT_INFO,
+        );
+        return Ok(());
+    }
+    for (name, args) in &found {
+        print::print_msg(&format!("{name} = \"{args}\""), "Alias");
+    }
+    if cfg.dry_run {
+        return Ok(());
+    }
+    let proceed = cfg.no_confirm
+        || task::block_in_place(|| {
+            exec::prompt(
+                "Add these aliases to the config file",
+                "[YES/no]",
+                &["", "y", "yes", "n", "no"],
+                false,
+            )
+        })
+        .pipe(|answer| matches!(answer, "" | "y" | "yes"));
+    if !proceed {
+        return Ok(());
+    }
+    let keys = found
+        .iter()
+        .map(|(name, _)| format!("aliases.{name}"))
+        .collect_vec();
+    let entries = keys
+        .iter()
+        .zip(&found)
+        .map(|(key, (_, args))| (key.as_str(), args.as_str()))
+        .collect_vec();
+    settings::set_all(&entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(
+            parse_alias("alias agi='sudo apt install'"),
+            Some(("agi", "sudo apt install"))
+        );
+        assert_eq!(
+            parse_alias("  alias bu \"brew upgrade\""),
+            Some(("bu", "brew upgrade"))
+        );
+        assert_eq!(parse_alias("export PATH=$HOME/bin"), None);
+
+        let args = |cmd, pass_through| convert(cmd, pass_through).map(|(_, args)| args);
+        assert_eq!(args("sudo apt install", false).as_deref(), Some("-S"));
+        assert_eq!(
+            args("apt-get -y install", false).as_deref(),
+            Some("-S --yes")
+        );
+        assert_eq!(
+            args("sudo dnf install -y git", false).as_deref(),
+            Some("-S --yes git")
+        );
+        assert_eq!(args("zypper ref", false).as_deref(), Some("-Sy"));
+        assert_eq!(args("apt install --no-install-recommends", false), None);
+        assert_eq!(args("sudo apt update && sudo apt upgrade", false), None);
+        assert_eq!(args("ls -la", false), None);
+        assert_eq!(args("apt -y", false), None);
+        assert_eq!(
+            args("brew install --cask docker", true).as_deref(),
+            Some("-S docker -- --cask")
+        );
+        assert_eq!(
+            args("choco upgrade all -y", true).as_deref(),
+            Some("-Su --yes")
+        );
+        assert_eq!(
+            convert("apt-get install -t bookworm-backports curl", true),
+            Some(("apt", "-S curl -- -t bookworm-backports".into()))
+        );
+        assert_eq!(
+            convert("yum --enablerepo epel install htop", true),
+            Some(("dnf", "-S htop -- --enablerepo epel".into()))
+        );
+    }
+
+    #[test]
+    fn found_aliases() {
+        let files = [indoc::indoc! {"
+            alias ll='ls -la'
+            alias agi='sudo apt install'
+            alias agu='sudo apt update'
+            alias agi='apt-get install -y'
+        "}
+        .to_owned()];
+        assert_eq!(
+            find(&files, &["agu"]),
+            [("agi".to_owned(), "-S".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! The disk usage of the installed packages, as shown by `pacaptr size`,
+//! largest first.
+//!
+//! The sizes are reported by each package manager with
+//! [`PmBase::sizes`](crate::pm::PmBase::sizes), eg. from `dpkg-query` or
+//! `rpm`, or measured on the directories the packages are installed to.
+
+use itertools::{chain, Itertools};
+
+use crate::{
+    error::Result,
+    exec::pager::{self, Pager},
+    pm::Pm,
+};
+
+/// Formats `bytes` with the largest binary unit keeping it above 1, eg.
+/// `1.5 MiB`.
+#[must_use]
+pub(crate) fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let mut size = bytes as f64 / 1024.;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024. {
+            break;
+        }
+        size /= 1024.;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
+
+/// Renders the table of the package sizes `rows`, in the order given, with a
+/// line for their total.
+#[must_use]
+pub(crate) fn render(rows: &[(String, u64)]) -> String {
+    let sizes = rows.iter().map(|(_, size)| human_size(*size)).collect_vec();
+    let total = human_size(rows.iter().map(|(_, size)| size).sum());
+    let width = chain!(&sizes, [&total]).map(String::len).max().unwrap_or(0);
+    let body = rows
+        .iter()
+        .zip(&sizes)
+        .map(|((name, _), size)| format!("{size:>width$}  {name}"));
+    chain!(body, [format!("{total:>width$}  Total")]).join("\n")
+}
+
+/// Prints the sizes of the packages `kws` installed with `pm`, or of all the
+/// packages installed if `kws` is empty, largest first. Only the `top` largest
+/// ones are shown if given.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`](crate::error::Error)
+/// when `pm` cannot report the sizes, or any error returned by it.
+pub(crate) async fn show(
+    pm: &dyn Pm,
+    kws: &[&str],
+    flags: &[&str],
+    top: Option<usize>,
+) -> Result<()> {
+    let mut rows = pm.sizes(flags).await?;
+    if !kws.is_empty() {
+        rows.retain(|(name, _)| kws.contains(&name.as_str()));
+    }
+    rows.sort_by(|(n0, s0), (n1, s1)| s1.cmp(s0).then_with(|| n0.cmp(n1)));
+    if let Some(top) = top {
+        rows.truncate(top);
+    }
+    let _pager = Pager::spawn(pm.cfg().no_pager);
+    pager::print(&render(&rows));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(3 * 1024 * 1024), "3.0 MiB");
+        assert_eq!(human_size(5 << 30), "5.0 GiB");
+    }
+
+    #[test]
+    fn rendered() {
+        let table = render(&[("vim".into(), 4 << 20), ("curl".into(), 512)]);
+        assert_eq!(table, "4.0 MiB  vim\n  512 B  curl\n4.0 MiB  Total");
+    }
+}
@@ -0,0 +1,167 @@
+//! Taking a snapshot of the system before the operations which change the
+//! installed packages, ie. `-S`, `-R` and `-Su`, with `snapshot_before` set,
+//! so that a botched transaction can be rolled back.
+//!
+//! Each [`Provider`] knows how to build the command taking a labelled
+//! snapshot. Providers are tried in the order of [`Provider::ALL`] when none
+//! is configured.
+
+use std::time::Duration;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dispatch::{history, Config},
+    error::{Error, Result},
+    exec::{is_exe, Cmd, Mode},
+    pm::Pm,
+};
+
+/// A tool taking snapshots of the system.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// `snapper`, with its default config.
+    Snapper,
+    /// `timeshift`, with its default device.
+    Timeshift,
+    /// A `zfs` snapshot of the dataset in `snapshot_target`.
+    Zfs,
+    /// A read-only `btrfs` snapshot of the subvolume in `snapshot_target`,
+    /// placed under its `.snapshots` directory.
+    Btrfs,
+    /// A local Time Machine snapshot on macOS.
+    Tmutil,
+}
+
+impl Provider {
+    /// All the providers, in the order in which they are detected.
+    pub const ALL: &'static [Self] = &[Self::Snapper, Self::Timeshift, Self::Tmutil];
+
+    /// The executable of the provider.
+    #[must_use]
+    fn exe(self) -> &'static str {
+        match self {
+            Self::Snapper => "snapper",
+            Self::Timeshift => "timeshift",
+            Self::Zfs => "zfs",
+            Self::Btrfs => "btrfs",
+            Self::Tmutil => "tmutil",
+        }
+    }
+
+    /// Builds the command taking a snapshot described as `desc`, eg.
+    /// `pacaptr -Rns curl`, and named `name` where a name is needed.
+    ///
+    /// # Errors
+    /// Returns an [`Error::ConfigError`] when the provider needs a
+    /// `snapshot_target` which is not set.
+    fn cmd(self, desc: &str, name: &str, target: Option<&str>) -> Result<Cmd> {
+        let target = || {
+            target.ok_or_else(|| Error::ConfigError {
+                msg: format!(
+                    "`snapshot_target` must be set to take snapshots with `{}`",
+                    self.exe()
+                ),
+            })
+        };
+        Ok(match self {
+            Self::Snapper => Cmd::with_sudo(&["snapper", "create", "--description", desc]),
+            Self::Timeshift => Cmd::with_sudo(&["timeshift", "--create", "--comments", desc]),
+            Self::Zfs => Cmd::with_sudo(&["zfs", "snapshot", &format!("{}@{name}", target()?)]),
+            Self::Btrfs => {
+                let target = target()?;
+                Cmd::with_sudo(&[
+                    "btrfs",
+                    "subvolume",
+                    "snapshot",
+                    "-r",
+                    target,
+                    &format!("{}/.snapshots/{name}", target.trim_end_matches('/')),
+                ])
+            }
+            Self::Tmutil => Cmd::new(&["tmutil", "localsnapshot"]),
+        })
+    }
+}
+
+/// Gets the configured [`Provider`], or detects the first one available.
+fn provider(cfg: &Config) -> Option<Provider> {
+    cfg.snapshot_provider.or_else(|| {
+        Provider::ALL
+            .iter()
+            .copied()
+            .find(|provider| is_exe(provider.exe(), ""))
+    })
+}
+
+/// Takes a snapshot before running `-{options}` with `kws` if it changes the
+/// installed packages and `snapshot_before` is set. In a dry run, the
+/// command is only printed.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when no provider is found or the
+/// snapshot fails, so that the operation is not run without one, or an
+/// [`Error::ConfigError`] when the provider is not configured properly.
+pub(crate) async fn take(pm: &dyn Pm, options: &str, kws: &[&str]) -> Result<()> {
+    let cfg = pm.cfg();
+    // Refreshing the databases alone, ie. `-Sy`, is not worth a snapshot.
+    if !cfg.snapshot_before || !history::is_mutating(options) || (options == "Sy" && kws.is_empty())
+    {
+        return Ok(());
+    }
+    let provider = provider(cfg).ok_or_else(|| {
+        Error::OtherError(
+            "No snapshot tool found, set `snapshot_provider` or turn `snapshot_before` off".into(),
+        )
+    })?;
+    let desc = format!("pacaptr -{options} {}", kws.join(" "));
+    let name = format!("pacaptr-{}", Local::now().format("%Y%m%d-%H%M%S"));
+    let mode = if cfg.dry_run {
+        Mode::PrintCmd
+    } else {
+        Mode::CheckErr
+    };
+    provider
+        .cmd(desc.trim_end(), &name, cfg.snapshot_target.as_deref())?
+        .sudo_with(cfg.sudo.clone())
+        .force_sudo(cfg.force_sudo)
+        .timeout(cfg.timeout.map(Duration::from_secs))
+        .exec(mode)
+        .await
+        .map_err(|e| {
+            Error::OtherError(format!(
+                "Failed to take a snapshot with `{}` before `-{options}`: {e}",
+                provider.exe()
+            ))
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmds() {
+        let cmd = |provider: Provider, target| {
+            provider
+                .cmd("pacaptr -S curl", "pacaptr-20220301-123000", target)
+                .map(|cmd| cmd.force_sudo(Some(false)).to_string())
+        };
+        assert_eq!(
+            cmd(Provider::Snapper, None).unwrap(),
+            "snapper create --description pacaptr -S curl"
+        );
+        assert_eq!(
+            cmd(Provider::Zfs, Some("rpool/ROOT")).unwrap(),
+            "zfs snapshot rpool/ROOT@pacaptr-20220301-123000"
+        );
+        assert_eq!(
+            cmd(Provider::Btrfs, Some("/")).unwrap(),
+            "btrfs subvolume snapshot -r / /.snapshots/pacaptr-20220301-123000"
+        );
+        assert!(cmd(Provider::Zfs, None).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! A guard against running out of disk space in the middle of an upgrade or
+//! an installation, which is painful to recover from.
+
+use std::path::Path;
+
+use itertools::Itertools;
+
+use crate::{
+    dispatch::history,
+    error::{Error, Result},
+    exec::{Cmd, Mode},
+    pm::Pm,
+    print::{self, PROMPT_WARN},
+};
+
+/// The number of bytes in a MiB.
+const MIB: u64 = 1024 * 1024;
+
+/// A filesystem written to by an operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Filesystem {
+    /// Where the filesystem is mounted, eg. `/`.
+    mount: String,
+    /// The available space in bytes.
+    avail: u64,
+    /// The estimated space required by the operation in bytes.
+    required: u64,
+}
+
+/// Parses the output of `df -Pk <path>` into the mount point of the
+/// filesystem and the available space in bytes.
+fn parse_df(out: &str) -> Option<(String, u64)> {
+    // The second line looks like `/dev/sda1 41152736 30012456 9026888 77% /`.
+    let cols = out.lines().nth(1)?.split_whitespace().collect_vec();
+    let avail: u64 = cols.get(3)?.parse().ok()?;
+    Some((cols.get(5..)?.join(" "), avail * 1024))
+}
+
+/// Gets the mount point of the filesystem of `path` and the available space
+/// on it in bytes.
+async fn free_space(path: &Path) -> Result<(String, u64)> {
+    let out = Cmd::new(&["df", "-Pk"])
+        .kws(&[path.to_string_lossy()])
+        .exec(Mode::Mute)
+        .await?;
+    parse_df(&String::from_utf8(out)?).ok_or_else(|| {
+        Error::OtherError(format!(
+            "Failed to parse the output of `df {}`",
+            path.display()
+        ))
+    })
+}
+
+/// Checks that upgrading or installing with `op` on `pm` leaves at least
+/// [`Config::min_free_space`](crate::dispatch::Config::min_free_space) MiB free
+/// on each filesystem written to, taking the estimated requirement into
+/// account. Filesystems whose free space cannot be found are skipped.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when there is not enough space, unless
+/// [`Config::warn_low_space`](crate::dispatch::Config::warn_low_space) is set.
+pub(crate) async fn guard(pm: &dyn Pm, op: &str, kws: &[&str], flags: &[&str]) -> Result<()> {
+    let cfg = pm.cfg();
+    if cfg.min_free_space == 0
+        || cfg.dry_run
+        || !history::is_install(op)
+        || (op == "Sy" && kws.is_empty())
+    {
+        return Ok(());
+    }
+    let Ok(usage) = pm.disk_usage(kws, flags).await else {
+        return Ok(());
+    };
+    // The requirements of the paths on the same filesystem add up.
+    let mut filesystems: Vec<Filesystem> = vec![];
+    for (path, required) in usage {
+        let Ok((mount, avail)) = free_space(&path).await else {
+            continue;
+        };
+        match filesystems.iter_mut().find(|fs| fs.mount == mount) {
+            Some(fs) => fs.required += required,
+            None => filesystems.push(Filesystem {
+                mount,
+                avail,
+                required,
+            }),
+        }
+    }
+    for fs in filesystems {
+        if fs.avail >= fs.required + cfg.min_free_space * MIB {
+            continue;
+        }
+        let msg = format!(
+            "Only {} MiB free on `{}`, while {} MiB are required and {} MiB should be kept free",
+            fs.avail / MIB,
+            fs.mount,
+            fs.required.div_ceil(MIB),
+            cfg.min_free_space,
+        );
+        if !cfg.warn_low_space {
+            return Err(Error::OtherError(msg));
+        }
+        print::print_err(msg, PROMPT_WARN);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn df() {
+        let out = indoc::indoc! {"
+            Filesystem     1024-blocks      Used Available Capacity Mounted on
+            /dev/sda1         41152736  30012456   9026888      77% /
+        "};
+        assert_eq!(parse_df(out), Some(("/".into(), 9_026_888 * 1024)));
+        let out = indoc::indoc! {"
+            Filesystem     1024-blocks      Used Available Capacity Mounted on
+            /dev/disk1s5     488245288 400000000  88245288      82% /System/Volumes/My Data
+        "};
+        assert_eq!(
+            parse_df(out),
+            Some(("/System/Volumes/My Data".into(), 88_245_288 * 1024))
+        );
+        assert_eq!(
+            parse_df("df: /nonexistent: No such file or directory"),
+            None
+        );
+    }
+}
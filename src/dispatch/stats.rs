@@ -0,0 +1,142 @@
+//! Local performance metrics of the operations, which are only collected if
+//! `metrics` is enabled in the [`Config`](crate::dispatch::Config), and never
+//! leave the machine.
+
+use std::{
+    fmt::Write as _,
+    time::{Duration, SystemTime},
+};
+
+use itertools::Itertools;
+
+use crate::{
+    error::{Error, Result},
+    state::{Run, State},
+};
+
+/// Records a run of `op` on `pm`, ignoring any failure.
+pub(crate) fn record(pm: &str, op: &str, duration: Duration, success: bool) {
+    let run = Run {
+        time: SystemTime::now(),
+        pm: pm.into(),
+        op: op.into(),
+        duration,
+        success,
+    };
+    State::open().and_then(|state| state.add_run(&run)).ok();
+}
+
+/// The statistics of all the runs of an operation on a package manager.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Summary<'r> {
+    pm: &'r str,
+    op: &'r str,
+    runs: usize,
+    failures: usize,
+    median: Duration,
+    /// The 90th percentile of the durations.
+    p90: Duration,
+}
+
+/// Summarizes the `runs`, slowest operations first.
+fn summarize(runs: &[Run]) -> Vec<Summary<'_>> {
+    runs.iter()
+        .into_group_map_by(|r| (&r.pm, &r.op))
+        .into_iter()
+        .map(|((pm, op), runs)| {
+            let durations = runs.iter().map(|r| r.duration).sorted().collect_vec();
+            let nth = |pct: usize| durations[(durations.len() - 1) * pct / 100];
+            Summary {
+                pm,
+                op,
+                runs: runs.len(),
+                failures: runs.iter().filter(|r| !r.success).count(),
+                median: nth(50),
+                p90: nth(90),
+            }
+        })
+        .sorted_by(|a, b| {
+            b.median
+                .cmp(&a.median)
+                .then((a.pm, a.op).cmp(&(b.pm, b.op)))
+        })
+        .collect()
+}
+
+/// Renders the `summaries` as a table.
+fn render(summaries: &[Summary]) -> String {
+    let secs = |d: Duration| format!("{:.1}s", d.as_secs_f64());
+    let mut table = format!(
+        "{:<10} {:<8} {:>6} {:>8} {:>9} {:>9}\n",
+        "PM", "OP", "RUNS", "FAILED", "MEDIAN", "P90"
+    );
+    for s in summaries {
+        writeln!(
+            table,
+            "{:<10} {:<8} {:>6} {:>8} {:>9} {:>9}",
+            s.pm,
+            format!("-{}", s.op),
+            s.runs,
+            s.failures,
+            secs(s.median),
+            secs(s.p90),
+        )
+        .ok();
+    }
+    table
+}
+
+/// Prints the performance statistics of the recorded operations, slowest
+/// first.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when no metrics have been recorded, or an
+/// [`Error::StateError`] when they cannot be read.
+pub(crate) fn perf() -> Result<()> {
+    let runs = State::open()?.runs()?;
+    if runs.is_empty() {
+        return Err(Error::OtherError(
+            "No metrics recorded yet, please set `metrics = true` in the config file".into(),
+        ));
+    }
+    print!("{}", render(&summarize(&runs)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(pm: &str, op: &str, secs: u64, success: bool) -> Run {
+        Run {
+            time: SystemTime::UNIX_EPOCH,
+            pm: pm.into(),
+            op: op.into(),
+            duration: Duration::from_secs(secs),
+            success,
+        }
+    }
+
+    #[test]
+    fn summaries() {
+        let runs = [
+            run("apt", "S", 10, true),
+            run("apt", "Suy", 60, true),
+            run("apt", "S", 30, false),
+            run("apt", "S", 20, true),
+            run("brew", "S", 5, true),
+        ];
+        let summaries = summarize(&runs);
+        assert_eq!(
+            summaries.iter().map(|s| (s.pm, s.op)).collect_vec(),
+            [("apt", "Suy"), ("apt", "S"), ("brew", "S")]
+        );
+        let apt_s = &summaries[1];
+        assert_eq!((apt_s.runs, apt_s.failures), (3, 1));
+        assert_eq!(apt_s.median, Duration::from_secs(20));
+        assert_eq!(apt_s.p90, Duration::from_secs(20));
+        assert!(
+            render(&summaries).contains("apt        -S            3        1     20.0s     20.0s")
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! A summary of the commands to be run by an operation which changes the
+//! installed packages, confirmed with a single prompt instead of one for each
+//! command, as set by `summary_prompt`.
+//!
+//! The commands are collected by running the operation as a dry run while
+//! [recording](crate::exec::record) them.
+
+use serde::{Deserialize, Serialize};
+use tap::prelude::*;
+use tokio::task;
+
+use crate::{
+    dispatch::{history, Config},
+    exec,
+    pm::Pm,
+    print::{self, PROMPT_INFO},
+};
+
+/// When the summary of the commands to be run is shown.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryPrompt {
+    /// Never, each command is confirmed on its own.
+    #[default]
+    Never,
+    /// Only when more than one command is to be run, eg. for `-Suy`.
+    Multi,
+    /// Always.
+    Always,
+}
+
+/// Makes the [`Pm`] collecting the commands run by `-{options}` on `pm`, if
+/// a summary of them is to be shown.
+#[must_use]
+pub(crate) fn previewer(pm: &dyn Pm, options: &str) -> Option<Box<dyn Pm>> {
+    let cfg = pm.cfg();
+    if cfg.summary_prompt == SummaryPrompt::Never
+        || cfg.dry_run
+        || cfg.no_confirm
+        || !history::is_mutating(options)
+    {
+        return None;
+    }
+    Config {
+        dry_run: true,
+        ..cfg.clone()
+    }
+    .conv::<Box<dyn Pm>>()
+    .pipe(Some)
+}
+
+/// Renders the summary of the commands `cmds` to be run by `pm`.
+#[must_use]
+fn render(pm: &str, cmds: &[String]) -> String {
+    let noun = if cmds.len() == 1 {
+        "command"
+    } else {
+        "commands"
+    };
+    let mut summary = format!("Will run {} {noun} via {pm}:", cmds.len());
+    for cmd in cmds {
+        summary.push_str("\n  ");
+        summary.push_str(cmd);
+    }
+    summary
+}
+
+/// Shows the summary of the commands `cmds` to be run by `pm` as set by
+/// `strategy`, and asks for a confirmation. Returns `None` if no summary is
+/// shown, so that each command is confirmed on its own, and whether to
+/// proceed otherwise.
+#[must_use]
+pub(crate) fn confirm(pm: &str, cmds: &[String], strategy: SummaryPrompt) -> Option<bool> {
+    if cmds.is_empty() || (strategy == SummaryPrompt::Multi && cmds.len() < 2) {
+        return None;
+    }
+    print::print_msg(&render(pm, cmds), PROMPT_INFO);
+    task::block_in_place(|| {
+        exec::prompt("Proceed", "[YES/no]", &["", "y", "yes", "n", "no"], false)
+    })
+    .pipe(|answer| matches!(answer, "" | "y" | "yes"))
+    .pipe(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary() {
+        let cmds = ["sudo apt update".to_owned(), "sudo apt upgrade".to_owned()];
+        assert_eq!(
+            render("apt", &cmds),
+            "Will run 2 commands via apt:\n  sudo apt update\n  sudo apt upgrade"
+        );
+        assert_eq!(confirm("apt", &cmds[..1], SummaryPrompt::Multi), None);
+        assert_eq!(confirm("apt", &[], SummaryPrompt::Always), None);
+    }
+}
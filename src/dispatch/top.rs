@@ -0,0 +1,60 @@
+//! A live view of an ongoing operation run by another [`pacaptr`](crate)
+//! instance.
+
+use std::{fs, time::Duration};
+
+use tokio::time;
+
+use crate::{
+    error::{Error, Result},
+    exec::status_fd::Snapshot,
+    print::{self, PROMPT_INFO, PROMPT_PROGRESS},
+};
+
+/// How often the published [`Snapshot`] is checked.
+const INTERVAL: Duration = Duration::from_millis(500);
+
+/// A published [`Snapshot`] older than this is considered to be left over by
+/// an interrupted operation.
+const STALE_AFTER: Duration = Duration::from_mins(10);
+
+/// Reads the currently published [`Snapshot`], if there is a fresh one.
+fn read_snapshot() -> Option<Snapshot> {
+    let path = Snapshot::path()?;
+    let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+    if age > STALE_AFTER {
+        return None;
+    }
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+/// Displays the progress of the ongoing operation until it finishes.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when there is no ongoing operation.
+pub(crate) async fn watch() -> Result<()> {
+    let mut cmd = None;
+    loop {
+        match (read_snapshot(), &cmd) {
+            (Some(snapshot), _) => {
+                if cmd.as_ref() != Some(&snapshot.cmd) {
+                    print::clear_progress();
+                    print::print_msg(&format!("Watching `{}`", snapshot.cmd), PROMPT_INFO);
+                    cmd = Some(snapshot.cmd.clone());
+                }
+                print::print_progress(&snapshot.render(), PROMPT_PROGRESS);
+            }
+            (None, Some(cmd)) => {
+                print::clear_progress();
+                print::print_msg(&format!("Finished `{cmd}`"), PROMPT_INFO);
+                return Ok(());
+            }
+            (None, None) => {
+                return Err(Error::OtherError(
+                    "No ongoing operation with progress reporting found".into(),
+                ))
+            }
+        }
+        time::sleep(INTERVAL).await;
+    }
+}
@@ -0,0 +1,60 @@
+//! `pacaptr translate`, converting a package manager command like
+//! `apt-get install -y nginx` into the equivalent invocation of `pacaptr`, eg.
+//! to migrate scripts and docs to the syntax of `pacman`.
+
+use crate::{
+    dispatch::shell_aliases,
+    error::{Error, Result},
+};
+
+/// Gets the invocation of `pacaptr` equivalent to the package manager command
+/// `cmd`, using the same package manager, or `None` if `cmd` cannot be
+/// translated.
+#[must_use]
+fn invocation(cmd: &str) -> Option<String> {
+    let (pm, args) = shell_aliases::convert(cmd, true)?;
+    Some(format!("pacaptr --using {pm} {args}"))
+}
+
+/// Prints the invocation of `pacaptr` equivalent to the package manager
+/// command `cmd`.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when `cmd` cannot be translated, eg. when
+/// its package manager or subcommand is unknown.
+pub(crate) fn translate(cmd: &str) -> Result<()> {
+    let invocation = invocation(cmd).ok_or_else(|| {
+        Error::OtherError(format!(
+            "Failed to translate `{cmd}`: unknown package manager, subcommand or shell syntax"
+        ))
+    })?;
+    println!("{invocation}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invocations() {
+        assert_eq!(
+            invocation("sudo apt-get install -y nginx").as_deref(),
+            Some("pacaptr --using apt -S --yes nginx")
+        );
+        assert_eq!(
+            invocation("apt-get -y install -t bookworm-backports nginx").as_deref(),
+            Some("pacaptr --using apt -S --yes nginx -- -t bookworm-backports")
+        );
+        assert_eq!(
+            invocation("brew install --cask docker").as_deref(),
+            Some("pacaptr --using brew -S docker -- --cask")
+        );
+        assert_eq!(
+            invocation("zypper --non-interactive in -r oss vim").as_deref(),
+            Some("pacaptr --using zypper -S vim -- --non-interactive -r oss")
+        );
+        assert_eq!(invocation("apt-get moo"), None);
+        assert_eq!(invocation("pip install requests"), None);
+    }
+}
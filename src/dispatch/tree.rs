@@ -0,0 +1,108 @@
+//! `pacaptr tree`, rendering the dependency tree of packages like `pactree`
+//! does, eg. to see what will be pulled in before installing them.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{error::Result, pm::Pm};
+
+/// The dependencies of each package resolved so far.
+type Deps = HashMap<String, Vec<String>>;
+
+/// Resolves the dependencies of `root` with
+/// [`PmBase::deps`](crate::pm::PmBase::deps), down to `depth` levels if set.
+/// Each package is only queried once, level by level, so that it's reached
+/// by its shortest path before the depth runs out.
+async fn resolve(pm: &dyn Pm, root: &str, flags: &[&str], depth: Option<usize>) -> Result<Deps> {
+    let mut deps = Deps::new();
+    let mut pending = VecDeque::from([(root.to_owned(), 0)]);
+    while let Some((pkg, level)) = pending.pop_front() {
+        if deps.contains_key(&pkg) || depth.is_some_and(|depth| level >= depth) {
+            continue;
+        }
+        let found = pm.deps(&pkg, flags).await?;
+        pending.extend(found.iter().map(|dep| (dep.clone(), level + 1)));
+        deps.insert(pkg, found);
+    }
+    Ok(deps)
+}
+
+/// Renders the tree of `deps` under `root`, one package per line. A package
+/// showing up more than once is only expanded the first time.
+#[must_use]
+fn render(root: &str, deps: &Deps) -> Vec<String> {
+    fn walk(
+        pkg: &str,
+        prefix: &str,
+        deps: &Deps,
+        shown: &mut HashSet<String>,
+        lines: &mut Vec<String>,
+    ) {
+        let Some(children) = deps.get(pkg) else {
+            return;
+        };
+        if !shown.insert(pkg.to_owned()) {
+            return;
+        }
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let (branch, indent) = if last {
+                ("└─", "  ")
+            } else {
+                ("├─", "│ ")
+            };
+            lines.push(format!("{prefix}{branch}{child}"));
+            walk(child, &format!("{prefix}{indent}"), deps, shown, lines);
+        }
+    }
+
+    let mut lines = vec![root.to_owned()];
+    walk(root, "", deps, &mut HashSet::new(), &mut lines);
+    lines
+}
+
+/// Prints the dependency tree of each of `kws`, down to `depth` levels if set.
+///
+/// # Errors
+/// Returns an
+/// [`Error::OperationUnimplementedError`](crate::error::Error::OperationUnimplementedError)
+/// when `pm` cannot list the dependencies of a package.
+pub(crate) async fn show(
+    pm: &dyn Pm,
+    kws: &[&str],
+    flags: &[&str],
+    depth: Option<usize>,
+) -> Result<()> {
+    for &kw in kws {
+        let deps = resolve(pm, kw, flags, depth).await?;
+        for line in render(kw, &deps) {
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendered() {
+        let deps = Deps::from([
+            ("curl".into(), vec!["libcurl4".into(), "libc6".into()]),
+            ("libcurl4".into(), vec!["libc6".into(), "zlib1g".into()]),
+            ("libc6".into(), vec!["libgcc-s1".into()]),
+            ("zlib1g".into(), vec![]),
+        ]);
+        assert_eq!(
+            render("curl", &deps),
+            [
+                "curl",
+                "├─libcurl4",
+                "│ ├─libc6",
+                "│ │ └─libgcc-s1",
+                "│ └─zlib1g",
+                "└─libc6",
+            ]
+        );
+    }
+}
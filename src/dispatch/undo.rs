@@ -0,0 +1,115 @@
+//! Reversal of the last operation recorded in the history, eg. after a
+//! fat-fingered `-Rns`.
+
+use itertools::Itertools;
+use tap::prelude::*;
+use tokio::task;
+
+use crate::{
+    dispatch::{guard::Guard, history, Config},
+    error::{Error, Result},
+    exec,
+    pm::Pm,
+    print::{self, PROMPT_INFO},
+    state::{State, Transaction},
+};
+
+/// Gets the operation which reverses `tx`, ie. `Rs` for an installation and
+/// `S` for a removal. Returns `None` if `tx` cannot be reversed, eg. an
+/// upgrade.
+fn reverse(tx: &Transaction) -> Option<&'static str> {
+    if tx.packages.is_empty() {
+        return None;
+    }
+    match tx.op.as_str() {
+        "S" | "Sy" => Some("Rs"),
+        op if op.starts_with('R') => Some("S"),
+        _ => None,
+    }
+}
+
+/// Runs `op` returned by [`reverse`] on `pm`.
+async fn run(pm: &dyn Pm, op: &str, kws: &[&str], flags: &[&str]) -> Result<()> {
+    match op {
+        "S" => pm.s(kws, flags).await,
+        "Rs" => pm.rs(kws, flags).await,
+        _ => unreachable!("unexpected operation `{op}`"),
+    }
+}
+
+/// Reverses the last successful operation which changed the installed
+/// packages, after previewing it in a dry run. Removed packages are
+/// reinstalled, and installed packages are removed along with their
+/// dependencies which are no longer required.
+///
+/// The reversal is recorded in the history too, so undoing it again redoes
+/// the original operation.
+///
+/// # Errors
+/// Returns an [`Error::OtherError`] when there is nothing to be undone, an
+/// [`Error::PolicyViolationError`] when the reversal is denied, or any error
+/// returned by the [`Pm`].
+pub(crate) async fn undo(cfg: &Config, flags: &[&str]) -> Result<()> {
+    let pm = cfg.clone().conv::<Box<dyn Pm>>();
+    let tx = State::open()?
+        .history()?
+        .into_iter()
+        .rev()
+        .find(|tx| tx.pm == pm.name() && tx.code == 0)
+        .ok_or_else(|| Error::OtherError(format!("Nothing to undo for `{}`", pm.name())))?;
+    let op = reverse(&tx)
+        .ok_or_else(|| Error::OtherError(format!("`{}` cannot be undone", history::render(&tx))))?;
+    print::print_msg(&format!("Undoing `{}`", history::render(&tx)), PROMPT_INFO);
+
+    let kws = tx.packages.iter().map(String::as_str).collect_vec();
+    cfg.policy.check(pm.name(), op, &kws)?;
+    let mut cfg = cfg.clone();
+    cfg.confine_no_confirm(op);
+    let preview = Config {
+        dry_run: true,
+        ..cfg.clone()
+    }
+    .conv::<Box<dyn Pm>>();
+    run(&*preview, op, &kws, flags).await?;
+    if cfg.dry_run {
+        return Ok(());
+    }
+    let proceed = cfg.no_confirm
+        || task::block_in_place(|| {
+            exec::prompt("Proceed", "[YES/no]", &["", "y", "yes", "n", "no"], false)
+        })
+        .pipe(|answer| matches!(answer, "" | "y" | "yes"));
+    if !proceed {
+        return Ok(());
+    }
+
+    let guard = Guard::enter(&*pm, op, &kws).await?;
+    let res = run(guard.pm(), op, &kws, flags).await;
+    history::record(pm.name(), op, &kws, &res);
+    guard.leave(&res).await?;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    #[test]
+    fn reversals() {
+        let tx = |op: &str, packages: &[&str]| Transaction {
+            time: SystemTime::now(),
+            pm: "apt".into(),
+            op: op.into(),
+            packages: packages.iter().map(|&pkg| pkg.into()).collect(),
+            code: 0,
+        };
+        assert_eq!(reverse(&tx("S", &["curl"])), Some("Rs"));
+        assert_eq!(reverse(&tx("Sy", &["curl"])), Some("Rs"));
+        assert_eq!(reverse(&tx("Rns", &["curl"])), Some("S"));
+        assert_eq!(reverse(&tx("R", &[])), None);
+        assert_eq!(reverse(&tx("Suy", &[])), None);
+        assert_eq!(reverse(&tx("U", &["./curl.deb"])), None);
+    }
+}
@@ -0,0 +1,176 @@
+//! Installing specific versions of packages, eg. `pacaptr -S curl=8.5.0` or
+//! `pacaptr -S 'curl>=8'`, by translating the version requirements of the
+//! keywords to the syntax of the package manager with
+//! [`PmBase::versioned`](crate::pm::PmBase::versioned).
+
+use itertools::{chain, Itertools};
+
+use crate::{
+    dispatch::{chunk, partial},
+    error::{Error, Result},
+    pm::{Pm, Versioned},
+    version::Dep,
+};
+
+/// Parses the version requirement of `kw` if it's written like with `pacman`,
+/// eg. `curl>=8`, ie. the name and the version are made of the characters
+/// `pacman` allows. The other keywords are left in the syntax of the package
+/// manager, eg. `requests~=2.31` with `pip` or `=dev-lang/python-3.11` with
+/// `emerge`.
+#[must_use]
+fn requirement(kw: &str) -> Option<(&str, &str, &str)> {
+    let dep = Dep::parse(kw);
+    let (op, ver) = dep.requirement()?;
+    let is_name = |c: char| c.is_ascii_alphanumeric() || "@._+-".contains(c);
+    let is_ver = |c: char| c.is_ascii_alphanumeric() || ".:_+~-".contains(c);
+    let is_pacman = !dep.name.is_empty()
+        && dep.name.chars().all(is_name)
+        && !ver.is_empty()
+        && ver.chars().all(is_ver);
+    is_pacman.then_some((dep.name, op, ver))
+}
+
+/// Checks if any of `kws` has a version requirement, eg. `curl>=8`.
+#[must_use]
+pub(crate) fn is_versioned(kws: &[&str]) -> bool {
+    kws.iter().any(|kw| requirement(kw).is_some())
+}
+
+/// Translates the version requirements of `kws` for `pm`, leaving the other
+/// keywords as is.
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] naming the first
+/// requirement which cannot be expressed with `pm`.
+fn translate(pm: &dyn Pm, options: &str, kws: &[&str]) -> Result<Vec<Versioned>> {
+    kws.iter()
+        .map(|&kw| {
+            let Some((name, op, ver)) = requirement(kw) else {
+                return Ok(Versioned {
+                    kw: kw.into(),
+                    ..Versioned::default()
+                });
+            };
+            pm.versioned(name, op, ver)
+                .ok_or_else(|| Error::OperationUnimplementedError {
+                    op: format!("{} {kw}", options.to_lowercase()),
+                    pm: pm.name().into(),
+                })
+        })
+        .try_collect()
+}
+
+/// Runs the operation `options`, ie. `S` or `Sw`, on the packages `kws` with
+/// their version requirements translated. The packages needing flags of
+/// their own, eg. `--version` with `choco`, are handled one by one after
+/// the others, which are split into chunks if needed. With `skip_missing`,
+/// all the packages are handled one by one, like with
+/// [`partial::run`].
+///
+/// # Errors
+/// Returns an [`Error::OperationUnimplementedError`] before running anything
+/// if any requirement cannot be expressed with `pm`, or any error returned
+/// by the [`Pm`] method.
+pub(crate) async fn run(
+    pm: &dyn Pm,
+    options: &str,
+    kws: &[&str],
+    flags: &[&str],
+    skip_missing: bool,
+) -> Result<()> {
+    /// Gets the flags of `versioned`, after the common ones `flags`.
+    fn flags_of<'a>(flags: &[&'a str], versioned: &'a Versioned) -> Vec<&'a str> {
+        chain!(
+            flags.iter().copied(),
+            versioned.flags.iter().map(String::as_str)
+        )
+        .collect()
+    }
+
+    let versioned = translate(pm, options, kws)?;
+    if skip_missing {
+        let mut results = vec![];
+        for versioned in &versioned {
+            let res =
+                partial::run_one(pm, options, &[&versioned.kw], &flags_of(flags, versioned)).await;
+            results.push((versioned.kw.as_str(), res));
+        }
+        return partial::report(&results);
+    }
+    let (plain, flagged): (Vec<_>, Vec<_>) = versioned
+        .iter()
+        .partition(|versioned| versioned.flags.is_empty());
+    if !plain.is_empty() {
+        let kws = plain
+            .iter()
+            .map(|versioned| versioned.kw.as_str())
+            .collect_vec();
+        chunk::run(pm, options, &kws, flags).await?;
+    }
+    for versioned in flagged {
+        partial::run_one(pm, options, &[&versioned.kw], &flags_of(flags, versioned)).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatch::Config,
+        pm::{Apt, Choco, Dnf, Emerge, Pip},
+    };
+
+    #[test]
+    fn translated() {
+        let kws = |pm: &dyn Pm, kws: &[&str]| {
+            translate(pm, "S", kws)
+                .map(|kws| kws.into_iter().map(|v| (v.kw, v.flags)).collect_vec())
+        };
+        let apt = Apt::new(Config::default());
+        assert!(is_versioned(&["curl", "wget=1.21"]));
+        assert!(!is_versioned(&["curl"]));
+        assert_eq!(
+            kws(&apt, &["curl", "wget=1.21"]).unwrap(),
+            [("curl".into(), vec![]), ("wget=1.21".into(), vec![])]
+        );
+        let err = kws(&apt, &["curl>=8"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Operation `s curl>=8` is not supported by `apt`"
+        );
+
+        let dnf = Dnf::new(Config::default());
+        assert_eq!(
+            kws(&dnf, &["curl=8.5.0", "wget>=1.21"]).unwrap(),
+            [
+                ("curl-8.5.0".into(), vec![]),
+                ("wget >= 1.21".into(), vec![])
+            ]
+        );
+
+        // The keywords in the syntax of the package manager are left as is.
+        let pip = Pip::new(Config::default());
+        assert!(!is_versioned(&["requests~=2.31", "requests!=2.0"]));
+        assert_eq!(
+            kws(&pip, &["requests~=2.31", "requests!=2.0", "idna>=3,<4"]).unwrap(),
+            [
+                ("requests~=2.31".into(), vec![]),
+                ("requests!=2.0".into(), vec![]),
+                ("idna>=3,<4".into(), vec![])
+            ]
+        );
+        let emerge = Emerge::new(Config::default());
+        assert!(!is_versioned(&["=dev-lang/python-3.11.4"]));
+        assert_eq!(
+            kws(&emerge, &["=dev-lang/python-3.11.4"]).unwrap(),
+            [("=dev-lang/python-3.11.4".into(), vec![])]
+        );
+
+        let choco = Choco::new(Config::default());
+        assert_eq!(
+            kws(&choco, &["curl==8.5.0"]).unwrap(),
+            [("curl".into(), vec!["--version=8.5.0".into()])]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! A short interactive wizard writing the config file on the first run, so
+//! that new users get to choose the package manager to be used instead of
+//! wondering why one has been picked.
+
+use std::io::{self, IsTerminal};
+
+use itertools::Itertools;
+
+use crate::{
+    dispatch::{config, detect, Config},
+    error::Result,
+    exec,
+    print::{self, PROMPT_INFO},
+};
+
+/// Renders the config file written by the wizard.
+fn render(default_pm: Option<&str>, no_confirm: bool) -> String {
+    let default_pm = default_pm.map_or_else(
+        || "# default_pm = \"\"".to_owned(),
+        |pm| format!("default_pm = {pm:?}"),
+    );
+    format!(
+        "# Written by the `pacaptr` setup wizard, see\n\
+         # {}#configuration for all the settings.\n\
+         {default_pm}\n\
+         no_confirm = {no_confirm}\n",
+        env!("CARGO_PKG_HOMEPAGE"),
+    )
+}
+
+/// Lets the user pick the default package manager among the detected `pms`,
+/// the first one being the preferred.
+fn pick_pm<'s>(pms: &[&'s str]) -> Option<&'s str> {
+    match pms {
+        [] => {
+            print::print_msg("No supported package manager detected", PROMPT_INFO);
+            None
+        }
+        [pm] => {
+            print::print_msg(&format!("Detected `{pm}`"), PROMPT_INFO);
+            Some(pm)
+        }
+        _ => {
+            print::print_msg("Detected the following package managers:", PROMPT_INFO);
+            for (i, pm) in pms.iter().enumerate() {
+                print::print_msg(pm, &(i + 1).to_string());
+            }
+            let choices = (1..=pms.len()).map(|i| i.to_string()).collect_vec();
+            let expected = std::iter::once("")
+                .chain(choices.iter().map(String::as_str))
+                .collect_vec();
+            let answer = exec::prompt(
+                "Default package manager",
+                &format!("[1-{}, default 1]", pms.len()),
+                &expected,
+                false,
+            );
+            Some(pms[answer.parse::<usize>().map_or(0, |i| i - 1)])
+        }
+    }
+}
+
+/// Offers to set up the config file if none of [`Config::paths`] exists, eg.
+/// the one in `PACAPTR_CONFIG`, when running in a terminal. The config file is
+/// written to [`Config::path`]. Answering `never` writes a config file with the
+/// default settings, so that the wizard is not offered again.
+///
+/// # Errors
+/// Returns an [`Error::ConfigError`](crate::error::Error::ConfigError) when
+/// `$HOME` is not found, or an [`Error::IoError`](crate::error::Error::IoError)
+/// when the config file cannot be written.
+pub(crate) fn offer() -> Result<()> {
+    let loaded = Config::paths().iter().any(|path| path.exists());
+    if loaded || !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Ok(());
+    }
+    let path = Config::path()?;
+    print::print_msg(
+        &format!(
+            "No config file found at `{}`, skip this with `--no-wizard`",
+            path.display()
+        ),
+        PROMPT_INFO,
+    );
+    let answer = exec::prompt(
+        "Set one up now",
+        "[YES/no/never]",
+        &["", "y", "yes", "n", "no", "never"],
+        false,
+    );
+    let contents = match answer {
+        "n" | "no" => return Ok(()),
+        "never" => render(None, false),
+        _ => {
+            let default_pm = pick_pm(&detect::pms(&[]));
+            let answer = exec::prompt(
+                "Answer yes to every question by default",
+                "[y/NO]",
+                &["", "y", "yes", "n", "no"],
+                false,
+            );
+            render(default_pm, matches!(answer, "y" | "yes"))
+        }
+    };
+    config::write(&path, &contents)?;
+    print::print_msg(
+        &format!("Config file written to `{}`", path.display()),
+        PROMPT_INFO,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendered_cfg() {
+        let cfg: Config = toml::from_str(&render(Some("apt"), true)).unwrap();
+        assert_eq!(cfg.default_pm.as_deref(), Some("apt"));
+        assert!(cfg.no_confirm);
+
+        let cfg: Config = toml::from_str(&render(None, false)).unwrap();
+        assert_eq!(cfg.default_pm, None);
+        assert!(!cfg.no_confirm);
+    }
+}
@@ -1,5 +1,7 @@
 //! Basic error definitions specific to this crate.
 
+use std::path::PathBuf;
+
 use thiserror::Error;
 use tokio::{io, task::JoinError};
 
@@ -7,7 +9,7 @@ use crate::exec::{Output, StatusCode};
 
 /// A specialized [`Result`](std::result::Result) type used by
 /// [`pacaptr`](crate).
-pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
+pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 /// Error type for the [`pacaptr`](crate) library.
 #[derive(Debug, Error)]
@@ -46,10 +48,21 @@ pub enum Error {
     #[allow(missing_docs)]
     CmdStatusCodeError { code: StatusCode, output: Output },
 
+    /// An [`Cmd`](crate::exec::Cmd) exits with the error code `raw`, which is
+    /// mapped to the `code` of `pacman` for the same kind of failure.
+    #[error("Subprocess exited with code {raw}")]
+    #[allow(missing_docs)]
+    CmdMappedStatusCodeError { code: StatusCode, raw: StatusCode },
+
     /// An [`Cmd`](crate::exec::Cmd) gets interrupted by a signal.
     #[error("Subprocess interrupted by signal")]
     CmdInterruptedError,
 
+    /// A subprocess was terminated as it ran for too long.
+    #[error("Subprocess timed out after {secs}s")]
+    #[allow(missing_docs)]
+    CmdTimeoutError { secs: u64 },
+
     /// Error while converting a [`Vec<u8>`] to a [`String`].
     #[error(transparent)]
     FromUtf8Error(#[from] std::string::FromUtf8Error),
@@ -58,12 +71,97 @@ pub enum Error {
     #[error(transparent)]
     IoError(#[from] io::Error),
 
-    /// A [`Pm`](crate::pm::Pm) operation is not implemented.
-    #[error("Operation `{op}` is unimplemented for `{pm}`")]
+    /// Error when accessing the [`State`](crate::state::State) store.
+    #[error("Failed to access the state store: {0}")]
+    StateError(#[from] rusqlite::Error),
+
+    /// Some dependencies checked with `-T` are not satisfied.
+    #[error("Unsatisfied dependencies: {}", .deps.join(", "))]
+    #[allow(missing_docs)]
+    DepTestError { deps: Vec<String> },
+
+    /// An operation run package by package failed for some of the packages.
+    #[error(
+        "Failed for {} of {total} package(s): {}",
+        .failed.len(),
+        .failed.join(", ")
+    )]
+    #[allow(missing_docs)]
+    BatchError { failed: Vec<String>, total: usize },
+
+    /// An operation run on several hosts with `--host` failed on some of
+    /// them. `code` is the highest exit code among them.
+    #[error(
+        "Failed on {} of {total} host(s): {}",
+        .failed.len(),
+        .failed.join(", ")
+    )]
+    #[allow(missing_docs)]
+    HostsError {
+        failed: Vec<String>,
+        total: usize,
+        code: i32,
+    },
+
+    /// A [`Pm`](crate::pm::Pm) operation is not supported by the package
+    /// manager.
+    #[error("Operation `{op}` is not supported by `{pm}`")]
     #[allow(missing_docs)]
     OperationUnimplementedError { op: String, pm: String },
 
+    /// A `pacman` operation, eg. `qc`, is not supported by the package
+    /// manager, with a hint of the closest native command if any.
+    #[error(
+        "Operation `{op}` is not supported by `{pm}`{}",
+        .hint.as_ref().map_or_else(String::new, |hint| format!(", try `{hint}` instead"))
+    )]
+    #[allow(missing_docs)]
+    OperationUnsupportedError {
+        op: String,
+        pm: String,
+        hint: Option<String>,
+    },
+
+    /// An operation is denied by the policy set by the administrator in
+    /// `/etc/pacaptr.toml`.
+    #[error("`{op}` is denied by the policy: {reason}")]
+    #[allow(missing_docs)]
+    PolicyViolationError { op: String, reason: String },
+
+    /// Another [`pacaptr`](crate) instance holds the lock taken before the
+    /// operations changing the installed packages.
+    #[error(
+        "Another pacaptr instance is running (pid {pid}), wait for it to finish \
+         or use `--no-lock` (lock: `{}`)",
+        .path.display()
+    )]
+    #[allow(missing_docs)]
+    LockError { pid: u32, path: PathBuf },
+
     /// Miscellaneous other error.
     #[error("{0}")]
     OtherError(String),
 }
+
+impl Error {
+    /// The code [`pacaptr`](crate) should exit with because of this error.
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::CmdStatusCodeError { code, .. }
+            | Error::CmdMappedStatusCodeError { code, .. }
+            | Error::HostsError { code, .. } => *code,
+            // The same as `pacman -T`.
+            Error::DepTestError { .. } => 127,
+            // The same as a shell interrupted by Ctrl-C.
+            Error::CmdInterruptedError => crate::exec::signal::EXIT_INTERRUPTED,
+            // The same as `timeout`.
+            Error::CmdTimeoutError { .. } => 124,
+            // `1` if all the packages failed, `2` if only some of them did.
+            Error::BatchError { failed, total } if failed.len() < *total => 2,
+            // The same as `EX_UNAVAILABLE` in `sysexits.h`.
+            Error::OperationUnsupportedError { .. } => 69,
+            _ => 1,
+        }
+    }
+}
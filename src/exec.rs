@@ -1,33 +1,57 @@
 //! APIs for spawning subprocesses and handling their results.
 
+pub(crate) mod container;
+pub mod executor;
+pub(crate) mod interop;
+pub(crate) mod pager;
+pub(crate) mod plan;
+pub(crate) mod record;
+pub(crate) mod remote;
+pub(crate) mod signal;
+mod spinner;
+pub(crate) mod status_code;
+pub(crate) mod status_fd;
+
 use std::{
+    collections::HashSet,
+    ops::Range,
     process::Stdio,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use bytes::{Bytes, BytesMut};
+use colored::Colorize;
 use futures::prelude::*;
 use indoc::indoc;
 use is_root::is_root;
 use itertools::{chain, Itertools};
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
 use tap::prelude::*;
 use tokio::{
-    io::{self, AsyncRead, AsyncWrite},
-    process::Command as Exec,
-    task::JoinHandle,
+    io::{self, AsyncRead, AsyncWrite, AsyncWriteExt},
+    process::{Child, Command as Exec},
+    time,
 };
-#[allow(clippy::wildcard_imports)]
 use tokio_util::{
-    codec::{BytesCodec, FramedRead},
-    compat::*,
+    codec::{Decoder, FramedRead, LinesCodec, LinesCodecError},
     either::Either,
 };
 use which::which;
 
+use self::{
+    container::Container,
+    executor::{CmdLine, Shared},
+    interop::Interop,
+    spinner::Spinner,
+};
 use crate::{
+    dispatch::EXEC_TARGET,
     error::{Error, Result},
-    print::{print_cmd, print_question, PROMPT_CANCELED, PROMPT_PENDING, PROMPT_RUN},
+    print::{self, print_cmd, print_question, PROMPT_CANCELED, PROMPT_PENDING, PROMPT_RUN},
 };
 
 /// Different ways in which a [`Cmd`] shall be dealt with.
@@ -58,6 +82,14 @@ pub(crate) enum Mode {
     /// its `stderr`. Also, this will ask for confirmation before
     /// proceeding.
     Prompt,
+
+    /// Prints out the command which should be executed, runs it and collects
+    /// its `stderr`, which is expected to be mixed with the machine-readable
+    /// status stream of `apt`/`dpkg` (see `-o APT::Status-Fd`).
+    ///
+    /// The status lines are not printed as is, but rendered as a progress
+    /// indicator and summarized when the command finishes.
+    StatusFd,
 }
 
 /// The status code type returned by a [`Cmd`],
@@ -83,6 +115,10 @@ fn exit_result(code: Option<StatusCode>, output: Output) -> Result<Output> {
 /// captured `stderr`.
 pub(crate) type Output = Vec<u8>;
 
+/// The commands, in order of preference, used to run commands as `root` when
+/// none is configured.
+static ESCALATIONS: &[&[&str]] = &[&["sudo", "-S"], &["doas"], &["run0"], &["pkexec"]];
+
 /// A command to be executed, provided in `command-flags-keywords` form.
 #[must_use]
 #[derive(Debug, Clone, Default)]
@@ -91,6 +127,18 @@ pub(crate) struct Cmd {
     /// `sudo`.
     pub sudo: bool,
 
+    /// The command used to run this command as `root`, eg. `doas`, detected
+    /// among the [`ESCALATIONS`] if not set.
+    pub sudo_with: Option<String>,
+
+    /// Whether to run this command as `root` even if we are `root` already or
+    /// no command to do so is found (if `true`), or never (if `false`).
+    /// Detected automatically if not set.
+    pub force_sudo: Option<bool>,
+
+    /// How long this command may run before being terminated.
+    pub timeout: Option<Duration>,
+
     /// The "command" part of the command string, eg. `brew install`.
     pub cmd: Vec<String>,
 
@@ -99,6 +147,27 @@ pub(crate) struct Cmd {
 
     /// The "keywords" part of the command string, eg. `curl fish`.
     pub kws: Vec<String>,
+
+    /// The extra environment variables of the command, eg.
+    /// `PACAPTR_OP=Suy`.
+    pub envs: Vec<(String, String)>,
+
+    /// Whether to run this command within PowerShell, as needed by the
+    /// package managers written in it, eg. `scoop`.
+    pub powershell: bool,
+
+    /// Where this command runs, eg. on Windows from WSL.
+    pub interop: Interop,
+
+    /// The remote host this command runs on over SSH, eg. `admin@web1`.
+    pub host: Option<String>,
+
+    /// The running container this command runs in, eg. with `docker exec`.
+    pub container: Option<Container>,
+
+    /// The custom [`Executor`](executor::Executor) running this command in
+    /// place of `pacaptr`, if any.
+    pub executor: Option<Shared>,
 }
 
 impl Cmd {
@@ -116,6 +185,15 @@ impl Cmd {
         Cmd::new(cmd).sudo(true)
     }
 
+    /// Makes a new [`Cmd`] instance with the given [`cmd`](Cmd::cmd) part,
+    /// setting [`powershell`](field@Cmd::powershell) to `true`.
+    pub(crate) fn with_powershell(cmd: &[impl AsRef<str>]) -> Self {
+        Cmd {
+            powershell: true,
+            ..Cmd::new(cmd)
+        }
+    }
+
     /// Overrides the value of [`flags`](field@Cmd::flags).
     pub(crate) fn flags(self, flags: &[impl AsRef<str>]) -> Self {
         Cmd {
@@ -132,71 +210,338 @@ impl Cmd {
         }
     }
 
+    /// Overrides the value of [`envs`](field@Cmd::envs).
+    pub(crate) fn envs(self, envs: &[(impl AsRef<str>, impl AsRef<str>)]) -> Self {
+        Cmd {
+            envs: envs
+                .iter()
+                .map(|(key, val)| (key.as_ref().into(), val.as_ref().into()))
+                .collect(),
+            ..self
+        }
+    }
+
     /// Overrides the value of [`sudo`](field@Cmd::sudo).
     pub(crate) fn sudo(self, sudo: bool) -> Self {
         Cmd { sudo, ..self }
     }
 
+    /// Overrides the value of [`sudo_with`](field@Cmd::sudo_with).
+    pub(crate) fn sudo_with(self, sudo_with: Option<String>) -> Self {
+        Cmd { sudo_with, ..self }
+    }
+
+    /// Overrides the value of [`force_sudo`](field@Cmd::force_sudo).
+    pub(crate) fn force_sudo(self, force_sudo: Option<bool>) -> Self {
+        Cmd { force_sudo, ..self }
+    }
+
+    /// Overrides the value of [`interop`](field@Cmd::interop).
+    pub(crate) fn interop(self, interop: Interop) -> Self {
+        Cmd { interop, ..self }
+    }
+
+    /// Overrides the value of [`host`](field@Cmd::host).
+    pub(crate) fn host(self, host: Option<String>) -> Self {
+        Cmd { host, ..self }
+    }
+
+    /// Overrides the value of [`container`](field@Cmd::container).
+    pub(crate) fn container(self, container: Option<Container>) -> Self {
+        Cmd { container, ..self }
+    }
+
+    /// Overrides the value of [`executor`](field@Cmd::executor).
+    pub(crate) fn executor(self, executor: Option<Shared>) -> Self {
+        Cmd { executor, ..self }
+    }
+
+    /// Overrides the value of [`timeout`](field@Cmd::timeout).
+    pub(crate) fn timeout(self, timeout: Option<Duration>) -> Self {
+        Cmd { timeout, ..self }
+    }
+
+    /// Gets the command prefix used to run this command as `root`, the first
+    /// of the [`ESCALATIONS`] found by default. Returns `None` if none of them
+    /// is found.
+    fn find_sudo_prefix(&self) -> Option<Vec<&str>> {
+        match self.sudo_with.as_deref().map(str::trim) {
+            Some("sudo") => Some(ESCALATIONS[0].to_vec()),
+            Some(sudo) => Some(sudo.split_whitespace().collect()),
+            None => ESCALATIONS
+                .iter()
+                .find(|cmd| is_exe(cmd[0], ""))
+                .map(|cmd| cmd.to_vec()),
+        }
+    }
+
+    /// Gets the command prefix used to run this command as `root`, falling
+    /// back to `sudo -S`.
+    fn sudo_prefix(&self) -> Vec<&str> {
+        // The commands found here say nothing about the other side.
+        if self.is_elsewhere() && self.sudo_with.is_none() {
+            return ESCALATIONS[0].to_vec();
+        }
+        self.find_sudo_prefix()
+            .unwrap_or_else(|| ESCALATIONS[0].to_vec())
+    }
+
+    /// Checks if this command runs on another system with a Linux userland,
+    /// ie. a remote host or a WSL distro from Windows.
+    #[must_use]
+    fn is_elsewhere(&self) -> bool {
+        self.host.is_some() || self.interop == Interop::Wsl
+    }
+
     /// Determines if this command actually needs to run with `sudo -S`.
     ///
     /// If a **normal admin** needs to run it with `sudo`, and we are not
-    /// `root`, then this is the case.
+    /// `root`, then this is the case, unless there is no command to do so, eg.
+    /// in a container without `sudo`. [`force_sudo`](field@Cmd::force_sudo)
+    /// overrides this.
     #[must_use]
     fn should_sudo(&self) -> bool {
-        self.sudo && !is_root()
+        if self.interop == Interop::Windows {
+            // The Windows package managers ask for elevation by themselves.
+            false
+        } else if self.container.is_some() {
+            // The commands already run as `root` in the container.
+            false
+        } else if self.is_elsewhere() {
+            // The user on the other side is unknown from here.
+            self.sudo && self.force_sudo != Some(false)
+        } else {
+            self.sudo
+                && self
+                    .force_sudo
+                    .unwrap_or_else(|| !is_root() && self.find_sudo_prefix().is_some())
+        }
     }
 
-    /// Converts a [`Cmd`] object into an [`Exec`].
+    /// Converts a [`Cmd`] object into an [`Exec`], asking it not to use colors
+    /// either if `pacaptr` doesn't.
     #[must_use]
     fn build(self) -> Exec {
+        let envs = self.envs.clone();
+        let mut exec = self.build_cmd();
+        exec.envs(envs).stdout(pager::stdout());
+        if !print::is_colored() {
+            exec.env("NO_COLOR", "1");
+        }
+        exec
+    }
+
+    /// Implements [`Cmd::build`].
+    #[must_use]
+    fn build_cmd(self) -> Exec {
+        let args = self.args();
+        let (exe, args) = args
+            .split_first()
+            .expect("Failed to build Cmd, command is empty");
+        Exec::new(exe).tap_mut(|builder| {
+            builder.args(args);
+        })
+    }
+
+    /// Gets the full command line of this command, the first argument being
+    /// the executable.
+    #[must_use]
+    fn args(&self) -> Vec<String> {
         // ! Special fix for `zypper`: `zypper install -y curl` is accepted,
         // ! but not `zypper install curl -y`.
         // ! So we place the flags first, and then keywords.
-        if self.should_sudo() {
-            Exec::new("sudo").tap_mut(|builder| {
-                builder
-                    .arg("-S")
-                    .args(&self.cmd)
-                    .args(&self.flags)
-                    .args(&self.kws);
-            })
+        let mut args = if self.should_sudo() {
+            self.sudo_prefix().into_iter().map(String::from).collect()
+        } else {
+            vec![]
+        };
+        let cmd = self
+            .interop
+            .paths(chain!(&self.cmd, &self.flags, &self.kws).cloned());
+        if self.powershell {
+            args.extend(powershell_args(&cmd));
         } else {
-            let (cmd, subcmd) = self
-                .cmd
-                .split_first()
-                .expect("Failed to build Cmd, command is empty");
-            Exec::new(cmd).tap_mut(|builder| {
-                builder.args(subcmd).args(&self.flags).args(&self.kws);
+            args.extend(cmd);
+        }
+        let mut args = self.interop.wrap(args);
+        if let Some(container) = &self.container {
+            args = container.wrap(args);
+        }
+        if let Some(host) = &self.host {
+            args = remote::ssh_args(host, &args);
+        }
+        args
+    }
+}
+
+/// Quotes `arg` as a PowerShell string literal, where it's taken verbatim.
+#[must_use]
+fn powershell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "''"))
+}
+
+/// Gets the arguments running the command `args` within PowerShell, ie.
+/// `pwsh -NoProfile -Command & 'scoop' 'install' 'git'`, preferring
+/// PowerShell 7+ (`pwsh`) if found. The exit code of the command is passed
+/// on.
+#[must_use]
+fn powershell_args<'a>(args: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    let exe = if is_exe("pwsh", "") {
+        "pwsh"
+    } else {
+        "powershell"
+    };
+    let line = args.into_iter().map(|arg| powershell_quote(arg)).join(" ");
+    [
+        exe,
+        "-NoProfile",
+        "-NonInteractive",
+        "-Command",
+        &format!("& {line}; exit $LASTEXITCODE"),
+    ]
+    .map(String::from)
+    .into()
+}
+
+/// The ID of the subprocess being run, if any, to be terminated when it
+/// times out or when `pacaptr` is interrupted.
+static CHILD_ID: Mutex<Option<u32>> = Mutex::new(None);
+
+/// Spawns `exec`, keeping track of its ID.
+///
+/// # Errors
+/// Returns an [`Error::CmdSpawnError`] when `exec` cannot be spawned.
+fn spawn(exec: &mut Exec) -> Result<Child> {
+    #[cfg(windows)]
+    exec.kill_on_drop(true);
+    let child = exec.spawn().map_err(Error::CmdSpawnError)?;
+    *CHILD_ID.lock().unwrap() = child.id();
+    Ok(child)
+}
+
+/// How long a subprocess which has timed out is given to exit after being
+/// terminated, before it's killed.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+/// Gets the IDs of the descendants of the process `id`, eg. the command run
+/// by `sudo`, with `pgrep`.
+#[must_use]
+fn descendants(id: u32) -> Vec<u32> {
+    let Ok(out) = std::process::Command::new("pgrep")
+        .args(["-P", &id.to_string()])
+        .output()
+    else {
+        return vec![];
+    };
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|ln| ln.trim().parse().ok())
+        .flat_map(|child| chain!([child], descendants(child)))
+        .collect()
+}
+
+/// Sends the signal `sig`, eg. `TERM`, to the subprocess being run and to
+/// its own subprocesses, as `sudo` only relays the signals sent to itself to
+/// the command it runs. Returns `false` if there is no such subprocess.
+///
+/// Signals are only sent on Unix. On Windows, the subprocess is killed along
+/// with its own subprocesses when it times out, see [`reap`].
+fn signal_child(sig: &str) -> bool {
+    let Some(id) = CHILD_ID.lock().ok().and_then(|id| *id) else {
+        return false;
+    };
+    if cfg!(unix) {
+        // The descendants are found first, as they are orphaned once `id` is
+        // gone.
+        let ids = chain!([id], descendants(id)).map(|id| id.to_string());
+        _ = std::process::Command::new("kill")
+            .arg(format!("-{sig}"))
+            .args(ids.collect_vec())
+            .status();
+    }
+    true
+}
+
+/// Waits for `fut`, which runs a subprocess, for at most `timeout`. The
+/// subprocess which has timed out is then left to [`reap`].
+///
+/// # Errors
+/// Returns an [`Error::CmdTimeoutError`] when `fut` times out, or any error
+/// returned by `fut`.
+async fn with_timeout(
+    timeout: Option<Duration>,
+    fut: impl Future<Output = Result<Output>>,
+) -> Result<Output> {
+    match timeout {
+        Some(timeout) => time::timeout(timeout, fut).await.unwrap_or_else(|_| {
+            Err(Error::CmdTimeoutError {
+                secs: timeout.as_secs(),
             })
+        }),
+        None => fut.await,
+    }
+}
+
+/// Makes sure that `child`, which has returned `res`, is gone. If it has
+/// timed out, it's [terminated](signal_child) along with its own
+/// subprocesses, killed if it's still running after [`KILL_GRACE`], and
+/// waited for, so that it doesn't hold the lock of the package manager when
+/// the command is retried.
+async fn reap(child: &mut Child, res: Result<Output>) -> Result<Output> {
+    if let Err(Error::CmdTimeoutError { .. }) = &res {
+        signal_child("TERM");
+        if cfg!(windows) || time::timeout(KILL_GRACE, child.wait()).await.is_err() {
+            if let (true, Some(id)) = (cfg!(windows), child.id()) {
+                _ = std::process::Command::new("taskkill")
+                    .args(["/T", "/F", "/PID", &id.to_string()])
+                    .status();
+            } else {
+                signal_child("KILL");
+            }
+            _ = child.start_kill();
+            _ = child.wait().await;
         }
     }
+    *CHILD_ID.lock().unwrap() = None;
+    res
 }
 
-/// Takes contents from an input stream and copy to an output stream (optional)
-/// and a [`Vec<u8>`], then returns the [`Vec<u8>`].
+/// The most bytes of `stderr` kept by [`Mode::CheckErr`] and [`Mode::Prompt`],
+/// as only its last lines are needed to tell why a command has failed.
+const STDERR_CAPTURED: usize = 64 * 1024;
+
+/// Takes the lines from an input stream and copies each of them to an output
+/// stream (optional) as soon as it's read, and to a [`Vec<u8>`], then returns
+/// the [`Vec<u8>`] holding at most the last `limit` bytes if set.
 ///
 /// Helper to implement [`Cmd::exec_checkerr`] and [`Cmd::exec_checkall`].
 ///
 /// # Arguments
 ///
-/// * `src` - The input stream to read from.
+/// * `src` - The input stream to read from, see [`into_lines`].
 /// * `out` - The optional output stream to write to.
+/// * `limit` - The optional number of bytes to keep.
 async fn exec_tee(
-    src: impl Stream<Item = io::Result<Bytes>>,
-    out: Option<impl AsyncWrite>,
-) -> Result<Vec<u8>> {
-    let mut buf = Vec::<u8>::new();
-    let buf_sink = (&mut buf).into_sink();
-
-    #[allow(clippy::option_if_let_else)]
-    let sink = if let Some(out) = out {
-        let out_sink = out.compat_write().into_sink();
-        buf_sink.fanout(out_sink).left_sink()
-    } else {
-        buf_sink.right_sink()
-    };
-
-    src.forward(sink).await?;
+    mut src: impl Stream<Item = io::Result<Bytes>> + Unpin,
+    mut out: Option<impl AsyncWrite + Unpin>,
+    limit: Option<usize>,
+) -> Result<Output> {
+    let mut buf = Output::new();
+    while let Some(line) = src.try_next().await? {
+        if let Some(out) = &mut out {
+            out.write_all(&line).await?;
+            out.flush().await?;
+        }
+        buf.extend_from_slice(&line);
+        // Trimmed once in a while instead of for each line, so that the bytes
+        // kept are not moved over and over again.
+        if let Some(limit) = limit.filter(|&limit| buf.len() > 2 * limit) {
+            buf.drain(..buf.len() - limit);
+        }
+    }
+    if let Some(limit) = limit.filter(|&limit| buf.len() > limit) {
+        buf.drain(..buf.len() - limit);
+    }
     Ok(buf)
 }
 
@@ -212,6 +557,7 @@ macro_rules! docs_errors_exec {
             - [`Error::CmdWaitError`]
             - [`Error::CmdStatusCodeError`]
             - [`Error::CmdInterruptedError`]
+            - [`Error::CmdTimeoutError`]
         "}
     };
 }
@@ -223,12 +569,31 @@ impl Cmd {
     /// of [`Mode`] for more info).
     #[doc = docs_errors_exec!()]
     pub(crate) async fn exec(self, mode: Mode) -> Result<Output> {
-        match mode {
+        let cmd = self.to_string();
+        tracing::debug!(
+            cmd,
+            ?mode,
+            sudo = self.should_sudo(),
+            timeout_secs = self.timeout.map(|t| t.as_secs()),
+            "Built the command"
+        );
+        let line = executor::is_capturing().then(|| CmdLine {
+            args: self.args(),
+            envs: self.envs.clone(),
+        });
+        let started = Instant::now();
+        let res = match mode {
             Mode::PrintCmd => {
-                print_cmd(&self, PROMPT_CANCELED);
-                Ok(Output::default())
+                if !record::push(cmd) {
+                    print_cmd(&self, PROMPT_CANCELED);
+                }
+                return Ok(Output::default());
+            }
+            _ if self.executor.is_some() => self.exec_custom().await,
+            Mode::Mute => {
+                let _spinner = Spinner::start(&self.cmd.iter().take(2).join(" "));
+                self.exec_checkall(true).await
             }
-            Mode::Mute => self.exec_checkall(true).await,
             Mode::CheckAll => {
                 print_cmd(&self, PROMPT_RUN);
                 self.exec_checkall(false).await
@@ -238,7 +603,32 @@ impl Cmd {
                 self.exec_checkerr(false).await
             }
             Mode::Prompt => self.exec_prompt(false).await,
+            Mode::StatusFd => {
+                print_cmd(&self, PROMPT_RUN);
+                self.exec_status_fd().await
+            }
+        };
+        record(&cmd, &res, started.elapsed());
+        if let Some(line) = line {
+            executor::captured(line, &res);
         }
+        res
+    }
+
+    /// Hands a [`Cmd`] over to its custom [`Executor`](executor::Executor),
+    /// printing nothing and asking for no confirmation. The timeout is left to
+    /// the executor.
+    #[doc = docs_errors_exec!()]
+    async fn exec_custom(self) -> Result<Output> {
+        let Some(Shared(executor)) = &self.executor else {
+            return self.exec_checkerr(false).await;
+        };
+        let cmd = CmdLine {
+            args: self.args(),
+            envs: self.envs.clone(),
+        };
+        let out = executor.exec(&cmd).await?;
+        exit_result(Some(out.code), out.output)
     }
 
     /// Inner implementation of [`Cmd::exec_checkerr`] (if `merge` is `false`)
@@ -246,17 +636,18 @@ impl Cmd {
     #[doc = docs_errors_exec!()]
     async fn exec_check_output(self, mute: bool, merge: bool) -> Result<Output> {
         use tokio_stream::StreamExt;
-        use Error::{CmdJoinError, CmdNoHandleError, CmdSpawnError, CmdWaitError};
+        use Error::{CmdNoHandleError, CmdWaitError};
 
         fn make_reader(
             src: Option<impl AsyncRead>,
             name: &str,
         ) -> Result<impl Stream<Item = io::Result<Bytes>>> {
-            src.map(into_bytes).ok_or_else(|| CmdNoHandleError {
+            src.map(into_lines).ok_or_else(|| CmdNoHandleError {
                 handle: name.into(),
             })
         }
 
+        let timeout = self.timeout;
         let mut child = self
             .build()
             .stderr(Stdio::piped())
@@ -265,9 +656,10 @@ impl Cmd {
                     cmd.stdout(Stdio::piped());
                 }
             })
-            .spawn()
-            .map_err(CmdSpawnError)?;
+            .pipe(spawn)?;
 
+        // The `stdout` and the `stderr` are merged line by line, so that their
+        // lines are not cut in the middle by each other.
         let stderr_reader = make_reader(child.stderr.take(), "stderr")?;
         let mut reader = if merge {
             let stdout_reader = make_reader(child.stdout.take(), "stdout")?;
@@ -282,14 +674,14 @@ impl Cmd {
             Either::Right(io::stderr())
         };
 
-        let code: JoinHandle<Result<Option<i32>>> = tokio::spawn(async move {
+        let res = with_timeout(timeout, async {
+            let limit = (!merge).then_some(STDERR_CAPTURED);
+            let output = exec_tee(&mut reader, (!mute).then_some(&mut out), limit).await?;
             let status = child.wait().await.map_err(CmdWaitError)?;
-            Ok(status.code())
-        });
-
-        let output = exec_tee(&mut reader, (!mute).then(|| &mut out)).await?;
-        let code = code.await.map_err(CmdJoinError)??;
-        exit_result(code, output)
+            exit_result(status.code(), output)
+        })
+        .await;
+        reap(&mut child, res).await
     }
 
     /// Executes a [`Cmd`] and returns its `stdout` and `stderr`.
@@ -357,11 +749,78 @@ impl Cmd {
     }
 }
 
+impl Cmd {
+    /// Executes a [`Cmd`] whose `stderr` carries a status stream, and
+    /// collects the rest of its `stderr`.
+    ///
+    /// See [`Mode::StatusFd`] for more info.
+    #[doc = docs_errors_exec!()]
+    async fn exec_status_fd(self) -> Result<Output> {
+        use Error::{CmdNoHandleError, CmdWaitError};
+
+        let mut report = status_fd::Report::new(self.to_string());
+        let timeout = self.timeout;
+        let mut child = self.build().stderr(Stdio::piped()).pipe(spawn)?;
+        let stderr = child.stderr.take().ok_or_else(|| CmdNoHandleError {
+            handle: "stderr".into(),
+        })?;
+
+        let mut lines = FramedRead::new(stderr, LinesCodec::new());
+        let res = with_timeout(timeout, async {
+            let mut output = Output::default();
+            while let Some(line) = lines.next().await {
+                let line = line.map_err(|e| match e {
+                    LinesCodecError::Io(e) => Error::IoError(e),
+                    e @ LinesCodecError::MaxLineLengthExceeded => Error::OtherError(e.to_string()),
+                })?;
+                if let Some(event) = status_fd::Event::parse(&line) {
+                    report.handle(event);
+                } else {
+                    eprintln!("{line}");
+                    output.extend(line.bytes().chain([b'\n']));
+                }
+            }
+
+            let status = child.wait().await.map_err(CmdWaitError)?;
+            report.finish();
+            exit_result(status.code(), output)
+        })
+        .await;
+        reap(&mut child, res).await
+    }
+}
+
 impl std::fmt::Display for Cmd {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let sudo: &str = self.should_sudo().then(|| "sudo -S ").unwrap_or("");
+        let sudo = if self.should_sudo() {
+            self.sudo_prefix()
+                .into_iter()
+                .map(|s| s.to_owned() + " ")
+                .collect()
+        } else {
+            String::new()
+        };
+        let host = self
+            .host
+            .as_deref()
+            .or_else(|| self.container.as_ref().map(|c| c.name.as_str()))
+            .map_or_else(String::new, |host| format!("{host}: "));
         let cmd = chain!(&self.cmd, &self.flags, &self.kws).join(" ");
-        write!(f, "{}{}", sudo, cmd)
+        write!(f, "{host}{sudo}{cmd}")
+    }
+}
+
+/// Keeps a record of the command `cmd` executed, which has returned `res`
+/// after `elapsed`, for the log file.
+#[allow(clippy::cast_possible_truncation)]
+fn record(cmd: &str, res: &Result<Output>, elapsed: Duration) {
+    let elapsed_ms = elapsed.as_millis() as u64;
+    match res {
+        Ok(_) => tracing::info!(target: EXEC_TARGET, cmd, code = 0, elapsed_ms, "Executed"),
+        Err(Error::CmdStatusCodeError { code, .. }) => {
+            tracing::info!(target: EXEC_TARGET, cmd, code, elapsed_ms, "Executed");
+        }
+        Err(e) => tracing::info!(target: EXEC_TARGET, cmd, error = %e, elapsed_ms, "Executed"),
     }
 }
 
@@ -372,7 +831,7 @@ impl std::fmt::Display for Cmd {
 /// patterns.
 #[must_use]
 #[allow(clippy::missing_panics_doc)]
-fn prompt<'a>(
+pub(crate) fn prompt<'a>(
     question: &str,
     options: &str,
     expected: &[&'a str],
@@ -410,29 +869,104 @@ macro_rules! docs_errors_grep {
     };
 }
 
-/// Finds all lines in the given `text` that matches all the `patterns`.
+/// A line matched by [`grep`], along with the byte ranges matched in it.
+type Matched<'t> = (&'t str, Vec<Range<usize>>);
+
+/// Finds all lines in the given `text` that matches all the `patterns`, along
+/// with the byte ranges matched in each of them, sorted and merged.
 ///
-/// We suppose that all patterns are legal regular expressions.
-/// An error message will be returned if this is not the case.
+/// The patterns are regular expressions matched case-insensitively, or
+/// literal strings matched as is if `exact`.
+/// An error message will be returned if a regular expression is ill-formed.
 #[doc = docs_errors_grep!()]
-fn grep<'t>(text: &'t str, patterns: &[&str]) -> Result<Vec<&'t str>> {
+fn grep<'t>(text: &'t str, patterns: &[&str], exact: bool) -> Result<Vec<Matched<'t>>> {
     let patterns: Vec<Regex> = patterns
         .iter()
-        .map(|pat| {
-            Regex::new(pat)
+        .map(|&pat| {
+            let escaped;
+            let pat = if exact {
+                escaped = regex::escape(pat);
+                &escaped
+            } else {
+                pat
+            };
+            RegexBuilder::new(pat)
+                .case_insensitive(!exact)
+                .build()
                 .map_err(|_e| Error::OtherError(format!("Pattern `{pat}` is ill-formed")))
         })
         .try_collect()?;
     Ok(text
         .lines()
         .filter(|line| patterns.iter().all(|pat| pat.is_match(line)))
+        .map(|line| {
+            let mut ranges = patterns
+                .iter()
+                .flat_map(|pat| pat.find_iter(line).map(|m| m.range()))
+                .filter(|range| !range.is_empty())
+                .sorted_unstable_by_key(|range| range.start)
+                .collect_vec();
+            ranges.dedup_by(|next, prev| {
+                let overlapped = next.start <= prev.end;
+                if overlapped {
+                    prev.end = prev.end.max(next.end);
+                }
+                overlapped
+            });
+            (line, ranges)
+        })
         .collect())
 }
 
-/// Prints the result of [`grep`] line by line.
+/// Highlights the byte `ranges` of `line`, if the colors are on.
+#[must_use]
+fn highlight(line: &str, ranges: &[Range<usize>]) -> String {
+    let mut highlighted = String::with_capacity(line.len());
+    let mut last = 0;
+    for range in ranges {
+        highlighted.push_str(&line[last..range.start]);
+        highlighted.push_str(&line[range.clone()].red().bold().to_string());
+        last = range.end;
+    }
+    highlighted.push_str(&line[last..]);
+    highlighted
+}
+
+/// Prints the result of [`grep`] line by line, with the matches highlighted.
 #[doc = docs_errors_grep!()]
-pub(crate) fn grep_print(text: &str, patterns: &[&str]) -> Result<()> {
-    grep(text, patterns).map(|lns| lns.iter().for_each(|ln| println!("{ln}")))
+pub(crate) fn grep_print(text: &str, patterns: &[&str], exact: bool) -> Result<()> {
+    for (ln, ranges) in grep(text, patterns, exact)? {
+        pager::print(&highlight(ln, &ranges));
+    }
+    Ok(())
+}
+
+/// Keeps the `lines` that also appear in `output`, ie. the results of a search
+/// for all the keywords when `lines` are the results for some of them and
+/// `output` for another one.
+///
+/// The blank lines are always kept, as they separate the entries in the
+/// output of some package managers, see [`squeeze_blanks`].
+pub(crate) fn intersect(lines: &mut Vec<String>, output: &str) {
+    let found = output.lines().collect::<HashSet<_>>();
+    lines.retain(|line| line.trim().is_empty() || found.contains(line.as_str()));
+}
+
+/// Squeezes the runs of blank lines in `lines` left by [`intersect`] into one,
+/// dropping those at the start and at the end.
+#[must_use]
+pub(crate) fn squeeze_blanks(lines: &[String]) -> Vec<&str> {
+    let mut res: Vec<&str> = vec![];
+    for line in lines {
+        let blank = line.trim().is_empty();
+        if !blank || res.last().is_some_and(|last| !last.trim().is_empty()) {
+            res.push(line);
+        }
+    }
+    if res.last().is_some_and(|last| last.trim().is_empty()) {
+        res.pop();
+    }
+    res
 }
 
 /// Checks if an executable exists by name (consult `$PATH`) or by path.
@@ -443,9 +977,188 @@ pub(crate) fn is_exe(name: &str, path: &str) -> bool {
     (!path.is_empty() && which(path).is_ok()) || (!name.is_empty() && which(name).is_ok())
 }
 
-/// Turns an [`AsyncRead`] into a [`Stream`].
-///
-/// _Shamelessly copied from [`StackOverflow`](https://stackoverflow.com/a/59327560)._
-fn into_bytes(reader: impl AsyncRead) -> impl Stream<Item = io::Result<Bytes>> {
-    FramedRead::new(reader, BytesCodec::new()).map_ok(BytesMut::freeze)
+/// The most bytes of a line yielded by [`LineCodec`] at once.
+const MAX_LINE: usize = 8 * 1024;
+
+/// A [`Decoder`] splitting the output of a [`Cmd`] into lines, each ending
+/// with `\n` or `\r`, so that the progress bars redrawn with `\r` are shown
+/// as soon as they are updated. The bytes are yielded as is, and the lines
+/// longer than [`MAX_LINE`] are yielded in several parts.
+#[derive(Copy, Clone, Debug, Default)]
+struct LineCodec;
+
+impl Decoder for LineCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Bytes>> {
+        let end = src
+            .iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+            .map(|i| i + 1)
+            .or_else(|| (src.len() >= MAX_LINE).then_some(MAX_LINE));
+        Ok(end.map(|end| src.split_to(end).freeze()))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Bytes>> {
+        match self.decode(src)? {
+            Some(line) => Ok(Some(line)),
+            None if src.is_empty() => Ok(None),
+            None => Ok(Some(src.split().freeze())),
+        }
+    }
+}
+
+/// Turns an [`AsyncRead`] into a [`Stream`] of lines, see [`LineCodec`].
+fn into_lines(reader: impl AsyncRead) -> impl Stream<Item = io::Result<Bytes>> {
+    FramedRead::new(reader, LineCodec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn timed_out() {
+        let err = Cmd::new(&["sleep", "5"])
+            .timeout(Some(Duration::from_millis(100)))
+            .exec(Mode::Mute)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::CmdTimeoutError { .. }));
+        assert_eq!(err.exit_code(), 124);
+
+        let out = Cmd::new(&["echo", "ok"])
+            .timeout(Some(Duration::from_secs(5)))
+            .exec(Mode::Mute)
+            .await
+            .unwrap();
+        assert_eq!(out, b"ok\n");
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn timed_out_tree() {
+        // The `sleep` run by the shell is terminated along with it.
+        let err = Cmd::new(&["sh", "-c", "sleep 7.25 & wait"])
+            .timeout(Some(Duration::from_millis(100)))
+            .exec(Mode::Mute)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::CmdTimeoutError { .. }));
+        let left = || {
+            std::process::Command::new("pgrep")
+                .args(["-fx", "sleep 7.25"])
+                .status()
+                .unwrap()
+                .success()
+        };
+        // The signal might take a moment to be handled.
+        for _ in 0..20 {
+            if !left() {
+                return;
+            }
+            time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("`sleep` is still running");
+    }
+
+    #[tokio::test]
+    async fn tee() {
+        let lines = |src: &'static [u8]| {
+            FramedRead::new(src, LineCodec)
+                .map_ok(|line| String::from_utf8_lossy(&line).into_owned())
+                .try_collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lines(b"Get:1 curl\n 50%\r100%\rdone").await.unwrap(),
+            ["Get:1 curl\n", " 50%\r", "100%\r", "done"]
+        );
+        assert_eq!(lines(&[b'a'; MAX_LINE + 1]).await.unwrap().len(), 2);
+
+        let src = || FramedRead::new(&b"a\nb\nc\n"[..], LineCodec);
+        let mut out = vec![];
+        let output = exec_tee(src(), Some(&mut out), None).await.unwrap();
+        assert_eq!((&*output, &*out), (&b"a\nb\nc\n"[..], &b"a\nb\nc\n"[..]));
+        let output = exec_tee(src(), None::<io::Sink>, Some(4)).await.unwrap();
+        assert_eq!(output, b"b\nc\n");
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn grepped() {
+        let text = "curl 8.5.0\nlibcurl4 8.5.0\nwget 1.21\n";
+        let lines = |patterns: &[&str], exact| grep(text, patterns, exact).unwrap();
+        assert_eq!(
+            lines(&["CURL", "8"], false),
+            [
+                ("curl 8.5.0", vec![0..4, 5..6]),
+                ("libcurl4 8.5.0", vec![3..7, 9..10])
+            ]
+        );
+        assert_eq!(lines(&["^c.rl"], false), [("curl 8.5.0", vec![0..4])]);
+        assert_eq!(lines(&["CURL"], true), []);
+        assert_eq!(lines(&["1.2"], true), [("wget 1.21", vec![5..8])]);
+        assert_eq!(lines(&["url", "rl"], false)[0].1, [1..4]);
+        assert!(grep(text, &["("], false).is_err());
+
+        assert_eq!(highlight("curl 8.5.0", &[]), "curl 8.5.0");
+        assert!(highlight("curl 8.5.0", &[0..4]).ends_with(" 8.5.0"));
+    }
+
+    #[test]
+    fn intersected() {
+        let lines = |out: &str| out.lines().map(String::from).collect_vec();
+        let curl = "curl 8.5.0\n  a tool\n\nlibcurl4 8.5.0\n  a library\n\npython3-pycurl 7.45\n";
+        let mut found = lines(curl);
+        intersect(
+            &mut found,
+            "libcurl4 8.5.0\n  a library\n\nlibssl3 3.0.13\n",
+        );
+        assert_eq!(squeeze_blanks(&found), ["libcurl4 8.5.0", "  a library"]);
+        let mut found = lines(curl);
+        intersect(&mut found, "curl 8.5.0\n  a tool\npython3-pycurl 7.45\n");
+        assert_eq!(
+            squeeze_blanks(&found),
+            ["curl 8.5.0", "  a tool", "", "python3-pycurl 7.45"]
+        );
+        let mut found = lines(curl);
+        intersect(&mut found, "wget 1.21\n");
+        assert!(squeeze_blanks(&found).is_empty());
+    }
+
+    #[test]
+    fn forced_sudo() {
+        let cmd = Cmd::with_sudo(&["apt", "install"]).kws(&["curl"]);
+        assert_eq!(
+            cmd.clone().force_sudo(Some(true)).to_string(),
+            "sudo -S apt install curl"
+        );
+        assert_eq!(
+            cmd.clone()
+                .sudo_with(Some("doas".into()))
+                .force_sudo(Some(true))
+                .to_string(),
+            "doas apt install curl"
+        );
+        assert_eq!(cmd.force_sudo(Some(false)).to_string(), "apt install curl");
+    }
+
+    #[test]
+    fn powershell() {
+        assert_eq!(powershell_quote("extras/vscode"), "'extras/vscode'");
+        assert_eq!(powershell_quote("it's"), "'it''s'");
+
+        let cmd = Cmd::with_powershell(&["scoop", "install"]).kws(&["it's"]);
+        assert_eq!(cmd.to_string(), "scoop install it's");
+        let exec = cmd.build();
+        let args = exec.as_std().get_args().collect_vec();
+        assert_eq!(
+            args.last().unwrap().to_str(),
+            Some("& 'scoop' 'install' 'it''s'; exit $LASTEXITCODE")
+        );
+        let exe = exec.as_std().get_program().to_string_lossy().into_owned();
+        assert!(exe == "pwsh" || exe == "powershell");
+    }
 }
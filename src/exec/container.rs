@@ -0,0 +1,137 @@
+//! Running the commands inside a running container with `docker exec` or
+//! `podman exec`, as asked with `--container`.
+
+use itertools::{chain, Itertools};
+
+use super::{is_exe, Cmd, Mode};
+use crate::error::{Error, Result};
+
+/// The container engines supported, in order of preference.
+const ENGINES: &[&str] = &["docker", "podman"];
+
+/// A running container.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Container {
+    /// The container engine managing it, eg. `docker`.
+    pub engine: String,
+    /// The name or the ID of the container.
+    pub name: String,
+}
+
+impl Container {
+    /// Finds the running container `name` with the first container engine
+    /// installed which knows it, as told by `<engine> container inspect`.
+    ///
+    /// # Errors
+    /// Returns an [`Error::OtherError`] if no container engine is found, or
+    /// if none of them runs the container `name`.
+    pub(crate) async fn find(name: &str) -> Result<Self> {
+        let engines = ENGINES
+            .iter()
+            .copied()
+            .filter(|engine| is_exe(engine, ""))
+            .collect_vec();
+        if engines.is_empty() {
+            return Err(Error::OtherError(
+                "Neither `docker` nor `podman` found".into(),
+            ));
+        }
+        for &engine in &engines {
+            let cmd = Cmd::new(&[
+                engine,
+                "container",
+                "inspect",
+                "--format",
+                "{{.State.Running}}",
+                name,
+            ]);
+            if let Ok(out) = cmd.exec(Mode::Mute).await {
+                if is_running(&out) {
+                    return Ok(Container {
+                        engine: engine.into(),
+                        name: name.into(),
+                    });
+                }
+                return Err(Error::OtherError(format!(
+                    "The container `{name}` is not running"
+                )));
+            }
+        }
+        Err(Error::OtherError(format!(
+            "No container `{name}` found with `{}`",
+            engines.iter().join("` or `")
+        )))
+    }
+
+    /// Wraps the command line `args`, the first one being the executable, so
+    /// that it runs in this container as `root`, eg.
+    /// `docker exec -i --user root devbox apt-get install curl`.
+    #[must_use]
+    pub(crate) fn wrap(&self, args: Vec<String>) -> Vec<String> {
+        chain!(
+            [
+                self.engine.as_str(),
+                "exec",
+                "-i",
+                "--user",
+                "root",
+                &self.name
+            ]
+            .map(String::from),
+            args
+        )
+        .collect()
+    }
+
+    /// Reads the `os-release` of this container.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] if the container is not running, or has no
+    /// `os-release`.
+    pub(crate) async fn os_release(&self) -> Result<String> {
+        let mut res = Err(Error::OtherError(format!(
+            "No `os-release` found in the container `{}`",
+            self.name
+        )));
+        for path in ["/etc/os-release", "/usr/lib/os-release"] {
+            let cmd = Cmd::new(&["cat", path]).container(Some(self.clone()));
+            match cmd.exec(Mode::Mute).await {
+                Ok(out) => return Ok(String::from_utf8_lossy(&out).into_owned()),
+                Err(e) => res = Err(e),
+            }
+        }
+        res
+    }
+}
+
+/// Checks if the output `out` of `<engine> container inspect --format
+/// {{.State.Running}}` says that the container is running.
+#[must_use]
+fn is_running(out: &[u8]) -> bool {
+    String::from_utf8_lossy(out).trim() == "true"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running() {
+        assert!(is_running(b"true\n"));
+        assert!(!is_running(b"false\n"));
+        assert!(!is_running(b""));
+    }
+
+    #[test]
+    fn wrapped() {
+        let container = Container {
+            engine: "podman".into(),
+            name: "devbox".into(),
+        };
+        let args = ["apk", "add", "curl"].map(String::from).to_vec();
+        assert_eq!(
+            container.wrap(args),
+            ["podman", "exec", "-i", "--user", "root", "devbox", "apk", "add", "curl"]
+        );
+    }
+}
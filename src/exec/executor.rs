@@ -0,0 +1,123 @@
+//! Running the commands of a [`Pm`](crate::pm::Pm) with a custom
+//! [`Executor`] instead of spawning them, eg. to capture their output or mock
+//! them when `pacaptr` is used as a library.
+
+use std::{cell::RefCell, fmt::Debug, future::Future, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::error::{Error, Result};
+
+/// A command line to be run by an [`Executor`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CmdLine {
+    /// The executable followed by its arguments, including the prefix running
+    /// it as `root` if needed, eg. `["sudo", "-S", "apt-get", "install",
+    /// "curl"]`.
+    pub args: Vec<String>,
+
+    /// The extra environment variables of the command, eg.
+    /// `PACAPTR_OP=Suy`.
+    pub envs: Vec<(String, String)>,
+}
+
+/// What a command run by an [`Executor`] has returned.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CmdOutput {
+    /// The exit code of the command.
+    pub code: i32,
+
+    /// The `stdout` and the `stderr` of the command combined, which some
+    /// operations parse, eg. [`PmBase::installed`](crate::pm::PmBase::installed).
+    pub output: Vec<u8>,
+}
+
+impl CmdOutput {
+    /// Makes the [`CmdOutput`] of a command which has exited with `code`,
+    /// having printed `output`.
+    #[must_use]
+    pub fn new(code: i32, output: impl Into<Vec<u8>>) -> Self {
+        CmdOutput {
+            code,
+            output: output.into(),
+        }
+    }
+}
+
+/// A command run by a [`Pm`](crate::pm::Pm), as returned by
+/// [`Pm::install`](crate::pm::Pm::install) and the like.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CmdRun {
+    /// The command line run.
+    pub cmd: CmdLine,
+
+    /// What the command has returned. Without an [`Executor`], the output
+    /// only holds the `stderr` of the commands which are not run silently,
+    /// as their `stdout` goes straight to the terminal.
+    pub output: CmdOutput,
+}
+
+tokio::task_local! {
+    /// The commands run so far in the current [`capture`].
+    static CAPTURED: RefCell<Vec<CmdRun>>;
+}
+
+/// Runs `fut`, returning the commands it has run along with their output, see
+/// [`captured`].
+///
+/// # Errors
+/// Returns any error returned by `fut`.
+pub(crate) async fn capture(fut: impl Future<Output = Result<()>> + Send) -> Result<Vec<CmdRun>> {
+    CAPTURED
+        .scope(RefCell::default(), async {
+            fut.await?;
+            Ok(CAPTURED.with(RefCell::take))
+        })
+        .await
+}
+
+/// Checks if a [`capture`] is ongoing in the current task.
+#[must_use]
+pub(crate) fn is_capturing() -> bool {
+    CAPTURED.try_with(|_| ()).is_ok()
+}
+
+/// Adds the command line `cmd` which has returned `res` to the ongoing
+/// [`capture`], if any. The commands which could not be run at all are left
+/// out, as their error is returned anyway.
+pub(crate) fn captured(cmd: CmdLine, res: &Result<Vec<u8>>) {
+    let output = match res {
+        Ok(output) => CmdOutput {
+            code: 0,
+            output: output.clone(),
+        },
+        Err(Error::CmdStatusCodeError { code, output }) => CmdOutput {
+            code: *code,
+            output: output.clone(),
+        },
+        Err(_) => return,
+    };
+    _ = CAPTURED.try_with(|runs| runs.borrow_mut().push(CmdRun { cmd, output }));
+}
+
+/// Runs the commands of a [`Pm`](crate::pm::Pm) in place of `pacaptr`, which
+/// then prints neither the commands nor their output, and asks for no
+/// confirmation.
+#[async_trait]
+pub trait Executor: Debug + Send + Sync {
+    /// Runs the command line `cmd`.
+    ///
+    /// # Errors
+    /// Returns an [`Error`](crate::error::Error) if `cmd` cannot be run at
+    /// all. A command exiting with a non-zero code should return its
+    /// [`CmdOutput`] instead.
+    async fn exec(&self, cmd: &CmdLine) -> Result<CmdOutput>;
+}
+
+/// An [`Executor`] shared by the [`Config`](crate::dispatch::Config) and the
+/// commands built from it.
+#[derive(Clone, Debug)]
+pub(crate) struct Shared(pub Arc<dyn Executor>);
@@ -0,0 +1,149 @@
+//! Running the package managers on the other side of WSL, ie. the Windows
+//! ones, eg. `choco.exe`, from a WSL distro through the interop layer, and the
+//! Linux ones, eg. `apt`, in the default WSL distro from Windows with
+//! `--using wsl:apt`.
+
+use std::{
+    env,
+    path::{self, Path},
+};
+
+use itertools::chain;
+
+/// Where a [`Cmd`](super::Cmd) runs, as seen from `pacaptr`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Interop {
+    /// On the same system as `pacaptr`.
+    #[default]
+    Native,
+    /// On Windows, from the WSL distro `pacaptr` runs in.
+    Windows,
+    /// In the default WSL distro, from Windows.
+    Wsl,
+}
+
+/// Checks if `pacaptr` runs inside a WSL distro.
+#[must_use]
+pub(crate) fn is_wsl() -> bool {
+    cfg!(target_os = "linux")
+        && (env::var_os("WSL_DISTRO_NAME").is_some()
+            || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists())
+}
+
+impl Interop {
+    /// Translates the paths of the existing files among the arguments `args`,
+    /// eg. the package files given to `-U`, for the other side.
+    #[must_use]
+    pub(crate) fn paths(self, args: impl IntoIterator<Item = String>) -> Vec<String> {
+        let args = args.into_iter();
+        if self != Self::Windows {
+            return args.collect();
+        }
+        let distro = env::var("WSL_DISTRO_NAME").unwrap_or_default();
+        args.map(|arg| match absolute_path(&arg) {
+            Some(path) => windows_path(&path, &distro),
+            None => arg,
+        })
+        .collect()
+    }
+
+    /// Wraps the command line `args`, the first one being the executable, so
+    /// that it runs on the other side.
+    #[must_use]
+    pub(crate) fn wrap(self, mut args: Vec<String>) -> Vec<String> {
+        match self {
+            Self::Native => args,
+            Self::Windows => {
+                if let Some(exe) = args.first_mut() {
+                    *exe = windows_exe(exe);
+                }
+                args
+            }
+            Self::Wsl => chain!(["wsl.exe".into(), "--exec".into()], args).collect(),
+        }
+    }
+}
+
+/// Gets the name of the Windows executable `exe` as seen from WSL, eg.
+/// `choco.exe`. PowerShell is always run as `powershell.exe`, since `pwsh`
+/// might only be installed on the Linux side.
+#[must_use]
+fn windows_exe(exe: &str) -> String {
+    match exe {
+        "pwsh" | "powershell" => "powershell.exe".into(),
+        exe if Path::new(exe)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("exe")) =>
+        {
+            exe.into()
+        }
+        exe => format!("{exe}.exe"),
+    }
+}
+
+/// Gets the absolute path of `arg` if it's the path of an existing file.
+#[must_use]
+fn absolute_path(arg: &str) -> Option<String> {
+    if !arg.contains('/') || !Path::new(arg).exists() {
+        return None;
+    }
+    path::absolute(arg).ok()?.to_str().map(Into::into)
+}
+
+/// Translates the absolute `path` in the WSL distro `distro` to a Windows
+/// path, like `wslpath -w`: the Windows drives are mounted under `/mnt`, and
+/// the rest of the distro is shared under `\\wsl.localhost`.
+#[must_use]
+fn windows_path(path: &str, distro: &str) -> String {
+    if let Some((drive, rest)) = path
+        .strip_prefix("/mnt/")
+        .map(|rest| rest.split_once('/').unwrap_or((rest, "")))
+    {
+        if drive.len() == 1 && drive.chars().all(|c| c.is_ascii_alphabetic()) {
+            let drive = drive.to_ascii_uppercase();
+            return format!("{drive}:\\{}", rest.replace('/', "\\"));
+        }
+    }
+    format!("\\\\wsl.localhost\\{distro}{}", path.replace('/', "\\"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tap::prelude::*;
+
+    use super::*;
+    use crate::{dispatch::Config, pm::Pm};
+
+    #[test]
+    fn translated() {
+        assert_eq!(
+            windows_path("/mnt/c/Users/me/curl.nupkg", "Ubuntu"),
+            r"C:\Users\me\curl.nupkg"
+        );
+        assert_eq!(
+            windows_path("/home/me/curl.nupkg", "Ubuntu"),
+            r"\\wsl.localhost\Ubuntu\home\me\curl.nupkg"
+        );
+        assert_eq!(windows_exe("choco"), "choco.exe");
+        assert_eq!(windows_exe("pwsh"), "powershell.exe");
+
+        let args = || vec!["choco".to_owned(), "install".to_owned()];
+        assert_eq!(Interop::Windows.wrap(args()), ["choco.exe", "install"]);
+        assert_eq!(
+            Interop::Wsl.wrap(args()),
+            ["wsl.exe", "--exec", "choco", "install"]
+        );
+        assert_eq!(Interop::Windows.paths(args()), args());
+    }
+
+    #[test]
+    fn wsl_pm() {
+        let pm = Config {
+            default_pm: Some("wsl:apt".into()),
+            ..Config::default()
+        }
+        .conv::<Box<dyn Pm>>();
+        assert_eq!(pm.name(), "apt");
+        assert_eq!(pm.cfg().interop, Interop::Wsl);
+    }
+}
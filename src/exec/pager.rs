@@ -0,0 +1,126 @@
+//! Paging the long outputs of the queries, eg. `-Ss`, through `$PAGER` (or
+//! `less` if unset) like `git` does, but only when `stdout` is a terminal.
+//!
+//! While a [`Pager`] is running, the `stdout` of every [`Cmd`](super::Cmd) is
+//! redirected to it.
+
+#[cfg(unix)]
+use std::os::fd::{AsFd, OwnedFd as Pipe};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, OwnedHandle as Pipe};
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::Mutex,
+};
+
+use crate::{dispatch::Config, exec::is_exe};
+
+/// The operations whose output is paged.
+static PAGED: &[&str] = &["Qi", "Ql", "Ss"];
+
+/// The input of the [`Pager`] running, if any.
+static INPUT: Mutex<Option<ChildStdin>> = Mutex::new(None);
+
+/// A pager running. It's waited for when dropped, ie. until the user quits it.
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct Pager(Child);
+
+impl Pager {
+    /// Starts a pager for the output of `-{options}` run with `cfg`, if it's
+    /// to be paged.
+    pub(crate) fn start(options: &str, cfg: &Config) -> Option<Self> {
+        if !PAGED.contains(&options) || cfg.dry_run {
+            return None;
+        }
+        Self::spawn(cfg.no_pager)
+    }
+
+    /// Starts a pager, unless `no_pager` or `stdout` is not a terminal.
+    pub(crate) fn spawn(no_pager: bool) -> Option<Self> {
+        if no_pager || !io::stdout().is_terminal() {
+            return None;
+        }
+        let pager = command()?;
+        let (exe, args) = pager.split_first()?;
+        let mut cmd = Command::new(exe);
+        cmd.args(args).stdin(Stdio::piped());
+        if env::var_os("LESS").is_none() {
+            // Quit if the output fits on one screen, keep the colors and
+            // don't clear the screen, as `git` does.
+            cmd.env("LESS", "FRX");
+        }
+        let mut child = cmd.spawn().ok()?;
+        *INPUT.lock().ok()? = child.stdin.take();
+        Some(Pager(child))
+    }
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        // The pager only quits at the end of its input.
+        if let Ok(mut input) = INPUT.lock() {
+            input.take();
+        }
+        _ = self.0.wait();
+    }
+}
+
+/// Gets the pager to run, ie. `$PAGER` split into words, or `less` if unset
+/// and found. Returns `None` if `$PAGER` is empty or `cat`.
+#[must_use]
+fn command() -> Option<Vec<String>> {
+    match env::var("PAGER") {
+        Ok(pager) => {
+            let pager = pager
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            (!pager.is_empty() && pager != ["cat"]).then_some(pager)
+        }
+        Err(_) => is_exe("less", "").then(|| vec!["less".into()]),
+    }
+}
+
+/// Gets the `stdout` of a [`Cmd`](super::Cmd), ie. the input of the
+/// [`Pager`] running, or the `stdout` of `pacaptr` otherwise.
+#[must_use]
+pub(crate) fn stdout() -> Stdio {
+    let input = INPUT.lock().ok().and_then(|input| {
+        let input = input.as_ref()?;
+        #[cfg(unix)]
+        let pipe = input.as_fd().try_clone_to_owned();
+        #[cfg(windows)]
+        let pipe = input.as_handle().try_clone_to_owned();
+        pipe.ok()
+    });
+    input.map_or_else(Stdio::inherit, |pipe: Pipe| pipe.into())
+}
+
+/// Prints `text` to the [`Pager`] running, or to `stdout` otherwise.
+pub(crate) fn print(text: &str) {
+    if let Ok(mut input) = INPUT.lock() {
+        if let Some(input) = input.as_mut() {
+            // The pager might have been quit already.
+            _ = writeln!(input, "{text}");
+            return;
+        }
+    }
+    println!("{text}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped() {
+        let cfg = Config::default();
+        // The output of the tests is not a terminal.
+        assert!(Pager::start("Ss", &cfg).is_none());
+        assert!(Pager::start("Suy", &cfg).is_none());
+        assert!(Pager::spawn(true).is_none());
+    }
+}
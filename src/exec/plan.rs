@@ -0,0 +1,108 @@
+//! Plans of compound operations, eg. `-Suy` as refreshing the package
+//! databases and then upgrading the packages.
+//!
+//! The steps of a [`Plan`] are known up front, so that the progress of each
+//! one can be aggregated into the overall progress of the operation.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    exec::record,
+    print::{self, PROMPT_INFO},
+};
+
+/// The steps of `-Suy` when it's run as `-Sy` followed by `-Su`.
+pub(crate) static STEPS_SUY: &[&str] =
+    &["Refreshing the package databases", "Upgrading the packages"];
+
+/// The step being run, if any.
+static CURRENT: Mutex<Option<Step>> = Mutex::new(None);
+
+/// A step of a [`Plan`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Step {
+    /// The position of this step, starting from `1`.
+    pub index: u32,
+    /// The number of steps in the [`Plan`].
+    pub total: u32,
+    /// What this step does, eg. `Refreshing the package databases`.
+    pub label: String,
+}
+
+impl Step {
+    /// Gets the overall progress of the [`Plan`] in percent, given the
+    /// progress of this step in percent.
+    #[must_use]
+    pub(crate) fn overall(&self, percent: f32) -> f32 {
+        #[allow(clippy::cast_precision_loss)]
+        let (done, total) = (self.index.saturating_sub(1) as f32, self.total as f32);
+        (done + percent.clamp(0.0, 100.0) / 100.0) / total.max(1.0) * 100.0
+    }
+}
+
+/// The steps of a compound operation. The current step is cleared when the
+/// [`Plan`] is dropped.
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct Plan {
+    labels: Vec<String>,
+}
+
+impl Plan {
+    /// Makes a new [`Plan`] with the given step labels.
+    pub(crate) fn new(labels: &[impl AsRef<str>]) -> Self {
+        Plan {
+            labels: labels.iter().map(|s| s.as_ref().into()).collect(),
+        }
+    }
+
+    /// Marks the step at `index` (starting from `0`) as the current one, and
+    /// prints its label, eg. `[1/2] Refreshing the package databases`.
+    pub(crate) fn step(&self, index: usize) {
+        let step = Step {
+            index: u32::try_from(index + 1).unwrap_or(u32::MAX),
+            total: u32::try_from(self.labels.len()).unwrap_or(u32::MAX),
+            label: self.labels[index].clone(),
+        };
+        if !record::is_active() {
+            print::print_msg(
+                &format!("[{}/{}] {}", step.index, step.total, step.label),
+                PROMPT_INFO,
+            );
+        }
+        *CURRENT.lock().unwrap() = Some(step);
+    }
+}
+
+impl Drop for Plan {
+    fn drop(&mut self) {
+        if let Ok(mut curr) = CURRENT.lock() {
+            *curr = None;
+        }
+    }
+}
+
+/// Gets the current [`Step`], if a [`Plan`] is being run.
+#[must_use]
+pub(crate) fn current() -> Option<Step> {
+    CURRENT.lock().ok()?.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overall() {
+        let step = |index| Step {
+            index,
+            total: 4,
+            label: "Refreshing flatpak".into(),
+        };
+        assert!((step(1).overall(0.0) - 0.0).abs() < f32::EPSILON);
+        assert!((step(2).overall(50.0) - 37.5).abs() < f32::EPSILON);
+        assert!((step(4).overall(120.0) - 100.0).abs() < f32::EPSILON);
+    }
+}
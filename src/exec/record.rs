@@ -0,0 +1,67 @@
+//! Recording the commands which an operation would run, instead of printing
+//! them as in a dry run, eg. to show a summary of them beforehand.
+
+use std::sync::Mutex;
+
+/// The commands recorded, if a [`Recording`] is ongoing.
+static RECORDED: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// An ongoing recording of the commands run in a dry run. The recording is
+/// stopped when it's dropped.
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct Recording(());
+
+impl Recording {
+    /// Starts recording the commands run in a dry run.
+    pub(crate) fn start() -> Self {
+        *RECORDED.lock().unwrap() = Some(vec![]);
+        Recording(())
+    }
+
+    /// Stops the recording, returning the commands recorded.
+    #[must_use]
+    pub(crate) fn finish(self) -> Vec<String> {
+        let cmds = RECORDED.lock().unwrap().take().unwrap_or_default();
+        drop(self);
+        cmds
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if let Ok(mut recorded) = RECORDED.lock() {
+            *recorded = None;
+        }
+    }
+}
+
+/// Checks if a [`Recording`] is ongoing.
+#[must_use]
+pub(crate) fn is_active() -> bool {
+    RECORDED.lock().is_ok_and(|recorded| recorded.is_some())
+}
+
+/// Records `cmd` if a [`Recording`] is ongoing. Returns `false` otherwise.
+pub(crate) fn push(cmd: String) -> bool {
+    let Ok(mut recorded) = RECORDED.lock() else {
+        return false;
+    };
+    recorded.as_mut().map(|cmds| cmds.push(cmd)).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording() {
+        assert!(!push("apt update".into()));
+        let recording = Recording::start();
+        assert!(is_active());
+        assert!(push("apt update".into()));
+        assert!(push("apt upgrade".into()));
+        assert_eq!(recording.finish(), ["apt update", "apt upgrade"]);
+        assert!(!is_active());
+    }
+}
@@ -0,0 +1,44 @@
+//! Running the commands on a remote host over SSH, as asked with `--host`.
+//!
+//! The commands are passed to `ssh` as a single line, which is run by the
+//! login shell of the remote user, so each argument is quoted for a POSIX
+//! shell.
+
+use itertools::{chain, Itertools};
+
+/// Quotes `arg` for a POSIX shell, unless it's made of safe characters only.
+#[must_use]
+fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:@,+%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.into();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Gets the arguments running the command line `args` on `host` with `ssh`,
+/// eg. `ssh admin@web1 -- apt-get install curl`.
+#[must_use]
+pub(crate) fn ssh_args(host: &str, args: &[String]) -> Vec<String> {
+    let line = args.iter().map(|arg| shell_quote(arg)).join(" ");
+    chain!(["ssh", host, "--"].map(String::from), [line]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted() {
+        assert_eq!(shell_quote("--assume-yes"), "--assume-yes");
+        assert_eq!(shell_quote("curl>=8"), "'curl>=8'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+
+        let args = ["apt-get", "install", "fish shell"].map(String::from);
+        assert_eq!(
+            ssh_args("admin@web1", &args),
+            ["ssh", "admin@web1", "--", "apt-get install 'fish shell'"]
+        );
+    }
+}
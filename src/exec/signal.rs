@@ -0,0 +1,73 @@
+//! Handling of `SIGINT` (Ctrl-C) and `SIGTERM`, so that the package manager
+//! being run gets the chance to exit cleanly, eg. releasing the `dpkg` lock,
+//! before `pacaptr` does.
+
+use std::{
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use tokio::task::JoinHandle;
+
+use super::signal_child;
+use crate::print;
+
+/// The exit code of a process interrupted by `SIGINT`.
+pub(crate) const EXIT_INTERRUPTED: i32 = 130;
+
+/// Set once `pacaptr` has been interrupted.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Checks if `pacaptr` has been interrupted.
+#[must_use]
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Handles the signal `sig`, eg. `INT`.
+///
+/// The subprocess being run, if any, is left to exit by itself after
+/// receiving the signal, which is forwarded to it unless it's Ctrl-C in a
+/// terminal, as the terminal sends it to the subprocess as well. Otherwise,
+/// or if `pacaptr` has already been interrupted, it exits right away.
+fn handle(sig: &str) {
+    let again = INTERRUPTED.swap(true, Ordering::SeqCst);
+    let forwarded = if sig == "INT" && io::stdin().is_terminal() {
+        super::CHILD_ID.lock().is_ok_and(|id| id.is_some())
+    } else {
+        signal_child(sig)
+    };
+    if again || !forwarded {
+        print::clear_progress();
+        eprintln!();
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+}
+
+/// Starts handling `SIGINT` and `SIGTERM` (only Ctrl-C on Windows) in the
+/// background, see [`handle`].
+pub(crate) fn forward() -> JoinHandle<()> {
+    tokio::spawn(async {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let (Ok(mut int), Ok(mut term)) = (
+                signal(SignalKind::interrupt()),
+                signal(SignalKind::terminate()),
+            ) else {
+                return;
+            };
+            loop {
+                tokio::select! {
+                    _ = int.recv() => handle("INT"),
+                    _ = term.recv() => handle("TERM"),
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        while tokio::signal::ctrl_c().await.is_ok() {
+            handle("INT");
+        }
+    })
+}
@@ -0,0 +1,74 @@
+//! A spinner shown on `stderr` while a muted command is running, eg. a query
+//! whose output is captured for processing, so that it doesn't look frozen.
+//!
+//! The spinner is left out when `stderr` is not a terminal and in the
+//! accessible mode, where control characters are not welcome.
+
+use std::{
+    io::{self, IsTerminal},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{task::JoinHandle, time};
+
+use crate::print::{self, PROMPT_RUN};
+
+/// The frames of the spinner.
+static FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// How long to wait before showing the spinner, so that it doesn't flicker
+/// for the commands finishing right away.
+const DELAY: Duration = Duration::from_millis(500);
+
+/// The interval between two frames of the spinner.
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// Checks if the spinner should be shown.
+#[must_use]
+fn is_enabled() -> bool {
+    io::stderr().is_terminal() && !print::is_accessible()
+}
+
+/// A spinner for a running command, which is cleared when dropped.
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct Spinner {
+    handle: Option<JoinHandle<()>>,
+    shown: Arc<AtomicBool>,
+}
+
+impl Spinner {
+    /// Starts spinning next to the name of the command `cmd`, eg.
+    /// `brew search`, if enabled.
+    pub(crate) fn start(cmd: &str) -> Self {
+        let shown = Arc::new(AtomicBool::new(false));
+        let handle = is_enabled().then(|| {
+            let (cmd, shown) = (cmd.to_owned(), Arc::clone(&shown));
+            tokio::spawn(async move {
+                time::sleep(DELAY).await;
+                shown.store(true, Ordering::Relaxed);
+                let mut interval = time::interval(INTERVAL);
+                for frame in FRAMES.iter().cycle() {
+                    interval.tick().await;
+                    print::print_progress(&format!("`{cmd}` {frame}"), PROMPT_RUN);
+                }
+            })
+        });
+        Spinner { handle, shown }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        if let Some(handle) = &self.handle {
+            handle.abort();
+        }
+        if self.shown.load(Ordering::Relaxed) {
+            print::clear_progress();
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Mapping the exit codes of the package managers to the ones of `pacman`, so
+//! that the scripts wrapping `pacaptr` can tell the kinds of failures apart
+//! whatever the package manager.
+//!
+//! The raw codes are passed through instead if `raw_exit_codes` is set.
+
+use super::StatusCode;
+use crate::error::Error;
+
+/// The code `pacman` exits with when a transaction fails, which is also used
+/// for any other failure.
+pub(crate) const FAILED: StatusCode = 1;
+
+/// The code for when a target is not found, the same as `EX_NOINPUT` in
+/// `sysexits.h`, which unlike `127` cannot be mistaken for a command not
+/// found by the shell.
+pub(crate) const NOT_FOUND: StatusCode = 66;
+
+/// The codes `pm` exits with when a target is not found.
+#[must_use]
+fn not_found_codes(pm: &str) -> &'static [StatusCode] {
+    match pm {
+        "xbps" => &[2],
+        // `ZYPPER_EXIT_INF_CAP_NOT_FOUND`.
+        "zypper" => &[104],
+        _ => &[],
+    }
+}
+
+/// The lowercase patterns showing up in the error output when a target is not
+/// found.
+static NOT_FOUND_PATTERNS: &[&str] = &[
+    // `pacman`.
+    "target not found",
+    // `apt`.
+    "unable to locate package",
+    "has no installation candidate",
+    "no packages found",
+    // `dnf`.
+    "no match for argument",
+    // `zypper`.
+    "not found in package names",
+    // `apk`.
+    "no such package",
+    // `brew`.
+    "no available formula",
+    "no formulae or casks found",
+    // `conda`.
+    "packagesnotfounderror",
+    // `pip`.
+    "no matching distribution found",
+    // `scoop`.
+    "couldn't find manifest",
+    // `port` and others.
+    "package not found",
+    // `choco`.
+    "the package was not found",
+];
+
+/// Maps the `code` which `pm` has exited with, along with its error `output`,
+/// to the one `pacman` would exit with.
+#[must_use]
+pub(crate) fn map(pm: &str, code: StatusCode, output: &[u8]) -> StatusCode {
+    let output = String::from_utf8_lossy(output).to_lowercase();
+    if not_found_codes(pm).contains(&code)
+        || NOT_FOUND_PATTERNS.iter().any(|pat| output.contains(pat))
+    {
+        NOT_FOUND
+    } else {
+        FAILED
+    }
+}
+
+/// Maps the exit code carried by `err`, if any, returned by `pm`. See
+/// [`map`].
+#[must_use]
+pub(crate) fn map_err(pm: &str, err: Error) -> Error {
+    match err {
+        Error::CmdStatusCodeError { code, output } => Error::CmdMappedStatusCodeError {
+            code: map(pm, code, &output),
+            raw: code,
+        },
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped() {
+        assert_eq!(
+            map("apt", 100, b"E: Unable to locate package foo\n"),
+            NOT_FOUND
+        );
+        assert_eq!(map("xbps", 2, b""), NOT_FOUND);
+        assert_eq!(map("apt", 2, b""), FAILED);
+        assert_eq!(
+            map(
+                "choco",
+                1,
+                b"foo not installed. The package was not found with the source(s) listed.\n"
+            ),
+            NOT_FOUND
+        );
+        assert_eq!(
+            map("brew", 1, b"Error: The file /tmp/foo was not found\n"),
+            FAILED
+        );
+        assert_eq!(
+            map(
+                "dnf",
+                1,
+                b"Error: Transaction test error:\n  file conflicts\n"
+            ),
+            FAILED
+        );
+        let err = map_err(
+            "zypper",
+            Error::CmdStatusCodeError {
+                code: 104,
+                output: vec![],
+            },
+        );
+        assert_eq!(err.exit_code(), NOT_FOUND);
+        assert_eq!(err.to_string(), "Subprocess exited with code 104");
+    }
+}
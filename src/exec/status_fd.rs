@@ -0,0 +1,381 @@
+//! Parsing and reporting of the machine-readable status stream emitted by
+//! `apt`/`dpkg` when `-o APT::Status-Fd=N` is set.
+//!
+//! See `apt-get(8)` and the `apt` source (`apt-pkg/deb/dpkgpm.cc`) for the
+//! format: each line looks like `pmstatus:<pkg>:<percent>:<message>`.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::plan::{self, Step};
+use crate::print::{self, PROMPT_ERROR, PROMPT_INFO, PROMPT_PROGRESS};
+
+/// The kind of a status line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum EventKind {
+    /// `pmstatus`: progress of the package manager itself.
+    Progress,
+    /// `dlstatus`: progress of a download.
+    Download,
+    /// `pmerror`: an error reported for a package.
+    Error,
+    /// `pmconffile`: a configuration file prompt.
+    ConfFile,
+    /// `media-change`: the user is asked to change the media.
+    MediaChange,
+}
+
+/// A single structured event parsed from the status stream.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Event {
+    pub kind: EventKind,
+    /// The package (or file, for [`EventKind::ConfFile`]) this event refers
+    /// to.
+    pub pkg: String,
+    /// The overall progress in percent.
+    pub percent: f32,
+    pub msg: String,
+}
+
+impl Event {
+    /// Parses a line of the status stream, returning `None` if the line is
+    /// not a status line (eg. a normal `stderr` message).
+    #[must_use]
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        // `\d` would panic, as `regex` is built without `unicode-perl`.
+        static RE: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(
+                r"^(pmstatus|dlstatus|pmerror|pmconffile|media-change):(.*?):([0-9]+(?:\.[0-9]+)?):(.*)$",
+            )
+            .unwrap()
+        });
+        let caps = RE.captures(line.trim_end())?;
+        let kind = match &caps[1] {
+            "pmstatus" => EventKind::Progress,
+            "dlstatus" => EventKind::Download,
+            "pmerror" => EventKind::Error,
+            "pmconffile" => EventKind::ConfFile,
+            _ => EventKind::MediaChange,
+        };
+        Some(Event {
+            kind,
+            pkg: caps[2].into(),
+            percent: caps[3].parse().ok()?,
+            msg: caps[4].into(),
+        })
+    }
+}
+
+/// The state of an ongoing operation at some point, which is rendered as a
+/// live progress line, and published for `pacaptr top` to display.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    /// The command being run.
+    pub cmd: String,
+    /// The step of the compound operation being run, if any.
+    #[serde(default)]
+    pub step: Option<Step>,
+    /// The position of the current item among all of them, eg. `(12, 87)`.
+    pub position: Option<(u32, u32)>,
+    /// The progress of the current phase (download or installation) in
+    /// percent.
+    pub percent: f32,
+    pub msg: String,
+    /// The download speed, if reported by the package manager.
+    pub speed: Option<String>,
+    /// The estimated remaining time of the current phase.
+    pub eta: Option<Duration>,
+}
+
+impl Snapshot {
+    /// The path to which the [`Snapshot`] of the ongoing operation is
+    /// published.
+    #[must_use]
+    pub(crate) fn path() -> Option<PathBuf> {
+        let dir = dirs_next::runtime_dir().or_else(dirs_next::cache_dir)?;
+        Some(dir.join(clap::crate_name!()).join("progress.json"))
+    }
+
+    /// Renders the [`Snapshot`] as a single line, eg.
+    /// `[12/87] [ 45%] Retrieving file 12 of 87 | 1.2 MB/s | ETA 1m20s`.
+    /// In a compound operation, the line starts with the step and the
+    /// overall progress, eg. `[2/2] Upgrading the packages (61%) | `.
+    #[must_use]
+    pub(crate) fn render(&self) -> String {
+        let mut line = String::new();
+        if let Some(step) = &self.step {
+            write!(
+                line,
+                "[{}/{}] {} ({:.0}%) | ",
+                step.index,
+                step.total,
+                step.label,
+                step.overall(self.percent)
+            )
+            .ok();
+        }
+        if let Some((pos, total)) = self.position {
+            write!(line, "[{pos}/{total}] ").ok();
+        }
+        write!(line, "[{:>3.0}%] {}", self.percent, self.msg).ok();
+        if let Some(speed) = &self.speed {
+            write!(line, " | {speed}").ok();
+        }
+        if let Some(eta) = self.eta {
+            let secs = eta.as_secs();
+            match secs / 60 {
+                0 => write!(line, " | ETA {secs}s"),
+                mins => write!(line, " | ETA {mins}m{:02}s", secs % 60),
+            }
+            .ok();
+        }
+        line
+    }
+}
+
+/// Collects [`Event`]s of a running command, renders the progress and
+/// summarizes the result when the command finishes.
+#[derive(Debug, Default)]
+pub(crate) struct Report {
+    /// Packages seen in [`EventKind::Progress`] events, in order.
+    pkgs: Vec<String>,
+    /// Errors reported by the package manager.
+    errors: Vec<(String, String)>,
+    /// If a progress line is currently displayed and should be overwritten.
+    dirty: bool,
+    /// The latest state of the operation.
+    snapshot: Snapshot,
+    /// The kind and start time of the current phase, used to estimate its
+    /// remaining time.
+    phase: Option<(EventKind, Instant)>,
+    /// If the [`Snapshot`] should be published.
+    publish: bool,
+}
+
+impl Report {
+    /// Creates a [`Report`] for `cmd`, publishing its [`Snapshot`]s for
+    /// `pacaptr top`.
+    pub(crate) fn new(cmd: String) -> Self {
+        Report {
+            snapshot: Snapshot {
+                cmd,
+                ..Snapshot::default()
+            },
+            publish: true,
+            ..Report::default()
+        }
+    }
+
+    /// Handles a new [`Event`].
+    pub(crate) fn handle(&mut self, event: Event) {
+        match event.kind {
+            EventKind::Progress | EventKind::Download => {
+                // `dpkg-exec` is a pseudo package used by `apt` itself.
+                if event.kind == EventKind::Progress
+                    && event.pkg != "dpkg-exec"
+                    && !self.pkgs.contains(&event.pkg)
+                {
+                    self.pkgs.push(event.pkg.clone());
+                }
+                self.update_snapshot(&event);
+                self.show_progress(&self.snapshot.render());
+                self.publish_snapshot();
+            }
+            EventKind::Error => {
+                self.clear();
+                print::print_err(format_args!("{}: {}", event.pkg, event.msg), PROMPT_ERROR);
+                self.errors.push((event.pkg, event.msg));
+            }
+            EventKind::ConfFile | EventKind::MediaChange => {
+                self.clear();
+                print::print_msg(&format!("{}: {}", event.pkg, event.msg), PROMPT_INFO);
+            }
+        }
+    }
+
+    /// Updates the [`Snapshot`] with a progress [`Event`].
+    fn update_snapshot(&mut self, event: &Event) {
+        static RE_POSITION: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"([0-9]+) of ([0-9]+)").unwrap());
+        static RE_SPEED: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"[0-9][0-9.,]* ?[kMG]?B/s").unwrap());
+
+        // The percentage restarts from zero when switching phases.
+        if !matches!(self.phase, Some((kind, _)) if kind == event.kind) {
+            self.phase = Some((event.kind, Instant::now()));
+        }
+        let elapsed = self
+            .phase
+            .map_or(Duration::ZERO, |(_, started)| started.elapsed());
+
+        let snapshot = &mut self.snapshot;
+        snapshot.step = plan::current();
+        snapshot.percent = event.percent;
+        // Download messages end with details like `(4s remaining)`, which are
+        // rendered from the other fields instead.
+        snapshot.msg = match event.kind {
+            EventKind::Download => event.msg.split(" (").next().unwrap_or_default().into(),
+            _ => event.msg.clone(),
+        };
+        snapshot.position = RE_POSITION
+            .captures(&event.msg)
+            .and_then(|caps| Some((caps[1].parse().ok()?, caps[2].parse().ok()?)));
+        snapshot.speed = RE_SPEED.find(&event.msg).map(|m| m.as_str().to_owned());
+        snapshot.eta = (event.percent >= 1.0 && elapsed >= Duration::from_secs(1))
+            .then(|| elapsed.mul_f32((100.0 - event.percent).max(0.0) / event.percent));
+    }
+
+    /// Publishes the [`Snapshot`] for `pacaptr top`, ignoring any failure.
+    fn publish_snapshot(&self) {
+        let Some(path) = self.publish.then(Snapshot::path).flatten() else {
+            return;
+        };
+        if let (Some(dir), Ok(json)) = (path.parent(), serde_json::to_vec(&self.snapshot)) {
+            fs::create_dir_all(dir)
+                .and_then(|()| fs::write(&path, json))
+                .ok();
+        }
+    }
+
+    /// Prints the summary of the whole operation.
+    pub(crate) fn finish(&mut self) {
+        if let Some(path) = self.publish.then(Snapshot::path).flatten() {
+            fs::remove_file(path).ok();
+        }
+        self.clear();
+        if !self.pkgs.is_empty() {
+            let msg = format!(
+                "Processed {} package(s): {}",
+                self.pkgs.len(),
+                self.pkgs.iter().join(", ")
+            );
+            print::print_msg(&msg, PROMPT_INFO);
+        }
+        if !self.errors.is_empty() {
+            let msg = format!(
+                "{} package(s) failed: {}",
+                self.errors.len(),
+                self.errors.iter().map(|(pkg, _)| pkg).unique().join(", ")
+            );
+            print::print_err(msg, PROMPT_ERROR);
+        }
+    }
+
+    /// Displays a progress line, overwriting the previous one on a terminal.
+    fn show_progress(&mut self, msg: &str) {
+        // Outside of a terminal, progress lines would only pollute the output.
+        if !io::stderr().is_terminal() {
+            return;
+        }
+        print::print_progress(msg, PROMPT_PROGRESS);
+        self.dirty = true;
+    }
+
+    /// Clears the progress line if there is one.
+    fn clear(&mut self) {
+        if self.dirty {
+            print::clear_progress();
+            self.dirty = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pmstatus() {
+        let ev = Event::parse("pmstatus:libc6:amd64:42.8571:Unpacking libc6:amd64 (2.31)").unwrap();
+        assert_eq!(ev.kind, EventKind::Progress);
+        assert_eq!(ev.pkg, "libc6:amd64");
+        assert!((ev.percent - 42.8571).abs() < f32::EPSILON);
+        assert_eq!(ev.msg, "Unpacking libc6:amd64 (2.31)");
+    }
+
+    #[test]
+    fn parse_pmerror() {
+        let ev = Event::parse(
+            "pmerror:/var/cache/apt/archives/foo.deb:50.0000:trying to overwrite '/usr/bin/foo'",
+        )
+        .unwrap();
+        assert_eq!(ev.kind, EventKind::Error);
+        assert_eq!(ev.pkg, "/var/cache/apt/archives/foo.deb");
+        assert_eq!(ev.msg, "trying to overwrite '/usr/bin/foo'");
+    }
+
+    #[test]
+    fn parse_whole_percent() {
+        let ev = Event::parse("pmstatus:curl:7:Installing curl").unwrap();
+        assert!((ev.percent - 7.0).abs() < f32::EPSILON);
+        let ev = Event::parse("dlstatus:1:100:Retrieving file 1 of 1").unwrap();
+        assert_eq!(ev.kind, EventKind::Download);
+        assert!((ev.percent - 100.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn parse_non_status() {
+        assert_eq!(Event::parse("E: Unable to locate package foo"), None);
+        assert_eq!(Event::parse("pmstatus:foo:bar"), None);
+    }
+
+    #[test]
+    fn report_errors() {
+        let mut report = Report::default();
+        ["pmstatus:curl:10:Installing curl", "pmerror:curl:20:broken"]
+            .into_iter()
+            .filter_map(Event::parse)
+            .for_each(|ev| report.handle(ev));
+        assert_eq!(report.pkgs, ["curl"]);
+        assert_eq!(report.errors, [("curl".to_owned(), "broken".to_owned())]);
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut report = Report::default();
+        ["dlstatus:3:25.0:Retrieving file 3 of 12 (1,024 kB/s)"]
+            .into_iter()
+            .filter_map(Event::parse)
+            .for_each(|ev| report.handle(ev));
+        let snapshot = &report.snapshot;
+        assert_eq!(snapshot.position, Some((3, 12)));
+        assert_eq!(snapshot.speed.as_deref(), Some("1,024 kB/s"));
+        assert_eq!(
+            snapshot.render(),
+            "[3/12] [ 25%] Retrieving file 3 of 12 | 1,024 kB/s"
+        );
+
+        let snapshot = Snapshot {
+            percent: 50.0,
+            msg: "Unpacking curl".into(),
+            eta: Some(Duration::from_secs(80)),
+            ..Snapshot::default()
+        };
+        assert_eq!(snapshot.render(), "[ 50%] Unpacking curl | ETA 1m20s");
+
+        let snapshot = Snapshot {
+            step: Some(Step {
+                index: 2,
+                total: 2,
+                label: "Upgrading the packages".into(),
+            }),
+            percent: 50.0,
+            msg: "Unpacking curl".into(),
+            ..Snapshot::default()
+        };
+        assert_eq!(
+            snapshot.render(),
+            "[2/2] Upgrading the packages (75%) | [ 50%] Unpacking curl"
+        );
+    }
+}
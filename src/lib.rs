@@ -17,9 +17,46 @@
 #![doc = pacaptr_macros::compat_table!()]
 //! Note: Some flags are "translated" so are not shown in this table, eg. `-p`
 //! in `-Sp`.
+//!
+//! # Library Usage
+//!
+//! The package managers can also be driven from Rust with a [`PmBuilder`],
+//! the methods of [`Pm`](pm::Pm) returning structured results and
+//! [`Error`](error::Error)s. The commands can be captured or mocked with a
+//! custom [`Executor`]:
+//!
+//! ```no_run
+//! # async fn run() -> pacaptr::error::Result<()> {
+//! use async_trait::async_trait;
+//! use pacaptr::{error::Result, CmdLine, CmdOutput, Executor, PmBuilder};
+//!
+//! #[derive(Debug)]
+//! struct Echo;
+//!
+//! #[async_trait]
+//! impl Executor for Echo {
+//!     async fn exec(&self, cmd: &CmdLine) -> Result<CmdOutput> {
+//!         println!("{}", cmd.args.join(" "));
+//!         Ok(CmdOutput::default())
+//!     }
+//! }
+//!
+//! let pm = PmBuilder::new().using("dnf").executor(Echo).build();
+//! pm.install(&["curl"]).await?;
+//! # Ok(())
+//! # }
+//! ```
 
 pub mod dispatch;
 pub mod error;
 mod exec;
-mod pm;
+mod net;
+pub mod pm;
 pub mod print;
+mod state;
+mod version;
+
+pub use self::{
+    dispatch::PmBuilder,
+    exec::executor::{CmdLine, CmdOutput, CmdRun, Executor},
+};
@@ -0,0 +1,175 @@
+//! Downloading files over the network, eg. the package files given to `-U` as
+//! URLs when the package manager cannot fetch them by itself.
+//!
+//! The downloads are made with an HTTP client respecting the proxy settings
+//! of the environment, eg. `HTTPS_PROXY`, and the certificates trusted by the
+//! system, into a private temporary directory, so that no other user can
+//! swap them before they are installed as `root`.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use futures::prelude::*;
+use tempfile::TempDir;
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::{
+    error::{Error, Result},
+    exec::record,
+    print::{self, PROMPT_CANCELED, PROMPT_RUN},
+};
+
+/// The interval between two updates of the progress of a download.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A temporary directory holding the files downloaded, which is removed along
+/// with them when it's dropped.
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct Downloads {
+    /// The directory, only readable by the current user, or `None` in a dry
+    /// run.
+    dir: Option<TempDir>,
+    /// The timeout of each download.
+    timeout: Option<Duration>,
+}
+
+impl Downloads {
+    /// Creates the temporary directory of the downloads, unless `dry_run`.
+    ///
+    /// # Errors
+    /// Returns an [`Error::IoError`] when the directory cannot be created.
+    pub(crate) fn new(dry_run: bool, timeout: Option<Duration>) -> Result<Self> {
+        let dir = if dry_run {
+            None
+        } else {
+            let mut builder = tempfile::Builder::new();
+            builder.prefix("pacaptr-");
+            #[cfg(unix)]
+            {
+                use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+                builder.permissions(Permissions::from_mode(0o700));
+            }
+            Some(builder.tempdir()?)
+        };
+        Ok(Downloads { dir, timeout })
+    }
+
+    /// Downloads the file at `url` into the directory, showing the progress,
+    /// and returns its path. The file keeps the name it has in `url`.
+    ///
+    /// # Errors
+    /// Returns an [`Error::OtherError`] when the download fails.
+    pub(crate) async fn fetch(&self, url: &str) -> Result<PathBuf> {
+        let name = file_name(url);
+        let Some(dir) = &self.dir else {
+            let msg = format!("download {url}");
+            if !record::push(msg.clone()) {
+                print::print_msg(&msg, PROMPT_CANCELED);
+            }
+            return Ok(PathBuf::from(name));
+        };
+        let path = dir.path().join(name);
+        print::print_msg(&format!("download {url}"), PROMPT_RUN);
+        let fail = |e: &dyn std::fmt::Display| {
+            Error::OtherError(format!("Failed to download `{url}`: {e}"))
+        };
+        let mut client = reqwest::Client::builder();
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        let client = client.build().map_err(|e| fail(&e))?;
+        let resp = client
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| fail(&e))?;
+        let total = resp.content_length();
+        let mut file = File::create(&path).await?;
+        let mut body = resp.bytes_stream();
+        let (mut done, mut last) = (0, Instant::now());
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| fail(&e))?;
+            file.write_all(&chunk).await?;
+            done += chunk.len() as u64;
+            if last.elapsed() >= PROGRESS_INTERVAL {
+                last = Instant::now();
+                print::print_progress(&progress(name, done, total), PROMPT_RUN);
+            }
+        }
+        file.flush().await?;
+        print::clear_progress();
+        Ok(path)
+    }
+}
+
+/// Describes the progress of the download of `name`, `done` bytes out of
+/// `total` if known.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+fn progress(name: &str, done: u64, total: Option<u64>) -> String {
+    let mib = |bytes| bytes as f64 / f64::from(1 << 20);
+    match total {
+        Some(total) if total > 0 => format!(
+            "`{name}` {:.1}/{:.1} MiB ({}%)",
+            mib(done),
+            mib(total),
+            done * 100 / total
+        ),
+        _ => format!("`{name}` {:.1} MiB", mib(done)),
+    }
+}
+
+/// Gets the name of the file at `url`, ie. the last segment of its path, or
+/// `download` if it's not a plain file name, eg. `..`.
+#[must_use]
+fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/')
+        .next()
+        .filter(|name| !matches!(*name, "" | "." | "..") && !name.contains('\\'))
+        .unwrap_or("download")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names() {
+        assert_eq!(file_name("https://example.com/pkgs/curl.deb"), "curl.deb");
+        assert_eq!(
+            file_name("https://example.com/curl.rpm?mirror=1#top"),
+            "curl.rpm"
+        );
+        assert_eq!(file_name("https://example.com/"), "download");
+        assert_eq!(file_name("https://example.com/pkgs/.."), "download");
+        assert_eq!(file_name(r"https://example.com/..\..\curl.deb"), "download");
+    }
+
+    #[test]
+    fn progresses() {
+        assert_eq!(
+            progress("curl.deb", 1 << 20, Some(4 << 20)),
+            "`curl.deb` 1.0/4.0 MiB (25%)"
+        );
+        assert_eq!(progress("curl.deb", 3 << 19, None), "`curl.deb` 1.5 MiB");
+    }
+
+    #[test]
+    fn private_dir() {
+        let downloads = Downloads::new(false, None).unwrap();
+        let dir = downloads.dir.as_ref().unwrap().path().to_owned();
+        assert!(dir.is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(dir.metadata().unwrap().permissions().mode() & 0o777, 0o700);
+        }
+        drop(downloads);
+        assert!(!dir.exists());
+    }
+}
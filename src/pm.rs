@@ -17,30 +17,52 @@ mods! {
     brew;
     choco;
     conda;
+    custom;
     dnf;
     emerge;
     pip;
     port;
     scoop;
+    pub testing;
     tlmgr;
     unknown;
     xbps;
     zypper;
 }
 
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, IsTerminal},
+    mem,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
 use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use futures::prelude::*;
 use itertools::Itertools;
 use macro_rules_attribute::macro_rules_attribute;
 use tt_call::tt_call;
 
 pub(crate) use self::{
-    apk::Apk, apt::Apt, brew::Brew, choco::Choco, conda::Conda, dnf::Dnf, emerge::Emerge, pip::Pip,
-    port::Port, scoop::Scoop, tlmgr::Tlmgr, unknown::Unknown, xbps::Xbps, zypper::Zypper,
+    apk::Apk, apt::Apt, brew::Brew, choco::Choco, conda::Conda, custom::CustomPm, dnf::Dnf,
+    emerge::Emerge, pip::Pip, port::Port, scoop::Scoop, tlmgr::Tlmgr, unknown::Unknown, xbps::Xbps,
+    zypper::Zypper,
 };
+pub use crate::version::Scheme;
 use crate::{
     dispatch::Config,
-    error::Result,
-    exec::{Cmd, Mode, Output},
+    error::{Error, Result},
+    exec::{
+        self,
+        executor::{self, CmdRun},
+        pager, record, Cmd, Mode, Output,
+    },
+    print::{self, PROMPT_RUN, PROMPT_WARN},
+    state::{Reason, State},
+    version::Dep,
 };
 
 /// The list of [`pacman`](https://wiki.archlinux.org/index.php/Pacman) methods supported by [`pacaptr`](crate).
@@ -51,98 +73,133 @@ macro_rules! methods {
         tt_call::tt_return! {
             $caller
             methods = [{
-                /// Q generates a list of installed packages.
-                async fn q;
+                /// The queries of the installed packages and the files, which change nothing, eg. `-Q`.
+                trait PmQuery {
+                    /// F queries the package which provides FILE from the sync databases, even if it is not installed.
+                    async fn f;
+
+                    /// Fy refreshes the file database, then queries the package which provides FILE.
+                    async fn fy;
+
+                    /// Q generates a list of installed packages.
+                    async fn q;
 
-                /// Qc shows the changelog of a package.
-                async fn qc;
+                    /// Qc shows the changelog of a package.
+                    async fn qc;
 
-                /// Qe lists packages installed explicitly (not as dependencies).
-                async fn qe;
+                    /// Qd lists packages installed as dependencies (not explicitly).
+                    async fn qd;
 
-                /// Qi displays local package information: name, version, description, etc.
-                async fn qi;
+                    /// Qdt lists packages installed as dependencies but no longer required by any installed package, aka orphans.
+                    async fn qdt;
 
-                /// Qk verifies one or more packages.
-                async fn qk;
+                    /// Qe lists packages installed explicitly (not as dependencies).
+                    async fn qe;
 
-                /// Ql displays files provided by local package.
-                async fn ql;
+                    /// Qi displays local package information: name, version, description, etc.
+                    async fn qi;
 
-                /// Qm lists packages that are installed but are not available in any installation source (anymore).
-                async fn qm;
+                    /// Qk verifies one or more packages.
+                    async fn qk;
 
-                /// Qo queries the package which provides FILE.
-                async fn qo;
+                    /// Ql displays files provided by local package.
+                    async fn ql;
 
-                /// Qp queries a package supplied through a file supplied on the command line rather than an entry in the package management database.
-                async fn qp;
+                    /// Qm lists packages that are installed but are not available in any installation source (anymore).
+                    async fn qm;
 
-                /// Qs searches locally installed package for names or descriptions.
-                async fn qs;
+                    /// Qo queries the package which provides FILE.
+                    async fn qo;
 
-                /// Qu lists packages which have an update available.
-                async fn qu;
+                    /// Qp queries a package supplied through a file supplied on the command line rather than an entry in the package management database.
+                    async fn qp;
 
-                /// R removes a single package, leaving all of its dependencies installed.
-                async fn r;
+                    /// Qs searches locally installed package for names or descriptions.
+                    async fn qs;
 
-                /// Rn removes a package and skips the generation of configuration backup files.
-                async fn rn;
+                    /// Qt lists packages which are not required by any installed package.
+                    async fn qt;
 
-                /// Rns removes a package and its dependencies which are not required by any other installed package,
-                /// and skips the generation of configuration backup files.
-                async fn rns;
+                    /// Qu lists packages which have an update available.
+                    async fn qu;
 
-                /// Rs removes a package and its dependencies which are not required by any other installed package,
-                /// and not explicitly installed by the user.
-                async fn rs;
+                    /// T checks if the given dependencies are satisfied, printing the unsatisfied ones.
+                    async fn t;
+                }
+
+                /// The removals of the installed packages, eg. `-R`.
+                trait PmRemove {
+                    /// R removes a single package, leaving all of its dependencies installed.
+                    async fn r;
 
-                /// Rss removes a package and its dependencies which are not required by any other installed package.
-                async fn rss;
+                    /// Rc removes a package and all the packages that depend on it.
+                    async fn rc;
 
-                /// S installs one or more packages by name.
-                async fn s;
+                    /// Rcs removes a package and all the packages that depend on it,
+                    /// along with their dependencies which are not required by any other installed package.
+                    async fn rcs;
 
-                /// Sc removes all the cached packages that are not currently installed, and the unused sync database.
-                async fn sc;
+                    /// Rn removes a package and skips the generation of configuration backup files.
+                    async fn rn;
 
-                /// Scc removes all files from the cache.
-                async fn scc;
+                    /// Rns removes a package and its dependencies which are not required by any other installed package,
+                    /// and skips the generation of configuration backup files.
+                    async fn rns;
 
-                /// Sccc ...
-                /// What is this?
-                async fn sccc;
+                    /// Rs removes a package and its dependencies which are not required by any other installed package,
+                    /// and not explicitly installed by the user.
+                    async fn rs;
+
+                    /// Rss removes a package and its dependencies which are not required by any other installed package.
+                    async fn rss;
+                }
 
-                /// Sg lists all packages belonging to the GROUP.
-                async fn sg;
+                /// The operations on the sync databases and the packages in them, eg. `-S`.
+                trait PmSync {
+                    /// S installs one or more packages by name.
+                    async fn s;
 
-                /// Si displays remote package information: name, version, description, etc.
-                async fn si;
+                    /// Sc removes all the cached packages that are not currently installed, and the unused sync database.
+                    async fn sc;
 
-                /// Sii displays packages which require X to be installed, aka reverse dependencies.
-                async fn sii;
+                    /// Scc removes all files from the cache.
+                    async fn scc;
 
-                /// Sl displays a list of all packages in all installation sources that are handled by the packages management.
-                async fn sl;
+                    /// Sccc ...
+                    /// What is this?
+                    async fn sccc;
 
-                /// Ss searches for package(s) by searching the expression in name, description, short description.
-                async fn ss;
+                    /// Sg lists all packages belonging to the GROUP, or all the groups if
+                    /// no GROUP is given.
+                    async fn sg;
 
-                /// Su updates outdated packages.
-                async fn su;
+                    /// Si displays remote package information: name, version, description, etc.
+                    async fn si;
 
-                /// Suy refreshes the local package database, then updates outdated packages.
-                async fn suy;
+                    /// Sii displays packages which require X to be installed, aka reverse dependencies.
+                    async fn sii;
 
-                /// Sw retrieves all packages from the server, but does not install/upgrade anything.
-                async fn sw;
+                    /// Sl displays a list of all packages in all installation sources that are handled by the packages management.
+                    async fn sl;
 
-                /// Sy refreshes the local package database.
-                async fn sy;
+                    /// Ss searches for package(s) by searching the expression in name, description, short description.
+                    async fn ss;
 
-                /// U upgrades or adds package(s) to the system and installs the required dependencies from sync repositories.
-                async fn u;
+                    /// Su updates outdated packages.
+                    async fn su;
+
+                    /// Suy refreshes the local package database, then updates outdated packages.
+                    async fn suy;
+
+                    /// Sw retrieves all packages from the server, but does not install/upgrade anything.
+                    async fn sw;
+
+                    /// Sy refreshes the local package database.
+                    async fn sy;
+
+                    /// U upgrades or adds package(s) to the system and installs the required dependencies from sync repositories.
+                    async fn u;
+                }
             }]
         }
     };
@@ -150,9 +207,10 @@ macro_rules! methods {
 
 macro_rules! make_op_body {
     ($self:ident, $method:ident) => {{
-        Err(crate::error::Error::OperationUnimplementedError {
+        Err(crate::error::Error::OperationUnsupportedError {
             op: stringify!($method).into(),
             pm: $self.name().into(),
+            hint: $self.unsupported_hint(stringify!($method)).map(Into::into),
         })
     }};
 }
@@ -165,28 +223,40 @@ macro_rules! _decor_pm {(
         }
     }]
     methods = [{ $(
-        $( #[$meta1:meta] )*
-        async fn $method:ident;
+        $( #[$tmeta:meta] )*
+        trait $cap:ident { $(
+            $( #[$meta1:meta] )*
+            async fn $method:ident;
+        )* }
     )* }]
 ) => {
     $( #[$meta0] )*
     $vis trait $t : $supert {
         $( $inner )*
+    }
 
-        // * Automatically generated methods below... *
+    // * Automatically generated traits below... *
+    $( $( #[$tmeta] )*
+    ///
+    /// Each operation not overridden returns an
+    /// [`Error::OperationUnsupportedError`] with the hint given by
+    /// [`PmBase::unsupported_hint`], so that the dispatcher reports it
+    /// uniformly as not supported by the package manager.
+    #[async_trait]
+    $vis trait $cap : $t {
         $( $( #[$meta1] )*
         async fn $method(&self, _kws: &[&str], _flags: &[&str]) -> Result<()> {
             make_op_body!(self, $method)
         } )*
-    }
+    } )*
 };}
 
 /// Send `methods!()` to `_decor_pm`, that is:
 ///
 /// ```rust
 /// _decor_pm! {
-///     def = [{ trait Pm { .. } }]
-///     methods = [{ q qc qe .. }] )
+///     def = [{ trait PmBase { .. } }]
+///     methods = [{ trait PmQuery { q qc qe .. } .. }] )
 /// }
 /// ```
 macro_rules! decor_pm {
@@ -200,27 +270,483 @@ macro_rules! decor_pm {
     };
 }
 
-/// The feature set of a Package Manager defined by `pacman` commands.
+/// The feature set of a Package Manager defined by `pacman` commands, split
+/// into the capabilities [`PmQuery`], [`PmRemove`] and [`PmSync`] upon the
+/// common [`PmBase`].
 ///
 /// For method explanation see:
 /// - <https://wiki.archlinux.org/index.php/Pacman>
 /// - <https://wiki.archlinux.org/index.php/Pacman/Rosetta>
+#[async_trait]
+pub trait Pm: PmQuery + PmRemove + PmSync {
+    /// Wraps the [`Pm`] instance in a [`Box`].
+    fn boxed<'a>(self) -> Box<dyn Pm + 'a>
+    where
+        Self: Sized + 'a,
+    {
+        Box::new(self)
+    }
+
+    /// Installs the packages `pkgs`, like `-S`, with the extra flags set with
+    /// [`PmBuilder::flags`](crate::PmBuilder::flags). Returns the commands run along with their output.
+    async fn install(&self, pkgs: &[&str]) -> Result<Vec<CmdRun>> {
+        let flags = self.cfg().flags.iter().map(String::as_str).collect_vec();
+        executor::capture(self.s(pkgs, &flags)).await
+    }
+
+    /// Removes the packages `pkgs`, like `-R`, with the extra flags set with
+    /// [`PmBuilder::flags`](crate::PmBuilder::flags). Returns the commands run along with their output.
+    async fn remove(&self, pkgs: &[&str]) -> Result<Vec<CmdRun>> {
+        let flags = self.cfg().flags.iter().map(String::as_str).collect_vec();
+        executor::capture(self.r(pkgs, &flags)).await
+    }
+
+    /// Upgrades all the outdated packages, like `-Su`, with the extra flags set
+    /// with [`PmBuilder::flags`](crate::PmBuilder::flags). Returns the commands run along with their output.
+    async fn upgrade(&self) -> Result<Vec<CmdRun>> {
+        let flags = self.cfg().flags.iter().map(String::as_str).collect_vec();
+        executor::capture(self.su(&[], &flags)).await
+    }
+
+    /// Refreshes the local package database, like `-Sy`, with the extra flags
+    /// set with [`PmBuilder::flags`](crate::PmBuilder::flags). Returns the commands run along with their output.
+    async fn refresh(&self) -> Result<Vec<CmdRun>> {
+        let flags = self.cfg().flags.iter().map(String::as_str).collect_vec();
+        executor::capture(self.sy(&[], &flags)).await
+    }
+}
+
+impl<P: PmQuery + PmRemove + PmSync + ?Sized> Pm for P {}
+
+macro_rules! _ops {(
+    methods = [{ $(
+        $( #[$tmeta:meta] )*
+        trait $cap:ident { $(
+            $( #[$meta:meta] )*
+            async fn $method:ident;
+        )* }
+    )* }]
+) => {
+    /// The names of all the operations mapped to `pacman` commands, eg. `"qi"`
+    /// for [`PmQuery::qi`].
+    pub(crate) const OPS: &[&str] = &[ $( $( stringify!($method), )* )* ];
+};}
+
+tt_call! {
+    macro = [{ methods }]
+    ~~> _ops
+}
+
+/// The operations supported by each built-in [`Pm`], as registered by the
+/// `SUPPORTED` constant of its module. The compatibility table is generated
+/// from the same registries.
+pub(crate) static COMPAT: &[(&str, &[&str])] = &[
+    ("apk", apk::SUPPORTED),
+    ("apt", apt::SUPPORTED),
+    ("brew", brew::SUPPORTED),
+    ("choco", choco::SUPPORTED),
+    ("conda", conda::SUPPORTED),
+    ("dnf", dnf::SUPPORTED),
+    ("emerge", emerge::SUPPORTED),
+    ("pip", pip::SUPPORTED),
+    ("port", port::SUPPORTED),
+    ("scoop", scoop::SUPPORTED),
+    ("tlmgr", tlmgr::SUPPORTED),
+    ("xbps", xbps::SUPPORTED),
+    ("zypper", zypper::SUPPORTED),
+];
+
+/// The common base of the capabilities of a [`Pm`], which the operations
+/// mapped to `pacman` commands are built upon.
 #[macro_rules_attribute(decor_pm!)]
 #[async_trait]
-pub(crate) trait Pm: Sync {
+pub trait PmBase: Sync {
     /// Gets the name of the package manager.
     fn name(&self) -> &str;
 
     /// Gets the config of the package manager.
     fn cfg(&self) -> &Config;
 
-    /// Wraps the [`Pm`] instance in a [`Box`].
-    fn boxed<'a>(self) -> Box<dyn Pm + 'a>
-    where
-        Self: Sized + 'a,
-    {
-        Box::new(self)
+    /// Gets the [`Scheme`] used to compare the versions of the packages.
+    fn version_scheme(&self) -> Scheme {
+        Scheme::default()
+    }
+
+    /// Checks if [`su`](PmSync::su) and [`suy`](PmSync::suy) leave out the packages in
+    /// `ignore`.
+    fn supports_ignore(&self) -> bool {
+        false
+    }
+
+    /// Checks if [`sw`](PmSync::sw) saves the packages to the directory set
+    /// with [`PmBuilder::download_dir`](crate::PmBuilder::download_dir).
+    fn supports_download_dir(&self) -> bool {
+        false
+    }
+
+    /// Checks if [`u`](PmSync::u) can fetch the package files given as URLs
+    /// by itself.
+    fn supports_url(&self) -> bool {
+        false
+    }
+
+    /// Checks if the package manager can operate on the system under the
+    /// directory set with [`PmBuilder::root`](crate::PmBuilder::root) instead
+    /// of `/`.
+    fn supports_root(&self) -> bool {
+        false
+    }
+
+    /// Gets the flags making the command `exe`, eg. `apt-get`, operate on the
+    /// system under `root` instead of `/`, if [`supports_root`] is `true`.
+    ///
+    /// [`supports_root`]: PmBase::supports_root
+    fn root_flags(&self, _exe: &str, _root: &str) -> Vec<String> {
+        vec![]
+    }
+
+    /// Gets the closest native command to the operation `op`, eg. `qc`, when
+    /// it's not supported by the package manager.
+    fn unsupported_hint(&self, _op: &str) -> Option<&str> {
+        None
+    }
+
+    /// Translates the package `name` with the version requirement `op` `ver`,
+    /// eg. `>=` `8.0`, to the syntax of the package manager for
+    /// [`s`](PmSync::s), or returns `None` if it cannot be expressed.
+    fn versioned(&self, _name: &str, _op: &str, _ver: &str) -> Option<Versioned> {
+        None
+    }
+
+    /// Lists the package files which would be downloaded by
+    /// [`sw`](PmSync::sw), along with the paths they are saved to.
+    async fn sw_files(&self, _kws: &[&str], _flags: &[&str]) -> Result<Vec<PkgFile>> {
+        Err(Error::OperationUnimplementedError {
+            op: "sw --manifest".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Searches the sync databases like [`ss`](PmSync::ss), returning the packages
+    /// found instead of printing them.
+    async fn search(&self, _kws: &[&str], _flags: &[&str]) -> Result<Vec<SearchHit>> {
+        Err(Error::OperationUnimplementedError {
+            op: "ss --interactive".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the packages which would be upgraded by [`su`](PmSync::su).
+    async fn upgradable(&self) -> Result<Vec<String>> {
+        Err(Error::OperationUnimplementedError {
+            op: "resume".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Upgrades only the packages in `kws`, leaving the other outdated
+    /// packages as they are.
+    async fn su_only(&self, _kws: &[&str], _flags: &[&str]) -> Result<()> {
+        Err(Error::OperationUnimplementedError {
+            op: "resume".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the paths written to by [`su`](PmSync::su), or by [`s`](PmSync::s) if
+    /// `kws` is not empty, eg. the package cache, along with the estimated
+    /// number of bytes required on each of them (`0` if unknown).
+    async fn disk_usage(&self, _kws: &[&str], _flags: &[&str]) -> Result<Vec<(PathBuf, u64)>> {
+        Err(Error::OperationUnimplementedError {
+            op: "disk_usage".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the explicitly installed packages, like [`qe`](PmQuery::qe) without
+    /// the versions.
+    async fn explicit(&self) -> Result<Vec<String>> {
+        Err(Error::OperationUnimplementedError {
+            op: "export".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the installed packages along with their versions.
+    async fn installed(&self) -> Result<Vec<(String, String)>> {
+        Err(Error::OperationUnimplementedError {
+            op: "installed".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the installed packages along with their installed sizes in bytes,
+    /// for `pacaptr size`.
+    async fn sizes(&self, _flags: &[&str]) -> Result<Vec<(String, u64)>> {
+        Err(Error::OperationUnimplementedError {
+            op: "size".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the directories of the package cache, as cleaned by
+    /// [`sc`](PmSync::sc) and [`scc`](PmSync::scc).
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        Err(Error::OperationUnimplementedError {
+            op: "cache info".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the security advisories affecting the installed packages, for
+    /// `pacaptr audit`.
+    async fn advisories(&self, _flags: &[&str]) -> Result<Vec<Advisory>> {
+        Err(Error::OperationUnimplementedError {
+            op: "audit".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the direct dependencies of the package `pkg`, for `pacaptr tree`.
+    async fn deps(&self, _pkg: &str, _flags: &[&str]) -> Result<Vec<String>> {
+        Err(Error::OperationUnimplementedError {
+            op: "tree".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Pins the packages `kws` to their installed versions, keeping them from
+    /// being upgraded, or lists the packages pinned if `kws` is empty, for
+    /// `pacaptr pin`.
+    async fn pin(&self, _kws: &[&str], _flags: &[&str]) -> Result<()> {
+        Err(Error::OperationUnimplementedError {
+            op: "pin".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Releases the packages `kws` pinned with [`pin`](PmBase::pin), for
+    /// `pacaptr unpin`.
+    async fn unpin(&self, _kws: &[&str], _flags: &[&str]) -> Result<()> {
+        Err(Error::OperationUnimplementedError {
+            op: "unpin".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Gets the details of the packages in `kws` like [`si`](PmSync::si), or like
+    /// [`qi`](PmQuery::qi) if `installed`, instead of printing them.
+    async fn info(&self, _kws: &[&str], _flags: &[&str], installed: bool) -> Result<Vec<PkgInfo>> {
+        Err(Error::OperationUnimplementedError {
+            op: if installed {
+                "qi --normalize"
+            } else {
+                "si --normalize"
+            }
+            .into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Lists the installed packages along with their installation dates.
+    async fn install_dates(&self, _flags: &[&str]) -> Result<Vec<(String, SystemTime)>> {
+        Err(Error::OperationUnimplementedError {
+            op: "q --since/--before".into(),
+            pm: self.name().into(),
+        })
+    }
+
+    /// Q with `--since`/`--before` lists the installed packages that were
+    /// installed within the given time range, sorted by their installation
+    /// dates. If `kws` is not empty, only the packages named in it are listed.
+    async fn q_by_date(
+        &self,
+        kws: &[&str],
+        flags: &[&str],
+        since: Option<SystemTime>,
+        before: Option<SystemTime>,
+    ) -> Result<()> {
+        let mut dates = self.install_dates(flags).await?;
+        dates.retain(|(name, time)| {
+            (kws.is_empty() || kws.contains(&name.as_str()))
+                && since.is_none_or(|since| *time >= since)
+                && before.is_none_or(|before| *time < before)
+        });
+        dates.sort_unstable_by(|(n1, t1), (n2, t2)| t1.cmp(t2).then_with(|| n1.cmp(n2)));
+        for (name, time) in dates {
+            let time = DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M");
+            println!("{time} {name}");
+        }
+        Ok(())
+    }
+}
+
+/// A package file downloaded by a [`Pm`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct PkgFile {
+    /// The name of the package.
+    pub name: String,
+    /// The version of the package.
+    pub version: String,
+    /// The repository of the package, which might be unknown.
+    pub repo: String,
+    /// The URL the file is downloaded from.
+    pub url: String,
+    /// The path the file is saved to.
+    pub path: PathBuf,
+}
+
+/// A package found in the sync databases by [`PmBase::search`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SearchHit {
+    /// The name of the package.
+    pub name: String,
+    /// The repository of the package, which might be unknown.
+    pub repo: String,
+    /// The latest version, which might be unknown.
+    pub version: String,
+    /// The description of the package.
+    pub desc: String,
+}
+
+/// A package with a version requirement, as passed to a [`Pm`], see
+/// [`PmBase::versioned`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Versioned {
+    /// The keyword naming the package and the version, eg. `curl=8.5.0`.
+    pub kw: String,
+    /// The flags to be passed along with the keyword, eg. `--version=8.5.0`.
+    pub flags: Vec<String>,
+}
+
+/// A security advisory affecting an installed package, as returned by
+/// [`PmBase::advisories`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Advisory {
+    /// The ID of the advisory, eg. `CVE-2023-38545` or `FEDORA-2023-0b3f1a1b7c`.
+    pub id: String,
+    /// The name of the package affected.
+    pub pkg: String,
+    /// The severity of the advisory, which might be unknown.
+    pub severity: String,
+    /// Whether an upgrade fixing the issue is available.
+    pub fixed: bool,
+}
+
+/// The details of a package, as returned by [`PmBase::info`]. The fields which
+/// are unknown are left empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PkgInfo {
+    /// The repository the package comes from.
+    pub repo: String,
+    /// The name of the package.
+    pub name: String,
+    /// The version of the package.
+    pub version: String,
+    /// The short description of the package.
+    pub desc: String,
+    /// The URL of the upstream project.
+    pub url: String,
+    /// The licenses of the package.
+    pub licenses: String,
+    /// The dependencies of the package.
+    pub depends: Vec<String>,
+    /// The size of the package once installed, eg. `1.2 MiB`.
+    pub installed_size: String,
+}
+
+/// Checks if the keyword `kw` given to [`u`](PmSync::u) is the URL of a
+/// package file rather than its path.
+#[must_use]
+pub(crate) fn is_url(kw: &str) -> bool {
+    ["http://", "https://", "ftp://"]
+        .iter()
+        .any(|scheme| kw.starts_with(scheme))
+}
+
+/// Makes the path of the package file `kw` given to [`u`](PmSync::u) look like
+/// a path, eg. `./curl.deb` instead of `curl.deb`, since most package managers
+/// would take it for the name of a package otherwise.
+#[must_use]
+fn local_path(kw: &str) -> String {
+    if is_url(kw) || kw.contains('/') {
+        kw.into()
+    } else {
+        format!("./{kw}")
+    }
+}
+
+/// Gets the total size in bytes of the files under `path`, without following
+/// the symbolic links inside. The files which cannot be read are skipped.
+#[must_use]
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let meta = entry.metadata().ok()?;
+            Some(if meta.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                meta.len()
+            })
+        })
+        .sum()
+}
+
+/// Splits `text` into paragraphs of `Key: value` fields, like the ones printed
+/// by `apt-cache show` or `dnf info`. Paragraphs are separated by blank lines,
+/// and the indented lines continue the value of the previous field.
+#[must_use]
+fn parse_fields(text: &str) -> Vec<HashMap<String, String>> {
+    let mut paragraphs = vec![];
+    let mut fields = HashMap::<String, String>::new();
+    let mut last: Option<String> = None;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !fields.is_empty() {
+                paragraphs.push(mem::take(&mut fields));
+            }
+            last = None;
+        } else if line.starts_with(char::is_whitespace) {
+            let Some(value) = last.as_ref().and_then(|key| fields.get_mut(key)) else {
+                continue;
+            };
+            let line = line.trim();
+            let line = line.strip_prefix(':').map_or(line, str::trim_start);
+            value.extend(["\n", line]);
+        } else if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim().to_owned();
+            fields.insert(key.clone(), value.trim().to_owned());
+            last = Some(key);
+        }
+    }
+    if !fields.is_empty() {
+        paragraphs.push(fields);
     }
+    paragraphs
+}
+
+/// The maximum number of searches run at the same time by
+/// [`PmHelper::search_each`].
+const SEARCH_JOBS: usize = 4;
+
+/// The packages to be listed by [`PmHelper::print_by_reason`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ReasonFilter {
+    /// Explicitly installed packages, like `-Qe`.
+    Explicit,
+    /// Packages installed as dependencies, like `-Qd`.
+    Dependency,
+    /// Dependencies which are no longer required, like `-Qdt`.
+    Orphan,
 }
 
 /// Extra implementation helper functions for [`Pm`],
@@ -228,12 +754,23 @@ pub(crate) trait Pm: Sync {
 /// [`Pm`]-specific context.
 #[async_trait]
 trait PmHelper: Pm {
+    /// Checks if the commands are run in a batch, ie. with `--yes` or when
+    /// `stdout` is not a terminal, where the commands meant for scripts are
+    /// preferred, eg. `apt-get` over `apt`.
+    #[must_use]
+    fn is_batch(&self) -> bool {
+        self.cfg().no_confirm || !io::stdout().is_terminal()
+    }
+
     /// Executes a command in the context of the [`Pm`] implementation. Returns
     /// the [`Output`] of this command.
     async fn check_output(&self, mut cmd: Cmd, mode: PmMode, strat: &Strategy) -> Result<Output> {
         async fn run(cfg: &Config, cmd: &Cmd, mode: PmMode, strat: &Strategy) -> Result<Output> {
             let mut curr_cmd = cmd.clone();
             let no_confirm = cfg.no_confirm;
+            if let PmMode::StatusFd(v) = mode {
+                curr_cmd.flags.extend(v.iter().map(|&s| s.into()));
+            }
             if cfg.no_cache {
                 if let NoCacheStrategy::WithFlags(v) = &strat.no_cache {
                     curr_cmd.flags.extend(v.clone());
@@ -241,7 +778,9 @@ trait PmHelper: Pm {
             }
             match &strat.prompt {
                 PromptStrategy::None => curr_cmd.exec(mode.into()).await,
-                PromptStrategy::CustomPrompt if no_confirm => curr_cmd.exec(mode.into()).await,
+                PromptStrategy::CustomPrompt if no_confirm || cfg.confirmed => {
+                    curr_cmd.exec(mode.into()).await
+                }
                 PromptStrategy::CustomPrompt => curr_cmd.exec(Mode::Prompt).await,
                 PromptStrategy::NativeNoConfirm(v) => {
                     if no_confirm {
@@ -249,6 +788,13 @@ trait PmHelper: Pm {
                     }
                     curr_cmd.exec(mode.into()).await
                 }
+                PromptStrategy::NativeNoConfirmGlobal(v) => {
+                    if no_confirm {
+                        let at = curr_cmd.cmd.len().min(1);
+                        curr_cmd.cmd.splice(at..at, v.clone());
+                    }
+                    curr_cmd.exec(mode.into()).await
+                }
                 PromptStrategy::NativeConfirm(v) => {
                     if !no_confirm {
                         curr_cmd.flags.extend(v.clone());
@@ -259,9 +805,30 @@ trait PmHelper: Pm {
         }
 
         let cfg = self.cfg();
+        cmd = cmd
+            .sudo_with(cfg.sudo.clone())
+            .force_sudo(cfg.force_sudo)
+            .timeout(cfg.timeout.map(Duration::from_secs))
+            .interop(cfg.interop)
+            .host(cfg.host.clone())
+            .container(cfg.container.clone())
+            .executor(cfg.executor.clone());
+        strat.needed.apply(&mut cmd, cfg.needed);
+        let mut root_flags = 0;
+        if let (Some(root), Some(exe)) = (&cfg.root, cmd.cmd.first()) {
+            let flags = self.root_flags(exe, &root.to_string_lossy());
+            root_flags = flags.len();
+            cmd.flags.splice(0..0, flags);
+        }
 
         // `--dry-run` should apply to both the main command and the cleanup.
         let res = match &strat.dry_run {
+            // The queries are run to find out which commands follow, but are
+            // left out of a summary of them.
+            _ if cfg.dry_run && record::is_active() => match mode {
+                PmMode::Mute => run(cfg, &cmd, mode, strat).await?,
+                _ => cmd.clone().exec(Mode::PrintCmd).await?,
+            },
             DryRunStrategy::PrintCmd if cfg.dry_run => cmd.clone().exec(Mode::PrintCmd).await?,
             DryRunStrategy::WithFlags(v) if cfg.dry_run => {
                 cmd.flags.extend(v.clone());
@@ -269,12 +836,20 @@ trait PmHelper: Pm {
                 cmd = cmd.sudo(false);
                 run(cfg, &cmd, mode, strat).await?
             }
+            DryRunStrategy::Declining(v) if cfg.dry_run => {
+                cmd.flags.extend(v.clone());
+                match run(cfg, &cmd, mode, strat).await {
+                    Err(Error::CmdStatusCodeError { output, .. }) => output,
+                    res => res?,
+                }
+            }
             _ => run(cfg, &cmd, mode, strat).await?,
         };
 
         // Perform the cleanup.
         if cfg.no_cache {
-            let flags = cmd.flags.iter().map(|s| s as _).collect_vec();
+            // The cleanup gets its own root flags.
+            let flags = cmd.flags[root_flags..].iter().map(|s| s as _).collect_vec();
             match &strat.no_cache {
                 NoCacheStrategy::Sc => self.sc(&[], &flags).await?,
                 NoCacheStrategy::Scc => self.scc(&[], &flags).await?,
@@ -298,6 +873,326 @@ trait PmHelper: Pm {
         self.run_with(cmd, PmMode::default(), &Strategy::default())
             .await
     }
+
+    /// Gets [`Config::download_dir`] for [`sw`](PmSync::sw), creating it
+    /// beforehand unless in a dry run.
+    fn download_dir(&self) -> Result<Option<String>> {
+        let Some(dir) = &self.cfg().download_dir else {
+            return Ok(None);
+        };
+        if !self.cfg().dry_run {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(Some(dir.to_string_lossy().into()))
+    }
+
+    /// Runs the search `cmd` once for each of `kws`, for the package managers
+    /// which only take a single query at a time, or which list the packages
+    /// matching any of the queries.
+    ///
+    /// The searches are run concurrently, at most [`SEARCH_JOBS`] at a time,
+    /// and only the lines found in the outputs of all of them are printed
+    /// (see [`exec::intersect`]), so that the packages matching all the `kws`
+    /// are listed, as in `pacman`. The outputs are intersected as they arrive,
+    /// and the results are printed as soon as the last one is in.
+    ///
+    /// A search which fails is reported right away and left out, so that the
+    /// results of the others are still printed. The first error is then
+    /// returned, if any.
+    async fn search_each(
+        &self,
+        cmd: Cmd,
+        kws: &[&str],
+        flags: &[&str],
+        strat: &Strategy,
+    ) -> Result<()> {
+        if kws.len() < 2 {
+            return self
+                .run_with(cmd.kws(kws).flags(flags), PmMode::default(), strat)
+                .await;
+        }
+        if self.cfg().dry_run {
+            for &kw in kws {
+                self.run_with(
+                    cmd.clone().kws(&[kw]).flags(flags),
+                    PmMode::default(),
+                    strat,
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+        let searches = kws
+            .iter()
+            .map(|&kw| {
+                let cmd = cmd.clone().kws(&[kw]).flags(flags);
+                print::print_cmd(&cmd, PROMPT_RUN);
+                self.check_output(cmd, PmMode::Mute, strat)
+                    .map(move |res| (kw, res))
+                    .boxed()
+            })
+            .collect_vec();
+        let mut searches = stream::iter(searches).buffered(SEARCH_JOBS);
+        let (mut lines, mut err) = (None::<Vec<String>>, None);
+        while let Some((kw, res)) = searches.next().await {
+            let out = match res.and_then(|out| Ok(String::from_utf8(out)?)) {
+                Ok(out) => out,
+                Err(e) => {
+                    print::print_msg(
+                        &format!("The search for `{kw}` has failed, leaving it out of the results"),
+                        PROMPT_WARN,
+                    );
+                    err.get_or_insert(e);
+                    continue;
+                }
+            };
+            match &mut lines {
+                Some(lines) => exec::intersect(lines, &out),
+                None => lines = Some(out.lines().map(String::from).collect()),
+            }
+        }
+        exec::squeeze_blanks(lines.as_deref().unwrap_or_default())
+            .into_iter()
+            .for_each(pager::print);
+        err.map_or(Ok(()), Err)
+    }
+
+    /// Silently runs `cmd` to search the sync databases, parsing each line of
+    /// its output with `parse`. Lines which cannot be parsed are skipped.
+    async fn search_lines(
+        &self,
+        cmd: Cmd,
+        parse: for<'a> fn(&'a str) -> Option<SearchHit>,
+    ) -> Result<Vec<SearchHit>> {
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        Ok(String::from_utf8(out)?.lines().filter_map(parse).collect())
+    }
+
+    /// Silently runs `cmd` to get the details of packages, converting each
+    /// paragraph of fields in its output (see [`parse_fields`]) with
+    /// `info_of`. Paragraphs which cannot be converted are skipped.
+    async fn info_from(
+        &self,
+        cmd: Cmd,
+        info_of: for<'a> fn(&'a HashMap<String, String>) -> Option<PkgInfo>,
+    ) -> Result<Vec<PkgInfo>> {
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        Ok(parse_fields(&String::from_utf8(out)?)
+            .iter()
+            .filter_map(info_of)
+            .collect())
+    }
+
+    /// Silently runs `cmd` to get the installed sizes of the installed
+    /// packages. Each line of its output should look like `<name>\t<size>`,
+    /// where the size is in units of `unit` bytes.
+    async fn sizes_from(&self, cmd: Cmd, unit: u64) -> Result<Vec<(String, u64)>> {
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        Ok(String::from_utf8(out)?
+            .lines()
+            .filter_map(|ln| {
+                let (name, size) = ln.trim().split_once('\t')?;
+                Some((name.to_owned(), size.trim().parse::<u64>().ok()? * unit))
+            })
+            .collect())
+    }
+
+    /// Silently runs `cmd` to get the installation dates of the installed
+    /// packages. Each line of its output should look like
+    /// `<seconds since epoch> <name>`.
+    async fn install_dates_from(&self, cmd: Cmd) -> Result<Vec<(String, SystemTime)>> {
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        Ok(String::from_utf8(out)?
+            .lines()
+            .filter_map(|ln| {
+                let (secs, name) = ln.trim().split_once(' ')?;
+                let time = SystemTime::UNIX_EPOCH + Duration::from_secs(secs.parse().ok()?);
+                Some((name.to_owned(), time))
+            })
+            .collect())
+    }
+
+    /// Checks if the dependencies in `kws` are satisfied by silently running
+    /// `query` with each package name appended.
+    ///
+    /// `version_of` extracts the installed version from the output of a
+    /// successful query, returning `None` if the package is not installed. A
+    /// query exiting with an error also means that the package is not
+    /// installed.
+    ///
+    /// Just like `pacman -T`, the unsatisfied dependencies are printed to
+    /// `stdout`, and [`Error::DepTestError`] is returned if there is any.
+    async fn deptest(
+        &self,
+        kws: &[&str],
+        query: Cmd,
+        version_of: for<'a> fn(&'a str) -> Option<&'a str>,
+    ) -> Result<()> {
+        let mut unsatisfied = vec![];
+        for dep in kws.iter().map(|kw| Dep::parse(kw)) {
+            let cmd = query.clone().kws(&[dep.name]);
+            if self.cfg().dry_run {
+                cmd.exec(Mode::PrintCmd).await?;
+                continue;
+            }
+            let satisfied = match self
+                .check_output(cmd, PmMode::Mute, &Strategy::default())
+                .await
+            {
+                Ok(out) => {
+                    let out = String::from_utf8(out)?;
+                    version_of(&out)
+                        .is_some_and(|ver| dep.is_satisfied_by(ver, self.version_scheme()))
+                }
+                Err(Error::CmdStatusCodeError { .. }) => false,
+                Err(e) => return Err(e),
+            };
+            if !satisfied {
+                println!("{}", dep.spec);
+                unsatisfied.push(dep.spec.to_owned());
+            }
+        }
+        if unsatisfied.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::DepTestError { deps: unsatisfied })
+        }
+    }
+
+    /// Runs `cmd`, which installs the `explicit` packages or removes some
+    /// packages, then records the [`Reason`]s of the packages installed by it
+    /// and forgets the ones of those removed by it.
+    ///
+    /// This is meant for the package managers that don't keep track of the
+    /// reasons themselves, and requires [`PmBase::installed`].
+    async fn run_tracked(&self, cmd: Cmd, explicit: &[&str], strat: &Strategy) -> Result<()> {
+        if self.cfg().dry_run {
+            return self.run_with(cmd, PmMode::default(), strat).await;
+        }
+        let names =
+            |pkgs: Vec<(String, String)>| pkgs.into_iter().map(|(name, _)| name).collect_vec();
+        let before = names(self.installed().await?);
+        let res = self.run_with(cmd, PmMode::default(), strat).await;
+        let after = names(self.installed().await?);
+
+        // Failing to record the reasons should not fail the operation itself.
+        let Ok(state) = State::open() else {
+            return res;
+        };
+        let explicit = after
+            .iter()
+            .filter(|pkg| explicit.iter().any(|kw| kw.eq_ignore_ascii_case(pkg)))
+            .collect_vec();
+        let dep = Reason::Dependency {
+            required_by: explicit.iter().map(|&pkg| pkg.clone()).collect(),
+        };
+        for pkg in &after {
+            if explicit.contains(&pkg) {
+                state.set_reason(self.name(), pkg, &Reason::Explicit).ok();
+            } else if !before.contains(pkg) {
+                state.set_reason(self.name(), pkg, &dep).ok();
+            }
+        }
+        for pkg in before.iter().filter(|pkg| !after.contains(pkg)) {
+            state.forget_reason(self.name(), pkg).ok();
+        }
+        res
+    }
+
+    /// Lists the installed packages along with their versions, keeping only
+    /// the ones matching `filter` according to the [`Reason`]s recorded by
+    /// [`run_tracked`](PmHelper::run_tracked). Packages without a recorded
+    /// [`Reason`] are considered as explicitly installed.
+    async fn by_reason(&self, filter: ReasonFilter) -> Result<Vec<(String, String)>> {
+        let installed = self.installed().await?;
+        let reasons = State::open()?.reasons(self.name())?;
+        let names = installed
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect_vec();
+        let matching = installed
+            .iter()
+            .filter(|(name, _)| match (reasons.get(name), filter) {
+                (None | Some(Reason::Explicit), ReasonFilter::Explicit)
+                | (Some(Reason::Dependency { .. }), ReasonFilter::Dependency) => true,
+                (Some(reason), ReasonFilter::Orphan) => reason.is_orphan(&names),
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        Ok(matching)
+    }
+
+    /// Prints the installed packages along with their versions, keeping only
+    /// the ones named in `kws` (if any) and matching `filter` (see
+    /// [`by_reason`](PmHelper::by_reason)).
+    async fn print_by_reason(&self, kws: &[&str], filter: ReasonFilter) -> Result<()> {
+        self.by_reason(filter)
+            .await?
+            .iter()
+            .filter(|(name, _)| {
+                kws.is_empty() || kws.iter().any(|kw| kw.eq_ignore_ascii_case(name))
+            })
+            .for_each(|(name, version)| match version.as_str() {
+                "" => println!("{name}"),
+                version => println!("{name} {version}"),
+            });
+        Ok(())
+    }
+
+    /// Silently runs `cmd`, extracting a package name from each line of its
+    /// output with `name_of`. Lines without one are skipped.
+    async fn names_from(
+        &self,
+        cmd: Cmd,
+        name_of: for<'a> fn(&'a str) -> Option<&'a str>,
+    ) -> Result<Vec<String>> {
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        Ok(String::from_utf8(out)?
+            .lines()
+            .filter_map(name_of)
+            .map(String::from)
+            .collect())
+    }
+
+    /// Holds back the packages in [`Config::ignore`] by running `hold` on them,
+    /// so that they are left out of an upcoming upgrade.
+    ///
+    /// `held` should list the packages which are already held back, whose
+    /// names are extracted from each line of its output by `name_of`. Those
+    /// packages are skipped, and the others are returned so that they can be
+    /// released after the upgrade.
+    async fn hold_ignored(
+        &self,
+        held: Cmd,
+        hold: Cmd,
+        name_of: for<'a> fn(&'a str) -> Option<&'a str>,
+    ) -> Result<Vec<String>> {
+        let ignore = &self.cfg().ignore;
+        if ignore.is_empty() {
+            return Ok(vec![]);
+        }
+        let held = self.names_from(held, name_of).await?;
+        let to_hold = ignore
+            .iter()
+            .filter(|pkg| !held.contains(pkg))
+            .cloned()
+            .collect_vec();
+        if !to_hold.is_empty() {
+            self.run(hold.kws(&to_hold)).await?;
+        }
+        Ok(to_hold)
+    }
 }
 
 impl<P: Pm> PmHelper for P {}
@@ -318,6 +1213,11 @@ enum PmMode {
     /// Prints out the command which should be executed, run it and collect its
     /// `stderr`. This will work with a colored `stdout`.
     CheckErr,
+
+    /// Like [`PmMode::CheckErr`], but the given flags are appended to make the
+    /// package manager write its machine-readable status stream to `stderr`,
+    /// which is then rendered as a progress indicator.
+    StatusFd(&'static [&'static str]),
 }
 
 impl Default for PmMode {
@@ -332,6 +1232,7 @@ impl From<PmMode> for Mode {
             PmMode::Mute => Mode::Mute,
             PmMode::CheckAll => Mode::CheckAll,
             PmMode::CheckErr => Mode::CheckErr,
+            PmMode::StatusFd(_) => Mode::StatusFd,
         }
     }
 }
@@ -348,6 +1249,10 @@ struct Strategy {
 
     /// How the cache is cleaned when `no_cache` is set to `true`.
     no_cache: NoCacheStrategy,
+
+    /// How the packages already installed are reinstalled when `needed` is
+    /// set to `false`.
+    needed: NeededStrategy,
 }
 
 /// How a dry run is dealt with.
@@ -357,6 +1262,11 @@ enum DryRunStrategy {
     PrintCmd,
     /// Invokes the corresponding package manager with the flags given.
     WithFlags(Vec<String>),
+    /// Invokes the corresponding package manager with the flags given, which
+    /// make it show the changes to be made and then decline them, eg.
+    /// `dnf --assumeno`. This needs `sudo` as the actual run does, and the
+    /// failure of declining is ignored.
+    Declining(Vec<String>),
 }
 
 impl DryRunStrategy {
@@ -365,6 +1275,13 @@ impl DryRunStrategy {
     fn with_flags(flags: &[impl AsRef<str>]) -> Self {
         Self::WithFlags(flags.iter().map(|s| s.as_ref().into()).collect())
     }
+
+    /// Invokes the corresponding package manager with the flags given, which
+    /// make it decline the changes to be made.
+    #[must_use]
+    fn declining(flags: &[impl AsRef<str>]) -> Self {
+        Self::Declining(flags.iter().map(|s| s.as_ref().into()).collect())
+    }
 }
 
 impl Default for DryRunStrategy {
@@ -384,6 +1301,10 @@ enum PromptStrategy {
     /// that can be disabled with a flag.
     NativeNoConfirm(Vec<String>),
     /// There is a native prompt provided by the package manager
+    /// that can be disabled with a global option, which comes before the
+    /// subcommand, eg. `zypper --non-interactive install`.
+    NativeNoConfirmGlobal(Vec<String>),
+    /// There is a native prompt provided by the package manager
     /// that can be enabled with a flag.
     NativeConfirm(Vec<String>),
 }
@@ -396,6 +1317,13 @@ impl PromptStrategy {
         Self::NativeNoConfirm(no_confirm.iter().map(|s| s.as_ref().into()).collect())
     }
 
+    /// There is a native prompt provided by the package manager
+    /// that can be disabled with a global option.
+    #[must_use]
+    fn native_no_confirm_global(no_confirm: &[impl AsRef<str>]) -> Self {
+        Self::NativeNoConfirmGlobal(no_confirm.iter().map(|s| s.as_ref().into()).collect())
+    }
+
     #[must_use]
     /// There is a native prompt provided by the package manager
     /// that can be enabled with a flag.
@@ -440,3 +1368,107 @@ impl Default for NoCacheStrategy {
         NoCacheStrategy::None
     }
 }
+
+/// How the packages already installed are reinstalled when `needed` is set to
+/// `false`, as `pacman -S` does.
+#[derive(Debug, Clone, Default)]
+enum NeededStrategy {
+    /// The packages already installed are left as is.
+    /// This variant MUST be used for the commands which don't install the
+    /// packages given, eg. `-Su`.
+    #[default]
+    None,
+    /// Invokes the corresponding package manager with the flags given, eg.
+    /// `apt install --reinstall`.
+    WithFlags(Vec<String>),
+    /// Invokes the command given instead, eg. `brew reinstall` rather than
+    /// `brew install`.
+    WithCmd(Vec<String>),
+}
+
+impl NeededStrategy {
+    /// Invokes the corresponding package manager with the flags given.
+    #[must_use]
+    fn with_flags(flags: &[impl AsRef<str>]) -> Self {
+        Self::WithFlags(flags.iter().map(|s| s.as_ref().into()).collect())
+    }
+
+    /// Invokes the command given instead.
+    #[must_use]
+    fn with_cmd(cmd: &[impl AsRef<str>]) -> Self {
+        Self::WithCmd(cmd.iter().map(|s| s.as_ref().into()).collect())
+    }
+
+    /// Applies the strategy to `cmd`, unless `needed` is set.
+    fn apply(&self, cmd: &mut Cmd, needed: bool) {
+        match self {
+            _ if needed => (),
+            Self::None => (),
+            // The flags are not passed to the cleanup of `no_cache`.
+            Self::WithFlags(v) => cmd.cmd.extend(v.clone()),
+            Self::WithCmd(v) => cmd.cmd.clone_from(v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unsupported() {
+        for &(name, supported) in COMPAT {
+            let pm = Box::<dyn Pm>::from(Config {
+                default_pm: Some(name.into()),
+                ..Config::default()
+            });
+            for op in supported {
+                assert_eq!(pm.unsupported_hint(op), None, "{name} supports `{op}`");
+            }
+        }
+        let err = Apt::new(Config::default()).qm(&[], &[]).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Operation `qm` is not supported by `apt`, try `apt list '?obsolete'` instead"
+        );
+        assert_eq!(err.exit_code(), 69);
+    }
+
+    #[test]
+    fn needed() {
+        let cmd = || Cmd::new(&["brew", "install"]).kws(&["curl"]);
+        let apply = |strat: &NeededStrategy, needed| {
+            let mut cmd = cmd();
+            strat.apply(&mut cmd, needed);
+            cmd.to_string()
+        };
+        let reinstall = NeededStrategy::with_cmd(&["brew", "reinstall"]);
+        assert_eq!(apply(&reinstall, false), "brew reinstall curl");
+        assert_eq!(apply(&reinstall, true), "brew install curl");
+        let force = NeededStrategy::with_flags(&["--force"]);
+        assert_eq!(apply(&force, false), "brew install --force curl");
+        assert_eq!(apply(&NeededStrategy::None, false), "brew install curl");
+    }
+
+    #[test]
+    fn local_paths() {
+        assert_eq!(local_path("curl.deb"), "./curl.deb");
+        assert_eq!(local_path("/tmp/curl.deb"), "/tmp/curl.deb");
+        assert_eq!(local_path("pkgs/curl.deb"), "pkgs/curl.deb");
+        assert!(is_url("https://example.com/curl.deb"));
+        assert_eq!(
+            local_path("https://example.com/curl.deb"),
+            "https://example.com/curl.deb"
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let text = "Package: curl\nVersion: 7.88.1-10\nDescription: command line tool\n .\n more\n\n\nName    : wget\nSummary : A utility\n        : for downloads\n";
+        let paragraphs = parse_fields(text);
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[0]["Version"], "7.88.1-10");
+        assert_eq!(paragraphs[0]["Description"], "command line tool\n.\nmore");
+        assert_eq!(paragraphs[1]["Summary"], "A utility\nfor downloads");
+    }
+}
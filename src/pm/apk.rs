@@ -1,14 +1,20 @@
 #![doc = docs_self!()]
 
+use std::{fs, path::PathBuf};
+
 use async_trait::async_trait;
 use indoc::indoc;
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{NoCacheStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    local_path, NoCacheStrategy, PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync,
+    PromptStrategy, SearchHit, Strategy, Versioned,
+};
 use crate::{
     dispatch::Config,
-    error::Result,
+    error::{Error, Result},
     exec::{self, Cmd},
     print::{self, PROMPT_RUN},
 };
@@ -46,8 +52,14 @@ impl Apk {
     }
 }
 
+/// The operations supported by [`Apk`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "q", "qi", "qk", "ql", "qo", "qs", "qu", "r", "rc", "rcs", "rn", "rns", "rs", "s", "sc", "scc",
+    "si", "sii", "sl", "ss", "su", "suy", "sw", "sy", "u",
+];
+
 #[async_trait]
-impl Pm for Apk {
+impl PmBase for Apk {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "apk"
@@ -57,6 +69,74 @@ impl Pm for Apk {
         &self.cfg
     }
 
+    fn supports_download_dir(&self) -> bool {
+        true
+    }
+
+    fn supports_root(&self) -> bool {
+        true
+    }
+
+    fn root_flags(&self, _exe: &str, root: &str) -> Vec<String> {
+        vec!["--root".into(), root.into()]
+    }
+
+    async fn search(&self, kws: &[&str], flags: &[&str]) -> Result<Vec<SearchHit>> {
+        // Each line looks like `<name>-<version>-r<release> - <description>`.
+        let cmd = Cmd::new(&["apk", "search", "-v"]).kws(kws).flags(flags);
+        self.search_lines(cmd, |ln| {
+            let (pkg, desc) = ln.split_once(" - ")?;
+            let (release, version, name) = pkg.rsplitn(3, '-').collect_tuple()?;
+            Some(SearchHit {
+                name: name.into(),
+                version: format!("{version}-{release}"),
+                desc: desc.into(),
+                ..SearchHit::default()
+            })
+        })
+        .await
+    }
+
+    async fn explicit(&self) -> Result<Vec<String>> {
+        // Each line of the world file looks like `<name>`, optionally followed by
+        // a version constraint (eg. `curl>=7.80`) or a repository tag (eg.
+        // `curl@edge`).
+        Ok(fs::read_to_string("/etc/apk/world")?
+            .lines()
+            .filter_map(|ln| {
+                let name = ln.trim().split(['<', '>', '=', '~', '@']).next()?;
+                (!name.is_empty()).then(|| name.into())
+            })
+            .collect())
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        Some(Versioned {
+            kw: format!("{name}{op}{ver}"),
+            ..Versioned::default()
+        })
+    }
+
+    async fn sizes(&self, _flags: &[&str]) -> Result<Vec<(String, u64)>> {
+        // Each package is a paragraph of `<key>:<value>` lines in the database,
+        // where `P` is its name and `I` its installed size in bytes.
+        let db = fs::read_to_string("/lib/apk/db/installed")?;
+        Ok(db
+            .split("\n\n")
+            .filter_map(|paragraph| {
+                let field = |key| paragraph.lines().find_map(|ln| ln.strip_prefix(key));
+                Some((field("P:")?.into(), field("I:")?.parse().ok()?))
+            })
+            .collect())
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        Ok(vec!["/var/cache/apk".into()])
+    }
+}
+
+#[async_trait]
+impl PmQuery for Apk {
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
@@ -71,6 +151,36 @@ impl Pm for Apk {
         self.si(kws, flags).await
     }
 
+    /// Qk verifies one or more packages.
+    async fn qk(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        if kws.is_empty() {
+            return self
+                .run(Cmd::new(&["apk", "audit", "--system"]).flags(flags))
+                .await;
+        }
+        // `apk audit` cannot be limited to some packages, so the packages with
+        // modified files are picked out of all of them.
+        let modified = self
+            .names_from(
+                Cmd::new(&["apk", "audit", "--system", "--packages"]).flags(flags),
+                |ln| Some(ln.trim()),
+            )
+            .await?
+            .into_iter()
+            .filter(|pkg| kws.contains(&pkg.as_str()))
+            .collect_vec();
+        if modified.is_empty() {
+            return Ok(());
+        }
+        for pkg in &modified {
+            println!("{pkg}: modified files found");
+        }
+        Err(Error::OtherError(format!(
+            "Failed to verify {}",
+            modified.iter().join(", ")
+        )))
+    }
+
     /// Ql displays files provided by local package.
     async fn ql(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.run(Cmd::new(&["apk", "info", "-L"]).kws(kws).flags(flags))
@@ -98,7 +208,7 @@ impl Pm for Apk {
         let out_bytes = self
             .check_output(cmd, PmMode::Mute, &Strategy::default())
             .await?;
-        exec::grep_print(&String::from_utf8(out_bytes)?, kws)
+        exec::grep_print(&String::from_utf8(out_bytes)?, kws, self.cfg.exact)
     }
 
     /// Qu lists packages which have an update available.
@@ -107,7 +217,10 @@ impl Pm for Apk {
         self.run(Cmd::new(&["apk", "version", "-l", "<"]).flags(flags))
             .await
     }
+}
 
+#[async_trait]
+impl PmRemove for Apk {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["apk", "del"])
@@ -117,6 +230,23 @@ impl Pm for Apk {
             .await
     }
 
+    /// Rc removes a package and all the packages that depend on it.
+    async fn rc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&["apk", "del", "-r"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .await
+    }
+
+    /// Rcs removes a package and all the packages that depend on it,
+    /// along with their dependencies which are not required by any other
+    /// installed package.
+    async fn rcs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `apk` always removes the dependencies which are no longer needed.
+        self.rc(kws, flags).await
+    }
+
     /// Rn removes a package and skips the generation of configuration backup
     /// files.
     async fn rn(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
@@ -143,7 +273,10 @@ impl Pm for Apk {
     async fn rs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.r(kws, flags).await
     }
+}
 
+#[async_trait]
+impl PmSync for Apk {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["apk", "add"])
@@ -193,7 +326,8 @@ impl Pm for Apk {
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["apk", "search", "-v"]).kws(kws).flags(flags))
+        let cmd = Cmd::new(&["apk", "search", "-v"]);
+        self.search_each(cmd, kws, flags, &Strategy::default())
             .await
     }
 
@@ -227,11 +361,11 @@ impl Pm for Apk {
     /// Sw retrieves all packages from the server, but does not install/upgrade
     /// anything.
     async fn sw(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["apk", "fetch"])
-            .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
-            .await
+        let mut cmd = Cmd::new(&["apk", "fetch"]).kws(kws).flags(flags);
+        if let Some(dir) = self.download_dir()? {
+            cmd.flags.push(format!("--output={dir}"));
+        }
+        self.run_with(cmd, PmMode::default(), &STRAT_PROMPT).await
     }
 
     /// Sy refreshes the local package database.
@@ -247,8 +381,9 @@ impl Pm for Apk {
     /// U upgrades or adds package(s) to the system and installs the required
     /// dependencies from sync repositories.
     async fn u(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let kws = kws.iter().map(|kw| local_path(kw)).collect_vec();
         Cmd::with_sudo(&["apk", "add", "--allow-untrusted"])
-            .kws(kws)
+            .kws(&kws)
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
             .await
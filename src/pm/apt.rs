@@ -1,12 +1,31 @@
 #![doc = docs_self!()]
 
+use std::{
+    fs, iter,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
 use async_trait::async_trait;
 use indoc::indoc;
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{NoCacheStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
-use crate::{dispatch::Config, error::Result, exec::Cmd};
+use super::{
+    local_path, Advisory, DryRunStrategy, NeededStrategy, NoCacheStrategy, PkgFile, PkgInfo, PmBase,
+    PmHelper, PmMode, PmQuery, PmRemove, PmSync, PromptStrategy, SearchHit, Strategy, Versioned,
+};
+use crate::{
+    dispatch::Config,
+    error::{Error, Result},
+    exec::{
+        plan::{Plan, STEPS_SUY},
+        Cmd,
+    },
+    print::{self, PROMPT_RUN},
+    version::Scheme,
+};
 
 macro_rules! docs_self {
     () => {
@@ -22,27 +41,130 @@ pub(crate) struct Apt {
     cfg: Config,
 }
 
+/// Makes `apt` report its progress on `stderr` in a machine-readable form.
+const MODE_STATUS_FD: PmMode = PmMode::StatusFd(&["-o", "APT::Status-Fd=2"]);
+
 static STRAT_PROMPT: Lazy<Strategy> = Lazy::new(|| Strategy {
     prompt: PromptStrategy::native_no_confirm(&["--yes"]),
+    dry_run: DryRunStrategy::with_flags(&["--simulate"]),
     ..Strategy::default()
 });
 
 static STRAT_INSTALL: Lazy<Strategy> = Lazy::new(|| Strategy {
     prompt: PromptStrategy::native_no_confirm(&["--yes"]),
     no_cache: NoCacheStrategy::Scc,
+    dry_run: DryRunStrategy::with_flags(&["--simulate"]),
     ..Strategy::default()
 });
 
+static STRAT_REINSTALL: Lazy<Strategy> = Lazy::new(|| Strategy {
+    needed: NeededStrategy::with_flags(&["--reinstall"]),
+    ..STRAT_INSTALL.clone()
+});
+
 impl Apt {
     #[must_use]
     #[allow(missing_docs)]
     pub(crate) fn new(cfg: Config) -> Self {
         Apt { cfg }
     }
+
+    /// Makes the command running `apt` with `args`, or rather `apt-get` or
+    /// `apt-cache` in a batch, as the CLI of `apt` is not stable.
+    #[must_use]
+    fn apt<'a>(&self, args: &[&'a str]) -> Vec<&'a str> {
+        let exe = match args.first() {
+            _ if !self.is_batch() => "apt",
+            Some(&("depends" | "rdepends" | "search" | "show")) => "apt-cache",
+            // `apt-get` has no `list`.
+            Some(&"list") => "apt",
+            _ => "apt-get",
+        };
+        iter::once(exe).chain(args.iter().copied()).collect()
+    }
+
+    /// Upgrades all the outdated packages.
+    async fn upgrade(&self, flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&self.apt(&["upgrade"]))
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_PROMPT))
+            .await?;
+        Cmd::with_sudo(&self.apt(&["dist-upgrade"]))
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_INSTALL))
+            .await
+    }
+}
+
+/// Parses a size printed by `apt-get`, eg. `1,234 kB`, into bytes.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn parse_size(size: &str) -> Option<u64> {
+    let (num, unit) = size.trim().split_once(' ')?;
+    let scale = match unit {
+        "B" => 1e0,
+        "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        _ => return None,
+    };
+    let num: f64 = num.replace(',', "").parse().ok()?;
+    Some((num * scale) as u64)
+}
+
+/// Parses the summary printed by `apt-get` before asking for confirmation
+/// into the sizes of the archives to be downloaded and of the additional disk
+/// space to be used, in bytes.
+fn parse_summary(out: &str) -> (u64, u64) {
+    let mut sizes = (0, 0);
+    for ln in out.lines() {
+        // Looks like `Need to get 1,234 kB/5,678 kB of archives.` when some of
+        // the archives are already downloaded.
+        if let Some(size) = ln
+            .strip_prefix("Need to get ")
+            .and_then(|ln| ln.split_once(" of archives"))
+            .and_then(|(size, _)| parse_size(size.split('/').next()?))
+        {
+            sizes.0 = size;
+        }
+        if let Some(size) = ln
+            .strip_prefix("After this operation, ")
+            .and_then(|ln| ln.strip_suffix(" of additional disk space will be used."))
+            .and_then(parse_size)
+        {
+            sizes.1 = size;
+        }
+    }
+    sizes
 }
 
+/// Parses a line of `debsecan`, which looks like `<id> <package> (<flags>)`,
+/// eg. `CVE-2023-38545 curl (remotely exploitable, high urgency)`.
+#[must_use]
+fn parse_advisory(ln: &str) -> Option<Advisory> {
+    let mut words = ln.trim().splitn(3, ' ');
+    let (id, pkg) = (words.next()?, words.next()?);
+    let flags = words.next().unwrap_or_default();
+    let flags = flags.trim_matches(['(', ')'].as_ref()).split(", ").collect_vec();
+    Some(Advisory {
+        id: id.into(),
+        pkg: pkg.into(),
+        severity: flags
+            .iter()
+            .find_map(|flag| flag.strip_suffix(" urgency"))
+            .unwrap_or_default()
+            .into(),
+        fixed: flags.contains(&"fixed"),
+    })
+}
+
+/// The operations supported by [`Apt`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "f", "fy", "q", "qc", "qd", "qdt", "qe", "qi", "qk", "ql", "qo", "qp", "qu", "r", "rc", "rcs", "rn",
+    "rns", "rs", "s", "sc", "scc", "sg", "si", "sii", "ss", "su", "suy", "sw", "sy", "t", "u",
+];
+
 #[async_trait]
-impl Pm for Apt {
+impl PmBase for Apt {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "apt"
@@ -52,18 +174,332 @@ impl Pm for Apt {
         &self.cfg
     }
 
+    fn unsupported_hint(&self, op: &str) -> Option<&str> {
+        match op {
+            "qm" => Some("apt list '?obsolete'"),
+            "qs" => Some("dpkg -l"),
+            _ => None,
+        }
+    }
+
+    fn version_scheme(&self) -> Scheme {
+        Scheme::Dpkg
+    }
+
+    fn supports_ignore(&self) -> bool {
+        true
+    }
+
+    fn supports_download_dir(&self) -> bool {
+        true
+    }
+
+    fn supports_root(&self) -> bool {
+        true
+    }
+
+    fn root_flags(&self, exe: &str, root: &str) -> Vec<String> {
+        if exe.starts_with("dpkg") {
+            vec![format!("--root={root}")]
+        } else {
+            // `dpkg` is run by `apt-get` on the same system.
+            vec![
+                "-o".into(),
+                format!("Dir={root}"),
+                "-o".into(),
+                format!("DPkg::Options::=--root={root}"),
+            ]
+        }
+    }
+
+    async fn sw_files(&self, kws: &[&str], flags: &[&str]) -> Result<Vec<PkgFile>> {
+        let cmd = Cmd::new(&["apt-get", "install", "--print-uris", "-qq"])
+            .kws(kws)
+            .flags(flags);
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        Ok(String::from_utf8(out)?
+            .lines()
+            .filter_map(parse_print_uri)
+            .collect())
+    }
+
+    async fn search(&self, kws: &[&str], flags: &[&str]) -> Result<Vec<SearchHit>> {
+        // Each line looks like `<name> - <description>`.
+        let cmd = Cmd::new(&["apt-cache", "search"]).kws(kws).flags(flags);
+        self.search_lines(cmd, |ln| {
+            let (name, desc) = ln.split_once(" - ")?;
+            Some(SearchHit {
+                name: name.into(),
+                desc: desc.into(),
+                ..SearchHit::default()
+            })
+        })
+        .await
+    }
+
+    async fn upgradable(&self) -> Result<Vec<String>> {
+        // Each line looks like `<name>/<suite> <version> <arch> [upgradable from: <version>]`.
+        self.names_from(Cmd::new(&self.apt(&["list", "--upgradable"])), |ln| {
+            ln.contains("[upgradable from")
+                .then(|| ln.split_once('/'))
+                .flatten()
+                .map(|(name, _)| name)
+        })
+        .await
+    }
+
+    async fn su_only(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&self.apt(&["install", "--only-upgrade"]))
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_INSTALL))
+            .await
+    }
+
+    async fn disk_usage(&self, kws: &[&str], flags: &[&str]) -> Result<Vec<(PathBuf, u64)>> {
+        // `apt-get` prints a summary before asking for confirmation, which is
+        // declined here. No lock is needed for that.
+        let cmd = Cmd::new(&[
+            "apt-get",
+            "--assume-no",
+            "-o",
+            "Debug::NoLocking=1",
+            if kws.is_empty() {
+                "dist-upgrade"
+            } else {
+                "install"
+            },
+        ])
+        .kws(kws)
+        .flags(flags);
+        let out = match self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await
+        {
+            Ok(out) | Err(Error::CmdStatusCodeError { output: out, .. }) => out,
+            Err(e) => return Err(e),
+        };
+        let (download, install) = parse_summary(&String::from_utf8(out)?);
+        Ok(vec![
+            ("/var/cache/apt/archives".into(), download),
+            ("/usr".into(), install),
+        ])
+    }
+
+    async fn explicit(&self) -> Result<Vec<String>> {
+        self.names_from(Cmd::new(&["apt-mark", "showmanual"]), |ln| Some(ln.trim()))
+            .await
+    }
+
+    async fn installed(&self) -> Result<Vec<(String, String)>> {
+        // Each line looks like `<status>\t<name>\t<version>`, where packages
+        // which are removed but not purged are also listed.
+        let cmd = Cmd::new(&["dpkg-query", "-W", "-f=${Status}\t${Package}\t${Version}\n"]);
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?
+            .pipe(String::from_utf8)?;
+        Ok(out
+            .lines()
+            .filter_map(|ln| {
+                let mut fields = ln.split('\t');
+                (fields.next()? == "install ok installed").then_some(())?;
+                Some((fields.next()?.into(), fields.next()?.into()))
+            })
+            .collect())
+    }
+
+    async fn deps(&self, pkg: &str, flags: &[&str]) -> Result<Vec<String>> {
+        // Each dependency looks like `  Depends: <name>`, where the alternatives
+        // are marked with `|`, and the virtual packages look like `<name>`.
+        let cmd = Cmd::new(&["apt-cache", "depends", "--important"])
+            .kws(&[pkg])
+            .flags(flags);
+        let deps = self
+            .names_from(cmd, |ln| {
+                let ln = ln.trim().trim_start_matches('|');
+                let dep = ln
+                    .strip_prefix("Depends: ")
+                    .or_else(|| ln.strip_prefix("PreDepends: "))?;
+                (!dep.starts_with('<')).then_some(dep)
+            })
+            .await?;
+        Ok(deps.into_iter().unique().collect())
+    }
+
+    async fn info(&self, kws: &[&str], flags: &[&str], installed: bool) -> Result<Vec<PkgInfo>> {
+        let cmd = if installed {
+            Cmd::new(&["dpkg-query", "--status"])
+        } else {
+            Cmd::new(&["apt-cache", "show", "--no-all-versions"])
+        };
+        self.info_from(cmd.kws(kws).flags(flags), |fields| {
+            let get = |key| fields.get(key).cloned().unwrap_or_default();
+            Some(PkgInfo {
+                name: fields.get("Package")?.clone(),
+                version: get("Version"),
+                // The first line of the description is the short one.
+                desc: get("Description").lines().next().unwrap_or_default().into(),
+                url: get("Homepage"),
+                depends: fields
+                    .get("Depends")
+                    .map(|deps| deps.split(", ").map(String::from).collect())
+                    .unwrap_or_default(),
+                installed_size: fields
+                    .get("Installed-Size")
+                    .map(|kib| format!("{kib} KiB"))
+                    .unwrap_or_default(),
+                ..PkgInfo::default()
+            })
+        })
+        .await
+    }
+
+    async fn install_dates(&self, _flags: &[&str]) -> Result<Vec<(String, SystemTime)>> {
+        // `dpkg` writes the list of files of each package to
+        // `<name>.list` when it is installed (or upgraded).
+        let dates = fs::read_dir("/var/lib/dpkg/info")?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let name = path.file_name()?.to_str()?.strip_suffix(".list")?;
+                let time = fs::metadata(&path).ok()?.modified().ok()?;
+                Some((name.to_owned(), time))
+            })
+            .collect();
+        Ok(dates)
+    }
+
+    async fn pin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        if kws.is_empty() {
+            return self
+                .run(Cmd::new(&["apt-mark", "showhold"]).flags(flags))
+                .await;
+        }
+        self.run(Cmd::with_sudo(&["apt-mark", "hold"]).kws(kws).flags(flags))
+            .await
+    }
+
+    async fn unpin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::with_sudo(&["apt-mark", "unhold"]).kws(kws).flags(flags))
+            .await
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        (op == "=").then(|| Versioned {
+            kw: format!("{name}={ver}"),
+            ..Versioned::default()
+        })
+    }
+
+    async fn sizes(&self, flags: &[&str]) -> Result<Vec<(String, u64)>> {
+        // Each line looks like `<status>\t<name>\t<size in KiB>`, where packages
+        // which are removed but not purged are also listed.
+        let cmd = Cmd::new(&[
+            "dpkg-query",
+            "-W",
+            "-f=${Status}\t${Package}\t${Installed-Size}\n",
+        ])
+        .flags(flags);
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?
+            .pipe(String::from_utf8)?;
+        Ok(out
+            .lines()
+            .filter_map(|ln| {
+                let mut fields = ln.split('\t');
+                (fields.next()? == "install ok installed").then_some(())?;
+                let name = fields.next()?;
+                let kib: u64 = fields.next()?.parse().ok()?;
+                Some((name.into(), kib * 1024))
+            })
+            .collect())
+    }
+
+    async fn advisories(&self, flags: &[&str]) -> Result<Vec<Advisory>> {
+        let out = self
+            .check_output(
+                Cmd::new(&["debsecan"]).flags(flags),
+                PmMode::Mute,
+                &Strategy::default(),
+            )
+            .await?
+            .pipe(String::from_utf8)?;
+        // `debsecan` only knows about the fixes released for the suite given
+        // with `--suite`, so the upgrades available are checked as well.
+        let upgradable = self.upgradable().await?;
+        Ok(out
+            .lines()
+            .filter_map(parse_advisory)
+            .map(|adv| Advisory {
+                fixed: adv.fixed || upgradable.contains(&adv.pkg),
+                ..adv
+            })
+            .collect())
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        Ok(vec!["/var/cache/apt/archives".into()])
+    }
+}
+
+#[async_trait]
+impl PmQuery for Apt {
+    /// F queries the package which provides FILE from the sync databases, even
+    /// if it is not installed.
+    async fn f(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["apt-file", "search"]).kws(kws).flags(flags))
+            .await
+    }
+
+    /// Fy refreshes the file database, then queries the package which provides
+    /// FILE.
+    async fn fy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::with_sudo(&["apt-file", "update"]).flags(flags))
+            .await?;
+        self.f(kws, flags).await
+    }
+
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["apt", "list"]).kws(kws).flags(flags))
+        self.run(Cmd::new(&self.apt(&["list"])).kws(kws).flags(flags))
             .await
     }
 
     /// Qc shows the changelog of a package.
     async fn qc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["apt", "changelog"]).kws(kws).flags(flags))
+        self.run(Cmd::new(&self.apt(&["changelog"])).kws(kws).flags(flags))
             .await
     }
 
+    /// Qd lists packages installed as dependencies (not explicitly).
+    async fn qd(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["apt-mark", "showauto"]).kws(kws).flags(flags))
+            .await
+    }
+
+    /// Qdt lists packages installed as dependencies but no longer required by
+    /// any installed package, aka orphans.
+    async fn qdt(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // The packages to be removed are shown as `Remv <name> [<version>]`.
+        let cmd = Cmd::new(&["apt-get", "autoremove", "--dry-run"])
+            .kws(kws)
+            .flags(flags);
+        if !self.cfg.dry_run {
+            print::print_cmd(&cmd, PROMPT_RUN);
+        }
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        String::from_utf8(out)?
+            .lines()
+            .filter_map(|ln| ln.strip_prefix("Remv ")?.split_whitespace().next())
+            .for_each(|pkg| println!("{pkg}"));
+        Ok(())
+    }
+
     /// Qe lists packages installed explicitly (not as dependencies).
     async fn qe(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.run(Cmd::new(&["apt-mark", "showmanual"]).kws(kws).flags(flags))
@@ -76,6 +512,19 @@ impl Pm for Apt {
             .await
     }
 
+    /// Qk verifies one or more packages.
+    async fn qk(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // Only the files which fail the check are reported.
+        self.run(Cmd::new(&["debsums", "--silent"]).kws(kws).flags(flags))
+            .await
+    }
+
+    /// Ql displays files provided by local package.
+    async fn ql(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["dpkg", "-L"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Qo queries the package which provides FILE.
     async fn qo(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.run(Cmd::new(&["dpkg-query", "-S"]).kws(kws).flags(flags))
@@ -91,29 +540,57 @@ impl Pm for Apt {
 
     /// Qu lists packages which have an update available.
     async fn qu(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["apt", "upgrade", "--trivial-only"])
+        Cmd::with_sudo(&self.apt(&["upgrade", "--trivial-only"]))
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run(cmd))
             .await
     }
 
+    /// T checks if the given dependencies are satisfied, printing the unsatisfied ones.
+    async fn t(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.deptest(kws, Cmd::new(&["dpkg", "-s"]).flags(flags), |out| {
+            // Removed packages might still have their configuration files left.
+            if !out.contains("Status: install ok installed") {
+                return None;
+            }
+            out.lines().find_map(|ln| ln.strip_prefix("Version: "))
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl PmRemove for Apt {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["apt", "remove"])
+        Cmd::with_sudo(&self.apt(&["remove"]))
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_PROMPT))
             .await
     }
 
+    /// Rc removes a package and all the packages that depend on it.
+    async fn rc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `apt` removes the packages depending on the targets anyway.
+        self.r(kws, flags).await
+    }
+
+    /// Rcs removes a package and all the packages that depend on it,
+    /// along with their dependencies which are not required by any other
+    /// installed package.
+    async fn rcs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.rs(kws, flags).await
+    }
+
     /// Rn removes a package and skips the generation of configuration backup
     /// files.
     async fn rn(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["apt", "purge"])
+        Cmd::with_sudo(&self.apt(&["purge"]))
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_PROMPT))
             .await
     }
 
@@ -121,40 +598,39 @@ impl Pm for Apt {
     /// other installed package, and skips the generation of configuration
     /// backup files.
     async fn rns(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["apt", "autoremove", "--purge"])
+        Cmd::with_sudo(&self.apt(&["autoremove", "--purge"]))
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_PROMPT))
             .await
     }
 
     /// Rs removes a package and its dependencies which are not required by any
     /// other installed package, and not explicitly installed by the user.
     async fn rs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["apt", "autoremove"])
+        Cmd::with_sudo(&self.apt(&["autoremove"]))
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_PROMPT))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Apt {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(if self.cfg.needed {
-            &["apt", "install"]
-        } else {
-            &["apt", "install", "--reinstall"]
-        })
-        .kws(kws)
-        .flags(flags)
-        .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-        .await
+        Cmd::with_sudo(&self.apt(&["install"]))
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_REINSTALL))
+            .await
     }
 
     /// Sc removes all the cached packages that are not currently installed, and
     /// the unused sync database.
     async fn sc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["apt", "clean"])
+        Cmd::with_sudo(&self.apt(&["clean"]))
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
@@ -163,14 +639,15 @@ impl Pm for Apt {
 
     /// Scc removes all files from the cache.
     async fn scc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["apt", "autoclean"])
+        Cmd::with_sudo(&self.apt(&["autoclean"]))
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
             .await
     }
 
-    /// Sg lists all packages belonging to the GROUP.
+    /// Sg lists all packages belonging to the GROUP, or all the groups if no
+    /// GROUP is given.
     async fn sg(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(if kws.is_empty() {
             &["tasksel", "--list-task"]
@@ -185,64 +662,187 @@ impl Pm for Apt {
 
     /// Si displays remote package information: name, version, description, etc.
     async fn si(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["apt", "show"]).kws(kws).flags(flags))
+        self.run(Cmd::new(&self.apt(&["show"])).kws(kws).flags(flags))
             .await
     }
 
     /// Sii displays packages which require X to be installed, aka reverse
     /// dependencies.
     async fn sii(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["apt", "rdepends"]).kws(kws).flags(flags))
+        self.run(Cmd::new(&self.apt(&["rdepends"])).kws(kws).flags(flags))
             .await
     }
 
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["apt", "search"]).kws(kws).flags(flags))
+        self.run(Cmd::new(&self.apt(&["search"])).kws(kws).flags(flags))
             .await
     }
 
     /// Su updates outdated packages.
     async fn su(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        if kws.is_empty() {
-            Cmd::with_sudo(&["apt", "upgrade"])
-                .flags(flags)
-                .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+        if !kws.is_empty() {
+            return self.s(kws, flags).await;
+        }
+        let held = self
+            .hold_ignored(
+                Cmd::new(&["apt-mark", "showhold"]),
+                Cmd::with_sudo(&["apt-mark", "hold"]),
+                |ln| Some(ln.trim()),
+            )
+            .await?;
+        let res = self.upgrade(flags).await;
+        if !held.is_empty() {
+            self.run(Cmd::with_sudo(&["apt-mark", "unhold"]).kws(&held))
                 .await?;
-            Cmd::with_sudo(&["apt", "dist-upgrade"])
-                .flags(flags)
-                .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-                .await
-        } else {
-            self.s(kws, flags).await
         }
+        res
     }
 
     /// Suy refreshes the local package database, then updates outdated
     /// packages.
     async fn suy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let plan = Plan::new(STEPS_SUY);
+        plan.step(0);
         self.sy(kws, flags).await?;
+        plan.step(1);
         self.su(kws, flags).await
     }
 
     /// Sw retrieves all packages from the server, but does not install/upgrade
     /// anything.
     async fn sw(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["apt", "install", "--download-only"])
+        let mut cmd = Cmd::with_sudo(&self.apt(&["install", "--download-only"]))
             .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-            .await
+            .flags(flags);
+        if let Some(dir) = self.download_dir()? {
+            // `apt` expects the `partial` directory to exist beforehand.
+            if !self.cfg.dry_run {
+                fs::create_dir_all(Path::new(&dir).join("partial"))?;
+            }
+            cmd.flags
+                .extend(["-o".into(), format!("Dir::Cache::Archives={dir}")]);
+        }
+        self.run_with(cmd, MODE_STATUS_FD, &STRAT_INSTALL).await
     }
 
     /// Sy refreshes the local package database.
     async fn sy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::with_sudo(&["apt", "update"]).kws(kws).flags(flags))
+        self.run(Cmd::with_sudo(&self.apt(&["update"])).kws(kws).flags(flags))
             .await?;
         if !kws.is_empty() {
             self.s(kws, flags).await?;
         }
         Ok(())
     }
+
+    /// U upgrades or adds package(s) to the system and installs the required
+    /// dependencies from sync repositories.
+    async fn u(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `apt` only treats the keywords as package files if they look like paths,
+        // then installs their dependencies as `dpkg -i` and `apt -f install` would.
+        let kws = kws.iter().map(|kw| local_path(kw)).collect_vec();
+        Cmd::with_sudo(&self.apt(&["install"]))
+            .kws(&kws)
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, MODE_STATUS_FD, &STRAT_INSTALL))
+            .await
+    }
+}
+
+/// Parses a line of `apt-get install --print-uris`, which looks like
+/// `'<url>' <name>_<version>_<arch>.deb <size> <hash>`, where `:` in
+/// `<version>` is escaped as `%3a`.
+///
+/// The repository of the file is the component of the archive in its URL,
+/// eg. `main` in `.../debian/pool/main/c/curl/...`, as `apt` has no
+/// repository names like those of `pacman`.
+#[must_use]
+fn parse_print_uri(ln: &str) -> Option<PkgFile> {
+    let mut fields = ln.split_whitespace();
+    let url = fields.next()?.trim_matches('\'');
+    let file = fields.next()?;
+    let mut parts = file.strip_suffix(".deb")?.split('_');
+    let repo = url
+        .split_once("/pool/")
+        .and_then(|(_, path)| path.split('/').next())
+        .unwrap_or_default();
+    Some(PkgFile {
+        name: parts.next()?.into(),
+        version: parts.next()?.replace("%3a", ":"),
+        repo: repo.into(),
+        url: url.into(),
+        path: Path::new("/var/cache/apt/archives").join(file),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary() {
+        let out = indoc! {"
+            The following packages will be upgraded:
+              curl libcurl4
+            2 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.
+            Need to get 1,234 kB/5,678 kB of archives.
+            After this operation, 12.5 MB of additional disk space will be used.
+            Do you want to continue? [Y/n] N
+            Abort.
+        "};
+        assert_eq!(parse_summary(out), (1_234_000, 12_500_000));
+        assert_eq!(parse_summary("After this operation, 0 B disk space will be freed."), (0, 0));
+        assert_eq!(parse_size("0 B"), Some(0));
+        assert_eq!(parse_size("2 TiB"), None);
+    }
+
+    #[test]
+    fn print_uris() {
+        let file = parse_print_uri(
+            "'http://deb.debian.org/debian/pool/main/c/curl/curl_7.88.1-10%3a1_amd64.deb' \
+             curl_7.88.1-10%3a1_amd64.deb 315048 SHA256:0123",
+        )
+        .unwrap();
+        assert_eq!(file.name, "curl");
+        assert_eq!(file.version, "7.88.1-10:1");
+        assert_eq!(file.repo, "main");
+        assert_eq!(
+            file.path,
+            Path::new("/var/cache/apt/archives/curl_7.88.1-10%3a1_amd64.deb")
+        );
+        let file = parse_print_uri("'file:/tmp/curl_8.5.0_amd64.deb' curl_8.5.0_amd64.deb 1 MD5:0");
+        assert_eq!(file.unwrap().repo, "");
+    }
+
+    #[test]
+    fn advisories() {
+        let adv = parse_advisory("CVE-2023-38545 curl (fixed, remotely exploitable, high urgency)")
+            .unwrap();
+        assert_eq!((adv.pkg.as_str(), adv.severity.as_str(), adv.fixed), ("curl", "high", true));
+        let adv = parse_advisory("CVE-2023-4039 gcc-12").unwrap();
+        assert_eq!((adv.pkg.as_str(), adv.severity.as_str(), adv.fixed), ("gcc-12", "", false));
+    }
+
+    #[test]
+    fn batch_cmds() {
+        let apt = Apt::new(Config {
+            no_confirm: true,
+            ..Config::default()
+        });
+        assert_eq!(apt.apt(&["install", "--reinstall"]), ["apt-get", "install", "--reinstall"]);
+        assert_eq!(apt.apt(&["show"]), ["apt-cache", "show"]);
+        assert_eq!(apt.apt(&["list"]), ["apt", "list"]);
+    }
+
+    #[test]
+    fn root_flags() {
+        let apt = Apt::new(Config::default());
+        assert_eq!(
+            apt.root_flags("apt-get", "/mnt"),
+            ["-o", "Dir=/mnt", "-o", "DPkg::Options::=--root=/mnt"]
+        );
+        assert_eq!(apt.root_flags("dpkg-query", "/mnt"), ["--root=/mnt"]);
+    }
 }
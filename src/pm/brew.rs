@@ -1,16 +1,35 @@
 #![doc = docs_self!()]
 
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
 use async_trait::async_trait;
+use futures::prelude::*;
 use indoc::indoc;
+use itertools::{chain, Itertools};
 use once_cell::sync::Lazy;
+use regex::RegexBuilder;
+use serde::Deserialize;
 use tap::prelude::*;
 
-use super::{DryRunStrategy, NoCacheStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    dir_size, DryRunStrategy, NeededStrategy, NoCacheStrategy, PmBase, PmHelper, PmMode, PmQuery,
+    PmRemove, PmSync, PromptStrategy, SearchHit, Strategy, Versioned,
+};
 use crate::{
     dispatch::Config,
-    error::Result,
-    exec::{self, Cmd},
-    print::{self, PROMPT_RUN},
+    error::{Error, Result},
+    exec::{
+        self, pager,
+        plan::{Plan, STEPS_SUY},
+        Cmd,
+    },
+    print::{self, PROMPT_RUN, PROMPT_WARN},
+    state::State,
+    version::Version,
 };
 
 macro_rules! docs_self {
@@ -38,7 +57,277 @@ static STRAT_INSTALL: Lazy<Strategy> = Lazy::new(|| Strategy {
     ..Strategy::default()
 });
 
+// If the package is not installed, `brew reinstall` behaves just like `brew
+// install`, so `brew reinstall` matches perfectly the behavior of `pacman -S`.
+static STRAT_REINSTALL: Lazy<Strategy> = Lazy::new(|| Strategy {
+    needed: NeededStrategy::with_cmd(&["brew", "reinstall"]),
+    ..STRAT_INSTALL.clone()
+});
+
+/// The base URL of the Homebrew JSON API.
+const API_URL: &str = "https://formulae.brew.sh/api";
+
+/// How long a cached JSON API response is considered fresh.
+const API_TTL: Duration = Duration::from_hours(24);
+
+/// The output of `brew info --json=v2`.
+#[derive(Debug, Default, Deserialize)]
+struct InfoV2 {
+    #[serde(default)]
+    formulae: Vec<Formula>,
+    #[serde(default)]
+    casks: Vec<Cask>,
+}
+
+/// A formula, as described by `brew info --json=v2` and the JSON API.
+#[derive(Debug, Deserialize)]
+struct Formula {
+    name: String,
+    tap: Option<String>,
+    desc: Option<String>,
+    homepage: Option<String>,
+    license: Option<String>,
+    versions: FormulaVersions,
+    #[serde(default)]
+    revision: u32,
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default)]
+    installed: Vec<FormulaInstalled>,
+    #[serde(default)]
+    outdated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormulaVersions {
+    stable: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormulaInstalled {
+    version: String,
+    /// The installation date in seconds since epoch.
+    time: Option<u64>,
+}
+
+/// A cask, as described by `brew info --json=v2` and the JSON API.
+#[derive(Debug, Deserialize)]
+struct Cask {
+    token: String,
+    tap: Option<String>,
+    desc: Option<String>,
+    homepage: Option<String>,
+    version: Option<String>,
+    installed: Option<String>,
+    /// The installation date in seconds since epoch.
+    installed_time: Option<u64>,
+    #[serde(default)]
+    outdated: bool,
+}
+
+/// A normalized view of a [`Formula`] or a [`Cask`].
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    repo: String,
+    version: String,
+    desc: String,
+    url: String,
+    license: String,
+    depends: Vec<String>,
+    installed: Option<String>,
+    installed_time: Option<SystemTime>,
+    outdated: bool,
+}
+
+/// Converts seconds since epoch to a [`SystemTime`].
+fn from_epoch(secs: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+impl From<Formula> for Entry {
+    fn from(f: Formula) -> Self {
+        let stable = f.versions.stable.unwrap_or_default();
+        Entry {
+            version: match f.revision {
+                0 => stable,
+                rev => format!("{stable}_{rev}"),
+            },
+            repo: f.tap.unwrap_or_else(|| "homebrew/core".into()),
+            desc: f.desc.unwrap_or_default(),
+            url: f.homepage.unwrap_or_default(),
+            license: f.license.unwrap_or_default(),
+            depends: f.dependencies,
+            installed_time: f.installed.last().and_then(|i| i.time).map(from_epoch),
+            installed: f.installed.into_iter().map(|i| i.version).next_back(),
+            outdated: f.outdated,
+            name: f.name,
+        }
+    }
+}
+
+impl From<Cask> for Entry {
+    fn from(c: Cask) -> Self {
+        Entry {
+            name: c.token,
+            repo: c.tap.unwrap_or_else(|| "homebrew/cask".into()),
+            version: c.version.unwrap_or_default(),
+            desc: c.desc.unwrap_or_default(),
+            url: c.homepage.unwrap_or_default(),
+            license: String::new(),
+            depends: vec![],
+            installed: c.installed,
+            installed_time: c.installed_time.map(from_epoch),
+            outdated: c.outdated,
+        }
+    }
+}
+
+impl Entry {
+    /// Checks if the installed version is older than the latest one.
+    fn is_outdated(&self) -> bool {
+        self.outdated
+            || matches!(&self.installed, Some(v) if Version::parse(v) < Version::parse(&self.version))
+    }
+
+    /// Prints the [`Entry`] in the style of `pacman -Si`.
+    fn print_info(&self) {
+        let or_none = |s: &str| if s.is_empty() { "None".into() } else { s.to_owned() };
+        print::print_field("Repository", &self.repo);
+        print::print_field("Name", &self.name);
+        print::print_field("Version", &self.version);
+        print::print_field("Description", &or_none(&self.desc));
+        print::print_field("URL", &or_none(&self.url));
+        print::print_field("Licenses", &or_none(&self.license));
+        print::print_field("Depends On", &or_none(&self.depends.join("  ")));
+        print::print_field(
+            "Installed",
+            self.installed.as_deref().unwrap_or("No"),
+        );
+        pager::print("");
+    }
+
+    /// Prints the [`Entry`] in the style of `pacman -Ss`.
+    fn print_search(&self) {
+        let installed = self
+            .installed
+            .as_ref()
+            .map_or_else(String::new, |v| format!(" [installed: {v}]"));
+        pager::print(&format!(
+            "{}/{} {}{installed}",
+            self.repo, self.name, self.version
+        ));
+        pager::print(&format!("    {}", self.desc));
+    }
+}
+
 impl Brew {
+    /// Runs `brew info --json=v2` with the given arguments and parses the
+    /// result.
+    ///
+    /// Returns `None` in a dry run.
+    async fn info_json(&self, args: &[&str], kws: &[&str], flags: &[&str]) -> Result<Option<Vec<Entry>>> {
+        let cmd = Cmd::new(&["brew", "info", "--json=v2"]).kws(&[args, kws].concat()).flags(flags);
+        if !self.cfg.dry_run {
+            print::print_cmd(&cmd, PROMPT_RUN);
+        }
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        if self.cfg.dry_run {
+            return Ok(None);
+        }
+        let info: InfoV2 = serde_json::from_slice(&out)
+            .map_err(|e| Error::OtherError(format!("Failed to parse `brew` output: {e}")))?;
+        Ok(Some(
+            info.formulae
+                .into_iter()
+                .map(Entry::from)
+                .chain(info.casks.into_iter().map(Entry::from))
+                .collect(),
+        ))
+    }
+
+    /// Gets the list of all formulae (or casks, if `kind` is `"cask"`) from
+    /// the Homebrew JSON API, using a cached copy if it is fresh enough.
+    ///
+    /// Returns `None` if the list cannot be fetched.
+    async fn api_list(&self, kind: &str) -> Option<Vec<Entry>> {
+        fn parse(kind: &str, bytes: &[u8]) -> Option<Vec<Entry>> {
+            let entries = if kind == "cask" {
+                serde_json::from_slice::<Vec<Cask>>(bytes)
+                    .ok()?
+                    .into_iter()
+                    .map(Entry::from)
+                    .collect()
+            } else {
+                serde_json::from_slice::<Vec<Formula>>(bytes)
+                    .ok()?
+                    .into_iter()
+                    .map(Entry::from)
+                    .collect()
+            };
+            Some(entries)
+        }
+
+        let key = format!("brew/{kind}.json");
+        let state = State::open().ok();
+        if let Some(bytes) = state
+            .as_ref()
+            .and_then(|state| state.cached(&key, API_TTL).ok().flatten())
+        {
+            return parse(kind, &bytes);
+        }
+        let url = format!("{API_URL}/{kind}.json");
+        let cmd = Cmd::new(&["curl", "-fsSL", &url]);
+        let bytes = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await
+            .ok()?;
+        // Only a valid response is cached, which also excludes the empty one
+        // of a dry run.
+        let entries = parse(kind, &bytes)?;
+        if let Some(state) = state {
+            state.cache(&key, &bytes).ok();
+        }
+        Some(entries)
+    }
+
+    /// Searches the (cached) JSON API for the entries whose names or
+    /// descriptions match all the `kws`, since `brew search` is slow.
+    ///
+    /// Returns `None` if `brew search` should be used instead.
+    async fn search_entries(&self, kws: &[&str], flags: &[&str]) -> Result<Option<Vec<Entry>>> {
+        if self.cfg.dry_run || !flags.is_empty() {
+            return Ok(None);
+        }
+        let Some(mut entries) = self.api_list("formula").await else {
+            return Ok(None);
+        };
+        if cfg!(target_os = "macos") {
+            if let Some(casks) = self.api_list("cask").await {
+                entries.extend(casks);
+            }
+        }
+
+        let patterns: Vec<_> = kws
+            .iter()
+            .map(|pat| {
+                RegexBuilder::new(pat)
+                    .case_insensitive(true)
+                    .unicode(false)
+                    .build()
+                    .map_err(|_e| Error::OtherError(format!("Pattern `{pat}` is ill-formed")))
+            })
+            .try_collect()?;
+        entries.retain(|entry| {
+            patterns
+                .iter()
+                .all(|pat| pat.is_match(&entry.name) || pat.is_match(&entry.desc))
+        });
+        Ok(Some(entries))
+    }
+
     async fn search_regex(&self, cmd: &[&str], kws: &[&str], flags: &[&str]) -> Result<()> {
         let cmd = Cmd::new(cmd).flags(flags);
         if !self.cfg.dry_run {
@@ -47,20 +336,66 @@ impl Brew {
         let out_bytes = self
             .check_output(cmd, PmMode::Mute, &Strategy::default())
             .await?;
-        exec::grep_print(&String::from_utf8(out_bytes)?, kws)
+        exec::grep_print(&String::from_utf8(out_bytes)?, kws, self.cfg.exact)
     }
 }
 
+/// The output of `brew tap-info --json`.
+#[derive(Debug, Deserialize)]
+struct TapInfo {
+    name: String,
+    #[serde(default)]
+    formula_names: Vec<String>,
+    #[serde(default)]
+    cask_tokens: Vec<String>,
+}
+
+/// Checks if `kw` names a tap, eg. `homebrew/cask-fonts`, rather than a
+/// formula or a cask, eg. `curl` or `homebrew/cask-fonts/font-fira-code`.
+fn is_tap(kw: &str) -> bool {
+    kw.split('/').filter(|part| !part.is_empty()).count() == 2
+}
+
 impl Brew {
     #[must_use]
     #[allow(missing_docs)]
     pub(crate) fn new(cfg: Config) -> Self {
         Brew { cfg }
     }
+
+    /// Adds `--cask` to `flags` when casks are requested with `--cask`, or
+    /// when they are preferred to formulae with `brew_prefer_cask` and all of
+    /// `kws` are known casks. Otherwise, eg. for `-Su` alone, `brew` deals
+    /// with both formulae and casks.
+    async fn cask_flags<'f>(&self, kws: &[&str], flags: &[&'f str]) -> Vec<&'f str> {
+        let mut flags = flags.to_vec();
+        if flags.contains(&"--cask") {
+            return flags;
+        }
+        let is_cask = |kw| async move {
+            let cmd = Cmd::new(&["brew", "info", "--cask", kw]);
+            self.check_output(cmd, PmMode::Mute, &Strategy::default())
+                .await
+                .is_ok()
+        };
+        let prefer = self.cfg.brew_prefer_cask
+            && !kws.is_empty()
+            && stream::iter(kws).all(|&kw| is_cask(kw)).await;
+        if self.cfg.cask || prefer {
+            flags.push("--cask");
+        }
+        flags
+    }
 }
 
+/// The operations supported by [`Brew`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "f", "fy", "q", "qc", "qdt", "qi", "qk", "ql", "qs", "qt", "qu", "r", "rc", "rcs", "rs", "s",
+    "sc", "scc", "si", "sii", "sl", "ss", "su", "suy", "sw", "sy", "t",
+];
+
 #[async_trait]
-impl Pm for Brew {
+impl PmBase for Brew {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "brew"
@@ -70,6 +405,168 @@ impl Pm for Brew {
         &self.cfg
     }
 
+    fn unsupported_hint(&self, op: &str) -> Option<&str> {
+        match op {
+            "qd" => Some("brew leaves --installed-as-dependency"),
+            "qe" => Some("brew leaves --installed-on-request"),
+            _ => None,
+        }
+    }
+
+    fn supports_ignore(&self) -> bool {
+        true
+    }
+
+    fn supports_download_dir(&self) -> bool {
+        true
+    }
+
+    async fn deps(&self, pkg: &str, flags: &[&str]) -> Result<Vec<String>> {
+        let cmd = Cmd::new(&["brew", "deps", "--1"]).kws(&[pkg]).flags(flags);
+        self.names_from(cmd, |ln| Some(ln.trim()).filter(|name| !name.is_empty()))
+            .await
+    }
+
+    async fn search(&self, kws: &[&str], flags: &[&str]) -> Result<Vec<SearchHit>> {
+        if let Some(entries) = self.search_entries(kws, flags).await? {
+            return Ok(entries
+                .into_iter()
+                .map(|entry| SearchHit {
+                    name: entry.name,
+                    repo: entry.repo,
+                    version: entry.version,
+                    desc: entry.desc,
+                })
+                .collect());
+        }
+        // `brew search` only prints the names, under headers like `==> Formulae`.
+        let cmd = Cmd::new(&["brew", "search"]).kws(kws).flags(flags);
+        self.search_lines(cmd, |ln| {
+            let name = ln.trim();
+            (!name.is_empty() && !name.starts_with("==>")).then(|| SearchHit {
+                name: name.into(),
+                ..SearchHit::default()
+            })
+        })
+        .await
+    }
+
+    async fn upgradable(&self) -> Result<Vec<String>> {
+        self.names_from(Cmd::new(&["brew", "outdated", "--quiet"]), |ln| {
+            Some(ln.trim()).filter(|name| !name.is_empty())
+        })
+        .await
+    }
+
+    async fn su_only(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::new(&["brew", "upgrade"])
+            .kws(kws)
+            .flags(&self.cask_flags(kws, flags).await)
+            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
+            .await
+    }
+
+    async fn disk_usage(&self, _kws: &[&str], _flags: &[&str]) -> Result<Vec<(PathBuf, u64)>> {
+        let mut paths = vec![];
+        for arg in ["--cache", "--cellar"] {
+            let out = self
+                .check_output(Cmd::new(&["brew", arg]), PmMode::Mute, &Strategy::default())
+                .await?;
+            paths.push((String::from_utf8(out)?.trim().into(), 0));
+        }
+        Ok(paths)
+    }
+
+    async fn explicit(&self) -> Result<Vec<String>> {
+        fn name_of(ln: &str) -> Option<&str> {
+            Some(ln.trim()).filter(|name| !name.is_empty())
+        }
+        let mut formulae = self
+            .names_from(Cmd::new(&["brew", "leaves", "--installed-on-request"]), name_of)
+            .await?;
+        let casks = self
+            .names_from(Cmd::new(&["brew", "list", "--cask", "-1"]), name_of)
+            .await?;
+        formulae.extend(casks);
+        Ok(formulae)
+    }
+
+    async fn install_dates(&self, flags: &[&str]) -> Result<Vec<(String, SystemTime)>> {
+        let entries = self
+            .info_json(&["--installed"], &[], flags)
+            .await?
+            .unwrap_or_default();
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| Some((entry.name, entry.installed_time?)))
+            .collect())
+    }
+
+    async fn pin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        if kws.is_empty() {
+            return self
+                .run(Cmd::new(&["brew", "list", "--pinned"]).flags(flags))
+                .await;
+        }
+        self.run(Cmd::new(&["brew", "pin"]).kws(kws).flags(flags))
+            .await
+    }
+
+    async fn unpin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["brew", "unpin"]).kws(kws).flags(flags))
+            .await
+    }
+
+    /// Only the versioned formulae are supported, eg. `python@3.12`.
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        (op == "=").then(|| Versioned {
+            kw: format!("{name}@{ver}"),
+            ..Versioned::default()
+        })
+    }
+
+    async fn sizes(&self, _flags: &[&str]) -> Result<Vec<(String, u64)>> {
+        // Each formula (or cask) is installed to a directory of its own.
+        let mut sizes = vec![];
+        for arg in ["--cellar", "--caskroom"] {
+            let out = self
+                .check_output(Cmd::new(&["brew", arg]), PmMode::Mute, &Strategy::default())
+                .await?;
+            let Ok(entries) = fs::read_dir(String::from_utf8(out)?.trim()) else {
+                continue;
+            };
+            sizes.extend(entries.flatten().map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                (name, dir_size(&entry.path()))
+            }));
+        }
+        Ok(sizes)
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        let out = self
+            .check_output(Cmd::new(&["brew", "--cache"]), PmMode::Mute, &Strategy::default())
+            .await?;
+        Ok(vec![String::from_utf8(out)?.trim().into()])
+    }
+}
+
+#[async_trait]
+impl PmQuery for Brew {
+    /// F queries the package which provides FILE from the sync databases, even
+    /// if it is not installed.
+    async fn f(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["brew", "which-formula"]).kws(kws).flags(flags))
+            .await
+    }
+
+    /// Fy refreshes the file database, then queries the package which provides
+    /// FILE.
+    async fn fy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.sy(&[], flags).await?;
+        self.f(kws, flags).await
+    }
+
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
@@ -85,11 +582,69 @@ impl Pm for Brew {
             .await
     }
 
+    /// Qdt lists packages installed as dependencies but no longer required by
+    /// any installed package, aka orphans.
+    async fn qdt(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["brew", "leaves", "--installed-as-dependency"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Qi displays local package information: name, version, description, etc.
     async fn qi(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.si(kws, flags).await
     }
 
+    /// Qk verifies one or more packages.
+    async fn qk(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `brew` has no way to verify the installed files, so each file listed
+        // is checked to still exist, just like `pacman -Qk` does.
+        let pkgs = if kws.is_empty() {
+            self.names_from(Cmd::new(&["brew", "list", "--formula", "-1"]), |ln| {
+                Some(ln.trim())
+            })
+            .await?
+        } else {
+            kws.iter().map(|&kw| kw.to_owned()).collect()
+        };
+        let mut broken = vec![];
+        for pkg in &pkgs {
+            let files = self
+                .names_from(
+                    Cmd::new(&["brew", "list", "--verbose", "--formula"])
+                        .kws(&[pkg])
+                        .flags(flags),
+                    |ln| Some(ln.trim()).filter(|ln| !ln.is_empty()),
+                )
+                .await?;
+            let missing = files
+                .iter()
+                .filter(|file| fs::symlink_metadata(file).is_err())
+                .collect_vec();
+            for file in &missing {
+                print::print_msg(
+                    &format!("{pkg}: {file} (No such file or directory)"),
+                    PROMPT_WARN,
+                );
+            }
+            println!(
+                "{pkg}: {} total files, {} missing files",
+                files.len(),
+                missing.len()
+            );
+            if !missing.is_empty() {
+                broken.push(pkg.as_str());
+            }
+        }
+        if broken.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::OtherError(format!(
+                "Failed to verify {}",
+                broken.join(", ")
+            )))
+        }
+    }
+
     /// Ql displays files provided by local package.
     async fn ql(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         // TODO: it seems that the output of `brew list python` in fish has a mechanism
@@ -115,16 +670,75 @@ impl Pm for Brew {
         Ok(())
     }
 
+    /// Qt lists packages which are not required by any installed package.
+    async fn qt(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["brew", "leaves"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Qu lists packages which have an update available.
     async fn qu(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["brew", "outdated"]).kws(kws).flags(flags))
-            .await
+        let Some(entries) = self.info_json(&["--installed"], &[], flags).await? else {
+            return Ok(());
+        };
+        entries
+            .iter()
+            .filter(|entry| kws.is_empty() || kws.contains(&entry.name.as_str()))
+            .filter(|entry| entry.is_outdated())
+            .for_each(|entry| {
+                let installed = entry.installed.as_deref().unwrap_or_default();
+                println!("{} {installed} -> {}", entry.name, entry.version);
+            });
+        Ok(())
     }
 
+    /// T checks if the given dependencies are satisfied, printing the unsatisfied ones.
+    async fn t(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // The output looks like `name 1.0 1.1`, with the newest version last.
+        self.deptest(
+            kws,
+            Cmd::new(&["brew", "list", "--versions"]).flags(flags),
+            |out| out.split_whitespace().skip(1).last(),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl PmRemove for Brew {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&["brew", "uninstall"])
             .kws(kws)
+            .flags(&self.cask_flags(kws, flags).await)
+            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .await
+    }
+
+    /// Rc removes a package and all the packages that depend on it.
+    async fn rc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `brew uninstall` refuses to remove a formula with dependents, so we
+        // have to find them first. Note that `brew uses` with multiple formulae
+        // lists the formulae depending on all of them instead of any of them.
+        let mut targets = vec![];
+        for &kw in kws {
+            let cmd = Cmd::new(&["brew", "uses", "--installed", "--recursive"]).kws(&[kw]);
+            let out = self
+                .check_output(cmd, PmMode::Mute, &Strategy::default())
+                .await?;
+            targets.extend(String::from_utf8(out)?.split_whitespace().map(String::from));
+        }
+        targets.extend(kws.iter().map(|&kw| kw.to_owned()));
+        let targets = targets.iter().map(String::as_str).unique().collect_vec();
+        self.r(&targets, flags).await
+    }
+
+    /// Rcs removes a package and all the packages that depend on it,
+    /// along with their dependencies which are not required by any other
+    /// installed package.
+    async fn rcs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.rc(kws, flags).await?;
+        Cmd::new(&["brew", "autoremove"])
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
             .await
@@ -139,21 +753,17 @@ impl Pm for Brew {
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Brew {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(if self.cfg.needed {
-            &["brew", "install"]
-        } else {
-            // If the package is not installed, `brew reinstall` behaves just like `brew
-            // install`, so `brew reinstall` matches perfectly the behavior of
-            // `pacman -S`.
-            &["brew", "reinstall"]
-        })
-        .kws(kws)
-        .flags(flags)
-        .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-        .await
+        Cmd::new(&["brew", "install"])
+            .kws(kws)
+            .flags(&self.cask_flags(kws, flags).await)
+            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_REINSTALL))
+            .await
     }
 
     /// Sc removes all the cached packages that are not currently installed, and
@@ -187,8 +797,10 @@ impl Pm for Brew {
 
     /// Si displays remote package information: name, version, description, etc.
     async fn si(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["brew", "info"]).kws(kws).flags(flags))
-            .await
+        if let Some(entries) = self.info_json(&[], kws, flags).await? {
+            entries.iter().for_each(Entry::print_info);
+        }
+        Ok(())
     }
 
     /// Sii displays packages which require X to be installed, aka reverse
@@ -198,26 +810,79 @@ impl Pm for Brew {
             .await
     }
 
+    /// Sl displays a list of all packages in all installation sources that are
+    /// handled by the packages management, ie. the formulae and casks of all
+    /// the taps, or of the given ones.
+    async fn sl(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let taps = if kws.is_empty() {
+            self.names_from(Cmd::new(&["brew", "tap"]), |ln| {
+                Some(ln.trim()).filter(|tap| !tap.is_empty())
+            })
+            .await?
+        } else {
+            kws.iter().map(|&kw| kw.to_owned()).collect()
+        };
+        let cmd = Cmd::new(&["brew", "tap-info", "--json"])
+            .kws(&taps)
+            .flags(flags);
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?;
+        if self.cfg.dry_run {
+            return Ok(());
+        }
+        let infos: Vec<TapInfo> = serde_json::from_slice(&out)
+            .map_err(|e| Error::OtherError(format!("Failed to parse the taps: {e}")))?;
+        for info in infos {
+            let prefix = format!("{}/", info.name);
+            for name in chain!(&info.formula_names, &info.cask_tokens) {
+                let name = name.strip_prefix(&prefix).unwrap_or(name);
+                println!("{} {name}", info.name);
+            }
+        }
+        Ok(())
+    }
+
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["brew", "search"]).kws(kws).flags(flags))
-            .await
+        let Some(entries) = self.search_entries(kws, flags).await? else {
+            let cmd = Cmd::new(&["brew", "search"]);
+            return self
+                .search_each(cmd, kws, flags, &Strategy::default())
+                .await;
+        };
+        entries.iter().for_each(Entry::print_search);
+        Ok(())
     }
 
     /// Su updates outdated packages.
     async fn su(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["brew", "upgrade"])
+        let pinned = self
+            .hold_ignored(
+                Cmd::new(&["brew", "list", "--pinned"]),
+                Cmd::new(&["brew", "pin"]),
+                |ln| Some(ln.trim()),
+            )
+            .await?;
+        let res = Cmd::new(&["brew", "upgrade"])
             .kws(kws)
-            .flags(flags)
+            .flags(&self.cask_flags(kws, flags).await)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-            .await
+            .await;
+        if !pinned.is_empty() {
+            self.run(Cmd::new(&["brew", "unpin"]).kws(&pinned)).await?;
+        }
+        res
     }
 
     /// Suy refreshes the local package database, then updates outdated
     /// packages.
     async fn suy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let plan = Plan::new(STEPS_SUY);
+        plan.step(0);
         self.sy(&[], flags).await?;
+        plan.step(1);
         self.su(kws, flags).await
     }
 
@@ -226,17 +891,111 @@ impl Pm for Brew {
     async fn sw(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&["brew", "fetch"])
             .kws(kws)
-            .flags(flags)
+            .flags(&self.cask_flags(kws, flags).await)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
-            .await
+            .await?;
+        let Some(dir) = self.download_dir()? else {
+            return Ok(());
+        };
+        if self.cfg.dry_run {
+            return Ok(());
+        }
+        // `brew` always downloads to its cache, where the files are then
+        // copied from.
+        let cmd = Cmd::new(&["brew", "--cache"]).kws(kws).flags(flags);
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?
+            .pipe(String::from_utf8)?;
+        for file in out.lines().map(Path::new) {
+            if let Some(name) = file.file_name() {
+                fs::copy(file, Path::new(&dir).join(name))?;
+            }
+        }
+        Ok(())
     }
 
-    /// Sy refreshes the local package database.
+    /// Sy refreshes the local package database, adding the taps given, eg.
+    /// `homebrew/cask-fonts`.
     async fn sy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let (taps, kws): (Vec<&str>, Vec<&str>) = kws.iter().partition(|kw| is_tap(kw));
+        if !taps.is_empty() {
+            Cmd::new(&["brew", "tap"])
+                .kws(&taps)
+                .flags(flags)
+                .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+                .await?;
+        }
         self.run(Cmd::new(&["brew", "update"]).flags(flags)).await?;
         if !kws.is_empty() {
-            self.s(kws, flags).await?;
+            self.s(&kws, flags).await?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn taps() {
+        assert!(is_tap("homebrew/cask-fonts"));
+        assert!(!is_tap("curl"));
+        assert!(!is_tap("homebrew/cask-fonts/font-fira-code"));
+
+        let json = r#"[{
+            "name": "homebrew/cask-fonts",
+            "user": "Homebrew",
+            "formula_names": [],
+            "cask_tokens": ["homebrew/cask-fonts/font-fira-code"]
+        }]"#;
+        let infos: Vec<TapInfo> = serde_json::from_str(json).unwrap();
+        assert_eq!(infos[0].cask_tokens, ["homebrew/cask-fonts/font-fira-code"]);
+    }
+
+    #[tokio::test]
+    async fn cask_flags() {
+        let brew = Brew::new(Config {
+            cask: true,
+            ..Config::default()
+        });
+        assert_eq!(brew.cask_flags(&["docker"], &["-v"]).await, ["-v", "--cask"]);
+        assert_eq!(brew.cask_flags(&[], &["--cask"]).await, ["--cask"]);
+        assert!(Brew::new(Config::default())
+            .cask_flags(&["docker"], &[])
+            .await
+            .is_empty());
+
+        // Upgrading everything deals with both formulae and casks.
+        let brew = Brew::new(Config {
+            brew_prefer_cask: true,
+            ..Config::default()
+        });
+        assert!(brew.cask_flags(&[], &[]).await.is_empty());
+    }
+
+    #[test]
+    fn parse_info_v2() {
+        let json = r#"{
+            "formulae": [{
+                "name": "curl",
+                "tap": "homebrew/core",
+                "desc": "Get a file from an HTTP, HTTPS or FTP server",
+                "homepage": "https://curl.se",
+                "license": "curl",
+                "versions": { "stable": "7.82.0", "head": "HEAD", "bottle": true },
+                "revision": 1,
+                "dependencies": ["openssl@1.1"],
+                "installed": [{ "version": "7.81.0" }],
+                "outdated": false
+            }],
+            "casks": []
+        }"#;
+        let info: InfoV2 = serde_json::from_str(json).unwrap();
+        let entry = info.formulae.into_iter().map(Entry::from).next().unwrap();
+        assert_eq!(entry.version, "7.82.0_1");
+        assert_eq!(entry.installed.as_deref(), Some("7.81.0"));
+        assert!(entry.is_outdated());
+    }
+}
@@ -1,13 +1,22 @@
 #![doc = docs_self!()]
 
 use async_trait::async_trait;
+use futures::prelude::*;
 use indoc::indoc;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{DryRunStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    DryRunStrategy, NeededStrategy, PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync,
+    PromptStrategy, ReasonFilter, SearchHit, Strategy, Versioned,
+};
 use crate::exec::Cmd;
-use crate::{dispatch::Config, error::Result};
+use crate::{
+    dispatch::Config,
+    error::{Error, Result},
+    print::{self, PROMPT_RUN},
+    version::Scheme,
+};
 
 macro_rules! docs_self {
     () => {
@@ -29,6 +38,11 @@ static STRAT_PROMPT: Lazy<Strategy> = Lazy::new(|| Strategy {
     ..Strategy::default()
 });
 
+static STRAT_REINSTALL: Lazy<Strategy> = Lazy::new(|| Strategy {
+    needed: NeededStrategy::with_flags(&["--force"]),
+    ..STRAT_PROMPT.clone()
+});
+
 static STRAT_CHECK_DRY: Lazy<Strategy> = Lazy::new(|| Strategy {
     dry_run: DryRunStrategy::with_flags(&["--what-if"]),
     ..Strategy::default()
@@ -45,11 +59,32 @@ impl Choco {
         self.run_with(cmd, PmMode::default(), &STRAT_CHECK_DRY)
             .await
     }
+
+    /// Silently runs a `choco` query with `--limit-output`, returning the
+    /// `(name, version)` pairs found in its output.
+    async fn query_versions(&self, cmd: Cmd) -> Result<Vec<(String, String)>> {
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?
+            .pipe(String::from_utf8)?;
+        Ok(out
+            .lines()
+            .filter_map(|ln| {
+                let mut fields = ln.trim().split('|');
+                Some((fields.next()?.into(), fields.next()?.into()))
+            })
+            .collect())
+    }
 }
 
 // Windows is so special! It's better not to "sudo" automatically.
+/// The operations supported by [`Choco`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "q", "qd", "qdt", "qe", "qi", "qu", "r", "rss", "s", "si", "ss", "su", "suy",
+];
+
 #[async_trait]
-impl Pm for Choco {
+impl PmBase for Choco {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "choco"
@@ -59,6 +94,74 @@ impl Pm for Choco {
         &self.cfg
     }
 
+    fn supports_ignore(&self) -> bool {
+        true
+    }
+
+    async fn explicit(&self) -> Result<Vec<String>> {
+        let explicit = self.by_reason(ReasonFilter::Explicit).await?;
+        Ok(explicit.into_iter().map(|(name, _)| name).collect())
+    }
+
+    async fn installed(&self) -> Result<Vec<(String, String)>> {
+        self.query_versions(Cmd::new(&["choco", "list", "--localonly", "--limit-output"]))
+            .await
+    }
+
+    async fn search(&self, kws: &[&str], flags: &[&str]) -> Result<Vec<SearchHit>> {
+        // Each line looks like `<name>|<version>`.
+        let cmd = Cmd::new(&["choco", "search", "--limit-output"])
+            .kws(kws)
+            .flags(flags);
+        self.search_lines(cmd, |ln| {
+            let (name, version) = ln.trim().split_once('|')?;
+            Some(SearchHit {
+                name: name.into(),
+                version: version.into(),
+                ..SearchHit::default()
+            })
+        })
+        .await
+    }
+
+    /// `NuGet` versions are mostly semantic versions, with legacy ones having a
+    /// fourth number.
+    fn version_scheme(&self) -> Scheme {
+        Scheme::Semver
+    }
+
+    async fn pin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        if kws.is_empty() {
+            return self
+                .run(Cmd::new(&["choco", "pin", "list"]).flags(flags))
+                .await;
+        }
+        // `choco pin` takes a single package at a time.
+        for kw in kws {
+            self.run(Cmd::new(&["choco", "pin", "add", &format!("--name={kw}")]).flags(flags))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn unpin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        for kw in kws {
+            self.run(Cmd::new(&["choco", "pin", "remove", &format!("--name={kw}")]).flags(flags))
+                .await?;
+        }
+        Ok(())
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        (op == "=").then(|| Versioned {
+            kw: name.into(),
+            flags: vec![format!("--version={ver}")],
+        })
+    }
+}
+
+#[async_trait]
+impl PmQuery for Choco {
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&["choco", "list", "--localonly"])
@@ -68,6 +171,22 @@ impl Pm for Choco {
             .await
     }
 
+    /// Qd lists packages installed as dependencies (not explicitly).
+    async fn qd(&self, kws: &[&str], _flags: &[&str]) -> Result<()> {
+        self.print_by_reason(kws, ReasonFilter::Dependency).await
+    }
+
+    /// Qdt lists packages installed as dependencies but no longer required by
+    /// any installed package, aka orphans.
+    async fn qdt(&self, kws: &[&str], _flags: &[&str]) -> Result<()> {
+        self.print_by_reason(kws, ReasonFilter::Orphan).await
+    }
+
+    /// Qe lists packages installed explicitly (not as dependencies).
+    async fn qe(&self, kws: &[&str], _flags: &[&str]) -> Result<()> {
+        self.print_by_reason(kws, ReasonFilter::Explicit).await
+    }
+
     /// Qi displays local package information: name, version, description, etc.
     async fn qi(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.si(kws, flags).await
@@ -75,16 +194,56 @@ impl Pm for Choco {
 
     /// Qu lists packages which have an update available.
     async fn qu(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.check_dry(Cmd::new(&["choco", "outdated"]).kws(kws).flags(flags))
-            .await
+        if self.cfg.dry_run {
+            return self
+                .check_dry(Cmd::new(&["choco", "outdated"]).kws(kws).flags(flags))
+                .await;
+        }
+
+        // `choco outdated` is slow, as it queries the sources one package at a
+        // time. Instead, we query them concurrently and compare the versions
+        // ourselves.
+        let cmd = Cmd::new(&["choco", "list", "--localonly", "--limit-output"]).flags(flags);
+        print::print_cmd(&cmd, PROMPT_RUN);
+        let installed = self
+            .query_versions(cmd)
+            .await?
+            .into_iter()
+            .filter(|(name, _)| kws.is_empty() || kws.iter().any(|kw| kw.eq_ignore_ascii_case(name)));
+
+        let outdated: Vec<_> = stream::iter(installed)
+            .map(|(name, current)| async move {
+                let latest = Cmd::new(&["choco", "search", "--limit-output", "--exact"])
+                    .kws(&[&name])
+                    .flags(flags)
+                    .pipe(|cmd| self.query_versions(cmd))
+                    .await?
+                    .into_iter()
+                    .find(|(found, _)| found.eq_ignore_ascii_case(&name))
+                    .map(|(_, latest)| latest);
+                Ok::<_, Error>((name, current, latest))
+            })
+            .buffered(8)
+            .try_collect()
+            .await?;
+
+        outdated
+            .into_iter()
+            .filter_map(|(name, current, latest)| Some((name, current, latest?)))
+            .filter(|(_, current, latest)| self.version_scheme().cmp(current, latest).is_lt())
+            .for_each(|(name, current, latest)| println!("{name} {current} -> {latest}"));
+        Ok(())
     }
+}
 
+#[async_trait]
+impl PmRemove for Choco {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&["choco", "uninstall"])
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .pipe(|cmd| self.run_tracked(cmd, &[], &STRAT_PROMPT))
             .await
     }
 
@@ -94,21 +253,20 @@ impl Pm for Choco {
         Cmd::new(&["choco", "uninstall", "--removedependencies"])
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .pipe(|cmd| self.run_tracked(cmd, &[], &STRAT_PROMPT))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Choco {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(if self.cfg.needed {
-            &["choco", "install"]
-        } else {
-            &["choco", "install", "--force"]
-        })
-        .kws(kws)
-        .flags(flags)
-        .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
-        .await
+        Cmd::new(&["choco", "install"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run_tracked(cmd, kws, &STRAT_REINSTALL))
+            .await
     }
 
     /// Si displays remote package information: name, version, description, etc.
@@ -120,21 +278,24 @@ impl Pm for Choco {
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.check_dry(Cmd::new(&["choco", "search"]).kws(kws).flags(flags))
+        self.search_each(Cmd::new(&["choco", "search"]), kws, flags, &STRAT_CHECK_DRY)
             .await
     }
 
     /// Su updates outdated packages.
     async fn su(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(if kws.is_empty() {
+        let mut cmd = Cmd::new(if kws.is_empty() {
             &["choco", "upgrade", "all"]
         } else {
             &["choco", "upgrade"]
         })
         .kws(kws)
-        .flags(flags)
-        .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
-        .await
+        .flags(flags);
+        if kws.is_empty() && !self.cfg.ignore.is_empty() {
+            cmd.flags
+                .push(format!("--except={}", self.cfg.ignore.join(",")));
+        }
+        self.run_with(cmd, PmMode::default(), &STRAT_PROMPT).await
     }
 
     /// Suy refreshes the local package database, then updates outdated
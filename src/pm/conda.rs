@@ -1,12 +1,14 @@
 #![doc = docs_self!()]
 
 use async_trait::async_trait;
-use futures::prelude::*;
 use indoc::indoc;
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync, PromptStrategy, Strategy, Versioned,
+};
 use crate::{
     dispatch::Config,
     error::Result,
@@ -41,8 +43,10 @@ impl Conda {
     }
 }
 
-#[async_trait]
-impl Pm for Conda {
+/// The operations supported by [`Conda`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &["q", "qo", "qs", "r", "s", "sc", "si", "ss", "su", "suy"];
+
+impl PmBase for Conda {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "conda"
@@ -52,6 +56,18 @@ impl Pm for Conda {
         &self.cfg
     }
 
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        // `<name>=<version>` also matches `<version>.*`.
+        let op = if op == "=" { "==" } else { op };
+        Some(Versioned {
+            kw: format!("{name}{op}{ver}"),
+            ..Versioned::default()
+        })
+    }
+}
+
+#[async_trait]
+impl PmQuery for Conda {
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
@@ -82,10 +98,13 @@ impl Pm for Conda {
         let out_bytes = self
             .check_output(cmd, PmMode::Mute, &Strategy::default())
             .await?;
-        exec::grep_print(&String::from_utf8(out_bytes)?, kws)?;
+        exec::grep_print(&String::from_utf8(out_bytes)?, kws, self.cfg.exact)?;
         Ok(())
     }
+}
 
+#[async_trait]
+impl PmRemove for Conda {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&["conda", "remove"])
@@ -94,7 +113,10 @@ impl Pm for Conda {
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Conda {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&["conda", "install"])
@@ -125,10 +147,15 @@ impl Pm for Conda {
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        stream::iter(kws)
-            .map(|s| Ok(format!("*{}*", s)))
-            .try_for_each(|kw| self.run(Cmd::new(&["conda", "search"]).kws(&[kw]).flags(flags)))
-            .await
+        let kws = kws.iter().map(|kw| format!("*{kw}*")).collect_vec();
+        let kws = kws.iter().map(String::as_str).collect_vec();
+        self.search_each(
+            Cmd::new(&["conda", "search"]),
+            &kws,
+            flags,
+            &Strategy::default(),
+        )
+        .await
     }
 
     /// Su updates outdated packages.
@@ -0,0 +1,214 @@
+#![doc = docs_self!()]
+
+use async_trait::async_trait;
+use indoc::indoc;
+use macro_rules_attribute::macro_rules_attribute;
+use tt_call::tt_call;
+
+use super::{DryRunStrategy, NeededStrategy, NoCacheStrategy, PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync, PromptStrategy, Strategy};
+use crate::{
+    dispatch::{self, Backend, Config},
+    methods,
+    error::{Error, Result},
+    exec::Cmd,
+};
+
+macro_rules! docs_self {
+    () => {
+        indoc! {"
+            A package manager defined in the config file, eg.
+
+            ```toml
+            default_pm = \"spack\"
+
+            [spack]
+            no_confirm_flags = [\"-y\"]
+
+            [spack.methods]
+            s = \"spack install\"
+            r = \"spack uninstall\"
+            ss = \"spack list\"
+            ```
+        "}
+    };
+}
+
+#[doc = docs_self!()]
+#[derive(Debug)]
+pub(crate) struct CustomPm {
+    name: String,
+    cfg: Config,
+}
+
+impl CustomPm {
+    #[must_use]
+    /// Creates a new [`CustomPm`] defined in the section `name` of the config.
+    pub(crate) fn new(name: String, cfg: Config) -> Self {
+        CustomPm { name, cfg }
+    }
+
+    /// Checks if `cfg` defines a custom package manager in the section `name`.
+    #[must_use]
+    pub(crate) fn is_defined(name: &str, cfg: &Config) -> bool {
+        cfg.backends
+            .get(name)
+            .is_some_and(|backend| !backend.methods.is_empty())
+    }
+
+    /// Gets the section of the config defining this package manager.
+    fn backend(&self) -> &Backend {
+        &self.cfg.backends[&self.name]
+    }
+
+    /// Gets the [`Strategy`] for `method`, as the prompt and the dry run only
+    /// matter when changing the installed packages.
+    fn strategy(&self, method: &str) -> Strategy {
+        let backend = self.backend();
+        if !dispatch::is_mutating(method) {
+            return Strategy::default();
+        }
+        Strategy {
+            prompt: if backend.no_confirm_flags.is_empty() {
+                PromptStrategy::CustomPrompt
+            } else {
+                PromptStrategy::native_no_confirm(&backend.no_confirm_flags)
+            },
+            dry_run: if backend.dry_run_flags.is_empty() {
+                DryRunStrategy::PrintCmd
+            } else {
+                DryRunStrategy::with_flags(&backend.dry_run_flags)
+            },
+            no_cache: if method.starts_with('s') && backend.methods.contains_key("sc") {
+                NoCacheStrategy::Sc
+            } else {
+                NoCacheStrategy::None
+            },
+            needed: if method == "s" {
+                NeededStrategy::with_flags(&backend.reinstall_flags)
+            } else {
+                NeededStrategy::None
+            },
+        }
+    }
+
+    /// Runs the command configured for `method`, eg. `s`.
+    async fn run_method(&self, method: &str, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let template = self.backend().methods.get(method).ok_or_else(|| {
+            Error::OperationUnsupportedError {
+                op: method.into(),
+                pm: self.name().into(),
+                hint: None,
+            }
+        })?;
+        let cmd = Cmd::new(&template.split_whitespace().collect::<Vec<_>>())
+            .sudo(self.backend().needs_root && dispatch::is_mutating(method))
+            .kws(kws)
+            .flags(flags);
+        self.run_with(cmd, PmMode::default(), &self.strategy(method))
+            .await
+    }
+}
+
+macro_rules! _decor_custom {(
+    def = [{
+        $( #[$meta0:meta] )*
+        impl $t:ident for $ty:ident {
+            $( $inner:tt )*
+        }
+    }]
+    methods = [{ $(
+        $( #[$tmeta:meta] )*
+        trait $cap:ident { $(
+            $( #[$meta1:meta] )*
+            async fn $method:ident;
+        )* }
+    )* }]
+) => {
+    $( #[$meta0] )*
+    impl $t for $ty {
+        $( $inner )*
+    }
+
+    // * Automatically generated impls below... *
+    $( #[async_trait]
+    impl $cap for $ty {
+        $( $( #[$meta1] )*
+        async fn $method(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+            self.run_method(stringify!($method), kws, flags).await
+        } )*
+    } )*
+};}
+
+/// Send `methods!()` to `_decor_custom`, implementing all the methods with
+/// [`CustomPm::run_method`].
+macro_rules! decor_custom {
+    ( $( $def:tt )* ) => {
+        tt_call! {
+            macro = [{ methods }]
+            ~~> _decor_custom! {
+                def = [{ $( $def )* }]
+            }
+        }
+    };
+}
+
+#[macro_rules_attribute(decor_custom!)]
+#[async_trait]
+impl PmBase for CustomPm {
+    /// Gets the name of the package manager.
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn cfg(&self) -> &Config {
+        &self.cfg
+    }
+
+    fn supports_root(&self) -> bool {
+        self.name == "pacman"
+    }
+
+    fn root_flags(&self, exe: &str, root: &str) -> Vec<String> {
+        match exe {
+            "pacman" => vec!["--sysroot".into(), root.into()],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strategies() {
+        let cfg: Config = toml::from_str(indoc! {r#"
+            [spack]
+            no_confirm_flags = ["-y"]
+            reinstall_flags = ["--force"]
+            needs_root = true
+
+            [spack.methods]
+            s = "spack install"
+            sc = "spack clean"
+            ss = "spack list"
+
+            [brew]
+            no_cache = true
+        "#})
+        .unwrap();
+        assert!(CustomPm::is_defined("spack", &cfg));
+        assert!(!CustomPm::is_defined("brew", &cfg));
+        assert!(!CustomPm::is_defined("nix", &cfg));
+
+        let pm = CustomPm::new("spack".into(), cfg);
+        let s = pm.strategy("s");
+        assert!(matches!(s.no_cache, NoCacheStrategy::Sc));
+        assert!(matches!(s.prompt, PromptStrategy::NativeNoConfirm(_)));
+        assert!(matches!(s.dry_run, DryRunStrategy::PrintCmd));
+        assert!(matches!(s.needed, NeededStrategy::WithFlags(v) if v == ["--force"]));
+        assert!(matches!(pm.strategy("su").needed, NeededStrategy::None));
+        assert!(matches!(pm.strategy("ss").no_cache, NoCacheStrategy::None));
+        assert!(matches!(pm.strategy("ss").prompt, PromptStrategy::None));
+    }
+}
@@ -1,23 +1,31 @@
 #![doc = docs_self!()]
 
+use std::{fs, path::PathBuf, time::SystemTime};
+
 use async_trait::async_trait;
 use futures::prelude::*;
 use indoc::indoc;
+use itertools::{chain, Itertools};
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{NoCacheStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    local_path, Advisory, DryRunStrategy, NoCacheStrategy, PkgInfo, PmBase, PmHelper, PmMode,
+    PmQuery, PmRemove, PmSync, PromptStrategy, SearchHit, Strategy, Versioned,
+};
 use crate::{
     dispatch::Config,
-    error::Result,
-    exec::{self, Cmd},
+    error::{Error, Result},
+    exec::{self, Cmd, Mode},
     print::{self, PROMPT_RUN},
+    version::Scheme,
 };
 
 macro_rules! docs_self {
     () => {
         indoc! {"
-            The [Dandified YUM](https://github.com/rpm-software-management/dnf).
+            The [Dandified YUM](https://github.com/rpm-software-management/dnf),
+            including [`dnf5`](https://github.com/rpm-software-management/dnf5).
         "}
     };
 }
@@ -26,10 +34,16 @@ macro_rules! docs_self {
 #[derive(Debug)]
 pub(crate) struct Dnf {
     cfg: Config,
+    /// Whether the command used is `dnf5`, whose flags and output differ.
+    dnf5: bool,
 }
 
+/// The exit code of `dnf check-update` when some updates are available.
+const UPDATES_AVAILABLE_CODE: i32 = 100;
+
 static STRAT_PROMPT: Lazy<Strategy> = Lazy::new(|| Strategy {
     prompt: PromptStrategy::native_no_confirm(&["-y"]),
+    dry_run: DryRunStrategy::declining(&["--assumeno"]),
     ..Strategy::default()
 });
 
@@ -41,6 +55,7 @@ static STRAT_PROMPT_CUSTOM: Lazy<Strategy> = Lazy::new(|| Strategy {
 static STRAT_INSTALL: Lazy<Strategy> = Lazy::new(|| Strategy {
     prompt: PromptStrategy::native_no_confirm(&["-y"]),
     no_cache: NoCacheStrategy::Sccc,
+    dry_run: DryRunStrategy::declining(&["--assumeno"]),
     ..Strategy::default()
 });
 
@@ -48,12 +63,114 @@ impl Dnf {
     #[must_use]
     #[allow(missing_docs)]
     pub(crate) fn new(cfg: Config) -> Self {
-        Dnf { cfg }
+        // `dnf` on this machine says nothing about that on a remote host, in
+        // a container (see [`Dnf::resolve_remote`]) or behind an executor.
+        let dnf5 = cfg.default_pm.as_deref() == Some("dnf5")
+            || (cfg.is_local() && cfg.executor.is_none() && is_dnf5());
+        Dnf { cfg, dnf5 }
+    }
+
+    /// Sets the `default_pm` of `cfg` to `dnf5` when it's `dnf`, and the
+    /// `dnf` of the remote host or of the container of `cfg` is actually
+    /// `dnf5`, which [`is_dnf5`] cannot tell from this machine.
+    pub(crate) async fn resolve_remote(cfg: &mut Config) {
+        if cfg.is_local() || cfg.default_pm.as_deref() != Some("dnf") {
+            return;
+        }
+        let cmd = Cmd::new(&["dnf", "--version"])
+            .host(cfg.host.clone())
+            .container(cfg.container.clone())
+            .executor(cfg.executor.clone());
+        if let Ok(out) = cmd.exec(Mode::Mute).await {
+            if is_dnf5_version(&String::from_utf8_lossy(&out)) {
+                cfg.default_pm = Some("dnf5".into());
+            }
+        }
+    }
+
+    /// Returns the command used to invoke [`Dnf`], eg. `dnf`, `dnf5`.
+    #[must_use]
+    fn cmd(&self) -> &str {
+        self.cfg
+            .default_pm
+            .as_deref()
+            .expect("default package manager should have been assigned before initialization")
     }
+
+    /// Makes the `--queryformat` of `repoquery`, as `dnf5` doesn't end each
+    /// entry with a newline.
+    #[must_use]
+    fn queryformat(&self, fmt: &str) -> String {
+        if self.dnf5 {
+            format!("{fmt}\n")
+        } else {
+            fmt.to_owned()
+        }
+    }
+}
+
+/// Checks if `dnf` is actually `dnf5`, as on Fedora 41+ where the former is a
+/// symlink to the latter.
+#[must_use]
+fn is_dnf5() -> bool {
+    which::which("dnf")
+        .ok()
+        .and_then(|path| fs::canonicalize(path).ok())
+        .is_some_and(|path| path.file_name().is_some_and(|name| name == "dnf5"))
+}
+
+/// Checks if the output `out` of `dnf --version` comes from `dnf5`, which
+/// prints `dnf5 version <version>` rather than the bare version.
+#[must_use]
+fn is_dnf5_version(out: &str) -> bool {
+    out.trim_start().starts_with("dnf5")
+}
+
+/// Parses a line of `dnf search`, which looks like `<name>.<arch> : <summary>`,
+/// or `<name>.<arch>\t<summary>` with `dnf5`.
+#[must_use]
+fn parse_search_hit(ln: &str) -> Option<SearchHit> {
+    let (name, desc) = ln.split_once(" : ").or_else(|| ln.split_once('\t'))?;
+    let name = name.trim();
+    Some(SearchHit {
+        name: name.rsplit_once('.').map_or(name, |(name, _arch)| name).into(),
+        desc: desc.trim().into(),
+        ..SearchHit::default()
+    })
 }
 
+/// Parses a line of `dnf updateinfo list --security`, which looks like `<id>
+/// <severity>/Sec. <nevra>`, or `<id> security <severity> <nevra> <date>`
+/// with `dnf5 advisory list --security`.
+#[must_use]
+fn parse_advisory(ln: &str) -> Option<Advisory> {
+    let fields = ln.split_whitespace().collect_vec();
+    let (id, severity, nevra) = match fields.as_slice() {
+        [id, kind, nevra] => (id, kind.strip_suffix("/Sec.")?, nevra),
+        [id, "security", severity, nevra, ..] => (id, *severity, nevra),
+        _ => return None,
+    };
+    // The NEVRA looks like `<name>-<version>-<release>.<arch>`.
+    let (nevr, _arch) = nevra.rsplit_once('.')?;
+    let (_release, _version, name) = nevr.rsplitn(3, '-').collect_tuple()?;
+    Some(Advisory {
+        id: (*id).into(),
+        pkg: name.into(),
+        severity: if severity == "None" { "" } else { severity }.into(),
+        // Only the advisories fixed by an upgrade available are listed.
+        fixed: true,
+    })
+}
+
+/// The operations supported by [`Dnf`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "f", "fy", "q", "qc", "qdt", "qe", "qi", "qk", "ql", "qm", "qo", "qp", "qs", "qu", "r", "rc",
+    "rcs", "s", "sc", "scc", "sccc", "sg", "si", "sii", "sl", "ss", "su", "suy", "sw", "sy", "t",
+    "u",
+];
+
 #[async_trait]
-impl Pm for Dnf {
+impl PmBase for Dnf {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "dnf"
@@ -63,6 +180,217 @@ impl Pm for Dnf {
         &self.cfg
     }
 
+    fn unsupported_hint(&self, op: &str) -> Option<&str> {
+        match op {
+            "qt" => Some("dnf leaves"),
+            _ => None,
+        }
+    }
+
+    fn supports_ignore(&self) -> bool {
+        true
+    }
+
+    fn supports_download_dir(&self) -> bool {
+        true
+    }
+
+    fn supports_root(&self) -> bool {
+        true
+    }
+
+    fn root_flags(&self, exe: &str, root: &str) -> Vec<String> {
+        match exe {
+            "rpm" => vec![format!("--root={root}")],
+            _ => vec![format!("--installroot={root}")],
+        }
+    }
+
+    fn version_scheme(&self) -> Scheme {
+        Scheme::Rpm
+    }
+
+    async fn search(&self, kws: &[&str], flags: &[&str]) -> Result<Vec<SearchHit>> {
+        // The headers, eg. `=== Name Matched: <kw> ===`, are skipped.
+        let cmd = Cmd::new(&[self.cmd(), "search", "--quiet"]).kws(kws).flags(flags);
+        self.search_lines(cmd, parse_search_hit).await
+    }
+
+    async fn deps(&self, pkg: &str, flags: &[&str]) -> Result<Vec<String>> {
+        // `dnf5` replaces `--resolve` with `--providers-of`.
+        let resolve: &[&str] = if self.dnf5 {
+            &["--providers-of=requires"]
+        } else {
+            &["--requires", "--resolve"]
+        };
+        let queryformat = self.queryformat("%{name}");
+        let flags = chain!(
+            resolve.iter().copied(),
+            ["--quiet", "--queryformat", &queryformat],
+            flags.iter().copied()
+        )
+        .collect_vec();
+        let cmd = Cmd::new(&[self.cmd(), "repoquery"])
+            .flags(&flags)
+            .kws(&[pkg]);
+        let deps = self
+            .names_from(cmd, |ln| Some(ln.trim()).filter(|name| !name.is_empty()))
+            .await?;
+        Ok(deps.into_iter().filter(|dep| dep != pkg).unique().collect())
+    }
+
+    async fn info(&self, kws: &[&str], flags: &[&str], installed: bool) -> Result<Vec<PkgInfo>> {
+        let cmd = if installed {
+            Cmd::new(&[self.cmd(), "info", "--quiet", "--installed"])
+        } else {
+            Cmd::new(&[self.cmd(), "info", "--quiet"])
+        };
+        self.info_from(cmd.kws(kws).flags(flags), |fields| {
+            let get = |key| fields.get(key).cloned().unwrap_or_default();
+            let version = match fields.get("Release") {
+                Some(release) => format!("{}-{release}", get("Version")),
+                None => get("Version"),
+            };
+            Some(PkgInfo {
+                // The installed packages come from the `@System` repository.
+                repo: get("Repository").pipe(|repo| if repo.starts_with('@') { String::new() } else { repo }),
+                name: fields.get("Name")?.clone(),
+                version,
+                desc: get("Summary"),
+                url: get("URL"),
+                licenses: get("License"),
+                installed_size: get("Installed size"),
+                ..PkgInfo::default()
+            })
+        })
+        .await
+    }
+
+    async fn upgradable(&self) -> Result<Vec<String>> {
+        // Each line looks like `<name>.<arch> <version> <repo>`, apart from the
+        // header `Available Upgrades`.
+        self.names_from(Cmd::new(&[self.cmd(), "list", "--upgrades", "--quiet"]), |ln| {
+            let (name, _version, _repo) = ln.split_whitespace().collect_tuple()?;
+            name.rsplit_once('.').map(|(name, _arch)| name)
+        })
+        .await
+    }
+
+    async fn su_only(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&[self.cmd(), "upgrade"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
+            .await
+    }
+
+    async fn disk_usage(&self, _kws: &[&str], _flags: &[&str]) -> Result<Vec<(PathBuf, u64)>> {
+        Ok(["/var/cache/dnf", "/usr"].map(|path| (path.into(), 0)).into())
+    }
+
+    async fn explicit(&self) -> Result<Vec<String>> {
+        let cmd = Cmd::new(&[
+            self.cmd(),
+            "repoquery",
+            "--userinstalled",
+            "--quiet",
+            "--queryformat",
+            &self.queryformat("%{name}"),
+        ]);
+        self.names_from(cmd, |ln| Some(ln.trim()).filter(|name| !name.is_empty()))
+            .await
+    }
+
+    async fn install_dates(&self, flags: &[&str]) -> Result<Vec<(String, SystemTime)>> {
+        self.install_dates_from(
+            Cmd::new(&["rpm", "-qa", "--queryformat", "%{INSTALLTIME} %{NAME}\n"]).flags(flags),
+        )
+        .await
+    }
+
+    async fn pin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `dnf` needs the `versionlock` plugin, which is built into `dnf5`.
+        if kws.is_empty() {
+            return self
+                .run(Cmd::new(&[self.cmd(), "versionlock", "list"]).flags(flags))
+                .await;
+        }
+        Cmd::with_sudo(&[self.cmd(), "versionlock", "add"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
+    async fn unpin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&[self.cmd(), "versionlock", "delete"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        // The other requirements are matched against the provides.
+        let kw = if op == "=" {
+            format!("{name}-{ver}")
+        } else {
+            format!("{name} {op} {ver}")
+        };
+        Some(Versioned {
+            kw,
+            ..Versioned::default()
+        })
+    }
+
+    fn supports_url(&self) -> bool {
+        true
+    }
+
+    async fn sizes(&self, flags: &[&str]) -> Result<Vec<(String, u64)>> {
+        let cmd = Cmd::new(&["rpm", "-qa", "--queryformat", "%{NAME}\t%{SIZE}\n"]).flags(flags);
+        self.sizes_from(cmd, 1).await
+    }
+
+    async fn advisories(&self, flags: &[&str]) -> Result<Vec<Advisory>> {
+        let subcmd = if self.dnf5 { "advisory" } else { "updateinfo" };
+        let cmd = Cmd::new(&[self.cmd(), subcmd, "list", "--security"]).flags(flags);
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?
+            .pipe(String::from_utf8)?;
+        Ok(out.lines().filter_map(parse_advisory).collect())
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        let dir = if self.dnf5 {
+            "/var/cache/libdnf5"
+        } else {
+            "/var/cache/dnf"
+        };
+        Ok(vec![dir.into()])
+    }
+}
+
+#[async_trait]
+impl PmQuery for Dnf {
+    /// F queries the package which provides FILE from the sync databases, even
+    /// if it is not installed.
+    async fn f(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&[self.cmd(), "provides"]).kws(kws).flags(flags))
+            .await
+    }
+
+    /// Fy refreshes the file database, then queries the package which provides
+    /// FILE.
+    async fn fy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::new(&[self.cmd(), "provides", "--refresh"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
@@ -82,9 +410,16 @@ impl Pm for Dnf {
             .await
     }
 
+    /// Qdt lists packages installed as dependencies but no longer required by
+    /// any installed package, aka orphans.
+    async fn qdt(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&[self.cmd(), "repoquery", "--unneeded"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Qe lists packages installed explicitly (not as dependencies).
     async fn qe(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["dnf", "repoquery", "--userinstalled"])
+        Cmd::new(&[self.cmd(), "repoquery", "--userinstalled"])
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run(cmd))
@@ -93,12 +428,25 @@ impl Pm for Dnf {
 
     /// Qi displays local package information: name, version, description, etc.
     async fn qi(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        stream::iter(&[
-            &["dnf", "info", "--installed"],
-            &["dnf", "repoquery", "--deplist"],
+        stream::iter([
+            [self.cmd(), "info", "--installed"],
+            [self.cmd(), "repoquery", "--deplist"],
         ])
         .map(Ok)
-        .try_for_each(|&cmd| self.run(Cmd::new(cmd).kws(kws).flags(flags)))
+        .try_for_each(|cmd| self.run(Cmd::new(&cmd).kws(kws).flags(flags)))
+        .await
+    }
+
+    /// Qk verifies one or more packages.
+    async fn qk(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::new(if kws.is_empty() {
+            &["rpm", "-Va"]
+        } else {
+            &["rpm", "-V"]
+        })
+        .kws(kws)
+        .flags(flags)
+        .pipe(|cmd| self.run(cmd))
         .await
     }
 
@@ -111,7 +459,7 @@ impl Pm for Dnf {
     /// Qm lists packages that are installed but are not available in any
     /// installation source (anymore).
     async fn qm(&self, _kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["dnf", "list", "--extras"]).flags(flags))
+        self.run(Cmd::new(&[self.cmd(), "list", "--extras"]).flags(flags))
             .await
     }
 
@@ -141,27 +489,59 @@ impl Pm for Dnf {
             .check_output(cmd, PmMode::Mute, &Strategy::default())
             .await?
             .pipe(String::from_utf8)?;
-        exec::grep_print(&out, kws)
+        exec::grep_print(&out, kws, self.cfg.exact)
     }
 
     /// Qu lists packages which have an update available.
     async fn qu(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["dnf", "list", "updates"]).kws(kws).flags(flags))
+        // `dnf5` takes `updates` as a package name.
+        let upgrades = if self.dnf5 { "--upgrades" } else { "updates" };
+        self.run(Cmd::new(&[self.cmd(), "list", upgrades]).kws(kws).flags(flags))
             .await
     }
 
+    /// T checks if the given dependencies are satisfied, printing the unsatisfied ones.
+    async fn t(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.deptest(
+            kws,
+            Cmd::new(&["rpm", "-q", "--queryformat", "%{VERSION}-%{RELEASE}\n"]).flags(flags),
+            |out| out.lines().last(),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl PmRemove for Dnf {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["dnf", "remove"])
+        Cmd::with_sudo(&[self.cmd(), "remove"])
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
             .await
     }
 
+    /// Rc removes a package and all the packages that depend on it.
+    async fn rc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `dnf` removes the packages depending on the targets anyway.
+        self.r(kws, flags).await
+    }
+
+    /// Rcs removes a package and all the packages that depend on it,
+    /// along with their dependencies which are not required by any other
+    /// installed package.
+    async fn rcs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `dnf` also removes the unneeded dependencies by default.
+        self.r(kws, flags).await
+    }
+}
+
+#[async_trait]
+impl PmSync for Dnf {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["dnf", "install"])
+        Cmd::with_sudo(&[self.cmd(), "install"])
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
@@ -171,7 +551,7 @@ impl Pm for Dnf {
     /// Sc removes all the cached packages that are not currently installed, and
     /// the unused sync database.
     async fn sc(&self, _kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["dnf", "clean", "expire-cache"])
+        Cmd::new(&[self.cmd(), "clean", "expire-cache"])
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT_CUSTOM))
             .await
@@ -179,7 +559,7 @@ impl Pm for Dnf {
 
     /// Scc removes all files from the cache.
     async fn scc(&self, _kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["dnf", "clean", "packages"])
+        Cmd::new(&[self.cmd(), "clean", "packages"])
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT_CUSTOM))
             .await
@@ -188,7 +568,7 @@ impl Pm for Dnf {
     /// Sccc ...
     /// What is this?
     async fn sccc(&self, _kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["dnf", "clean", "all"])
+        Cmd::new(&[self.cmd(), "clean", "all"])
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT_CUSTOM))
             .await
@@ -197,27 +577,25 @@ impl Pm for Dnf {
     /// Si displays remote package information: name, version, description, etc.
 
     async fn si(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["dnf", "info"]).kws(kws).flags(flags))
+        self.run(Cmd::new(&[self.cmd(), "info"]).kws(kws).flags(flags))
             .await
     }
 
     /// Sii displays packages which require X to be installed, aka reverse
     /// dependencies.
     async fn sii(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["dnf", "repoquery", "--deplist"])
+        Cmd::new(&[self.cmd(), "repoquery", "--whatrequires"])
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run(cmd))
             .await
     }
 
-    /// Sg lists all packages belonging to the GROUP.
+    /// Sg lists all packages belonging to the GROUP, or all the groups if no
+    /// GROUP is given.
     async fn sg(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(if kws.is_empty() {
-            &["dnf", "group", "list"]
-        } else {
-            &["dnf", "group", "info"]
-        })
+        let subcmd = if kws.is_empty() { "list" } else { "info" };
+        Cmd::new(&[self.cmd(), "group", subcmd])
         .kws(kws)
         .flags(flags)
         .pipe(|cmd| self.run(cmd))
@@ -227,7 +605,7 @@ impl Pm for Dnf {
     /// Sl displays a list of all packages in all installation sources that are
     /// handled by the packages management.
     async fn sl(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["dnf", "list", "--available"])
+        Cmd::new(&[self.cmd(), "list", "--available"])
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run(cmd))
@@ -237,17 +615,18 @@ impl Pm for Dnf {
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["dnf", "search"]).kws(kws).flags(flags))
+        self.run(Cmd::new(&[self.cmd(), "search"]).kws(kws).flags(flags))
             .await
     }
 
     /// Su updates outdated packages.
     async fn su(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["dnf", "upgrade"])
-            .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-            .await
+        let mut cmd = Cmd::with_sudo(&[self.cmd(), "upgrade"]).kws(kws).flags(flags);
+        if !self.cfg.ignore.is_empty() {
+            cmd.flags
+                .push(format!("--exclude={}", self.cfg.ignore.join(",")));
+        }
+        self.run_with(cmd, PmMode::default(), &STRAT_INSTALL).await
     }
 
     /// Suy refreshes the local package database, then updates outdated
@@ -259,18 +638,35 @@ impl Pm for Dnf {
     /// Sw retrieves all packages from the server, but does not install/upgrade
     /// anything.
     async fn sw(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["dnf", "install", "--downloadonly"])
-            .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-            .await
+        let mut cmd = Cmd::with_sudo(&[self.cmd(), "install", "--downloadonly"]).kws(kws).flags(flags);
+        if let Some(dir) = self.download_dir()? {
+            let flag = if self.dnf5 { "--destdir" } else { "--downloaddir" };
+            cmd.flags.push(format!("{flag}={dir}"));
+        }
+        self.run_with(cmd, PmMode::default(), &STRAT_INSTALL).await
     }
 
     /// Sy refreshes the local package database.
     async fn sy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.sc(&[], flags).await?;
-        self.run(Cmd::new(&["dnf", "check-update"]).flags(flags))
-            .await?;
+        if self.dnf5 {
+            // `--refresh` expires the cache before refreshing it.
+            self.run(Cmd::new(&[self.cmd(), "makecache", "--refresh"]).flags(flags))
+                .await?;
+        } else {
+            self.sc(&[], flags).await?;
+            match self
+                .run(Cmd::new(&[self.cmd(), "check-update"]).flags(flags))
+                .await
+            {
+                // Some updates being available is not a failure.
+                Ok(())
+                | Err(Error::CmdStatusCodeError {
+                    code: UPDATES_AVAILABLE_CODE,
+                    ..
+                }) => (),
+                Err(e) => return Err(e),
+            }
+        }
         if !kws.is_empty() {
             self.s(kws, flags).await?;
         }
@@ -280,6 +676,62 @@ impl Pm for Dnf {
     /// U upgrades or adds package(s) to the system and installs the required
     /// dependencies from sync repositories.
     async fn u(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.s(kws, flags).await
+        let kws = kws.iter().map(|kw| local_path(kw)).collect_vec();
+        let kws = kws.iter().map(String::as_str).collect_vec();
+        self.s(&kws, flags).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dnf5_versions() {
+        assert!(is_dnf5_version("dnf5 version 5.2.6.2\ndnf5 plugin API version 2.0\n"));
+        assert!(!is_dnf5_version("4.21.1\n  Installed: dnf-0:4.21.1-1.fc40.noarch\n"));
+    }
+
+    #[test]
+    fn search_hits() {
+        let names = |out: &str| {
+            out.lines()
+                .filter_map(parse_search_hit)
+                .map(|hit| (hit.name, hit.desc))
+                .collect_vec()
+        };
+        let hit = || ("nginx".to_owned(), "A high performance web server".to_owned());
+        assert_eq!(
+            names("=== Name Exactly Matched: nginx ===\nnginx.x86_64 : A high performance web server"),
+            [hit()]
+        );
+        // `dnf5`
+        assert_eq!(
+            names(" Matched fields: name (exact)\n nginx.x86_64\tA high performance web server"),
+            [hit()]
+        );
+    }
+    #[test]
+    fn advisories() {
+        let advs = |out: &str| {
+            out.lines()
+                .filter_map(parse_advisory)
+                .map(|adv| (adv.id, adv.pkg, adv.severity))
+                .collect_vec()
+        };
+        let adv = |severity: &str| {
+            ("FEDORA-2023-0b3f1a1b7c".to_owned(), "curl".to_owned(), severity.to_owned())
+        };
+        assert_eq!(
+            advs("FEDORA-2023-0b3f1a1b7c Important/Sec. curl-8.0.1-4.fc38.x86_64
+                  FEDORA-2023-1c2d3e4f5a bugfix      wget-1.21.3-5.fc38.x86_64"),
+            [adv("Important")]
+        );
+        // `dnf5`
+        assert_eq!(
+            advs("Name                   Type     Severity  Package                    Issued
+                  FEDORA-2023-0b3f1a1b7c security None      curl-8.0.1-4.fc38.x86_64   2023-10-11 01:23:45"),
+            [adv("")]
+        );
     }
 }
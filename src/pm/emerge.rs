@@ -1,13 +1,21 @@
 #![doc = docs_self!()]
 
+use std::path::PathBuf;
+
 use async_trait::async_trait;
 use indoc::indoc;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{NoCacheStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
-use crate::{dispatch::Config, error::Result, exec::Cmd};
+use super::{
+    NoCacheStrategy, PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync, PromptStrategy, Strategy,
+    Versioned,
+};
+use crate::{dispatch::Config, error::Result, exec::{
+        plan::{Plan, STEPS_SUY},
+        Cmd,
+    }};
 
 macro_rules! docs_self {
     () => {
@@ -47,8 +55,14 @@ impl Emerge {
     }
 }
 
+/// The operations supported by [`Emerge`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "f", "q", "qdt", "qi", "ql", "qo", "qs", "qu", "r", "rs", "s", "sc", "scc", "sg", "si", "sii",
+    "ss", "su", "suy", "sw", "sy",
+];
+
 #[async_trait]
-impl Pm for Emerge {
+impl PmBase for Emerge {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "emerge"
@@ -58,11 +72,63 @@ impl Pm for Emerge {
         &self.cfg
     }
 
+    fn unsupported_hint(&self, op: &str) -> Option<&str> {
+        match op {
+            "qk" => Some("qcheck"),
+            _ => None,
+        }
+    }
+
+    fn supports_ignore(&self) -> bool {
+        true
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        Some(Versioned {
+            kw: format!("{op}{name}-{ver}"),
+            ..Versioned::default()
+        })
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        // The source files are kept in `DISTDIR`, and the binary packages in
+        // `PKGDIR`.
+        let out = self
+            .check_output(
+                Cmd::new(&["portageq", "envvar", "DISTDIR", "PKGDIR"]),
+                PmMode::Mute,
+                &Strategy::default(),
+            )
+            .await?;
+        Ok(String::from_utf8(out)?
+            .lines()
+            .map(str::trim)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl PmQuery for Emerge {
+    /// F queries the package which provides FILE from the sync databases, even
+    /// if it is not installed.
+    async fn f(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["e-file"]).kws(kws).flags(flags)).await
+    }
+
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.qs(kws, flags).await
     }
 
+    /// Qdt lists packages installed as dependencies but no longer required by
+    /// any installed package, aka orphans.
+    async fn qdt(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["emerge", "--depclean", "--pretend"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Qi displays local package information: name, version, description, etc.
     async fn qi(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.si(kws, flags).await
@@ -83,7 +149,8 @@ impl Pm for Emerge {
     // when including multiple search terms, only packages with descriptions
     // matching ALL of those terms are returned.
     async fn qs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["qlist", "-I"]).kws(kws).flags(flags))
+        let cmd = Cmd::new(&["qlist", "-I"]);
+        self.search_each(cmd, kws, flags, &Strategy::default())
             .await
     }
 
@@ -92,7 +159,10 @@ impl Pm for Emerge {
         self.run(Cmd::new(&["emerge", "-uDNp", "@world"]).flags(flags))
             .await
     }
+}
 
+#[async_trait]
+impl PmRemove for Emerge {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["emerge", "--unmerge"])
@@ -111,7 +181,10 @@ impl Pm for Emerge {
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_ASK))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Emerge {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["emerge"])
@@ -136,6 +209,22 @@ impl Pm for Emerge {
         self.sc(kws, flags).await
     }
 
+    /// Sg lists all packages belonging to the GROUP, or all the groups if no
+    /// GROUP is given.
+    async fn sg(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // Groups are package sets, which are referred to as `@<set>`.
+        let sets = kws.iter().map(|kw| format!("@{kw}")).collect_vec();
+        Cmd::new(if kws.is_empty() {
+            &["emerge", "--list-sets"]
+        } else {
+            &["emerge", "--pretend", "--quiet", "--nodeps", "--emptytree"]
+        })
+        .kws(&sets)
+        .flags(flags)
+        .pipe(|cmd| self.run(cmd))
+        .await
+    }
+
     /// Si displays remote package information: name, version, description, etc.
     async fn si(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         let kws = kws.iter().map(|kw| format!("^{}$", kw)).collect_vec();
@@ -143,28 +232,51 @@ impl Pm for Emerge {
             .await
     }
 
+    /// Sii displays packages which require X to be installed, aka reverse
+    /// dependencies.
+    async fn sii(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["equery", "depends"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["qsearch"]).kws(kws).flags(flags)).await
+        let cmd = Cmd::new(&["qsearch"]);
+        self.search_each(cmd, kws, flags, &Strategy::default())
+            .await
     }
 
     /// Su updates outdated packages.
     async fn su(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["emerge", "-uDN"])
+        let mut cmd = Cmd::with_sudo(&["emerge", "-uDN"])
             .kws(if kws.is_empty() { &["@world"] } else { kws })
-            .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-            .await
+            .flags(flags);
+        cmd.flags
+            .extend(self.cfg.ignore.iter().map(|pkg| format!("--exclude={pkg}")));
+        self.run_with(cmd, PmMode::default(), &STRAT_INSTALL).await
     }
 
     /// Suy refreshes the local package database, then updates outdated
     /// packages.
     async fn suy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let plan = Plan::new(STEPS_SUY);
+        plan.step(0);
         self.sy(&[], flags).await?;
+        plan.step(1);
         self.su(kws, flags).await
     }
 
+    /// Sw retrieves all packages from the server, but does not install/upgrade
+    /// anything.
+    async fn sw(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&["emerge", "--fetchonly"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_ASK))
+            .await
+    }
+
     /// Sy refreshes the local package database.
     async fn sy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.run(Cmd::with_sudo(&["emerge", "--sync"]).flags(flags))
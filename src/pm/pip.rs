@@ -1,11 +1,15 @@
 #![doc = docs_self!()]
 
+use std::path::PathBuf;
+
 use async_trait::async_trait;
 use indoc::indoc;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync, PromptStrategy, Strategy, Versioned,
+};
 use crate::{
     dispatch::Config,
     error::{Error, Result},
@@ -56,8 +60,11 @@ impl Pip {
     }
 }
 
+/// The operations supported by [`Pip`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &["q", "qi", "qs", "qu", "r", "s", "sc", "su", "sw", "t"];
+
 #[async_trait]
-impl Pm for Pip {
+impl PmBase for Pip {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "pip"
@@ -67,6 +74,41 @@ impl Pm for Pip {
         &self.cfg
     }
 
+    fn unsupported_hint(&self, op: &str) -> Option<&str> {
+        match op {
+            "qe" => Some("pip list --not-required"),
+            "ql" => Some("pip show --files"),
+            "ss" => Some("pip index versions"),
+            _ => None,
+        }
+    }
+
+    fn supports_download_dir(&self) -> bool {
+        true
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        let op = if op == "=" { "==" } else { op };
+        Some(Versioned {
+            kw: format!("{name}{op}{ver}"),
+            ..Versioned::default()
+        })
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        let out = self
+            .check_output(
+                Cmd::new(&[self.cmd(), "cache", "dir"]),
+                PmMode::Mute,
+                &Strategy::default(),
+            )
+            .await?;
+        Ok(vec![String::from_utf8(out)?.trim().into()])
+    }
+}
+
+#[async_trait]
+impl PmQuery for Pip {
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
@@ -95,7 +137,7 @@ impl Pm for Pip {
         let out_bytes = self
             .check_output(cmd, PmMode::Mute, &Strategy::default())
             .await?;
-        exec::grep_print(&String::from_utf8(out_bytes)?, kws)?;
+        exec::grep_print(&String::from_utf8(out_bytes)?, kws, self.cfg.exact)?;
         Ok(())
     }
 
@@ -108,6 +150,17 @@ impl Pm for Pip {
             .await
     }
 
+    /// T checks if the given dependencies are satisfied, printing the unsatisfied ones.
+    async fn t(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.deptest(kws, Cmd::new(&[self.cmd(), "show"]).flags(flags), |out| {
+            out.lines().find_map(|ln| ln.strip_prefix("Version: "))
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl PmRemove for Pip {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&[self.cmd(), "uninstall"] as _)
@@ -116,7 +169,10 @@ impl Pm for Pip {
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_UNINSTALL))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Pip {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&[self.cmd(), "install"] as _)
@@ -151,10 +207,10 @@ impl Pm for Pip {
     /// Sw retrieves all packages from the server, but does not install/upgrade
     /// anything.
     async fn sw(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&[self.cmd(), "download"] as _)
-            .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.run(cmd))
-            .await
+        let mut cmd = Cmd::new(&[self.cmd(), "download"] as _).kws(kws).flags(flags);
+        if let Some(dir) = self.download_dir()? {
+            cmd.flags.push(format!("--dest={dir}"));
+        }
+        self.run(cmd).await
     }
 }
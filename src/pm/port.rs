@@ -5,8 +5,11 @@ use indoc::indoc;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{NoCacheStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
-use crate::{dispatch::Config, error::Result, exec::Cmd};
+use super::{NoCacheStrategy, PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync, PromptStrategy, Strategy};
+use crate::{dispatch::Config, error::Result, exec::{
+        plan::{Plan, STEPS_SUY},
+        Cmd,
+    }};
 
 macro_rules! docs_self {
     () => {
@@ -41,8 +44,13 @@ impl Port {
     }
 }
 
-#[async_trait]
-impl Pm for Port {
+/// The operations supported by [`Port`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "q", "qc", "qi", "ql", "qo", "qs", "qu", "r", "rss", "s", "sc", "scc", "si", "sii", "ss", "su",
+    "suy", "sy",
+];
+
+impl PmBase for Port {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "port"
@@ -52,6 +60,18 @@ impl Pm for Port {
         &self.cfg
     }
 
+    fn unsupported_hint(&self, op: &str) -> Option<&str> {
+        match op {
+            "qd" => Some("port echo unrequested"),
+            "qdt" => Some("port echo leaves"),
+            "qe" => Some("port echo requested"),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl PmQuery for Port {
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.run(Cmd::new(&["port", "installed"]).kws(kws).flags(flags))
@@ -95,7 +115,10 @@ impl Pm for Port {
         self.run(Cmd::new(&["port", "outdated"]).kws(kws).flags(flags))
             .await
     }
+}
 
+#[async_trait]
+impl PmRemove for Port {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["port", "uninstall"])
@@ -114,7 +137,10 @@ impl Pm for Port {
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Port {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["port", "install"])
@@ -157,10 +183,18 @@ impl Pm for Port {
             .await
     }
 
+    /// Sii displays packages which require X to be installed, aka reverse
+    /// dependencies.
+    async fn sii(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["port", "dependents"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["port", "search"]).kws(kws).flags(flags))
+        let cmd = Cmd::new(&["port", "search"]);
+        self.search_each(cmd, kws, flags, &Strategy::default())
             .await
     }
 
@@ -180,7 +214,10 @@ impl Pm for Port {
     /// Suy refreshes the local package database, then updates outdated
     /// packages.
     async fn suy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let plan = Plan::new(STEPS_SUY);
+        plan.step(0);
         self.sy(&[], flags).await?;
+        plan.step(1);
         self.su(kws, flags).await
     }
 
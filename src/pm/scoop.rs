@@ -1,15 +1,28 @@
 #![doc = docs_self!()]
 
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
 use async_trait::async_trait;
 use indoc::indoc;
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{NoCacheStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    dir_size, NoCacheStrategy, PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync, PromptStrategy,
+    ReasonFilter, Strategy, Versioned,
+};
 use crate::{
     dispatch::Config,
-    error::Result,
-    exec::{self, Cmd},
+    error::{Error, Result},
+    exec::{
+        self,
+        plan::{Plan, STEPS_SUY},
+        Cmd,
+    },
     print::{self, PROMPT_RUN},
 };
 
@@ -38,6 +51,55 @@ static STRAT_INSTALL: Lazy<Strategy> = Lazy::new(|| Strategy {
     ..Strategy::default()
 });
 
+/// Gets the root directory of Scoop, ie. `$SCOOP` or `~/scoop`.
+fn root() -> Result<PathBuf> {
+    env::var_os("SCOOP")
+        .map(PathBuf::from)
+        .or_else(|| dirs_next::home_dir().map(|home| home.join("scoop")))
+        .ok_or_else(|| Error::OtherError("Scoop root path not found".into()))
+}
+
+/// Gets the version in the app manifest at `path`, if any.
+fn version_of(path: &Path) -> String {
+    fs::read(path)
+        .ok()
+        .and_then(|json| serde_json::from_slice::<serde_json::Value>(&json).ok())
+        .and_then(|manifest| manifest["version"].as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+/// Lists the buckets added, ie. the directories in `$SCOOP/buckets`.
+fn buckets() -> Result<Vec<String>> {
+    let dir = root()?.join("buckets");
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut buckets = vec![];
+    for entry in fs::read_dir(dir)? {
+        buckets.push(entry?.file_name().to_string_lossy().into_owned());
+    }
+    buckets.sort_unstable();
+    Ok(buckets)
+}
+
+/// Gets the buckets of the apps given as `<bucket>/<app>` in `kws`, eg.
+/// `extras/vscode`, which are not among the `added` ones.
+fn missing_buckets<'k>(kws: &[&'k str], added: &[String]) -> Vec<&'k str> {
+    kws.iter()
+        .filter_map(|kw| kw.split_once('/'))
+        .filter(|(bucket, app)| {
+            !app.contains('/')
+                && !bucket.is_empty()
+                && bucket
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        })
+        .map(|(bucket, _)| bucket)
+        .filter(|bucket| !added.iter().any(|added| added == bucket))
+        .unique()
+        .collect()
+}
+
 impl Scoop {
     #[must_use]
     #[allow(missing_docs)]
@@ -45,22 +107,40 @@ impl Scoop {
         Scoop { cfg }
     }
 
+    /// Adds the buckets of the apps given as `<bucket>/<app>` in `kws` which
+    /// are missing, so that they can be installed.
+    async fn add_buckets(&self, kws: &[&str]) -> Result<()> {
+        let added = buckets().unwrap_or_default();
+        for bucket in missing_buckets(kws, &added) {
+            Cmd::with_powershell(&["scoop", "bucket", "add", bucket])
+                .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+                .await?;
+        }
+        Ok(())
+    }
+
     async fn search_regex(&self, cmd: &[&str], kws: &[&str], flags: &[&str]) -> Result<()> {
-        let cmd = Cmd::new(cmd).flags(flags);
+        let cmd = Cmd::with_powershell(cmd).flags(flags);
         if !self.cfg.dry_run {
             print::print_cmd(&cmd, PROMPT_RUN);
         }
         let out_bytes = self
             .check_output(cmd, PmMode::Mute, &Strategy::default())
             .await?;
-        exec::grep_print(&String::from_utf8(out_bytes)?, kws)?;
+        exec::grep_print(&String::from_utf8(out_bytes)?, kws, self.cfg.exact)?;
         Ok(())
     }
 }
 
 // Windows is so special! It's better not to "sudo" automatically.
+/// The operations supported by [`Scoop`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "q", "qd", "qdt", "qe", "qi", "qs", "qu", "r", "rn", "s", "sc", "scc", "si", "sl", "ss", "su",
+    "suy", "sy",
+];
+
 #[async_trait]
-impl Pm for Scoop {
+impl PmBase for Scoop {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "scoop"
@@ -70,16 +150,100 @@ impl Pm for Scoop {
         &self.cfg
     }
 
+    async fn explicit(&self) -> Result<Vec<String>> {
+        let explicit = self.by_reason(ReasonFilter::Explicit).await?;
+        Ok(explicit.into_iter().map(|(name, _)| name).collect())
+    }
+
+    async fn installed(&self) -> Result<Vec<(String, String)>> {
+        // Each app is installed to `$SCOOP/apps/<name>`, along with the
+        // manifest of its current version.
+        let mut apps = vec![];
+        for entry in fs::read_dir(root()?.join("apps"))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Scoop itself is also installed as an app.
+            if name == "scoop" {
+                continue;
+            }
+            let version = version_of(&entry.path().join("current").join("manifest.json"));
+            apps.push((name, version));
+        }
+        apps.sort_unstable();
+        Ok(apps)
+    }
+
+    async fn pin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // The apps held are marked as `Held package` by `scoop list`.
+        let cmd = if kws.is_empty() {
+            Cmd::with_powershell(&["scoop", "list"])
+        } else {
+            Cmd::with_powershell(&["scoop", "hold"]).kws(kws)
+        };
+        self.run(cmd.flags(flags)).await
+    }
+
+    async fn unpin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_powershell(&["scoop", "unhold"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        (op == "=").then(|| Versioned {
+            kw: format!("{name}@{ver}"),
+            ..Versioned::default()
+        })
+    }
+
+    async fn sizes(&self, _flags: &[&str]) -> Result<Vec<(String, u64)>> {
+        let apps = root()?.join("apps");
+        Ok(self
+            .installed()
+            .await?
+            .into_iter()
+            .map(|(name, _)| {
+                let size = dir_size(&apps.join(&name).join("current"));
+                (name, size)
+            })
+            .collect())
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        Ok(vec![root()?.join("cache")])
+    }
+}
+
+#[async_trait]
+impl PmQuery for Scoop {
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
-            self.run(Cmd::new(&["powershell", "scoop", "list"]).flags(flags))
+            self.run(Cmd::with_powershell(&["scoop", "list"]).flags(flags))
                 .await
         } else {
             self.qs(kws, flags).await
         }
     }
 
+    /// Qd lists packages installed as dependencies (not explicitly).
+    async fn qd(&self, kws: &[&str], _flags: &[&str]) -> Result<()> {
+        self.print_by_reason(kws, ReasonFilter::Dependency).await
+    }
+
+    /// Qdt lists packages installed as dependencies but no longer required by
+    /// any installed package, aka orphans.
+    async fn qdt(&self, kws: &[&str], _flags: &[&str]) -> Result<()> {
+        self.print_by_reason(kws, ReasonFilter::Orphan).await
+    }
+
+    /// Qe lists packages installed explicitly (not as dependencies).
+    async fn qe(&self, kws: &[&str], _flags: &[&str]) -> Result<()> {
+        self.print_by_reason(kws, ReasonFilter::Explicit).await
+    }
+
     /// Qi displays local package information: name, version, description, etc.
     async fn qi(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.si(kws, flags).await
@@ -90,51 +254,59 @@ impl Pm for Scoop {
     // when including multiple search terms, only packages with descriptions
     // matching ALL of those terms are returned.
     async fn qs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.search_regex(&["powershell", "scoop", "list"], kws, flags)
+        self.search_regex(&["scoop", "list"], kws, flags)
             .await
     }
 
     /// Qu lists packages which have an update available.
     async fn qu(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["powershell", "scoop", "status"])
+        Cmd::with_powershell(&["scoop", "status"])
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run(cmd))
             .await
     }
+}
 
+#[async_trait]
+impl PmRemove for Scoop {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["powershell", "scoop", "uninstall"])
+        Cmd::with_powershell(&["scoop", "uninstall"])
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .pipe(|cmd| self.run_tracked(cmd, &[], &STRAT_PROMPT))
             .await
     }
 
     /// Rn removes a package and skips the generation of configuration backup
     /// files.
     async fn rn(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["powershell", "scoop", "uninstall", "--purge"])
+        Cmd::with_powershell(&["scoop", "uninstall", "--purge"])
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
+            .pipe(|cmd| self.run_tracked(cmd, &[], &STRAT_PROMPT))
             .await
     }
+}
 
-    /// S installs one or more packages by name.
+#[async_trait]
+impl PmSync for Scoop {
+    /// S installs one or more packages by name, adding the missing buckets of
+    /// the ones given as `<bucket>/<app>` beforehand.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["powershell", "scoop", "install"])
+        self.add_buckets(kws).await?;
+        Cmd::with_powershell(&["scoop", "install"])
             .kws(kws)
             .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
+            .pipe(|cmd| self.run_tracked(cmd, kws, &STRAT_INSTALL))
             .await
     }
 
     /// Sc removes all the cached packages that are not currently installed, and
     /// the unused sync database.
     async fn sc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["powershell", "scoop", "cache", "rm"])
+        Cmd::with_powershell(&["scoop", "cache", "rm"])
             .kws(if kws.is_empty() { &["*"] } else { kws })
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
@@ -148,26 +320,56 @@ impl Pm for Scoop {
 
     /// Si displays remote package information: name, version, description, etc.
     async fn si(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["powershell", "scoop", "info"])
+        Cmd::with_powershell(&["scoop", "info"])
             .kws(kws)
             .flags(flags)
             .pipe(|cmd| self.run(cmd))
             .await
     }
 
+    /// Sl displays a list of all packages in all installation sources that are
+    /// handled by the packages management, ie. the apps of the buckets added,
+    /// or of the given ones.
+    async fn sl(&self, kws: &[&str], _flags: &[&str]) -> Result<()> {
+        let dir = root()?.join("buckets");
+        let buckets = if kws.is_empty() {
+            buckets()?
+        } else {
+            kws.iter().map(|&kw| kw.to_owned()).collect()
+        };
+        for bucket in buckets {
+            // The app manifests are in the `bucket` directory of each bucket.
+            let manifests = dir.join(&bucket).join("bucket");
+            if !manifests.exists() {
+                return Err(Error::OtherError(format!("Bucket `{bucket}` not found")));
+            }
+            let mut apps = vec![];
+            for entry in fs::read_dir(manifests)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    let app = path.file_stem().unwrap_or_default().to_string_lossy();
+                    apps.push((app.into_owned(), version_of(&path)));
+                }
+            }
+            apps.sort_unstable();
+            for (app, version) in apps {
+                println!("{bucket} {app} {version}");
+            }
+        }
+        Ok(())
+    }
+
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["powershell", "scoop", "search"])
-            .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.run(cmd))
+        let cmd = Cmd::with_powershell(&["scoop", "search"]);
+        self.search_each(cmd, kws, flags, &Strategy::default())
             .await
     }
 
     /// Sy refreshes the local package database.
     async fn sy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["powershell", "scoop", "update"]).flags(flags))
+        self.run(Cmd::with_powershell(&["scoop", "update"]).flags(flags))
             .await?;
         if !kws.is_empty() {
             self.s(kws, flags).await?;
@@ -177,7 +379,7 @@ impl Pm for Scoop {
 
     /// Su updates outdated packages.
     async fn su(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["powershell", "scoop", "update"])
+        Cmd::with_powershell(&["scoop", "update"])
             .kws(if kws.is_empty() { &["*"] } else { kws })
             .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
@@ -187,7 +389,28 @@ impl Pm for Scoop {
     /// Suy refreshes the local package database, then updates outdated
     /// packages.
     async fn suy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let plan = Plan::new(STEPS_SUY);
+        plan.step(0);
         self.sy(&[], flags).await?;
+        plan.step(1);
         self.su(kws, flags).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refs() {
+        let added = ["main".to_owned(), "extras".to_owned()];
+        assert_eq!(
+            missing_buckets(
+                &["git", "extras/vscode", "java/temurin-jdk", "java/openjdk"],
+                &added
+            ),
+            ["java"]
+        );
+        assert!(missing_buckets(&["https://example.com/app.json", "./app.json"], &added).is_empty());
+    }
+}
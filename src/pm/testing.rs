@@ -0,0 +1,186 @@
+#![doc = docs_self!()]
+
+use std::{fmt, sync::Mutex};
+
+use async_trait::async_trait;
+use indoc::indoc;
+use tt_call::tt_call;
+
+use super::{PmBase, PmQuery, PmRemove, PmSync};
+use crate::{dispatch::Config, error::Result, methods};
+
+macro_rules! docs_self {
+    () => {
+        indoc! {"
+            Test utilities for the code built upon [`Pm`](super::Pm), eg. a [`MockPm`]
+            which records the methods called on it instead of running any
+            command.
+
+            ```
+            use pacaptr::{
+                dispatch::Config,
+                pm::{testing::{Call, MockPm}, PmSync},
+            };
+
+            # #[tokio::main]
+            # async fn main() -> Result<(), pacaptr::error::Error> {
+            let pm = MockPm::new(Config::default());
+            pm.s(&[\"curl\"], &[\"--verbose\"]).await?;
+            assert_eq!(pm.calls(), [Call::new(\"s\", &[\"curl\"], &[\"--verbose\"])]);
+            # Ok(())
+            # }
+            ```
+        "}
+    };
+}
+
+/// A method called on a [`MockPm`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Call {
+    /// The name of the method, eg. `suy` for `-Syu`.
+    pub method: String,
+    /// The keywords passed to the method.
+    pub kws: Vec<String>,
+    /// The flags passed to the method.
+    pub flags: Vec<String>,
+}
+
+impl Call {
+    /// Makes the [`Call`] of `method` with `kws` and `flags`.
+    #[must_use]
+    pub fn new(method: impl Into<String>, kws: &[&str], flags: &[&str]) -> Self {
+        let to_vec = |strs: &[&str]| strs.iter().map(|&s| s.to_owned()).collect();
+        Call {
+            method: method.into(),
+            kws: to_vec(kws),
+            flags: to_vec(flags),
+        }
+    }
+}
+
+/// The response of a [`MockPm`] to a [`Call`].
+type Respond = Box<dyn Fn(&Call) -> Result<()> + Send + Sync>;
+
+/// A package manager which records the methods called on it, and succeeds
+/// without running any command unless told otherwise with
+/// [`respond_with`](MockPm::respond_with).
+///
+/// Only the methods mapped to `pacman` operations, eg. [`PmSync::s`], are
+/// recorded. The other ones keep their default implementations.
+pub struct MockPm {
+    cfg: Config,
+    calls: Mutex<Vec<Call>>,
+    respond: Option<Respond>,
+}
+
+impl MockPm {
+    /// The name of every [`MockPm`].
+    pub const NAME: &'static str = "mockpm";
+
+    /// Creates a new [`MockPm`] with the given [`Config`].
+    #[must_use]
+    pub fn new(cfg: Config) -> Self {
+        MockPm {
+            cfg,
+            calls: Mutex::default(),
+            respond: None,
+        }
+    }
+
+    /// Makes each method called return the result of `respond`, eg. to
+    /// simulate a failure, once the [`Call`] has been recorded.
+    #[must_use]
+    pub fn respond_with(
+        self,
+        respond: impl Fn(&Call) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        MockPm {
+            respond: Some(Box::new(respond)),
+            ..self
+        }
+    }
+
+    /// Gets the methods called so far, in order.
+    ///
+    /// # Panics
+    /// Panics if a previous [`Call`] panicked while being recorded.
+    #[must_use]
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Records a [`Call`] and responds to it.
+    fn call(&self, method: &str, kws: &[&str], flags: &[&str]) -> Result<()> {
+        let call = Call::new(method, kws, flags);
+        self.calls.lock().unwrap().push(call.clone());
+        self.respond.as_ref().map_or(Ok(()), |respond| respond(&call))
+    }
+}
+
+impl fmt::Debug for MockPm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockPm")
+            .field("cfg", &self.cfg)
+            .field("calls", &self.calls)
+            .finish_non_exhaustive()
+    }
+}
+
+macro_rules! impl_pm_mock {(
+    methods = [{ $(
+        $( #[$tmeta:meta] )*
+        trait $cap:ident { $(
+            $( #[$meta:meta] )*
+            async fn $method:ident;
+        )* }
+    )* }]
+) => {
+    impl PmBase for MockPm {
+        /// Gets the name of the package manager.
+        fn name(&self) -> &str {
+            Self::NAME
+        }
+
+        fn cfg(&self) -> &Config {
+            &self.cfg
+        }
+
+        /// A [`MockPm`] leaves out the ignored packages as asked.
+        fn supports_ignore(&self) -> bool {
+            true
+        }
+
+    }
+
+    // * Automatically generated impls below... *
+    $( #[async_trait]
+    impl $cap for MockPm {
+        $( async fn $method(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+            self.call(stringify!($method), kws, flags)
+        } )*
+    } )*
+};}
+
+tt_call! {
+    macro = [{ methods }]
+    ~~> impl_pm_mock
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn recorded() {
+        let pm = MockPm::new(Config::default()).respond_with(|call| match call.method.as_str() {
+            "r" => Err(Error::OtherError("nope".into())),
+            _ => Ok(()),
+        });
+        pm.suy(&[], &[]).await.unwrap();
+        assert!(pm.r(&["curl"], &[]).await.is_err());
+        let methods = pm.calls().into_iter().map(|call| call.method).collect::<Vec<_>>();
+        assert_eq!(methods, ["suy", "r"]);
+    }
+}
@@ -5,7 +5,7 @@ use indoc::indoc;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{DryRunStrategy, Pm, PmHelper, PmMode, Strategy};
+use super::{DryRunStrategy, PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync, Strategy};
 use crate::{dispatch::Config, error::Result, exec::Cmd};
 
 macro_rules! docs_self {
@@ -35,8 +35,12 @@ impl Tlmgr {
     }
 }
 
-#[async_trait]
-impl Pm for Tlmgr {
+/// The operations supported by [`Tlmgr`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "q", "qi", "qk", "ql", "r", "s", "sg", "si", "sl", "ss", "su", "suy", "u",
+];
+
+impl PmBase for Tlmgr {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "tlmgr"
@@ -45,7 +49,10 @@ impl Pm for Tlmgr {
     fn cfg(&self) -> &Config {
         &self.cfg
     }
+}
 
+#[async_trait]
+impl PmQuery for Tlmgr {
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.qi(kws, flags).await
@@ -74,7 +81,10 @@ impl Pm for Tlmgr {
             .pipe(|cmd| self.run(cmd))
             .await
     }
+}
 
+#[async_trait]
+impl PmRemove for Tlmgr {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&["tlmgr", "remove"])
@@ -83,7 +93,10 @@ impl Pm for Tlmgr {
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_CHECK_DRY))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Tlmgr {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(&["tlmgr", "install"])
@@ -93,6 +106,17 @@ impl Pm for Tlmgr {
             .await
     }
 
+    /// Sg lists all packages belonging to the GROUP, or all the groups if no
+    /// GROUP is given.
+    async fn sg(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // Groups are collections, whose members are shown as dependencies.
+        Cmd::new(&["tlmgr", "info"])
+            .kws(if kws.is_empty() { &["collections"] } else { kws })
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
     /// Si displays remote package information: name, version, description, etc.
     async fn si(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.run(Cmd::new(&["tlmgr", "info"]).kws(kws).flags(flags))
@@ -108,10 +132,8 @@ impl Pm for Tlmgr {
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["tlmgr", "search", "--global"])
-            .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.run(cmd))
+        let cmd = Cmd::new(&["tlmgr", "search", "--global"]);
+        self.search_each(cmd, kws, flags, &Strategy::default())
             .await
     }
 
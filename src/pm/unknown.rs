@@ -1,9 +1,8 @@
 #![doc = docs_self!()]
 
-use async_trait::async_trait;
 use indoc::indoc;
 
-use super::Pm;
+use super::{PmBase, PmQuery, PmRemove, PmSync};
 use crate::dispatch::Config;
 
 macro_rules! docs_self {
@@ -32,8 +31,7 @@ impl Unknown {
     }
 }
 
-#[async_trait]
-impl Pm for Unknown {
+impl PmBase for Unknown {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         &self.name
@@ -43,3 +41,9 @@ impl Pm for Unknown {
         &self.cfg
     }
 }
+
+impl PmQuery for Unknown {}
+
+impl PmRemove for Unknown {}
+
+impl PmSync for Unknown {}
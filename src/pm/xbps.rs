@@ -1,6 +1,6 @@
 #![doc = docs_self!()]
 
-use std::io::Write;
+use std::{io::Write, path::PathBuf};
 
 use async_trait::async_trait;
 use futures::prelude::*;
@@ -8,7 +8,9 @@ use indoc::indoc;
 use once_cell::sync::Lazy;
 use tap::Pipe;
 
-use super::{Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    PmBase, PmHelper, PmMode, PmQuery, PmRemove, PmSync, PromptStrategy, Strategy, Versioned,
+};
 use crate::{
     dispatch::Config,
     error::{Error, Result},
@@ -45,8 +47,14 @@ impl Xbps {
     }
 }
 
+/// The operations supported by [`Xbps`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "f", "fy", "q", "qdt", "qe", "qi", "ql", "qo", "qs", "r", "rs", "s", "sc", "si", "sii", "ss", "su",
+    "suy", "sw", "sy", "t",
+];
+
 #[async_trait]
-impl Pm for Xbps {
+impl PmBase for Xbps {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "xbps"
@@ -56,6 +64,65 @@ impl Pm for Xbps {
         &self.cfg
     }
 
+    fn unsupported_hint(&self, op: &str) -> Option<&str> {
+        match op {
+            "qk" => Some("xbps-pkgdb"),
+            _ => None,
+        }
+    }
+
+    fn supports_download_dir(&self) -> bool {
+        true
+    }
+
+    async fn pin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        if kws.is_empty() {
+            return self.run(Cmd::new(&["xbps-query", "-H"]).flags(flags)).await;
+        }
+        Cmd::with_sudo(&["xbps-pkgdb", "-m", "hold"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
+    async fn unpin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&["xbps-pkgdb", "-m", "unhold"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        // An exact version would need the revision, eg. `curl-8.5.0_1`.
+        (op != "=").then(|| Versioned {
+            kw: format!("{name}{op}{ver}"),
+            ..Versioned::default()
+        })
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        Ok(vec!["/var/cache/xbps".into()])
+    }
+}
+
+#[async_trait]
+impl PmQuery for Xbps {
+    /// F queries the package which provides FILE from the sync databases, even
+    /// if it is not installed.
+    async fn f(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["xbps-query", "-Ro"]).kws(kws).flags(flags))
+            .await
+    }
+
+    /// Fy refreshes the file database, then queries the package which provides
+    /// FILE.
+    async fn fy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.sy(&[], flags).await?;
+        self.f(kws, flags).await
+    }
+
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
@@ -101,6 +168,13 @@ impl Pm for Xbps {
         })?
     }
 
+    /// Qdt lists packages installed as dependencies but no longer required by
+    /// any installed package, aka orphans.
+    async fn qdt(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["xbps-query", "-O"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Qe lists packages installed explicitly (not as dependencies).
     async fn qe(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
@@ -167,12 +241,33 @@ impl Pm for Xbps {
             .await
     }
 
+    /// Qo queries the package which provides FILE.
+    async fn qo(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["xbps-query", "-o"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Qs searches locally installed package for names or descriptions.
     async fn qs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["xbps-query", "-s"]).kws(kws).flags(flags))
+        let cmd = Cmd::new(&["xbps-query", "-s"]);
+        self.search_each(cmd, kws, flags, &Strategy::default())
             .await
     }
 
+    /// T checks if the given dependencies are satisfied, printing the unsatisfied ones.
+    async fn t(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // The output looks like `name-1.0_1`.
+        self.deptest(
+            kws,
+            Cmd::new(&["xbps-query", "-p", "pkgver"]).flags(flags),
+            |out| out.trim().rsplit_once('-').map(|(_, ver)| ver),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl PmRemove for Xbps {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["xbps-remove"])
@@ -191,7 +286,10 @@ impl Pm for Xbps {
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Xbps {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["xbps-install"])
@@ -227,7 +325,8 @@ impl Pm for Xbps {
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.run(Cmd::new(&["xbps-query", "-Rs"]).kws(kws).flags(flags))
+        let cmd = Cmd::new(&["xbps-query", "-Rs"]);
+        self.search_each(cmd, kws, flags, &Strategy::default())
             .await
     }
 
@@ -250,11 +349,11 @@ impl Pm for Xbps {
     /// Sw retrieves all packages from the server, but does not install/upgrade
     /// anything.
     async fn sw(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["xbps-install", "-D"])
-            .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
-            .await
+        let mut cmd = Cmd::with_sudo(&["xbps-install", "-D"]).kws(kws).flags(flags);
+        if let Some(dir) = self.download_dir()? {
+            cmd.flags.push(format!("--cachedir={dir}"));
+        }
+        self.run_with(cmd, PmMode::default(), &STRAT_PROMPT).await
     }
 
     /// Su updates outdated packages.
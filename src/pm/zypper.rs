@@ -1,15 +1,22 @@
 #![doc = docs_self!()]
 
+use std::{path::PathBuf, time::SystemTime};
+
 use async_trait::async_trait;
 use indoc::indoc;
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use tap::prelude::*;
 
-use super::{DryRunStrategy, NoCacheStrategy, Pm, PmHelper, PmMode, PromptStrategy, Strategy};
+use super::{
+    local_path, Advisory, DryRunStrategy, NoCacheStrategy, PmBase, PmHelper, PmMode, PmQuery,
+    PmRemove, PmSync, PromptStrategy, SearchHit, Strategy, Versioned,
+};
 use crate::{
     dispatch::Config,
     error::Result,
     exec::{self, Cmd},
+    version::Scheme,
 };
 
 macro_rules! docs_self {
@@ -32,15 +39,16 @@ static STRAT_CHECK_DRY: Lazy<Strategy> = Lazy::new(|| Strategy {
 });
 
 static STRAT_PROMPT: Lazy<Strategy> = Lazy::new(|| Strategy {
-    prompt: PromptStrategy::native_no_confirm(&["-y"]),
+    prompt: PromptStrategy::native_no_confirm_global(&["--non-interactive"]),
     dry_run: DryRunStrategy::with_flags(&["--dry-run"]),
     ..Strategy::default()
 });
 
 static STRAT_INSTALL: Lazy<Strategy> = Lazy::new(|| Strategy {
-    prompt: PromptStrategy::native_no_confirm(&["-y"]),
+    prompt: PromptStrategy::native_no_confirm_global(&["--non-interactive"]),
     no_cache: NoCacheStrategy::Scc,
     dry_run: DryRunStrategy::with_flags(&["--dry-run"]),
+    ..Strategy::default()
 });
 
 impl Zypper {
@@ -54,10 +62,63 @@ impl Zypper {
         self.run_with(cmd, PmMode::default(), &STRAT_CHECK_DRY)
             .await
     }
+
+    /// Runs `zypper dist-upgrade`, or `zypper patch` with `--patch`, with the
+    /// given global options, locking the ignored packages in the meantime.
+    async fn dist_upgrade(&self, global_opts: &[&str], flags: &[&str]) -> Result<()> {
+        let subcmd = if self.cfg.patch {
+            "patch"
+        } else {
+            "dist-upgrade"
+        };
+        // Each lock is listed as a row like `1 | curl | package | (any)`.
+        let locked = self
+            .hold_ignored(
+                Cmd::new(&["zypper", "--quiet", "locks"]),
+                Cmd::with_sudo(&["zypper", "addlock"]),
+                |ln| ln.split('|').nth(1).map(str::trim),
+            )
+            .await?;
+        let res = Cmd::with_sudo(&[&["zypper"], global_opts, &[subcmd]].concat())
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
+            .await;
+        if !locked.is_empty() {
+            self.run(Cmd::with_sudo(&["zypper", "removelock"]).kws(&locked))
+                .await?;
+        }
+        res
+    }
+}
+
+/// The operations supported by [`Zypper`], as listed in the compatibility table.
+pub(crate) const SUPPORTED: &[&str] = &[
+    "f", "fy", "q", "qc", "qdt", "qi", "qk", "ql", "qm", "qo", "qp", "qs", "qu", "r", "rc", "rcs",
+    "rss", "s", "sc", "scc", "sg", "si", "sii", "sl", "ss", "su", "suy", "sw", "sy", "t", "u",
+];
+
+/// Parses a row of `zypper list-patches`, which looks like `<repo> | <patch> |
+/// <category> | <severity> | <interactive> | <status> | <summary>`, keeping
+/// only the security patches.
+#[must_use]
+fn parse_advisory(ln: &str) -> Option<Advisory> {
+    let (_repo, id, category, severity, _interactive, _status, summary) =
+        ln.split('|').map(str::trim).collect_tuple()?;
+    (category == "security").then(|| Advisory {
+        id: id.into(),
+        // The summary looks like `Security update for curl`.
+        pkg: summary
+            .strip_prefix("Security update for ")
+            .unwrap_or(summary)
+            .into(),
+        severity: severity.into(),
+        // Only the patches needed are listed.
+        fixed: true,
+    })
 }
 
 #[async_trait]
-impl Pm for Zypper {
+impl PmBase for Zypper {
     /// Gets the name of the package manager.
     fn name(&self) -> &str {
         "zypper"
@@ -67,6 +128,155 @@ impl Pm for Zypper {
         &self.cfg
     }
 
+    fn unsupported_hint(&self, op: &str) -> Option<&str> {
+        match op {
+            "qe" => Some("zypper packages --userinstalled"),
+            "qt" => Some("zypper packages --unneeded"),
+            _ => None,
+        }
+    }
+
+    fn supports_ignore(&self) -> bool {
+        true
+    }
+
+    fn supports_download_dir(&self) -> bool {
+        true
+    }
+
+    fn version_scheme(&self) -> Scheme {
+        Scheme::Rpm
+    }
+
+    async fn search(&self, kws: &[&str], flags: &[&str]) -> Result<Vec<SearchHit>> {
+        // Each package is listed as a row like `i | curl | <summary> | package`.
+        let cmd = Cmd::new(&["zypper", "--quiet", "search", "--type", "package"])
+            .kws(kws)
+            .flags(flags);
+        self.search_lines(cmd, |ln| {
+            let (_status, name, desc, _kind) = ln.split('|').map(str::trim).collect_tuple()?;
+            (name != "Name").then(|| SearchHit {
+                name: name.into(),
+                desc: desc.into(),
+                ..SearchHit::default()
+            })
+        })
+        .await
+    }
+
+    async fn upgradable(&self) -> Result<Vec<String>> {
+        // Each update is listed as a row like `v | <repo> | curl | <current> |
+        // <available> | <arch>`.
+        self.names_from(Cmd::new(&["zypper", "--quiet", "list-updates"]), |ln| {
+            let mut cols = ln.split('|').map(str::trim);
+            (cols.next()? == "v").then(|| cols.nth(1)).flatten()
+        })
+        .await
+    }
+
+    async fn su_only(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&["zypper", "update"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
+            .await
+    }
+
+    async fn disk_usage(&self, _kws: &[&str], _flags: &[&str]) -> Result<Vec<(PathBuf, u64)>> {
+        Ok(["/var/cache/zypp", "/usr"].map(|path| (path.into(), 0)).into())
+    }
+
+    async fn explicit(&self) -> Result<Vec<String>> {
+        // Each package installed by the user is listed as a row like `i+ | curl |
+        // <summary> | package`.
+        let cmd = Cmd::new(&[
+            "zypper",
+            "--quiet",
+            "search",
+            "--installed-only",
+            "--type",
+            "package",
+        ]);
+        self.names_from(cmd, |ln| {
+            let mut cols = ln.split('|').map(str::trim);
+            (cols.next()? == "i+").then(|| cols.next()).flatten()
+        })
+        .await
+    }
+
+    async fn install_dates(&self, flags: &[&str]) -> Result<Vec<(String, SystemTime)>> {
+        self.install_dates_from(
+            Cmd::new(&["rpm", "-qa", "--queryformat", "%{INSTALLTIME} %{NAME}\n"]).flags(flags),
+        )
+        .await
+    }
+
+    async fn pin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        if kws.is_empty() {
+            return self.run(Cmd::new(&["zypper", "locks"]).flags(flags)).await;
+        }
+        Cmd::with_sudo(&["zypper", "addlock"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
+    async fn unpin(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::with_sudo(&["zypper", "removelock"])
+            .kws(kws)
+            .flags(flags)
+            .pipe(|cmd| self.run(cmd))
+            .await
+    }
+
+    fn versioned(&self, name: &str, op: &str, ver: &str) -> Option<Versioned> {
+        Some(Versioned {
+            kw: format!("{name}{op}{ver}"),
+            ..Versioned::default()
+        })
+    }
+
+    fn supports_url(&self) -> bool {
+        true
+    }
+
+    async fn sizes(&self, flags: &[&str]) -> Result<Vec<(String, u64)>> {
+        let cmd = Cmd::new(&["rpm", "-qa", "--queryformat", "%{NAME}\t%{SIZE}\n"]).flags(flags);
+        self.sizes_from(cmd, 1).await
+    }
+
+    async fn advisories(&self, flags: &[&str]) -> Result<Vec<Advisory>> {
+        let cmd = Cmd::new(&["zypper", "--quiet", "list-patches", "--category", "security"])
+            .flags(flags);
+        let out = self
+            .check_output(cmd, PmMode::Mute, &Strategy::default())
+            .await?
+            .pipe(String::from_utf8)?;
+        Ok(out.lines().filter_map(parse_advisory).collect())
+    }
+
+    async fn cache_dirs(&self) -> Result<Vec<PathBuf>> {
+        Ok(vec!["/var/cache/zypp/packages".into()])
+    }
+}
+
+#[async_trait]
+impl PmQuery for Zypper {
+    /// F queries the package which provides FILE from the sync databases, even
+    /// if it is not installed.
+    async fn f(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["zypper", "what-provides"]).kws(kws).flags(flags))
+            .await
+    }
+
+    /// Fy refreshes the file database, then queries the package which provides
+    /// FILE.
+    async fn fy(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.sy(&[], flags).await?;
+        self.f(kws, flags).await
+    }
+
     /// Q generates a list of installed packages.
     async fn q(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         if kws.is_empty() {
@@ -88,11 +298,31 @@ impl Pm for Zypper {
             .await
     }
 
+    /// Qdt lists packages installed as dependencies but no longer required by
+    /// any installed package, aka orphans.
+    async fn qdt(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["zypper", "packages", "--unneeded"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Qi displays local package information: name, version, description, etc.
     async fn qi(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.si(kws, flags).await
     }
 
+    /// Qk verifies one or more packages.
+    async fn qk(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        Cmd::new(if kws.is_empty() {
+            &["rpm", "-Va"]
+        } else {
+            &["rpm", "-V"]
+        })
+        .kws(kws)
+        .flags(flags)
+        .pipe(|cmd| self.run(cmd))
+        .await
+    }
+
     /// Ql displays files provided by local package.
     async fn ql(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         self.run(Cmd::new(&["rpm", "-ql"]).kws(kws).flags(flags))
@@ -108,7 +338,7 @@ impl Pm for Zypper {
             .await?;
         let out = String::from_utf8(out_bytes)?;
 
-        exec::grep_print(&out, &["System Packages"])?;
+        exec::grep_print(&out, &["System Packages"], true)?;
         Ok(())
     }
 
@@ -130,19 +360,34 @@ impl Pm for Zypper {
     // when including multiple search terms, only packages with descriptions
     // matching ALL of those terms are returned.
     async fn qs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::new(&["zypper", "search", "--installed-only"])
-            .kws(kws)
-            .flags(flags)
-            .pipe(|cmd| self.check_dry(cmd))
-            .await
+        let cmd = Cmd::new(&["zypper", "search", "--installed-only"]);
+        self.search_each(cmd, kws, flags, &STRAT_CHECK_DRY).await
     }
 
     /// Qu lists packages which have an update available.
     async fn qu(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.check_dry(Cmd::new(&["zypper", "list-updates"]).kws(kws).flags(flags))
+        let subcmd = if self.cfg.patch {
+            "list-patches"
+        } else {
+            "list-updates"
+        };
+        self.check_dry(Cmd::new(&["zypper", subcmd]).kws(kws).flags(flags))
             .await
     }
 
+    /// T checks if the given dependencies are satisfied, printing the unsatisfied ones.
+    async fn t(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.deptest(
+            kws,
+            Cmd::new(&["rpm", "-q", "--queryformat", "%{VERSION}-%{RELEASE}\n"]).flags(flags),
+            |out| out.lines().last(),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl PmRemove for Zypper {
     /// R removes a single package, leaving all of its dependencies installed.
     async fn r(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["zypper", "remove"])
@@ -152,6 +397,19 @@ impl Pm for Zypper {
             .await
     }
 
+    /// Rc removes a package and all the packages that depend on it.
+    async fn rc(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        // `zypper` removes the packages depending on the targets anyway.
+        self.r(kws, flags).await
+    }
+
+    /// Rcs removes a package and all the packages that depend on it,
+    /// along with their dependencies which are not required by any other
+    /// installed package.
+    async fn rcs(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.rss(kws, flags).await
+    }
+
     /// Rss removes a package and its dependencies which are not required by any
     /// other installed package.
     async fn rss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
@@ -161,7 +419,10 @@ impl Pm for Zypper {
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_PROMPT))
             .await
     }
+}
 
+#[async_trait]
+impl PmSync for Zypper {
     /// S installs one or more packages by name.
     async fn s(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::with_sudo(&["zypper", "install"])
@@ -189,12 +450,13 @@ impl Pm for Zypper {
         self.sc(_kws, flags).await
     }
 
-    /// Sg lists all packages belonging to the GROUP.
+    /// Sg lists all packages belonging to the GROUP, or all the groups if no
+    /// GROUP is given.
     async fn sg(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
         Cmd::new(if kws.is_empty() {
             &["zypper", "patterns"]
         } else {
-            &["zypper", "info"]
+            &["zypper", "info", "--type", "pattern"]
         })
         .kws(kws)
         .flags(flags)
@@ -211,6 +473,13 @@ impl Pm for Zypper {
             .await
     }
 
+    /// Sii displays packages which require X to be installed, aka reverse
+    /// dependencies.
+    async fn sii(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
+        self.run(Cmd::new(&["zypper", "search", "--requires"]).kws(kws).flags(flags))
+            .await
+    }
+
     /// Sl displays a list of all packages in all installation sources that are
     /// handled by the packages management.
     async fn sl(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
@@ -224,39 +493,43 @@ impl Pm for Zypper {
             .check_output(cmd, PmMode::Mute, &STRAT_CHECK_DRY)
             .await?
             .pipe(String::from_utf8)?;
-        exec::grep_print(&out, kws)
+        exec::grep_print(&out, kws, self.cfg.exact)
     }
 
     /// Ss searches for package(s) by searching the expression in name,
     /// description, short description.
     async fn ss(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.check_dry(Cmd::new(&["zypper", "search"]).kws(kws).flags(flags))
+        self.search_each(Cmd::new(&["zypper", "search"]), kws, flags, &STRAT_CHECK_DRY)
             .await
     }
 
     /// Su updates outdated packages.
     async fn su(&self, _kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["zypper", "--no-refresh", "dist-upgrade"])
-            .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-            .await
+        self.dist_upgrade(&["--no-refresh"], flags).await
     }
 
     /// Suy refreshes the local package database, then updates outdated
     /// packages.
     async fn suy(&self, _kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["zypper", "dist-upgrade"])
-            .flags(flags)
-            .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
-            .await
+        self.dist_upgrade(&[], flags).await
     }
 
     /// Sw retrieves all packages from the server, but does not install/upgrade
     /// anything.
     async fn sw(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        Cmd::with_sudo(&["zypper", "install", "--download-only"])
-            .kws(kws)
-            .flags(flags)
+        // `--pkg-cache-dir` is a global option, which comes before `install`.
+        match self.download_dir()? {
+            Some(dir) => Cmd::with_sudo(&[
+                "zypper",
+                "--pkg-cache-dir",
+                &dir,
+                "install",
+                "--download-only",
+            ]),
+            None => Cmd::with_sudo(&["zypper", "install", "--download-only"]),
+        }
+        .kws(kws)
+        .flags(flags)
             .pipe(|cmd| self.run_with(cmd, PmMode::default(), &STRAT_INSTALL))
             .await
     }
@@ -274,6 +547,8 @@ impl Pm for Zypper {
     /// U upgrades or adds package(s) to the system and installs the required
     /// dependencies from sync repositories.
     async fn u(&self, kws: &[&str], flags: &[&str]) -> Result<()> {
-        self.s(kws, flags).await
+        let kws = kws.iter().map(|kw| local_path(kw)).collect_vec();
+        let kws = kws.iter().map(String::as_str).collect_vec();
+        self.s(&kws, flags).await
     }
 }
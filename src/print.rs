@@ -2,19 +2,105 @@
 
 #![allow(missing_docs, clippy::module_name_repetitions)]
 
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
 use colored::Colorize;
 
-use crate::exec::Cmd;
+use crate::exec::{pager, Cmd};
 
 pub(crate) static PROMPT_CANCELED: &str = "Canceled";
 pub(crate) static PROMPT_PENDING: &str = "Pending";
 pub(crate) static PROMPT_RUN: &str = "Running";
 pub(crate) static PROMPT_INFO: &str = "Info";
+pub(crate) static PROMPT_PROGRESS: &str = "Progress";
+pub(crate) static PROMPT_WARN: &str = "Warning";
 pub static PROMPT_ERROR: &str = "Error";
 
 /// The right indentation to be applied on prompt prefixes.
 static PROMPT_INDENT: usize = 9;
 
+/// If the output is meant for screen readers and braille displays, see
+/// [`set_accessible`].
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+/// The minimal interval between two progress lines in the accessible mode.
+const ACCESSIBLE_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// When the last progress line was printed in the accessible mode.
+static LAST_PROGRESS: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// When to use colors in the output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ColorChoice {
+    /// Only when `stdout` is a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Checks if colors should be used, given the value of `NO_COLOR` and if
+    /// `stdout` is a terminal.
+    #[must_use]
+    fn resolve(self, no_color: Option<&str>, is_tty: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => no_color.is_none_or(str::is_empty) && is_tty,
+        }
+    }
+}
+
+/// Turns the colors on or off according to `choice`, see
+/// <https://no-color.org>.
+pub(crate) fn set_color(choice: ColorChoice) {
+    let no_color = env::var("NO_COLOR").ok();
+    let on = choice.resolve(no_color.as_deref(), io::stdout().is_terminal());
+    colored::control::set_override(on);
+}
+
+/// Checks if the colors are on, see [`set_color`].
+#[must_use]
+pub(crate) fn is_colored() -> bool {
+    colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+/// Turns the accessible mode on or off. In this mode, colors and control
+/// characters are left out, and each line begins with its role, eg.
+/// `INFO: ...`, instead of a right-aligned prompt.
+pub(crate) fn set_accessible(on: bool) {
+    ACCESSIBLE.store(on, Ordering::Relaxed);
+    if on {
+        colored::control::set_override(false);
+    }
+}
+
+/// Checks if the accessible mode is on, see [`set_accessible`].
+#[must_use]
+pub(crate) fn is_accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+/// Gets the role of the lines printed after `prompt` in the accessible mode,
+/// eg. `RUN` for [`PROMPT_RUN`].
+fn role(prompt: &str) -> String {
+    match prompt {
+        _ if prompt == PROMPT_RUN => "RUN".into(),
+        _ if prompt == PROMPT_PENDING => "ASK".into(),
+        _ if prompt == PROMPT_WARN => "WARN".into(),
+        prompt => prompt.to_uppercase(),
+    }
+}
+
 macro_rules! prompt_format {
     () => {
         "{:>indent$}"
@@ -41,6 +127,9 @@ macro_rules! question_format {
 
 /// Prints out the command after the given prompt.
 pub(crate) fn print_cmd(cmd: &Cmd, prompt: &str) {
+    if is_accessible() {
+        return println!("{}: {cmd}", role(prompt));
+    }
     println!(
         cmd_format!(),
         prompt.green().bold(),
@@ -51,6 +140,9 @@ pub(crate) fn print_cmd(cmd: &Cmd, prompt: &str) {
 
 /// Prints out a message after the given prompt.
 pub(crate) fn print_msg(msg: &str, prompt: &str) {
+    if is_accessible() {
+        return println!("{}: {msg}", role(prompt));
+    }
     println!(
         msg_format!(),
         prompt.green().bold(),
@@ -59,8 +151,45 @@ pub(crate) fn print_msg(msg: &str, prompt: &str) {
     );
 }
 
+/// Prints out a progress message after the given prompt to `stderr`,
+/// overwriting the current line. In the accessible mode, a new line is
+/// printed instead, at most every [`ACCESSIBLE_PROGRESS_INTERVAL`].
+pub(crate) fn print_progress(msg: &str, prompt: &str) {
+    if is_accessible() {
+        let mut last = LAST_PROGRESS.lock().unwrap();
+        if last.is_none_or(|last| last.elapsed() >= ACCESSIBLE_PROGRESS_INTERVAL) {
+            *last = Some(Instant::now());
+            eprintln!("{}: {msg}", role(prompt));
+        }
+        return;
+    }
+    eprint!(
+        concat!("\r\x1b[2K", msg_format!()),
+        prompt.green().bold(),
+        msg,
+        indent = PROMPT_INDENT
+    );
+    io::stderr().flush().ok();
+}
+
+/// Clears the progress line printed by [`print_progress`], if any.
+pub(crate) fn clear_progress() {
+    if !is_accessible() {
+        eprint!("\r\x1b[2K");
+        io::stderr().flush().ok();
+    }
+}
+
+/// Prints out a `key : value` pair in the style of `pacman -Si`.
+pub(crate) fn print_field(key: &str, val: &str) {
+    pager::print(&format!("{key:<15} : {val}"));
+}
+
 /// Prints out an error after the given prompt.
 pub fn print_err(err: impl std::fmt::Display, prompt: &str) {
+    if is_accessible() {
+        return eprintln!("{}: {err:#}", role(prompt));
+    }
     eprintln!(
         msg_format!(),
         prompt.bright_red().bold(),
@@ -71,6 +200,9 @@ pub fn print_err(err: impl std::fmt::Display, prompt: &str) {
 
 /// Prints out a question after the given prompt.
 pub(crate) fn print_question(question: &str, options: &str) {
+    if is_accessible() {
+        return print!("ASK: {question}? {options} ");
+    }
     print!(
         question_format!(),
         question.yellow(),
@@ -78,3 +210,27 @@ pub(crate) fn print_question(question: &str, options: &str) {
         indent = PROMPT_INDENT
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles() {
+        assert_eq!(role(PROMPT_RUN), "RUN");
+        assert_eq!(role(PROMPT_PENDING), "ASK");
+        assert_eq!(role(PROMPT_INFO), "INFO");
+        assert_eq!(role(PROMPT_WARN), "WARN");
+        assert_eq!(role(PROMPT_CANCELED), "CANCELED");
+    }
+
+    #[test]
+    fn color_choices() {
+        assert!(ColorChoice::Auto.resolve(None, true));
+        assert!(ColorChoice::Auto.resolve(Some(""), true));
+        assert!(!ColorChoice::Auto.resolve(Some("1"), true));
+        assert!(!ColorChoice::Auto.resolve(None, false));
+        assert!(ColorChoice::Always.resolve(Some("1"), false));
+        assert!(!ColorChoice::Never.resolve(None, true));
+    }
+}
@@ -0,0 +1,554 @@
+//! A small embedded store for the state kept by [`pacaptr`](crate) across
+//! runs, eg. metrics and caches, backed by `SQLite`.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::{Error, Result};
+
+/// The tables of the store. New tables should be added here with `IF NOT
+/// EXISTS`, so that older stores are upgraded on the fly.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        time    INTEGER NOT NULL,
+        pm      TEXT    NOT NULL,
+        op      TEXT    NOT NULL,
+        millis  INTEGER NOT NULL,
+        success INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS cache (
+        key   TEXT    PRIMARY KEY,
+        value BLOB    NOT NULL,
+        time  INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS history (
+        time     INTEGER NOT NULL,
+        pm       TEXT    NOT NULL,
+        op       TEXT    NOT NULL,
+        packages TEXT    NOT NULL,
+        code     INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS upgrades (
+        pm        TEXT    PRIMARY KEY,
+        planned   TEXT    NOT NULL,
+        remaining TEXT    NOT NULL,
+        time      INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS reasons (
+        pm          TEXT    NOT NULL,
+        name        TEXT    NOT NULL,
+        required_by TEXT,
+        time        INTEGER NOT NULL,
+        PRIMARY KEY (pm, name)
+    );
+    CREATE TABLE IF NOT EXISTS refreshes (
+        pm   TEXT    PRIMARY KEY,
+        time INTEGER NOT NULL
+    );
+";
+
+/// How long to wait for another [`pacaptr`](crate) instance to release the
+/// store.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Converts a [`SystemTime`] to seconds since the epoch.
+fn to_secs(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs().try_into().unwrap_or(i64::MAX))
+}
+
+/// Converts seconds since the epoch to a [`SystemTime`].
+fn from_secs(secs: i64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(secs.try_into().unwrap_or_default())
+}
+
+/// A single run of an operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Run {
+    /// The time the operation finished at.
+    pub time: SystemTime,
+    /// The name of the package manager, eg. `apt`.
+    pub pm: String,
+    /// The operation, eg. `Suy`.
+    pub op: String,
+    pub duration: Duration,
+    pub success: bool,
+}
+
+/// A mutating operation, eg. an installation, recorded in the history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Transaction {
+    /// The time the operation finished at.
+    pub time: SystemTime,
+    /// The name of the package manager, eg. `apt`.
+    pub pm: String,
+    /// The operation, eg. `S`.
+    pub op: String,
+    /// The packages given to the operation.
+    pub packages: Vec<String>,
+    /// The exit code of the operation.
+    pub code: i32,
+}
+
+/// A system upgrade which failed partway, to be resumed with `pacaptr resume`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Upgrade {
+    /// The time the upgrade failed at.
+    pub time: SystemTime,
+    /// The name of the package manager, eg. `apt`.
+    pub pm: String,
+    /// The packages which were to be upgraded in the first place.
+    pub planned: Vec<String>,
+    /// The packages which are not upgraded yet.
+    pub remaining: Vec<String>,
+}
+
+/// The reason why a package has been installed, which is recorded for the
+/// package managers that don't keep track of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Reason {
+    /// Explicitly requested by the user.
+    Explicit,
+    /// Installed as a dependency of the given packages.
+    Dependency { required_by: Vec<String> },
+}
+
+impl Reason {
+    /// Checks if the package is a dependency which is no longer required, ie.
+    /// none of the packages it was installed for is `installed` anymore.
+    #[must_use]
+    pub(crate) fn is_orphan(&self, installed: &[&str]) -> bool {
+        match self {
+            Reason::Explicit => false,
+            Reason::Dependency { required_by } => required_by
+                .iter()
+                .all(|pkg| !installed.contains(&pkg.as_str())),
+        }
+    }
+}
+
+/// A connection to the state store.
+#[derive(Debug)]
+pub(crate) struct State {
+    conn: Connection,
+}
+
+impl State {
+    /// The path to the store, ie. `$XDG_DATA_HOME/pacaptr/state.db` on Linux.
+    fn path() -> Option<PathBuf> {
+        Some(
+            dirs_next::data_local_dir()?
+                .join(clap::crate_name!())
+                .join("state.db"),
+        )
+    }
+
+    /// Sets up the store behind `conn`.
+    fn with_conn(conn: Connection) -> Result<Self> {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(State { conn })
+    }
+
+    /// Opens the store, creating it if it doesn't exist yet.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be opened.
+    pub(crate) fn open() -> Result<Self> {
+        // Tests should never touch the real store.
+        if cfg!(test) {
+            return Self::with_conn(Connection::open_in_memory()?);
+        }
+        let path = Self::path()
+            .ok_or_else(|| Error::OtherError("Local data directory not found".into()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Self::with_conn(Connection::open(path)?)
+    }
+
+    /// Records a [`Run`].
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn add_run(&self, run: &Run) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO runs (time, pm, op, millis, success) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                to_secs(run.time),
+                run.pm,
+                run.op,
+                i64::try_from(run.duration.as_millis()).unwrap_or(i64::MAX),
+                run.success,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Gets all the recorded [`Run`]s, oldest first.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be read.
+    pub(crate) fn runs(&self) -> Result<Vec<Run>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT time, pm, op, millis, success FROM runs ORDER BY time")?;
+        let runs = stmt
+            .query_map([], |row| {
+                Ok(Run {
+                    time: from_secs(row.get(0)?),
+                    pm: row.get(1)?,
+                    op: row.get(2)?,
+                    duration: Duration::from_millis(row.get(3)?),
+                    success: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(runs)
+    }
+
+    /// Records a [`Transaction`] in the history.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn add_transaction(&self, tx: &Transaction) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO history (time, pm, op, packages, code) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                to_secs(tx.time),
+                tx.pm,
+                tx.op,
+                tx.packages.join(" "),
+                tx.code
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Gets the history of [`Transaction`]s, oldest first.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be read.
+    pub(crate) fn history(&self) -> Result<Vec<Transaction>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT time, pm, op, packages, code FROM history ORDER BY time, rowid")?;
+        let history = stmt
+            .query_map([], |row| {
+                let packages: String = row.get(3)?;
+                Ok(Transaction {
+                    time: from_secs(row.get(0)?),
+                    pm: row.get(1)?,
+                    op: row.get(2)?,
+                    packages: packages.split_whitespace().map(String::from).collect(),
+                    code: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(history)
+    }
+
+    /// Records an [`Upgrade`] which failed partway, replacing any previous
+    /// one of the same package manager.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn set_upgrade(&self, upgrade: &Upgrade) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO upgrades (pm, planned, remaining, time) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                upgrade.pm,
+                upgrade.planned.join(" "),
+                upgrade.remaining.join(" "),
+                to_secs(upgrade.time),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Forgets the [`Upgrade`] of `pm`, eg. after it has been completed.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn forget_upgrade(&self, pm: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM upgrades WHERE pm = ?1", [pm])?;
+        Ok(())
+    }
+
+    /// Gets the [`Upgrade`] of `pm` which failed partway, if any.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be read.
+    pub(crate) fn upgrade(&self, pm: &str) -> Result<Option<Upgrade>> {
+        let pkgs = |s: String| s.split_whitespace().map(String::from).collect();
+        let upgrade = self
+            .conn
+            .query_row(
+                "SELECT planned, remaining, time FROM upgrades WHERE pm = ?1",
+                [pm],
+                |row| {
+                    Ok(Upgrade {
+                        time: from_secs(row.get(2)?),
+                        pm: pm.into(),
+                        planned: pkgs(row.get(0)?),
+                        remaining: pkgs(row.get(1)?),
+                    })
+                },
+            )
+            .optional()?;
+        Ok(upgrade)
+    }
+
+    /// Records that the package databases of `pm` have been refreshed at
+    /// `time` without upgrading the system, replacing any previous record.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn set_refreshed(&self, pm: &str, time: SystemTime) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO refreshes (pm, time) VALUES (?1, ?2)",
+            params![pm, to_secs(time)],
+        )?;
+        Ok(())
+    }
+
+    /// Forgets the refresh of `pm`, eg. after the system has been upgraded.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn forget_refreshed(&self, pm: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM refreshes WHERE pm = ?1", [pm])?;
+        Ok(())
+    }
+
+    /// Gets when the package databases of `pm` have been refreshed without
+    /// upgrading the system, if they have.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be read.
+    pub(crate) fn refreshed(&self, pm: &str) -> Result<Option<SystemTime>> {
+        let time = self
+            .conn
+            .query_row("SELECT time FROM refreshes WHERE pm = ?1", [pm], |row| {
+                row.get(0).map(from_secs)
+            })
+            .optional()?;
+        Ok(time)
+    }
+
+    /// Gets the value cached under `key`, if it has been cached within `ttl`.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be read.
+    pub(crate) fn cached(&self, key: &str, ttl: Duration) -> Result<Option<Vec<u8>>> {
+        let since = to_secs(SystemTime::now() - ttl);
+        let value = self
+            .conn
+            .query_row(
+                "SELECT value FROM cache WHERE key = ?1 AND time >= ?2",
+                params![key, since],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    /// Caches `value` under `key`, replacing any previous value.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn cache(&self, key: &str, value: &[u8]) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cache (key, value, time) VALUES (?1, ?2, ?3)",
+            params![key, value, to_secs(SystemTime::now())],
+        )?;
+        Ok(())
+    }
+
+    /// Records the [`Reason`] why the package `name` of `pm` has been
+    /// installed, replacing any previous one.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn set_reason(&self, pm: &str, name: &str, reason: &Reason) -> Result<()> {
+        let required_by = match reason {
+            Reason::Explicit => None,
+            Reason::Dependency { required_by } => Some(required_by.join(",")),
+        };
+        self.conn.execute(
+            "INSERT OR REPLACE INTO reasons (pm, name, required_by, time) VALUES (?1, ?2, ?3, ?4)",
+            params![pm, name, required_by, to_secs(SystemTime::now())],
+        )?;
+        Ok(())
+    }
+
+    /// Forgets the [`Reason`] of the package `name` of `pm`, eg. after it has
+    /// been removed.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be written.
+    pub(crate) fn forget_reason(&self, pm: &str, name: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM reasons WHERE pm = ?1 AND name = ?2",
+            params![pm, name],
+        )?;
+        Ok(())
+    }
+
+    /// Gets the recorded [`Reason`]s of the packages of `pm`.
+    ///
+    /// # Errors
+    /// Returns an [`Error::StateError`] when the store cannot be read.
+    pub(crate) fn reasons(&self, pm: &str) -> Result<HashMap<String, Reason>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, required_by FROM reasons WHERE pm = ?1")?;
+        let reasons = stmt
+            .query_map([pm], |row| {
+                let required_by: Option<String> = row.get(1)?;
+                let reason = match required_by {
+                    None => Reason::Explicit,
+                    Some(pkgs) => Reason::Dependency {
+                        required_by: pkgs.split(',').map(String::from).collect(),
+                    },
+                };
+                Ok((row.get(0)?, reason))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(reasons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs() {
+        let state = State::open().unwrap();
+        let run = Run {
+            time: from_secs(1_650_000_000),
+            pm: "apt".into(),
+            op: "Suy".into(),
+            duration: Duration::from_millis(42_195),
+            success: true,
+        };
+        state.add_run(&run).unwrap();
+        state
+            .add_run(&Run {
+                time: from_secs(1_640_000_000),
+                success: false,
+                ..run.clone()
+            })
+            .unwrap();
+        let runs = state.runs().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert!(!runs[0].success);
+        assert_eq!(runs[1], run);
+    }
+
+    #[test]
+    fn history() {
+        let state = State::open().unwrap();
+        let tx = Transaction {
+            time: from_secs(1_650_000_000),
+            pm: "apt".into(),
+            op: "S".into(),
+            packages: vec!["curl".into(), "wget".into()],
+            code: 0,
+        };
+        let upgrade = Transaction {
+            op: "Suy".into(),
+            packages: vec![],
+            code: 100,
+            ..tx.clone()
+        };
+        state.add_transaction(&tx).unwrap();
+        state.add_transaction(&upgrade).unwrap();
+        assert_eq!(state.history().unwrap(), [tx, upgrade]);
+    }
+
+    #[test]
+    fn refreshes() {
+        let state = State::open().unwrap();
+        let time = from_secs(1_650_000_000);
+        assert_eq!(state.refreshed("xbps").unwrap(), None);
+        state.set_refreshed("xbps", time).unwrap();
+        assert_eq!(state.refreshed("xbps").unwrap(), Some(time));
+        assert_eq!(state.refreshed("pacman").unwrap(), None);
+        state.forget_refreshed("xbps").unwrap();
+        assert_eq!(state.refreshed("xbps").unwrap(), None);
+    }
+
+    #[test]
+    fn upgrades() {
+        let state = State::open().unwrap();
+        let upgrade = Upgrade {
+            time: from_secs(1_650_000_000),
+            pm: "apt".into(),
+            planned: vec!["curl".into(), "git".into(), "vim".into()],
+            remaining: vec!["git".into(), "vim".into()],
+        };
+        assert_eq!(state.upgrade("apt").unwrap(), None);
+        state.set_upgrade(&upgrade).unwrap();
+        state
+            .set_upgrade(&Upgrade {
+                remaining: vec!["vim".into()],
+                ..upgrade.clone()
+            })
+            .unwrap();
+        assert_eq!(state.upgrade("apt").unwrap().unwrap().remaining, ["vim"]);
+        assert_eq!(state.upgrade("dnf").unwrap(), None);
+        state.forget_upgrade("apt").unwrap();
+        assert_eq!(state.upgrade("apt").unwrap(), None);
+    }
+
+    #[test]
+    fn reasons() {
+        let state = State::open().unwrap();
+        let dep = Reason::Dependency {
+            required_by: vec!["git".into(), "vim".into()],
+        };
+        state.set_reason("choco", "git", &Reason::Explicit).unwrap();
+        state.set_reason("choco", "vim", &Reason::Explicit).unwrap();
+        state.set_reason("choco", "7zip", &dep).unwrap();
+        state.set_reason("scoop", "git", &dep).unwrap();
+        state.forget_reason("choco", "vim").unwrap();
+
+        let reasons = state.reasons("choco").unwrap();
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(reasons["git"], Reason::Explicit);
+        assert_eq!(reasons["7zip"], dep);
+
+        assert!(!dep.is_orphan(&["7zip", "git"]));
+        assert!(dep.is_orphan(&["7zip"]));
+        assert!(!Reason::Explicit.is_orphan(&[]));
+    }
+
+    #[test]
+    fn cache() {
+        let state = State::open().unwrap();
+        let ttl = Duration::from_hours(1);
+        assert_eq!(state.cached("brew/formula.json", ttl).unwrap(), None);
+        state.cache("brew/formula.json", b"[]").unwrap();
+        state.cache("brew/formula.json", b"[{}]").unwrap();
+        assert_eq!(
+            state.cached("brew/formula.json", ttl).unwrap().as_deref(),
+            Some(&b"[{}]"[..])
+        );
+
+        state
+            .conn
+            .execute("UPDATE cache SET time = time - 7200", [])
+            .unwrap();
+        assert_eq!(state.cached("brew/formula.json", ttl).unwrap(), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! Parsing and comparison of package version strings.
+
+mod dpkg;
+mod rpm;
+mod semver;
+
+use std::{cmp::Ordering, fmt};
+
+use itertools::Itertools;
+
+/// A segment of a [`Version`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Num(u64),
+    Alpha(String),
+}
+
+impl PartialOrd for Segment {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Segment {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Segment::Num(a), Segment::Num(b)) => a.cmp(b),
+            (Segment::Alpha(a), Segment::Alpha(b)) => a.cmp(b),
+            // Numbers are considered newer than letters, eg. `1.0.1 > 1.0.a`.
+            (Segment::Num(_), Segment::Alpha(_)) => Ordering::Greater,
+            (Segment::Alpha(_), Segment::Num(_)) => Ordering::Less,
+        }
+    }
+}
+
+/// Splits `s` into runs of digits and runs of letters, treating everything
+/// else as a separator, eg. `1.2b_3 => [1, 2, b, 3]`.
+fn segments(s: &str) -> Vec<Segment> {
+    s.split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|part| {
+            part.chars()
+                .group_by(char::is_ascii_digit)
+                .into_iter()
+                .map(|(is_num, run)| {
+                    let run: String = run.collect();
+                    if is_num {
+                        // Absurdly long numbers saturate instead of failing the parse.
+                        Segment::Num(run.parse().unwrap_or(u64::MAX))
+                    } else {
+                        Segment::Alpha(run.to_lowercase())
+                    }
+                })
+                .collect_vec()
+        })
+        .collect()
+}
+
+/// A version string, parsed in a semver-ish way while tolerating common vendor
+/// quirks:
+///
+/// - A leading `v` is ignored: `v1.2 == 1.2`.
+/// - Build metadata is ignored: `1.2+build5 == 1.2`.
+/// - Trailing zeros are insignificant: `1.2 == 1.2.0 == 1.2.0.0` (`choco`).
+/// - Any number of segments separated by `.`, `_` (`brew` revisions) or `-`
+///   followed by a digit (date-like versions) is supported.
+/// - A pre-release (eg. `1.2-beta1`) is older than its release.
+#[derive(Clone, Debug)]
+pub(crate) struct Version {
+    raw: String,
+    release: Vec<Segment>,
+    pre: Vec<Segment>,
+}
+
+impl Version {
+    /// Parses a version string. This never fails, since any string is a
+    /// (possibly weird) version.
+    #[must_use]
+    pub(crate) fn parse(raw: &str) -> Self {
+        let trimmed = raw.trim();
+        let s = trimmed
+            .strip_prefix(['v', 'V'])
+            .filter(|s| s.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or(trimmed);
+        let s = s.split_once('+').map_or(s, |(s, _build)| s);
+        // A `-` followed by a non-digit starts a pre-release.
+        let (release, pre) = s
+            .match_indices('-')
+            .find(|(i, _)| !s[i + 1..].starts_with(|c: char| c.is_ascii_digit()))
+            .map_or((s, ""), |(i, _)| (&s[..i], &s[i + 1..]));
+
+        let mut release = segments(release);
+        while release.last() == Some(&Segment::Num(0)) {
+            release.pop();
+        }
+        Version {
+            raw: raw.into(),
+            release,
+            pre: segments(pre),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.release.cmp(&other.release).then_with(|| {
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                // A release is newer than any of its pre-releases.
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            }
+        })
+    }
+}
+
+/// The rules used to compare versions, which differ between package managers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Scheme {
+    /// Semver-ish with vendor quirks, eg. `1.2-beta1` or `1.2_1` (`brew`).
+    #[default]
+    Loose,
+    /// [Semantic Versioning](https://semver.org/), falling back to
+    /// [`Scheme::Loose`] if any of the versions is not compliant.
+    Semver,
+    /// `rpmvercmp`, as used by `rpm`, `dnf` and `zypper`.
+    Rpm,
+    /// `vercmp`, as used by `pacman`.
+    // Not used by any backend yet, since `pacman` itself is not wrapped.
+    #[allow(dead_code)]
+    Pacman,
+    /// `dpkg --compare-versions`, as used by `apt`.
+    Dpkg,
+}
+
+impl Scheme {
+    /// Compares two versions under this scheme.
+    #[must_use]
+    pub(crate) fn cmp(self, a: &str, b: &str) -> Ordering {
+        match self {
+            Scheme::Loose => Version::parse(a).cmp(&Version::parse(b)),
+            Scheme::Semver => semver::cmp(a, b).unwrap_or_else(|| Scheme::Loose.cmp(a, b)),
+            Scheme::Rpm => rpm::cmp_evr(a, b, false),
+            Scheme::Pacman => rpm::cmp_evr(a, b, true),
+            Scheme::Dpkg => dpkg::cmp(a, b),
+        }
+    }
+}
+
+/// A dependency specification as accepted by `pacman -T`, eg. `curl`,
+/// `curl>=7.80` or `python=3.10`. `==` is accepted as well as `=`.
+#[derive(Clone, Debug)]
+pub(crate) struct Dep<'s> {
+    /// The original specification.
+    pub spec: &'s str,
+    /// The name of the package.
+    pub name: &'s str,
+    /// The comparison operator, the accepted [`Ordering`]s of the installed
+    /// version compared to the required one, and the required version itself.
+    req: Option<(&'static str, &'static [Ordering], &'s str)>,
+}
+
+impl<'s> Dep<'s> {
+    /// Parses a dependency specification.
+    #[must_use]
+    pub(crate) fn parse(spec: &'s str) -> Self {
+        use Ordering::{Equal, Greater, Less};
+        let Some(i) = spec.find(['<', '>', '=']) else {
+            return Dep {
+                spec,
+                name: spec,
+                req: None,
+            };
+        };
+        let (name, rest) = spec.split_at(i);
+        let (op, accepted, ver): (_, &[_], _) = [
+            ("<=", "<=", &[Less, Equal] as &[_]),
+            (">=", ">=", &[Greater, Equal]),
+            ("<", "<", &[Less]),
+            (">", ">", &[Greater]),
+            ("==", "=", &[Equal]),
+            ("=", "=", &[Equal]),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op, accepted)| Some((op, accepted, rest.strip_prefix(prefix)?)))
+        .expect("`rest` should start with a comparison operator");
+        Dep {
+            spec,
+            name,
+            req: Some((op, accepted, ver)),
+        }
+    }
+
+    /// Gets the comparison operator, one of `<`, `<=`, `=`, `>=` and `>`,
+    /// and the required version, if any.
+    #[must_use]
+    pub(crate) fn requirement(&self) -> Option<(&'static str, &'s str)> {
+        self.req.map(|(op, _, ver)| (op, ver))
+    }
+
+    /// Checks if the dependency is satisfied by the `installed` version of the
+    /// package, compared under `scheme`. A dependency without version
+    /// requirements is satisfied by any version.
+    #[must_use]
+    pub(crate) fn is_satisfied_by(&self, installed: &str, scheme: Scheme) -> bool {
+        self.req
+            .is_none_or(|(_, accepted, req)| accepted.contains(&scheme.cmp(installed, req)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        Version::parse(s)
+    }
+
+    #[test]
+    fn numeric_order() {
+        assert!(v("1.2.9") < v("1.2.10"));
+        assert!(v("2.0") > v("1.99.99"));
+        assert!(v("1.2.10") < v("1.2.10_1"));
+    }
+
+    #[test]
+    fn vendor_quirks() {
+        assert_eq!(v("v1.2.3"), v("1.2.3"));
+        assert_eq!(v("1.2"), v("1.2.0.0"));
+        assert_eq!(v("1.2.3+build.7"), v("1.2.3"));
+        assert!(v("2022.03.17") < v("2022.04.01"));
+        assert!(v("1.0-20220101") < v("1.0-20220301"));
+    }
+
+    #[test]
+    fn pre_releases() {
+        assert!(v("1.2.3-beta1") < v("1.2.3"));
+        assert!(v("1.2.3-alpha") < v("1.2.3-beta"));
+        assert!(v("1.2.3-rc.1") < v("1.2.3-rc.2"));
+        assert!(v("1.2.3-rc.2") < v("1.2.4-alpha"));
+    }
+
+    #[test]
+    fn dep_requirements() {
+        let loose = Scheme::Loose;
+        let dep = Dep::parse("curl>=7.80");
+        assert_eq!(dep.name, "curl");
+        assert!(dep.is_satisfied_by("7.80.0", loose));
+        assert!(dep.is_satisfied_by("8.0", loose));
+        assert!(!dep.is_satisfied_by("7.79.1", loose));
+
+        assert!(Dep::parse("curl").is_satisfied_by("0.1", loose));
+        assert!(Dep::parse("python=3.10").is_satisfied_by("3.10.0", loose));
+        assert!(!Dep::parse("python<3").is_satisfied_by("3.10", loose));
+
+        // `7.80` is older than `7.80.0` for `rpm`.
+        assert!(!Dep::parse("curl>=7.80.0").is_satisfied_by("7.80", Scheme::Rpm));
+        assert!(Dep::parse("bash>=5.1").is_satisfied_by("5.1-6ubuntu1", Scheme::Dpkg));
+
+        assert_eq!(Dep::parse("curl").requirement(), None);
+        assert_eq!(Dep::parse("curl<=8").requirement(), Some(("<=", "8")));
+        assert_eq!(
+            Dep::parse("requests==2.31").requirement(),
+            Some(("=", "2.31"))
+        );
+    }
+
+    #[test]
+    fn schemes() {
+        use Ordering::{Equal, Greater, Less};
+        for (a, b, [loose, semver, rpm, pacman, dpkg]) in [
+            ("1.0", "1.0.0", [Equal, Equal, Less, Less, Less]),
+            ("1.0a", "1.0", [Greater, Greater, Greater, Less, Greater]),
+            ("1.0~rc1", "1.0", [Greater, Greater, Less, Less, Less]),
+            ("1.0-rc1", "1.0", [Less, Less, Equal, Equal, Greater]),
+            ("1:1.0", "2.0", [Less, Less, Greater, Greater, Greater]),
+        ] {
+            for (scheme, ord) in [
+                (Scheme::Loose, loose),
+                (Scheme::Semver, semver),
+                (Scheme::Rpm, rpm),
+                (Scheme::Pacman, pacman),
+                (Scheme::Dpkg, dpkg),
+            ] {
+                assert_eq!(scheme.cmp(a, b), ord, "{scheme:?}: {a} vs {b}");
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Version comparison as done by `dpkg --compare-versions`.
+//!
+//! See `deb-version(7)` for the format, which is
+//! `[epoch:]upstream_version[-debian_revision]`.
+
+use std::cmp::Ordering;
+
+/// Splits `s` into its epoch (`0` if absent), upstream version and revision
+/// (empty if absent) parts.
+fn split(s: &str) -> (u64, &str, &str) {
+    let (epoch, rest) = s
+        .split_once(':')
+        .and_then(|(epoch, rest)| Some((epoch.parse().ok()?, rest)))
+        .unwrap_or((0, s));
+    let (upstream, rev) = rest.rsplit_once('-').unwrap_or((rest, ""));
+    (epoch, upstream, rev)
+}
+
+/// Compares two `dpkg` versions.
+pub(super) fn cmp(a: &str, b: &str) -> Ordering {
+    let (ea, ua, ra) = split(a.trim());
+    let (eb, ub, rb) = split(b.trim());
+    ea.cmp(&eb)
+        .then_with(|| verrevcmp(ua, ub))
+        .then_with(|| verrevcmp(ra, rb))
+}
+
+/// The weight of a non-digit character: `~` sorts before anything (even the
+/// end of the string), then letters, then all the other characters.
+fn order(c: Option<u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => i32::from(c),
+        Some(b'~') => -1,
+        Some(c) => i32::from(c) + 256,
+    }
+}
+
+/// Compares two upstream versions or revisions.
+fn verrevcmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    let is_digit = |s: &[u8], k: usize| s.get(k).is_some_and(u8::is_ascii_digit);
+
+    while i < a.len() || j < b.len() {
+        // Compare the non-digit prefixes character by character.
+        while (i < a.len() && !is_digit(a, i)) || (j < b.len() && !is_digit(b, j)) {
+            let (oa, ob) = (order(a.get(i).copied()), order(b.get(j).copied()));
+            if oa != ob {
+                return oa.cmp(&ob);
+            }
+            i += 1;
+            j += 1;
+        }
+
+        // Then compare the digit parts numerically.
+        while a.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while b.get(j) == Some(&b'0') {
+            j += 1;
+        }
+        let mut first_diff = Ordering::Equal;
+        while is_digit(a, i) && is_digit(b, j) {
+            if first_diff.is_eq() {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if is_digit(a, i) {
+            return Ordering::Greater;
+        }
+        if is_digit(b, j) {
+            return Ordering::Less;
+        }
+        if first_diff.is_ne() {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use Ordering::{Equal, Greater, Less};
+
+    use super::*;
+
+    // Mostly taken from `dpkg`'s `lib/dpkg/t/t-version.c`.
+    #[test]
+    fn compare_versions() {
+        for (a, b, ord) in [
+            ("0", "0", Equal),
+            ("0", "00", Equal),
+            ("1", "0", Greater),
+            ("1.0", "1.0-0", Equal),
+            ("1:0", "0:0", Greater),
+            ("1:0", "10", Greater),
+            ("0:0-0", "0:0-0", Equal),
+            ("0:0.0-0", "0:0-0", Greater),
+            ("0:0-0", "0:0-1", Less),
+            ("0:0-a", "0:0-b", Less),
+            ("0:0-a", "0:0-1", Greater),
+            ("0:0-a.a", "0:0-a", Greater),
+            ("0:0-~", "0:0", Less),
+            ("0:0~a", "0:0", Less),
+            ("0:0~", "0:0~a", Less),
+            ("0:0~~", "0:0~", Less),
+            ("1.2.3", "1.2.3+dfsg", Less),
+            ("2.30-1ubuntu1", "2.30-1", Greater),
+            ("7.81.0-1ubuntu1.4", "7.81.0-1ubuntu1.10", Less),
+            ("1.0~rc1-1", "1.0-1", Less),
+            ("2:1.0-1", "1:9.9-9", Greater),
+        ] {
+            assert_eq!(cmp(a, b), ord, "{a} vs {b}");
+            assert_eq!(cmp(b, a), ord.reverse(), "{b} vs {a}");
+        }
+    }
+}
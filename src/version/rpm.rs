@@ -0,0 +1,246 @@
+//! Version comparison as done by `rpm` (`rpmvercmp`) and `pacman` (`vercmp`).
+//!
+//! Both compare `[epoch:]version[-release]` strings with almost the same
+//! algorithm, except that:
+//!
+//! - `rpm` supports `~` (sorts before anything, even the end of the string) and
+//!   `^` (sorts after the end of the string, but before anything else).
+//! - An alphabetic suffix makes an `rpm` version newer (`1.0a > 1.0`), but a
+//!   `pacman` version older (`1.0a < 1.0`).
+
+use std::cmp::Ordering;
+
+/// Splits `s` into its epoch (`0` if absent), version and release parts.
+fn split_evr(s: &str) -> (&str, &str, Option<&str>) {
+    let (epoch, rest) = match s.split_once(':') {
+        Some((epoch, rest)) if epoch.bytes().all(|b| b.is_ascii_digit()) => (epoch, rest),
+        _ => ("0", s),
+    };
+    let (ver, rel) = rest
+        .rsplit_once('-')
+        .map_or((rest, None), |(ver, rel)| (ver, Some(rel)));
+    (epoch, ver, rel)
+}
+
+/// Compares two `[epoch:]version[-release]` strings. The releases are only
+/// compared if both of them are present.
+pub(super) fn cmp_evr(a: &str, b: &str, pacman: bool) -> Ordering {
+    let (ea, va, ra) = split_evr(a);
+    let (eb, vb, rb) = split_evr(b);
+    vercmp(ea, eb, pacman)
+        .then_with(|| vercmp(va, vb, pacman))
+        .then_with(|| match (ra, rb) {
+            (Some(ra), Some(rb)) => vercmp(ra, rb, pacman),
+            _ => Ordering::Equal,
+        })
+}
+
+/// Takes the longest prefix of `s` whose bytes all satisfy `pred`.
+fn take_while(s: &[u8], pred: impl Fn(&u8) -> bool) -> (&[u8], &[u8]) {
+    let len = s.iter().take_while(|b| pred(b)).count();
+    s.split_at(len)
+}
+
+/// Compares two version (or release) strings.
+fn vercmp(a: &str, b: &str, pacman: bool) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let is_special = |b: &u8| !pacman && matches!(b, b'~' | b'^');
+    let is_sep = |b: &u8| !b.is_ascii_alphanumeric() && !is_special(b);
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    loop {
+        let (delim_a, rest_a) = take_while(a, is_sep);
+        let (delim_b, rest_b) = take_while(b, is_sep);
+        (a, b) = (rest_a, rest_b);
+        // `pacman` considers the version with the longer separator as newer.
+        if pacman && !a.is_empty() && !b.is_empty() && delim_a.len() != delim_b.len() {
+            return delim_a.len().cmp(&delim_b.len());
+        }
+
+        if !pacman {
+            // `~` sorts before everything else.
+            match (a.first(), b.first()) {
+                (Some(b'~'), Some(b'~')) => {
+                    (a, b) = (&a[1..], &b[1..]);
+                    continue;
+                }
+                (Some(b'~'), _) => return Ordering::Less,
+                (_, Some(b'~')) => return Ordering::Greater,
+                _ => (),
+            }
+            // `^` sorts after the end of the string, but before anything else.
+            match (a.first(), b.first()) {
+                (Some(b'^'), Some(b'^')) => {
+                    (a, b) = (&a[1..], &b[1..]);
+                    continue;
+                }
+                (Some(b'^'), None) | (None, Some(b'^')) => {
+                    return if a.is_empty() {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    };
+                }
+                (Some(b'^'), _) => return Ordering::Less,
+                (_, Some(b'^')) => return Ordering::Greater,
+                _ => (),
+            }
+        }
+
+        if a.is_empty() || b.is_empty() {
+            break;
+        }
+
+        let is_num = a[0].is_ascii_digit();
+        let pred = |b: &u8| {
+            if is_num {
+                b.is_ascii_digit()
+            } else {
+                b.is_ascii_alphabetic()
+            }
+        };
+        let (seg_a, rest_a) = take_while(a, pred);
+        let (seg_b, rest_b) = take_while(b, pred);
+        (a, b) = (rest_a, rest_b);
+
+        // Segments of different types: numbers are always newer.
+        if seg_b.is_empty() {
+            return if is_num {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let ord = if is_num {
+            let (_, seg_a) = take_while(seg_a, |&b| b == b'0');
+            let (_, seg_b) = take_while(seg_b, |&b| b == b'0');
+            seg_a.len().cmp(&seg_b.len()).then_with(|| seg_a.cmp(seg_b))
+        } else {
+            seg_a.cmp(seg_b)
+        };
+        if ord.is_ne() {
+            return ord;
+        }
+    }
+
+    match (a.first(), b.first()) {
+        (None, None) => Ordering::Equal,
+        // `pacman` never lets a remaining alphabetic suffix win.
+        (None, Some(c)) if pacman => {
+            if c.is_ascii_alphabetic() {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        }
+        (Some(c), _) if pacman && c.is_ascii_alphabetic() => Ordering::Less,
+        (Some(_), _) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Ordering::{Equal, Greater, Less};
+
+    use super::*;
+
+    fn rpm(a: &str, b: &str) -> Ordering {
+        cmp_evr(a, b, false)
+    }
+
+    fn pacman(a: &str, b: &str) -> Ordering {
+        cmp_evr(a, b, true)
+    }
+
+    /// Checks `cmp(a, b) == ord` as well as its symmetric counterpart.
+    fn check(cmp: fn(&str, &str) -> Ordering, a: &str, b: &str, ord: Ordering) {
+        assert_eq!(cmp(a, b), ord, "{a} vs {b}");
+        assert_eq!(cmp(b, a), ord.reverse(), "{b} vs {a}");
+    }
+
+    // Taken from `rpm`'s `tests/rpmvercmp.at`.
+    #[test]
+    fn rpmvercmp() {
+        for (a, b, ord) in [
+            ("1.0", "1.0", Equal),
+            ("1.0", "2.0", Less),
+            ("2.0.1", "2.0.1", Equal),
+            ("2.0", "2.0.1", Less),
+            ("2.0.1a", "2.0.1a", Equal),
+            ("2.0.1a", "2.0.1", Greater),
+            ("5.5p1", "5.5p2", Less),
+            ("5.5p10", "5.5p1", Greater),
+            ("10xyz", "10.1xyz", Less),
+            ("xyz10", "xyz10.1", Less),
+            ("xyz.4", "8", Less),
+            ("5.5p1", "5.6p1", Less),
+            ("6.0.rc1", "6.0", Greater),
+            ("10b2", "10a1", Greater),
+            ("1.0aa", "1.0a", Greater),
+            ("10.0001", "10.1", Equal),
+            ("10.0001", "10.0039", Less),
+            ("4.999.9", "5.0", Less),
+            ("20101121", "20101122", Less),
+            ("2_0", "2_0", Equal),
+            ("2.0", "2_0", Equal),
+            ("a", "a", Equal),
+            ("a+", "a_", Equal),
+            ("+", "_", Equal),
+            ("1.0~rc1", "1.0~rc1", Equal),
+            ("1.0~rc1", "1.0", Less),
+            ("1.0~rc1", "1.0~rc2", Less),
+            ("1.0~rc1~git123", "1.0~rc1", Less),
+            ("1.0^", "1.0", Greater),
+            ("1.0^git1", "1.0", Greater),
+            ("1.0^git1", "1.0^git2", Less),
+            ("1.0^git1", "1.01", Less),
+            ("1.0^20160101", "1.0.1", Less),
+            ("1.0~rc1^git1", "1.0~rc1", Greater),
+            ("1.0^git1~pre", "1.0^git1", Less),
+        ] {
+            check(rpm, a, b, ord);
+        }
+    }
+
+    // Taken from `pacman`'s `test/util/vercmptest.sh`.
+    #[test]
+    fn pacman_vercmp() {
+        for (a, b, ord) in [
+            ("1.5.0", "1.5.0", Equal),
+            ("1.5.1", "1.5.0", Greater),
+            ("1.5.1", "1.5", Greater),
+            ("1.5.0-1", "1.5.0-1", Equal),
+            ("1.5.0-1", "1.5.0-2", Less),
+            ("1.5.0-1", "1.5.1-1", Less),
+            ("1.5.0-2", "1.5.1-1", Less),
+            ("1.5-1", "1.5", Equal),
+            ("1.1-1", "1.1", Equal),
+            ("1.1-1", "1.1-1.1", Less),
+            ("1.0", "1.0a", Greater),
+            ("1.0a", "1.0b", Less),
+            ("1.0b", "1.0beta", Less),
+            ("1.0beta", "1.0p", Less),
+            ("1.0p", "1.0pre", Less),
+            ("1.0pre", "1.0rc", Less),
+            ("1.0rc", "1.0", Less),
+            ("1.0", "1.0.a", Greater),
+            ("1.0.a", "1.0.1", Less),
+            ("1.0", "1.0.1", Less),
+            ("1.0a", "1.0alpha", Less),
+            ("1.0", "1.0.0", Less),
+            ("1.0.0", "1.0.a", Greater),
+            ("1.0", "1.0.", Equal),
+            ("1:1.0", "1.0", Greater),
+            ("1:1.0", "2.0", Greater),
+            ("0:1.0", "1.0", Equal),
+            ("1:1.0-1", "2:1.0-1", Less),
+            ("1.1.1", "1.1..1", Less),
+        ] {
+            check(pacman, a, b, ord);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Version comparison following [Semantic Versioning](https://semver.org/).
+
+use std::cmp::Ordering;
+
+/// A pre-release identifier.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Ident<'s> {
+    // Numeric identifiers have lower precedence, so this variant comes first.
+    Num(u64),
+    Alpha(&'s str),
+}
+
+/// A parsed semantic version. Build metadata is dropped as it has no effect on
+/// the precedence.
+#[derive(Debug, PartialEq, Eq)]
+struct SemVer<'s> {
+    core: [u64; 3],
+    pre: Vec<Ident<'s>>,
+}
+
+impl<'s> SemVer<'s> {
+    /// Parses a semantic version, tolerating a leading `v` and a missing
+    /// minor or patch number (eg. `v1.2`).
+    fn parse(s: &'s str) -> Option<Self> {
+        let s = s.trim();
+        let s = s.strip_prefix(['v', 'V']).unwrap_or(s);
+        let s = s.split_once('+').map_or(s, |(s, _build)| s);
+        let (core_str, pre) = match s.split_once('-') {
+            Some((_, "")) => return None,
+            Some(split) => split,
+            None => (s, ""),
+        };
+
+        let mut core = [0; 3];
+        let mut parts = core_str.split('.');
+        for (i, part) in parts.by_ref().take(3).enumerate() {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            core[i] = part.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let pre = if pre.is_empty() {
+            vec![]
+        } else {
+            pre.split('.')
+                .map(|id| match id.parse() {
+                    _ if id.is_empty() => None,
+                    Ok(n) => Some(Ident::Num(n)),
+                    Err(_) => Some(Ident::Alpha(id)),
+                })
+                .collect::<Option<_>>()?
+        };
+        Some(SemVer { core, pre })
+    }
+}
+
+impl Ord for SemVer<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.core.cmp(&other.core).then_with(|| {
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                // A release has a higher precedence than its pre-releases.
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            }
+        })
+    }
+}
+
+impl PartialOrd for SemVer<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares two semantic versions, returning `None` if any of them is not one.
+pub(super) fn cmp(a: &str, b: &str) -> Option<Ordering> {
+    Some(SemVer::parse(a)?.cmp(&SemVer::parse(b)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence() {
+        // The example given in the specification, in ascending order.
+        let versions = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "2.0.0",
+            "2.1.0",
+            "2.1.1",
+        ];
+        for (a, b) in versions.iter().zip(&versions[1..]) {
+            assert_eq!(cmp(a, b), Some(Ordering::Less), "{a} vs {b}");
+            assert_eq!(cmp(b, a), Some(Ordering::Greater), "{b} vs {a}");
+        }
+    }
+
+    #[test]
+    fn build_metadata_and_leniency() {
+        assert_eq!(cmp("1.0.0+build.1", "1.0.0+build.2"), Some(Ordering::Equal));
+        assert_eq!(cmp("v1.2", "1.2.0"), Some(Ordering::Equal));
+        assert_eq!(cmp("1.10.0", "1.9.0"), Some(Ordering::Greater));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(cmp("1.2.3.4", "1.2.3"), None);
+        assert_eq!(cmp("1.2.x", "1.2.3"), None);
+        assert_eq!(cmp("1.2.3-", "1.2.3"), None);
+        assert_eq!(cmp("1.2.3-a..b", "1.2.3"), None);
+    }
+}
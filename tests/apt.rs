@@ -49,6 +49,14 @@ fn apt_qi() {
     "## }
 }
 
+#[test]
+fn apt_ql() {
+    test_dsl! { r##"
+        in -Ql apt
+        ou ^/usr/bin/apt-get$
+    "## }
+}
+
 #[test]
 fn apt_qo() {
     test_dsl! { r##"
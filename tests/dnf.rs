@@ -44,6 +44,14 @@ fn dnf_qi() {
     "## }
 }
 
+#[test]
+fn dnf_qk() {
+    test_dsl! { r##"
+        in -Qk dnf
+        ou rpm -V dnf
+    "## }
+}
+
 #[test]
 fn dnf_ql() {
     test_dsl! { r##"
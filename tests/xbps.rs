@@ -37,6 +37,14 @@ fn xbps_qi() {
     "}
 }
 
+#[test]
+fn xbps_qo() {
+    test_dsl! {"
+        in -Qo /usr/bin/xbps-install
+        ou ^xbps-[0-9.]+_[0-9]+: /usr/bin/xbps-install
+    "}
+}
+
 #[test]
 fn xbps_r_s() {
     test_dsl! {"
@@ -36,6 +36,14 @@ fn zypper_qi() {
 "## }
 }
 
+#[test]
+fn zypper_qk() {
+    test_dsl! { r##"
+        in -Qk rpm
+        ou rpm -V rpm
+    "## }
+}
+
 #[test]
 fn zypper_ql() {
     test_dsl! { r##"